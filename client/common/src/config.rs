@@ -10,6 +10,16 @@ pub struct Config{
     pub segment_configs: Vec<SegmentConfig>,
     pub disk_cache_config: DiskCacheConfig,
     pub backend_store_config: BackendStoreConfig,
+    #[serde(default)]
+    pub leader_config: LeaderConfig,
+    #[serde(default)]
+    pub recovery_config: RecoveryConfig,
+    #[serde(default)]
+    pub dir_placement_config: DirPlacementConfig,
+    #[serde(default)]
+    pub dir_health_config: DirHealthConfig,
+    #[serde(default)]
+    pub scrub_config: ScrubConfig,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -27,12 +37,74 @@ pub struct S3Config {
 #[derive(Deserialize, Debug, Clone)]
 pub struct MetaServerConfig {
     pub meta_server: String,
+    // how to authenticate the requests to meta server: "none", "v4" or "token".
+    // "v4" signs with the ak/sk of s3_config, which the meta server must set as auth_access_key/auth_secret_key.
+    #[serde(default)]
+    pub auth_type: String,
+    // the shared token used by "token" auth_type.
+    #[serde(default)]
+    pub auth_token: String,
+    // the tls settings used when meta_server is https.
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TlsConfig {
+    // the pem file of the CA used to verify the server, the system roots are used if empty.
+    #[serde(default)]
+    pub ca_file: String,
+    // the pem files of the client cert and its pkcs8 key for mutual tls.
+    #[serde(default)]
+    pub client_cert_file: String,
+    #[serde(default)]
+    pub client_key_file: String,
+    // override the server name used for SNI and cert verification, the url host is used if empty.
+    #[serde(default)]
+    pub server_name: String,
+    // skip the verification of the server cert, only for test labs.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsConfig {
+    // parse the tls settings from the key/value settings, such as the backend store settings.
+    // the keys are the field names with the "tls_" prefix.
+    pub fn from_settings(settings: &HashMap<String, String>) -> Result<TlsConfig, String> {
+        let mut cfg: TlsConfig = Default::default();
+        if let Some(v) = settings.get("tls_ca_file") {
+            cfg.ca_file = v.clone();
+        }
+        if let Some(v) = settings.get("tls_client_cert_file") {
+            cfg.client_cert_file = v.clone();
+        }
+        if let Some(v) = settings.get("tls_client_key_file") {
+            cfg.client_key_file = v.clone();
+        }
+        if let Some(v) = settings.get("tls_server_name") {
+            cfg.server_name = v.clone();
+        }
+        if let Some(v) = settings.get("tls_insecure_skip_verify") {
+            match v.parse::<bool>() {
+                Ok(ret) => {
+                    cfg.insecure_skip_verify = ret;
+                }
+                Err(err) => {
+                    return Err(format!("invalid tls_insecure_skip_verify: {}, err: {}", v, err));
+                }
+            }
+        }
+        Ok(cfg)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ZoneConfig {
     pub zone: String,
     pub machine: String,
+    // the address the leader rpc server listens on, 0.0.0.0:<peer_port> if empty.
+    #[serde(default)]
+    pub bind_addr: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -42,6 +114,82 @@ pub struct SegmentConfig {
     pub num: u32, // by default is 0.
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RecoveryConfig {
+    // the segment files not referred by any file are moved into the "quarantine" sub dir
+    // of their data dir on startup, they are only reported if false.
+    pub quarantine: bool,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        RecoveryConfig{
+            quarantine: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DirPlacementConfig {
+    // how to choose the data dir of the new segments: "hash", "weight", "least_used" or "round_robin".
+    // "hash" maps the segment ids to the dirs equally, "weight" by the size of their file systems,
+    // "least_used" chooses the dir with the lowest used ratio, and "round_robin" the dirs in turn.
+    pub policy: String,
+    // the dir is not chosen if its free space would drop below this ratio of its file system
+    // after a new segment is full.
+    pub min_free_ratio: f64,
+}
+
+impl Default for DirPlacementConfig {
+    fn default() -> Self {
+        DirPlacementConfig{
+            policy: String::from("hash"),
+            min_free_ratio: 0.05,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DirHealthConfig {
+    // how often the data dirs are probed and their free space is checked, 0 disables it.
+    pub check_interval_secs: u64,
+    // the dir fails if more than this ratio of the io on it fail between two checks.
+    pub max_error_ratio: f64,
+    // the error ratio is only used after this number of io.
+    pub min_ios: u64,
+}
+
+impl Default for DirHealthConfig {
+    fn default() -> Self {
+        DirHealthConfig{
+            check_interval_secs: 10,
+            max_error_ratio: 0.5,
+            min_ios: 8,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScrubConfig {
+    // how often all the local segment files are scrubbed, 0 disables it.
+    pub interval_secs: u64,
+    // the max bytes read by the scrubber per second from the disks and the backend store, 0 is unlimited.
+    pub bytes_per_sec: u64,
+}
+
+impl Default for ScrubConfig {
+    fn default() -> Self {
+        ScrubConfig{
+            interval_secs: 86400,
+            bytes_per_sec: 16 << 20,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct DiskCacheConfig{
     pub thread_num: u32,
@@ -51,4 +199,102 @@ pub struct DiskCacheConfig{
 pub struct BackendStoreConfig {
     pub backend_type: u32,
     pub settings: HashMap<String, String>,
-}
\ No newline at end of file
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LeaderConfig {
+    // the lease of the file leader in seconds, other machines can take over the file after it expires.
+    pub lease_secs: u64,
+    // how often the leases are renewed, must be less than lease_secs.
+    pub renew_interval_secs: u64,
+    // the port of the leader rpc server on every machine.
+    pub peer_port: u16,
+    // the timeout of connecting, sending and receiving the requests forwarded to peer leaders.
+    pub rpc_timeout_ms: u64,
+    // how many times a request to the peer leader is retried.
+    pub rpc_retries: u32,
+    // the max idle connections kept for each peer.
+    pub rpc_pool_size: usize,
+    // the token shared by the machines to authenticate the leader rpc, the sk of s3_config is used if empty.
+    pub rpc_token: String,
+    // the max requests served for one peer at the same time, the others wait.
+    pub rpc_peer_concurrency: usize,
+    // the max peer connections served by the leader rpc server at the same time, the others are closed.
+    pub rpc_server_conns: usize,
+    // how to choose the leader of the new files: "local", "zone", "weight" or "load".
+    // "local" leads the files by this machine, "zone" chooses the machines in the same zone
+    // by weight, "weight" chooses the machines in all the zones by weight, and "load"
+    // chooses the least loaded machine in the same zone.
+    pub placement_policy: String,
+    // how often the machines of the zones are refreshed from the meta server.
+    pub placement_refresh_secs: u64,
+    // the small sequential writes of a file are buffered up to this size and written as one block,
+    // the writes are not buffered if it is 0.
+    pub write_buffer_size: usize,
+    // the buffered writes are flushed after this time even if the buffer is not full.
+    pub write_flush_ms: u64,
+    // the data after the sequential reads of a file is read ahead up to this size, 0 disables it.
+    pub readahead_window: u64,
+    // the max size of the data read ahead kept in memory.
+    pub read_cache_size: u64,
+    // the segments of the opened files whose live data is less than this ratio are compacted.
+    pub gc_min_live_ratio: f64,
+    // how often the segments are checked for compaction, 0 disables it.
+    pub gc_interval_secs: u64,
+    // the segment is sealed and uploaded after no data is written into it for this time, 0 disables
+    // it and only the full segments are sealed.
+    pub seal_idle_secs: u64,
+    // the max bytes uploaded to the backend store per second, 0 is unlimited.
+    pub upload_bytes_per_sec: u64,
+    // the sealed segment is uploaded in chunks of this size.
+    pub upload_chunk_size: u64,
+    // how many times a failed upload is retried, the segment stays local after all of them fail.
+    pub upload_retries: u32,
+    // the wait before the first retry of a failed upload, it is doubled for every retry.
+    pub upload_retry_ms: u64,
+    // how often the usage of the data dirs is checked for eviction, 0 disables it.
+    pub evict_interval_secs: u64,
+    // which uploaded segments are evicted first: "lru" or "lfu".
+    pub evict_policy: String,
+    // the bytes of the segment files allowed in each data dir, 0 uses the size of its file system.
+    pub evict_capacity: u64,
+    // the uploaded segments are evicted from the dir whose usage is above the high watermark,
+    // until it is below the low watermark.
+    pub evict_high_watermark: f64,
+    pub evict_low_watermark: f64,
+}
+
+impl Default for LeaderConfig {
+    fn default() -> Self {
+        LeaderConfig{
+            lease_secs: 30,
+            renew_interval_secs: 10,
+            peer_port: 9098,
+            rpc_timeout_ms: 5000,
+            rpc_retries: 2,
+            rpc_pool_size: 8,
+            rpc_token: String::new(),
+            rpc_peer_concurrency: 16,
+            rpc_server_conns: 256,
+            placement_policy: String::from("local"),
+            placement_refresh_secs: 10,
+            write_buffer_size: 1048576,
+            write_flush_ms: 1000,
+            readahead_window: 4194304,
+            read_cache_size: 67108864,
+            gc_min_live_ratio: 0.5,
+            gc_interval_secs: 300,
+            seal_idle_secs: 60,
+            upload_bytes_per_sec: 0,
+            upload_chunk_size: 4194304,
+            upload_retries: 5,
+            upload_retry_ms: 1000,
+            evict_interval_secs: 60,
+            evict_policy: String::from("lru"),
+            evict_capacity: 0,
+            evict_high_watermark: 0.9,
+            evict_low_watermark: 0.8,
+        }
+    }
+}
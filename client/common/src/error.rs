@@ -22,9 +22,35 @@ pub enum Errno{
     Eaccess = 9,
     // range error
     Erange = 10,
+    // stale file handle, the ino is reused by another generation.
+    Estale = 11,
+    // the peer is unreachable.
+    Eunreach = 12,
+    // the data fails the checksum.
+    Ecorrupt = 13,
 }
 
 impl Errno {
+    // convert the errno code received from the peers.
+    pub fn from_code(code: i32) -> Errno {
+        match code {
+            0 => Errno::Esucc,
+            2 => Errno::Enoent,
+            3 => Errno::Eexists,
+            4 => Errno::Eseek,
+            5 => Errno::Eeof,
+            6 => Errno::Enotf,
+            7 => Errno::Enotsupp,
+            28 => Errno::Enospc,
+            9 => Errno::Eaccess,
+            10 => Errno::Erange,
+            11 => Errno::Estale,
+            12 => Errno::Eunreach,
+            13 => Errno::Ecorrupt,
+            _ => Errno::Eintr,
+        }
+    }
+
     pub fn is_exists(&self)->bool {
         match *self {
             Errno::Eexists => {
@@ -90,4 +116,48 @@ impl Errno {
             }
         }
     }
+
+    pub fn is_enotsupp(&self) -> bool {
+        match *self {
+            Errno::Enotsupp => {
+                true
+            }
+            _ => {
+                false
+            }
+        }
+    }
+
+    pub fn is_estale(&self) -> bool {
+        match *self {
+            Errno::Estale => {
+                true
+            }
+            _ => {
+                false
+            }
+        }
+    }
+
+    pub fn is_eunreach(&self) -> bool {
+        match *self {
+            Errno::Eunreach => {
+                true
+            }
+            _ => {
+                false
+            }
+        }
+    }
+
+    pub fn is_ecorrupt(&self) -> bool {
+        match *self {
+            Errno::Ecorrupt => {
+                true
+            }
+            _ => {
+                false
+            }
+        }
+    }
 }
@@ -9,9 +9,11 @@ use bytes::Buf;
 use bytes::Bytes;
 use hyper::{Client, Request, Body};
 use hyper::client::HttpConnector;
-use hyper_tls::HttpsConnector;
 use http::header::{HeaderName, HeaderValue};
 
+use crate::config::TlsConfig;
+use crate::tls::TlsConnector;
+
 
 pub enum HttpMethod{
     Get,
@@ -30,7 +32,7 @@ pub struct RespText{
 pub struct HttpClient{
     pub retry_times: u32,
     http_client: Client<HttpConnector, hyper::Body>,
-    https_client: Client<hyper_tls::HttpsConnector<HttpConnector>, hyper::Body>,
+    https_client: Client<TlsConnector, hyper::Body>,
     pub headers: BTreeMap<String, Vec<Vec<u8>>>,
 }
 
@@ -42,13 +44,19 @@ struct Resp{
 
 impl HttpClient{
     pub fn new(retry_times: u32) -> HttpClient{
-        let https = HttpsConnector::new();
-        HttpClient{
+        // the default tls config always builds.
+        HttpClient::new_with_tls(retry_times, &Default::default()).unwrap()
+    }
+
+    // create the client whose https requests use the CA, client cert and SNI in tls.
+    pub fn new_with_tls(retry_times: u32, tls: &TlsConfig) -> Result<HttpClient, String>{
+        let https = TlsConnector::new(tls)?;
+        Ok(HttpClient{
             retry_times: retry_times,
             http_client: Client::new(),
             https_client: Client::builder().build::<_, hyper::Body>(https),
             headers: BTreeMap::new(),
-        }
+        })
     }
 
     pub fn set_headers(&mut self, headers: BTreeMap<String, Vec<Vec<u8>>>) {
@@ -56,10 +64,31 @@ impl HttpClient{
     }
 
     pub async fn request(&self, url: &String, body: &[u8], method: &HttpMethod, is_v4: bool) -> Result<RespText, String>
+    {
+        if is_v4 {
+            if self.headers.is_empty() {
+                return Err(format!("V4 request headers is not None"));
+            }
+            return self.request_with_headers(url, body, method, &self.headers).await;
+        }
+        return self.request_with_headers(url, body, method, &BTreeMap::new()).await;
+    }
+
+    // send the request with the extra headers, such as the signed headers for the request.
+    // if headers is empty, the request is sent as json.
+    pub async fn request_with_headers(&self, url: &String, body: &[u8], method: &HttpMethod, headers: &BTreeMap<String, Vec<Vec<u8>>>) -> Result<RespText, String>
+    {
+        self.request_with_signer(url, body, method, || Ok(headers.clone())).await
+    }
+
+    // send the request with the headers got from sign for every attempt, so that the retries
+    // carry their own date and nonce, and are not rejected as the replays of the first one.
+    pub async fn request_with_signer<F: Fn() -> Result<BTreeMap<String, Vec<Vec<u8>>>, String>>(&self, url: &String, body: &[u8], method: &HttpMethod, sign: F) -> Result<RespText, String>
     {
         let mut count = self.retry_times;
         while count > 0 {
             count -= 1;
+            let headers = sign()?;
             let mut req : Request<Body>;
             let ret = hyper::Request::builder().
                         method(self.get_http_method(method)).
@@ -71,12 +100,8 @@ impl HttpClient{
                     req = ret;
                     // use http2
                     //*(req.version_mut()) = hyper::Version::HTTP_2;
-                    if is_v4 {
-                        if self.headers.is_empty() {
-                            return Err(format!("V4 request headers is not None"));
-                        }
-
-                        for h in self.headers.iter() {
+                    if !headers.is_empty() {
+                        for h in headers.iter() {
                             // add header
                             let header_name = match h.0.parse::<HeaderName>() {
                                 Ok(name) => name,
@@ -1,6 +1,7 @@
 pub mod config;
 pub mod json;
 pub mod http_client;
+pub mod tls;
 pub mod time;
 pub mod error;
 pub mod uuid;
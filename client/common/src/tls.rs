@@ -0,0 +1,121 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::Uri;
+use hyper_tls::MaybeHttpsStream;
+use tokio::net::TcpStream;
+
+use crate::config::TlsConfig;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// TlsConnector is the https connector built from TlsConfig.
+// unlike hyper_tls::HttpsConnector, it supports the SNI override.
+#[derive(Clone)]
+pub struct TlsConnector {
+    http: HttpConnector,
+    tls: tokio_native_tls::TlsConnector,
+    server_name: String,
+}
+
+impl TlsConnector {
+    pub fn new(cfg: &TlsConfig) -> Result<TlsConnector, String> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if !cfg.ca_file.is_empty() {
+            let pem = TlsConnector::read_file(&cfg.ca_file)?;
+            match native_tls::Certificate::from_pem(&pem) {
+                Ok(ret) => {
+                    builder.add_root_certificate(ret);
+                }
+                Err(err) => {
+                    return Err(format!("invalid ca_file: {}, err: {}", cfg.ca_file, err));
+                }
+            }
+        }
+        if !cfg.client_cert_file.is_empty() || !cfg.client_key_file.is_empty() {
+            if cfg.client_cert_file.is_empty() || cfg.client_key_file.is_empty() {
+                return Err(format!("both client_cert_file and client_key_file are required for mutual tls"));
+            }
+            let cert = TlsConnector::read_file(&cfg.client_cert_file)?;
+            let key = TlsConnector::read_file(&cfg.client_key_file)?;
+            match native_tls::Identity::from_pkcs8(&cert, &key) {
+                Ok(ret) => {
+                    builder.identity(ret);
+                }
+                Err(err) => {
+                    return Err(format!("invalid client cert: {} or key: {}, err: {}",
+                    cfg.client_cert_file, cfg.client_key_file, err));
+                }
+            }
+        }
+        if cfg.insecure_skip_verify {
+            println!("TlsConnector: the server cert verification is skipped, it is unsafe out of test labs.");
+            builder.danger_accept_invalid_certs(true);
+            builder.danger_accept_invalid_hostnames(true);
+        }
+        let tls: native_tls::TlsConnector;
+        match builder.build() {
+            Ok(ret) => {
+                tls = ret;
+            }
+            Err(err) => {
+                return Err(format!("failed to create tls connector, err: {}", err));
+            }
+        }
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        Ok(TlsConnector{
+            http: http,
+            tls: tokio_native_tls::TlsConnector::from(tls),
+            server_name: cfg.server_name.clone(),
+        })
+    }
+
+    fn read_file(path: &String) -> Result<Vec<u8>, String> {
+        match std::fs::read(path) {
+            Ok(ret) => {
+                return Ok(ret);
+            }
+            Err(err) => {
+                return Err(format!("failed to read {}, err: {}", path, err));
+            }
+        }
+    }
+}
+
+impl Service<Uri> for TlsConnector {
+    type Response = MaybeHttpsStream<TcpStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.http.poll_ready(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let is_https = dst.scheme_str() == Some("https");
+        // use the host of url if the server name is not overridden.
+        let mut server_name = self.server_name.clone();
+        if server_name.is_empty() {
+            server_name = String::from(dst.host().unwrap_or("").trim_matches(|c| c == '[' || c == ']'));
+        }
+        let connecting = self.http.call(dst);
+        let tls = self.tls.clone();
+        let fut = async move {
+            let tcp = connecting.await?;
+            if !is_https {
+                return Ok(MaybeHttpsStream::Http(tcp));
+            }
+            let stream = tls.connect(&server_name, tcp).await?;
+            Ok(MaybeHttpsStream::Https(stream))
+        };
+        Box::pin(fut)
+    }
+}
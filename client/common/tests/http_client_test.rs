@@ -33,3 +33,63 @@ fn test_https_client_get()->Result<(), String> {
     println!("{}", resp.body);
     return Ok(());
 }
+
+// read the request on conn, and return the value of the header name.
+fn read_request_header(conn: &mut std::net::TcpStream, name: &str) -> Result<String, String> {
+    use std::io::Read;
+    let mut buf = Vec::<u8>::new();
+    let mut b = [0u8; 1024];
+    while !String::from_utf8_lossy(&buf).contains("\r\n\r\n") {
+        let n = conn.read(&mut b).map_err(|err| format!("failed to read request, err: {}", err))?;
+        if n == 0 {
+            return Err(String::from("the request is cut short"));
+        }
+        buf.extend_from_slice(&b[..n]);
+    }
+    let req = String::from_utf8_lossy(&buf).to_lowercase();
+    for line in req.lines() {
+        if let Some(v) = line.strip_prefix(&format!("{}: ", name)) {
+            return Ok(String::from(v));
+        }
+    }
+    Err(format!("no header {} in request: {}", name, req))
+}
+
+#[test]
+fn test_http_client_sign_retries() -> Result<(), String> {
+    use std::io::Write;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?;
+    let url = format!("http://{}/v1/file/attr", listener.local_addr().map_err(|err| format!("{}", err))?);
+    // the first attempt is dropped after the server reads it, the second one is served.
+    let server = std::thread::spawn(move || -> Result<Vec<String>, String> {
+        let mut nonces = Vec::<String>::new();
+        for i in 0..2 {
+            let (mut conn, _) = listener.accept().map_err(|err| format!("failed to accept, err: {}", err))?;
+            nonces.push(read_request_header(&mut conn, "x-yigfs-nonce")?);
+            if i == 1 {
+                conn.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").map_err(|err| format!("failed to write, err: {}", err))?;
+            }
+        }
+        Ok(nonces)
+    });
+    let exec = Executor::create();
+    let client = http_client::HttpClient::new(3);
+    let signed = Mutex::new(0);
+    let resp = exec.get_runtime().block_on(client.request_with_signer(&url, b"{}", &http_client::HttpMethod::Get, || {
+        let mut n = signed.lock().unwrap();
+        *n += 1;
+        let mut headers = BTreeMap::<String, Vec<Vec<u8>>>::new();
+        headers.insert(String::from("x-yigfs-nonce"), vec![format!("nonce-{}", n).into_bytes()]);
+        Ok(headers)
+    }))?;
+    if resp.status != 200 {
+        return Err(format!("got status {}", resp.status));
+    }
+    let nonces = server.join().map_err(|_| String::from("failed to join the server"))??;
+    if nonces != vec![String::from("nonce-1"), String::from("nonce-2")] {
+        return Err(format!("got nonces: {:?}", nonces));
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use common::config::TlsConfig;
+use common::http_client::HttpClient;
+
+#[test]
+fn test_tls_from_settings() -> Result<(), String> {
+    let mut settings = HashMap::new();
+    settings.insert(String::from("tls_ca_file"), String::from("/etc/yigfs/ca.pem"));
+    settings.insert(String::from("tls_server_name"), String::from("s3.test.com"));
+    settings.insert(String::from("tls_insecure_skip_verify"), String::from("true"));
+    let cfg = TlsConfig::from_settings(&settings)?;
+    if cfg.ca_file != "/etc/yigfs/ca.pem" || cfg.server_name != "s3.test.com" || !cfg.insecure_skip_verify {
+        return Err(format!("got invalid tls config: {:?}", cfg));
+    }
+    if !cfg.client_cert_file.is_empty() || !cfg.client_key_file.is_empty() {
+        return Err(format!("got unexpected client cert: {:?}", cfg));
+    }
+
+    settings.insert(String::from("tls_insecure_skip_verify"), String::from("yes"));
+    match TlsConfig::from_settings(&settings) {
+        Ok(cfg) => {
+            return Err(format!("invalid tls_insecure_skip_verify is accepted: {:?}", cfg));
+        }
+        Err(_) => {
+            return Ok(());
+        }
+    }
+}
+
+#[test]
+fn test_tls_ca_file() -> Result<(), String> {
+    let mut cfg: TlsConfig = Default::default();
+    cfg.ca_file = String::from("../../server/integrate/metaconf/servercert.pem");
+    HttpClient::new_with_tls(3, &cfg)?;
+
+    cfg.ca_file = String::from("../../server/integrate/metaconf/not_exist.pem");
+    match HttpClient::new_with_tls(3, &cfg) {
+        Ok(_) => {
+            return Err(format!("missing ca_file is accepted"));
+        }
+        Err(_) => {
+            return Ok(());
+        }
+    }
+}
+
+#[test]
+fn test_tls_client_identity() -> Result<(), String> {
+    let mut cfg: TlsConfig = Default::default();
+    cfg.client_cert_file = String::from("../../server/integrate/metaconf/servercert.pem");
+    match HttpClient::new_with_tls(3, &cfg) {
+        Ok(_) => {
+            return Err(format!("client cert without key is accepted"));
+        }
+        Err(_) => {}
+    }
+    cfg.client_key_file = String::from("../../server/integrate/metaconf/serverkey.pem");
+    HttpClient::new_with_tls(3, &cfg)?;
+    Ok(())
+}
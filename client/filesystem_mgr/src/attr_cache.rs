@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use metaservice_mgr::types::{FileAttr, FileType};
+
+struct CachedAttr {
+    attr: FileAttr,
+    expire: Instant,
+}
+
+// AttrCache caches the attrs got by readdir, so that the following lookup & getattr
+// from kernel needn't go to the meta server one by one.
+pub struct AttrCache {
+    ttl: Duration,
+    max_entries: usize,
+    // ino -> attr
+    attrs: HashMap<u64, CachedAttr>,
+    // (parent ino, name) -> ino
+    entries: HashMap<(u64, String), u64>,
+    // dir ino -> parent ino, used by the ".." entry of readdir.
+    parents: HashMap<u64, u64>,
+}
+
+impl AttrCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        AttrCache{
+            ttl: ttl,
+            max_entries: max_entries,
+            attrs: HashMap::new(),
+            entries: HashMap::new(),
+            parents: HashMap::new(),
+        }
+    }
+
+    pub fn put(&mut self, attr: &FileAttr) {
+        if self.attrs.len() >= self.max_entries {
+            self.purge();
+        }
+        self.attrs.insert(attr.ino, CachedAttr{
+            attr: attr.clone(),
+            expire: Instant::now() + self.ttl,
+        });
+    }
+
+    pub fn put_entry(&mut self, parent: u64, name: &String, attr: &FileAttr) {
+        self.put(attr);
+        self.entries.insert((parent, name.clone()), attr.ino);
+        if let FileType::DIR = attr.kind {
+            self.parents.insert(attr.ino, parent);
+        }
+    }
+
+    pub fn parent(&self, ino: u64) -> Option<u64> {
+        if let Some(parent) = self.parents.get(&ino) {
+            return Some(*parent);
+        }
+        None
+    }
+
+    pub fn get(&self, ino: u64) -> Option<FileAttr> {
+        if let Some(c) = self.attrs.get(&ino) {
+            if c.expire > Instant::now() {
+                return Some(c.attr.clone());
+            }
+        }
+        None
+    }
+
+    pub fn lookup(&self, parent: u64, name: &String) -> Option<FileAttr> {
+        if let Some(ino) = self.entries.get(&(parent, name.clone())) {
+            return self.get(*ino);
+        }
+        None
+    }
+
+    // drop the attr of ino, such as the size is changed by write.
+    pub fn invalidate(&mut self, ino: u64) {
+        self.attrs.remove(&ino);
+    }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    // remove the expired attrs, and all the attrs if the cache is still full.
+    fn purge(&mut self) {
+        let now = Instant::now();
+        self.attrs.retain(|_, c| c.expire > now);
+        if self.attrs.len() >= self.max_entries {
+            self.attrs.clear();
+        }
+        let attrs = &self.attrs;
+        self.entries.retain(|_, ino| attrs.contains_key(ino));
+        self.parents.retain(|ino, _| attrs.contains_key(ino));
+    }
+}
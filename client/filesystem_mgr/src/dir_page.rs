@@ -0,0 +1,63 @@
+use common::error::Errno;
+use metaservice_mgr::types::DirEntry;
+
+// the readdir offset is the cookie of the last entry returned to kernel:
+// 1 for ".", 2 for "..", and the cursor of meta server plus 2 for the others.
+pub const DOT_COOKIE: i64 = 1;
+pub const DOTDOT_COOKIE: i64 = 2;
+
+pub struct DirPageEntry {
+    pub entry: DirEntry,
+    // kernel passes it back as the offset to continue after this entry.
+    pub cookie: i64,
+}
+
+// the cursor of meta server to continue after the readdir offset.
+pub fn cursor_of_offset(offset: i64) -> i64 {
+    if offset > DOTDOT_COOKIE {
+        return offset - DOTDOT_COOKIE;
+    }
+    0
+}
+
+// read the page of entries after cursor by read, which returns the entries and the next cursor.
+// an empty page is the end of dir.
+pub fn read_page<F>(cursor: i64, mut read: F) -> Result<Vec<DirPageEntry>, Errno>
+where F: FnMut(i64) -> Result<(Vec<DirEntry>, i64), Errno> {
+    let mut cursor = cursor;
+    loop {
+        let (entrys, next_cursor) = read(cursor)?;
+        if entrys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let page = dir_page(entrys, next_cursor);
+        // the page has only the dropped rows, continue with the next page.
+        if !page.is_empty() || next_cursor <= cursor {
+            return Ok(page);
+        }
+        cursor = next_cursor;
+    }
+}
+
+// assign the cookies to the page of entries read from meta server.
+// the "." & ".." rows stored by meta server for the root dir are dropped,
+// readdir adds them itself.
+fn dir_page(entrys: Vec<DirEntry>, next_cursor: i64) -> Vec<DirPageEntry> {
+    let mut page = Vec::new();
+    let count = entrys.len();
+    for (i, entry) in entrys.into_iter().enumerate() {
+        // the listing is ordered by ino, so the ino of entry is the cursor to continue after it.
+        let mut entry_cursor = entry.ino as i64;
+        if i + 1 == count {
+            entry_cursor = next_cursor;
+        }
+        if entry.name == "." || entry.name == ".." {
+            continue;
+        }
+        page.push(DirPageEntry{
+            entry: entry,
+            cookie: entry_cursor + DOTDOT_COOKIE,
+        });
+    }
+    page
+}
@@ -22,6 +22,7 @@ pub enum FileHandleInfoOp {
 #[derive(Debug)]
 pub struct FileHandleInfo {
     pub ino: u64,
+    pub generation: u64,
     pub leader: String,
 }
 
@@ -205,6 +206,7 @@ impl HandleCacher {
         if let Some(h) = self.handles.get(&msg.ino){
             handle = Some(FileHandleInfo{
                 ino: h.ino,
+                generation: h.generation,
                 leader: h.leader.clone(),
             });
         }
@@ -3,7 +3,9 @@ extern crate libc;
 extern crate time;
 
 use std::ffi::OsStr;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use libc::{ENOENT, c_int};
 use time::Timespec;
 use fuse::{FileType, FileAttr, Filesystem, Request, 
@@ -11,15 +13,26 @@ use fuse::{FileType, FileAttr, Filesystem, Request,
 use metaservice_mgr::{mgr::MetaServiceMgr, types::{FileLeader, NewFileInfo, SetFileAttr}};
 use segment_mgr::leader_mgr::LeaderMgr;
 use common::uuid;
+use common::error::Errno;
 use crate::handle::{FileHandleInfo, FileHandleInfoMgr};
+use crate::attr_cache::AttrCache;
+use crate::dir_page::{self, DOT_COOKIE, DOTDOT_COOKIE};
 
 const TTL: Timespec = Timespec { sec: 1, nsec: 0 };                     // 1 second
+const ATTR_CACHE_TTL: Duration = Duration::from_secs(1);
+const ATTR_CACHE_MAX_ENTRIES: usize = 100000;
 
 
 pub struct Yigfs{
-    meta_service_mgr: Rc<dyn MetaServiceMgr>,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
     leader_mgr: LeaderMgr,
     handle_cacher: FileHandleInfoMgr,
+    // attrs got by readdir, used by the following lookup & getattr.
+    attr_cache: AttrCache,
+    // whether the meta server supports reading the entries with their attrs.
+    read_dir_plus_supp: bool,
+    // whether the meta server supports reading the attrs of many inos in one request.
+    read_file_attrs_supp: bool,
     // fsid for this mounted yigfs instance
     fsid: String,
 }
@@ -56,9 +69,14 @@ impl Filesystem for Yigfs {
             }
         }
         println!("lookup: parent: {}, name: {}", parent, name_str);
+        if let Some(attr) = self.attr_cache.lookup(parent, &name_str) {
+            reply.entry(&TTL, &self.to_usefs_attr(&attr), attr.generation);
+            return;
+        }
         let ret = self.meta_service_mgr.read_dir_file_attr(parent, &name_str);
         match ret {
             Ok(ret) => {
+                self.attr_cache.put_entry(parent, &name_str, &ret);
                 let file_attr = self.to_usefs_attr(&ret);
                 println!("lookup: parent: {}, name: {}, attr: {:?}", parent, name_str, file_attr);
                 reply.entry(&TTL, &file_attr, ret.generation);
@@ -71,9 +89,14 @@ impl Filesystem for Yigfs {
     }
 
     fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        if let Some(attr) = self.attr_cache.get(ino) {
+            reply.attr(&TTL, &self.to_usefs_attr(&attr));
+            return;
+        }
         let ret = self.meta_service_mgr.read_file_attr(ino);
         match ret {
             Ok(ret) => {
+                self.attr_cache.put(&ret);
                 let attr = self.to_usefs_attr(&ret);
                 println!("getattr: ino: {}, attr: {:?}", ino, attr);
                 reply.attr(&TTL, &attr);
@@ -133,19 +156,23 @@ impl Filesystem for Yigfs {
             }
             Err(err) => {
                 println!("failed to set_file_attr for {:?}, err: {:?}", set_attr, err);
+                self.attr_cache.invalidate(ino);
                 reply.error(libc::EIO);
                 return;
             }
         }
+        self.attr_cache.put(&file_attr);
         reply.attr(&TTL, &self.to_usefs_attr(&file_attr));
     }
 
     fn read(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, size: u32, reply: ReplyData) {
         let leader: String;
+        let generation: u64;
         let ret = self.handle_cacher.get_handle_info(ino);
         match ret {
             Ok(ret) => {
                 leader = ret.leader;
+                generation = ret.generation;
             }
             Err(err) => {
                 println!("read: file ino: {} is not opened yet, err: {:?}.", ino, err);
@@ -153,9 +180,12 @@ impl Filesystem for Yigfs {
                 return;
             }
         }
-        // get the leader.
-        let leader_io = self.leader_mgr.get_leader(&leader);
-        let ret = leader_io.read(ino, offset as u64, size);
+        let mut ret = self.leader_mgr.read(&leader, ino, generation, offset as u64, size);
+        if let Err(Errno::Eunreach) = ret {
+            if let Ok(leader) = self.takeover(ino, generation) {
+                ret = self.leader_mgr.read(&leader, ino, generation, offset as u64, size);
+            }
+        }
         match ret {
             Ok(ret) => {
                 reply.data(ret.as_slice());
@@ -163,7 +193,7 @@ impl Filesystem for Yigfs {
             }
             Err(err) => {
                 println!("read: failed to read ino: {}, offset: {}, err: {:?}", ino, offset, err);
-                reply.error(libc::EIO);
+                reply.error(self.to_libc_err(&err));
                 return;
             }
         }
@@ -172,26 +202,39 @@ impl Filesystem for Yigfs {
     fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
         // must authorize the request here by checking _req.
         println!("readdir: ino: {}, offset: {}", ino, offset);
-        let entrys : Vec<metaservice_mgr::types::DirEntry>;
-        let ret = self.meta_service_mgr.read_dir(ino, offset);
+        if offset < DOT_COOKIE {
+            if reply.add(ino, DOT_COOKIE, FileType::Directory, ".") {
+                reply.ok();
+                return;
+            }
+        }
+        if offset < DOTDOT_COOKIE {
+            let parent = self.attr_cache.parent(ino).unwrap_or(ino);
+            if reply.add(parent, DOTDOT_COOKIE, FileType::Directory, "..") {
+                reply.ok();
+                return;
+            }
+        }
+        let cursor = dir_page::cursor_of_offset(offset);
+        let page: Vec<dir_page::DirPageEntry>;
+        let ret = dir_page::read_page(cursor, |c| self.read_dir_with_attrs(ino, c));
         match ret {
             Ok(ret) => {
-                entrys = ret;
+                page = ret;
             }
             Err(error) => {
                 println!("failed to readdir for ino: {}, offset: {}, err: {:?}", ino, offset, error);
-                reply.error(ENOENT);
+                reply.error(libc::EIO);
                 return;
             }
         }
-        // chech whether entrys is empty.
-        if entrys.is_empty(){
-            reply.error(ENOENT);
-            return;
-        }
-        
-        for entry in entrys {
-            reply.add(entry.ino, entry.ino as i64, self.ft_to_fuse_ft(&entry.file_type), entry.name);
+        // empty page is the end of dir.
+        for e in page {
+            let ft = self.ft_to_fuse_ft(&e.entry.file_type);
+            // the reply buffer is full, kernel will continue from the last added entry.
+            if reply.add(e.entry.ino, e.cookie, ft, e.entry.name) {
+                break;
+            }
         }
         reply.ok();
     }
@@ -215,7 +258,8 @@ impl Filesystem for Yigfs {
         println!("create: uid: {}, gid: {}, parent: {}, name: {}, mod: {}, flags: {}",
         req.uid(), req.gid(), parent, name, mode, flags);
         let file_info: NewFileInfo;
-        let ret = self.meta_service_mgr.new_ino_leader(parent, &name, req.uid(), req.gid(), mode);
+        let leader = self.leader_mgr.choose_leader(parent, &name);
+        let ret = self.meta_service_mgr.new_ino_leader(parent, &name, req.uid(), req.gid(), mode, &leader);
         match ret {
             Ok(ret ) => {
                 file_info = ret;
@@ -234,6 +278,7 @@ impl Filesystem for Yigfs {
         }
         let ret = self.handle_cacher.add_handle_info(FileHandleInfo{
             ino: file_info.attr.ino,
+            generation: file_info.attr.generation,
             leader: file_info.leader_info.leader.clone(),
         });
         if !ret.is_success(){
@@ -242,7 +287,7 @@ impl Filesystem for Yigfs {
             return;
         }
         let leader_io = self.leader_mgr.get_leader(&file_info.leader_info.leader);
-        let ret = leader_io.open(file_info.attr.ino);
+        let ret = leader_io.open(file_info.attr.ino, file_info.attr.generation);
         if !ret.is_success(){
             println!("create: failed to open name: {}, ino: {}", name, file_info.attr.ino);
             reply.error(libc::EIO);
@@ -250,11 +295,12 @@ impl Filesystem for Yigfs {
         }
         // will check flags and set this later.
         // cache ino->leader to reduce the net io.
+        self.attr_cache.put_entry(parent, &name, &file_info.attr);
         reply.created(&TTL, &self.to_usefs_attr(&file_info.attr), file_info.attr.generation, file_info.attr.ino, flags);
     }
 
     fn open(&mut self, req: &Request, ino: u64, flags: u32, reply: ReplyOpen){
-        let file_leader_info : FileLeader;
+        let mut file_leader_info : FileLeader;
         println!("open: uid: {}, gid: {}, ino: {}, flags: {}",
         req.uid(), req.gid(), ino, flags);
         let ret = self.meta_service_mgr.get_file_leader(ino);
@@ -269,9 +315,26 @@ impl Filesystem for Yigfs {
                 return;
             }
         }
+        let generation: u64;
+        let ret = self.get_generation(ino);
+        match ret {
+            Ok(ret) => {
+                generation = ret;
+            }
+            Err(err) => {
+                println!("open: failed to get generation for ino: {}, err: {:?}", ino, err);
+                reply.error(libc::ENOENT);
+                return;
+            }
+        }
+        // take over the file if the leader is gone.
+        file_leader_info.leader = self.leader_mgr.resolve_leader(ino, generation, &file_leader_info);
+        // the file may be written by others since it is closed on this machine.
+        self.leader_mgr.invalidate_segments(ino);
         let leader = self.leader_mgr.get_leader(&file_leader_info.leader);
         let ret = self.handle_cacher.add_handle_info(FileHandleInfo{
             ino: ino,
+            generation: generation,
             leader: file_leader_info.leader.clone(),
         });
         if !ret.is_success() {
@@ -279,13 +342,13 @@ impl Filesystem for Yigfs {
             reply.error(libc::EBADF);
             return;
         }
-        let ret = leader.open(ino);
+        let ret = leader.open(ino, generation);
         if ret.is_success() {
             reply.opened(ino, flags);
             return;
         }
         println!("open: failed to open ino: {}, err: {:?}", ino, ret);
-        reply.error(libc::EIO);
+        reply.error(self.to_libc_err(&ret));
         return;        
     }
 
@@ -294,10 +357,12 @@ impl Filesystem for Yigfs {
         //req.uid(), req.gid(), ino, fh, offset, data.len(), flags);
         // get the file leader ip.
         let leader: String;
+        let generation: u64;
         let ret = self.handle_cacher.get_handle_info(ino);
         match ret {
             Ok(ret) => {
                 leader = ret.leader;
+                generation = ret.generation;
             }
             Err(err) => {
                 println!("write: file ino: {} is not opened yet, err: {:?}.", ino, err);
@@ -305,9 +370,17 @@ impl Filesystem for Yigfs {
                 return;
             }
         }
+        // the size & mtime are changed by the write, and so are the segments.
+        self.attr_cache.invalidate(ino);
+        self.leader_mgr.invalidate_segments(ino);
         // get the leader.
         let leader_io = self.leader_mgr.get_leader(&leader);
-        let ret = leader_io.write(ino, offset as u64, data);
+        let mut ret = leader_io.write(ino, generation, offset as u64, data);
+        if let Err(Errno::Eunreach) = ret {
+            if let Ok(leader) = self.takeover(ino, generation) {
+                ret = self.leader_mgr.get_leader(&leader).write(ino, generation, offset as u64, data);
+            }
+        }
         match ret {
             Ok(ret) => {
                 reply.written(ret.size);
@@ -316,12 +389,20 @@ impl Filesystem for Yigfs {
             Err(err) => {
                 println!("write: failed to write ino: {}, offset: {}, err: {:?}",
                 ino, offset, err);
-                reply.error(libc::EIO);
+                reply.error(self.to_libc_err(&err));
                 return;
             }
         }
     }
 
+    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        self.flush_ino(ino, reply);
+    }
+
+    fn fsync(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        self.flush_ino(ino, reply);
+    }
+
     fn release(&mut self, req: &Request, ino: u64, fh: u64, flags: u32, lock_owner: u64, flush: bool, reply: ReplyEmpty) {
         println!("release: uid: {}, gid: {}, ino: {}, fh: {}, flags: {}, lock_owner: {}, flush: {}", 
         req.uid(), req.gid(), ino, fh, flags, lock_owner, flush);
@@ -329,7 +410,7 @@ impl Filesystem for Yigfs {
         match ret {
             Ok(ret) => {
                 let leader = self.leader_mgr.get_leader(&ret.leader);
-                let err = leader.close(ino);
+                let err = leader.close(ino, ret.generation);
                 if !err.is_success(){
                     println!("release: failed to close ino: {}, err: {:?}", ino, err);
                 }
@@ -338,6 +419,8 @@ impl Filesystem for Yigfs {
                 println!("release: failed to get handle for ino: {}, err: {:?}", ino, err);
             }
         }
+        self.attr_cache.invalidate(ino);
+        self.leader_mgr.invalidate_segments(ino);
         let err = self.handle_cacher.del_handle_info(ino);
         if !err.is_success() {
             println!("release: failed to del handle for ino: {}, err: {:?}", ino, err);
@@ -347,14 +430,159 @@ impl Filesystem for Yigfs {
 }
 
 impl Yigfs{
-    pub fn create(meta: Rc<dyn MetaServiceMgr>, leader_mgr: LeaderMgr)-> Yigfs{
+    pub fn create(meta: Arc<dyn MetaServiceMgr>, leader_mgr: LeaderMgr)-> Yigfs{
         Yigfs{
             meta_service_mgr: meta,
             leader_mgr: leader_mgr,
             handle_cacher: FileHandleInfoMgr::new(),
+            attr_cache: AttrCache::new(ATTR_CACHE_TTL, ATTR_CACHE_MAX_ENTRIES),
+            read_dir_plus_supp: true,
+            read_file_attrs_supp: true,
             fsid: uuid::uuid_string(),
         }
     }
+
+    // the leader of ino is unreachable, take over the file and serve it locally.
+    fn takeover(&self, ino: u64, generation: u64) -> Result<String, Errno> {
+        let file_leader: FileLeader;
+        let ret = self.meta_service_mgr.get_file_leader(ino);
+        match ret {
+            Ok(ret) => {
+                file_leader = ret;
+            }
+            Err(err) => {
+                println!("takeover: failed to get_file_leader for ino: {}, err: {:?}", ino, err);
+                return Err(err);
+            }
+        }
+        let leader: String;
+        let ret = self.leader_mgr.takeover(ino, generation, &file_leader);
+        match ret {
+            Ok(ret) => {
+                leader = ret;
+            }
+            Err(err) => {
+                println!("takeover: failed to take over ino: {} from {}, err: {:?}", ino, file_leader.leader, err);
+                return Err(err);
+            }
+        }
+        let ret = self.handle_cacher.add_handle_info(FileHandleInfo{
+            ino: ino,
+            generation: generation,
+            leader: leader.clone(),
+        });
+        if !ret.is_success() {
+            println!("takeover: failed to update handle cache for ino: {}, leader: {}", ino, leader);
+            return Err(ret);
+        }
+        Ok(leader)
+    }
+
+    // write the buffered writes of ino into the segments on its leader.
+    fn flush_ino(&self, ino: u64, reply: ReplyEmpty) {
+        let ret = self.handle_cacher.get_handle_info(ino);
+        match ret {
+            Ok(ret) => {
+                let err = self.leader_mgr.get_leader(&ret.leader).flush(ino, ret.generation);
+                if !err.is_success() {
+                    println!("flush_ino: failed to flush ino: {}, err: {:?}", ino, err);
+                    reply.error(self.to_libc_err(&err));
+                    return;
+                }
+                reply.ok();
+            }
+            Err(err) => {
+                println!("flush_ino: file ino: {} is not opened yet, err: {:?}.", ino, err);
+                reply.error(libc::EBADF);
+            }
+        }
+    }
+
+    // read the entries of dir ino, and cache their attrs for the following lookup & getattr.
+    fn read_dir_with_attrs(&mut self, ino: u64, offset: i64) -> Result<(Vec<metaservice_mgr::types::DirEntry>, i64), Errno> {
+        if self.read_dir_plus_supp {
+            let ret = self.meta_service_mgr.read_dir_plus(ino, offset);
+            match ret {
+                Ok(ret) => {
+                    let mut entrys = Vec::new();
+                    for e in ret.0 {
+                        self.attr_cache.put_entry(ino, &e.entry.name, &e.attr);
+                        entrys.push(e.entry);
+                    }
+                    return Ok((entrys, ret.1));
+                }
+                Err(err) => {
+                    if !err.is_enotsupp() {
+                        return Err(err);
+                    }
+                    println!("read_dir_plus is not supported by meta server, fallback to read_dir.");
+                    self.read_dir_plus_supp = false;
+                }
+            }
+        }
+
+        let (entrys, next_offset) = self.meta_service_mgr.read_dir(ino, offset)?;
+        // read the attrs of this page in one request.
+        let mut inos = Vec::new();
+        for e in &entrys {
+            inos.push(e.ino);
+        }
+        if inos.is_empty() || !self.read_file_attrs_supp {
+            return Ok((entrys, next_offset));
+        }
+        let ret = self.meta_service_mgr.read_file_attrs(&inos);
+        match ret {
+            Ok(ret) => {
+                let mut attrs = HashMap::new();
+                for attr in ret {
+                    attrs.insert(attr.ino, attr);
+                }
+                for e in &entrys {
+                    if let Some(attr) = attrs.get(&e.ino) {
+                        self.attr_cache.put_entry(ino, &e.name, attr);
+                    }
+                }
+            }
+            Err(err) => {
+                if err.is_enotsupp() {
+                    println!("read_file_attrs is not supported by meta server, the attrs will be got by lookup.");
+                    self.read_file_attrs_supp = false;
+                    return Ok((entrys, next_offset));
+                }
+                // the attrs will be got by lookup.
+                println!("failed to read_file_attrs for dir ino: {}, offset: {}, err: {:?}", ino, offset, err);
+            }
+        }
+        Ok((entrys, next_offset))
+    }
+
+    // get the generation of ino from attr cache or meta server.
+    fn get_generation(&mut self, ino: u64) -> Result<u64, Errno> {
+        if let Some(attr) = self.attr_cache.get(ino) {
+            return Ok(attr.generation);
+        }
+        let attr = self.meta_service_mgr.read_file_attr(ino)?;
+        self.attr_cache.put(&attr);
+        Ok(attr.generation)
+    }
+
+    fn to_libc_err(&self, err: &Errno) -> c_int {
+        match err {
+            Errno::Estale => {
+                libc::ESTALE
+            }
+            Errno::Eunreach => {
+                libc::EHOSTUNREACH
+            }
+            Errno::Ecorrupt => {
+                libc::EBADMSG
+            }
+            _ => {
+                libc::EIO
+            }
+        }
+    }
+
     fn to_usefs_attr(&self, attr: &metaservice_mgr::types::FileAttr) -> FileAttr {
         FileAttr{
             ino: attr.ino,
@@ -0,0 +1,94 @@
+use std::time::Duration;
+use filesystem_mgr::attr_cache::AttrCache;
+use metaservice_mgr::types::{FileAttr, FileType};
+
+fn new_attr(ino: u64, size: u64) -> FileAttr {
+    FileAttr{
+        ino: ino,
+        generation: 0,
+        size: size,
+        blocks: 0,
+        atime: 0,
+        mtime: 0,
+        ctime: 0,
+        kind: FileType::FILE,
+        perm: 0o644,
+        nlink: 1,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        flags: 0,
+    }
+}
+
+#[test]
+fn test_attr_cache_lookup() -> Result<(), String> {
+    let mut cache = AttrCache::new(Duration::from_secs(60), 100);
+    cache.put_entry(1, &String::from("a.txt"), &new_attr(10, 4096));
+    match cache.lookup(1, &String::from("a.txt")) {
+        Some(attr) => {
+            if attr.ino != 10 || attr.size != 4096 {
+                return Err(format!("got invalid attr: {:?}", attr));
+            }
+        }
+        None => {
+            return Err(format!("a.txt is not found in cache"));
+        }
+    }
+    if cache.lookup(2, &String::from("a.txt")).is_some() {
+        return Err(format!("a.txt is found under the wrong parent"));
+    }
+    if cache.get(10).is_none() {
+        return Err(format!("ino 10 is not found in cache"));
+    }
+    // the entry is still there, but the attr must be reloaded.
+    cache.invalidate(10);
+    if cache.lookup(1, &String::from("a.txt")).is_some() || cache.get(10).is_some() {
+        return Err(format!("ino 10 is still in cache after invalidate"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_attr_cache_expire() -> Result<(), String> {
+    let mut cache = AttrCache::new(Duration::from_millis(10), 100);
+    cache.put(&new_attr(10, 1));
+    std::thread::sleep(Duration::from_millis(20));
+    if cache.get(10).is_some() {
+        return Err(format!("ino 10 is not expired"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_attr_cache_bounded() -> Result<(), String> {
+    let mut cache = AttrCache::new(Duration::from_secs(60), 10);
+    for i in 0..25 {
+        cache.put_entry(1, &format!("file_{}", i), &new_attr(100 + i, i));
+    }
+    if cache.len() > 10 {
+        return Err(format!("cache has {} attrs, exceeds 10", cache.len()));
+    }
+    // the latest one must be cached.
+    if cache.lookup(1, &String::from("file_24")).is_none() {
+        return Err(format!("file_24 is not found in cache"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_attr_cache_dir_parent() -> Result<(), String> {
+    let mut cache = AttrCache::new(Duration::from_secs(60), 100);
+    let mut dir = new_attr(20, 0);
+    dir.kind = FileType::DIR;
+    cache.put_entry(1, &String::from("subdir"), &dir);
+    cache.put_entry(20, &String::from("a.txt"), &new_attr(21, 1));
+    if cache.parent(20) != Some(1) {
+        return Err(format!("got parent: {:?} for dir 20", cache.parent(20)));
+    }
+    // only the parent of dir is tracked.
+    if cache.parent(21).is_some() {
+        return Err(format!("got parent for file 21"));
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use filesystem_mgr::dir_page::{self, DOTDOT_COOKIE};
+use metaservice_mgr::types::{DirEntry, FileType};
+
+// the dir listing of meta server: "ino > cursor order by ino limit page_size",
+// the next cursor is the last listed ino, or the same cursor at the end of dir.
+fn list_dir(rows: &Vec<(u64, &str)>, cursor: i64, page_size: usize) -> (Vec<DirEntry>, i64) {
+    let mut entrys = Vec::new();
+    for (ino, name) in rows {
+        if (*ino as i64) <= cursor {
+            continue;
+        }
+        if entrys.len() == page_size {
+            break;
+        }
+        entrys.push(DirEntry{
+            ino: *ino,
+            file_type: FileType::FILE,
+            name: String::from(*name),
+        });
+    }
+    let mut next_cursor = cursor;
+    if let Some(e) = entrys.last() {
+        next_cursor = e.ino as i64;
+    }
+    (entrys, next_cursor)
+}
+
+// read the whole dir as kernel does: each readdir takes at most reply_size entries,
+// and continues from the cookie of the last one.
+fn read_all(rows: &Vec<(u64, &str)>, page_size: usize, reply_size: usize) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut offset = DOTDOT_COOKIE;
+    loop {
+        let page = dir_page::read_page(dir_page::cursor_of_offset(offset), |c| Ok(list_dir(rows, c, page_size)))
+            .map_err(|err| format!("failed to read_page, err: {:?}", err))?;
+        if page.is_empty() {
+            return Ok(names);
+        }
+        for e in page.iter().take(reply_size) {
+            if e.cookie <= offset {
+                return Err(format!("cookie {} of {} does not move forward from {}", e.cookie, e.entry.name, offset));
+            }
+            names.push(e.entry.name.clone());
+            offset = e.cookie;
+        }
+        if names.len() > rows.len() {
+            return Err(format!("readdir loops, got {} names for {} rows", names.len(), rows.len()));
+        }
+    }
+}
+
+#[test]
+fn test_dir_page_skip_dot_rows() -> Result<(), String> {
+    // the root dir rows stored by meta server.
+    let rows = vec![(1, "."), (2, ".."), (3, "a.txt"), (5, "b.txt")];
+    let page = dir_page::read_page(0, |c| Ok(list_dir(&rows, c, 1000)))
+        .map_err(|err| format!("failed to read_page, err: {:?}", err))?;
+    if page.len() != 2 {
+        return Err(format!("got {} entries, want 2", page.len()));
+    }
+    if page[0].entry.name != "a.txt" || page[0].cookie != 3 + DOTDOT_COOKIE {
+        return Err(format!("got first entry {} with cookie {}", page[0].entry.name, page[0].cookie));
+    }
+    if page[1].entry.name != "b.txt" || page[1].cookie != 5 + DOTDOT_COOKIE {
+        return Err(format!("got second entry {} with cookie {}", page[1].entry.name, page[1].cookie));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dir_page_paging() -> Result<(), String> {
+    let mut rows = vec![(1, "."), (2, "..")];
+    let mut names = Vec::new();
+    for i in 0..50 {
+        names.push(format!("file_{}", i));
+    }
+    for (i, name) in names.iter().enumerate() {
+        // the inos are not contiguous.
+        rows.push(((i as u64) * 3 + 10, name.as_str()));
+    }
+    for (page_size, reply_size) in vec![(1000, 1000), (7, 1000), (7, 3), (2, 1), (1, 1)] {
+        let got = read_all(&rows, page_size, reply_size)?;
+        if got.len() != names.len() {
+            return Err(format!("page_size: {}, reply_size: {}, got {} names, want {}",
+            page_size, reply_size, got.len(), names.len()));
+        }
+        let mut seen = HashSet::new();
+        for (i, name) in got.iter().enumerate() {
+            if *name != names[i] {
+                return Err(format!("page_size: {}, reply_size: {}, got {} at {}, want {}",
+                page_size, reply_size, name, i, names[i]));
+            }
+            if !seen.insert(name.clone()) {
+                return Err(format!("page_size: {}, reply_size: {}, got duplicated {}", page_size, reply_size, name));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dir_page_only_dot_rows() -> Result<(), String> {
+    // the first page has only the dropped rows, the next page must be read.
+    let rows = vec![(1, "."), (2, ".."), (3, "a.txt")];
+    let page = dir_page::read_page(0, |c| Ok(list_dir(&rows, c, 2)))
+        .map_err(|err| format!("failed to read_page, err: {:?}", err))?;
+    if page.len() != 1 || page[0].entry.name != "a.txt" {
+        return Err(format!("got {} entries after the dot rows", page.len()));
+    }
+    // the dir with only the dot rows is empty.
+    let rows = vec![(1, "."), (2, "..")];
+    let page = dir_page::read_page(0, |c| Ok(list_dir(&rows, c, 2)))
+        .map_err(|err| format!("failed to read_page, err: {:?}", err))?;
+    if !page.is_empty() {
+        return Err(format!("got {} entries for the empty root dir", page.len()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dir_page_cursor_of_offset() -> Result<(), String> {
+    for (offset, cursor) in vec![(0, 0), (1, 0), (2, 0), (3, 1), (102, 100)] {
+        if dir_page::cursor_of_offset(offset) != cursor {
+            return Err(format!("got cursor {} for offset {}, want {}", dir_page::cursor_of_offset(offset), offset, cursor));
+        }
+    }
+    Ok(())
+}
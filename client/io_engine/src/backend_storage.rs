@@ -5,12 +5,14 @@ use std::collections::HashMap;
 /*
 * note that BackendStore must be implemented as threadsafe.
 */
-pub trait BackendStore{
+pub trait BackendStore: Send + Sync {
     // return: file size
     fn open(&self, id0: u64, id1: u64) -> Errno;
     fn write(&self, id0: u64, id1: u64, offset: u64, data: &[u8])->Errno;
     fn read(&self, id0: u64, id1: u64, offset: u64, size: u32)->Result<Vec<u8>, Errno>;
     fn close(&self, id0: u64, id1: u64) -> Errno;
+    // remove the object of the segment, it succeeds if the object doesn't exist.
+    fn delete(&self, id0: u64, id1: u64) -> Errno;
 }
 
 pub trait BackendStoreFactory {
@@ -9,9 +9,10 @@ use common::error::Errno;
 use common::runtime::Executor;
 use crossbeam_channel::{Receiver, select};
 
-use crate::types::{MsgFileCloseOp, MsgFileOp, MsgFileOpenOp, 
+use crate::types::{MsgFileCloseOp, MsgFileDelOp, MsgFileOp, MsgFileOpenOp, MsgFileReplaceOp, 
     MsgFileReadData, MsgFileReadOp, MsgFileWriteOp, MsgFileWriteResp};
 use crate::file_handle_ref::FileHandleRef;
+use crate::segment_format::{HEADER_SIZE, SegmentFile, SegmentHeader, SegmentScan, crc32c};
 
 struct DiskIoWorker {
     //id0&id1 -> File
@@ -75,19 +76,31 @@ impl DiskIoWorker {
             MsgFileOp::OpClose(msg) => {
                 self.do_close(msg);
             }
+            MsgFileOp::OpDel(msg) => {
+                self.do_del(msg);
+            }
+            MsgFileOp::OpReplace(msg) => {
+                self.do_replace(msg);
+            }
         }
     }
 
     fn do_open(&mut self, msg: &MsgFileOpenOp){
         let d = NumberOp::to_u128(msg.id0, msg.id1);
-        let name = self.to_file_name(msg.id0, msg.id1, &msg.dir);
-        let f: File;
         // check wether the handle already opened
         if let Some(rh) = self.handles.get_mut(&d) {
             rh.get();
             msg.response(Errno::Esucc);
             return;
         }
+        let ret = self.open_handle(msg.id0, msg.id1, &msg.dir);
+        msg.response(ret);
+    }
+
+    // open the segment file and scan its records, the torn tail left by crash is cut off.
+    fn open_handle(&mut self, id0: u64, id1: u64, dir: &String) -> Errno {
+        let name = self.to_file_name(id0, id1, dir);
+        let mut f: File;
         let ret = OpenOptions::new().create(true).read(true).append(true).open(&name);
         match ret {
             Ok(ret) => {
@@ -95,13 +108,30 @@ impl DiskIoWorker {
             }
             Err(err) => {
                 println!("failed to open({}), err: {}", name, err);
-                msg.response(Errno::Eintr);
-                return;
+                return Errno::Eintr;
+            }
+        }
+        let mut scan: SegmentScan;
+        let ret = SegmentFile::scan_file(&mut f, false);
+        match ret {
+            Ok(ret) => {
+                scan = ret;
+            }
+            Err(err) => {
+                println!("failed to scan({}), err: {}", name, err);
+                return Errno::Eintr;
             }
         }
-        self.handles.insert(d, FileHandleRef::new(f));
-        msg.response(Errno::Esucc);
-        return;
+        if scan.is_torn() {
+            println!("open: cut off the torn tail of {} from {} to {}", name, scan.file_size, scan.valid_size);
+            if let Err(err) = f.set_len(scan.valid_size) {
+                println!("failed to cut off the torn tail of {}, err: {}", name, err);
+                return Errno::Eintr;
+            }
+            scan.file_size = scan.valid_size;
+        }
+        self.handles.insert(NumberOp::to_u128(id0, id1), FileHandleRef::new(f, scan));
+        Errno::Esucc
     }
 
     fn do_write(&mut self, msg: &MsgFileWriteOp) {
@@ -113,34 +143,33 @@ impl DiskIoWorker {
         };
         // open the file first.
         if !self.handles.contains_key(&d) {
-            let name = self.to_file_name(msg.id0, msg.id1, &msg.dir);
-            let ret = OpenOptions::new().create(true).read(true).append(true).open(&name);
-            match ret {
-                Ok(f) => {
-                    self.handles.insert(d, FileHandleRef::new(f));
-                }
-                Err(err) => {
-                    println!("do_write: failed to open({}), err: {}", name, err);
-                    resp_msg.err = Errno::Eintr;
-                    msg.response(resp_msg);
-                    return;
-                }
+            let ret = self.open_handle(msg.id0, msg.id1, &msg.dir);
+            if !ret.is_success() {
+                println!("do_write: failed to open seg(id0: {}, id1: {}), err: {:?}", msg.id0, msg.id1, ret);
+                resp_msg.err = ret;
+                msg.response(resp_msg);
+                return;
             }
         }
 
         if let Some(rf) = self.handles.get_mut(&d) {
-            // should we seek to end before write?
-            let ret = rf.file.seek(SeekFrom::End(0));
-            match ret {
-                Ok(ret) => {
-                    resp_msg.offset = ret;
-                }
-                Err(err) => {
-                    println!("do_write: failed to seek to end for msg({:?}, err: {}", msg, err);
-                    resp_msg.err = Errno::Eseek;
-                    msg.response(resp_msg);
-                    return;
+            if rf.scan.is_raw() {
+                // should we seek to end before write?
+                let ret = rf.file.seek(SeekFrom::End(0));
+                match ret {
+                    Ok(ret) => {
+                        resp_msg.offset = ret;
+                    }
+                    Err(err) => {
+                        println!("do_write: failed to seek to end for msg({:?}, err: {}", msg, err);
+                        resp_msg.err = Errno::Eseek;
+                        msg.response(resp_msg);
+                        return;
+                    }
                 }
+            } else {
+                // the offset in the segment is the size of the data in the records.
+                resp_msg.offset = rf.scan.data_size;
             }
             // check whether segment has enough space for this write.
             if msg.max_size > resp_msg.offset {
@@ -159,16 +188,53 @@ impl DiskIoWorker {
                 msg.response(resp_msg);
                 return;
             }
-            let ret = rf.file.write(msg.data.as_slice());
+            if rf.scan.is_raw() {
+                let ret = rf.file.write(msg.data.as_slice());
+                match ret {
+                    Ok(ret) => {
+                        resp_msg.nwrite = ret as u32;
+                        resp_msg.err = Errno::Esucc;
+                        msg.response(resp_msg);
+                        return;
+                    }
+                    Err(err) => {
+                        println!("failed to write(id0: {}, id1: {}) with offset: {}, err: {}", msg.id0, msg.id1, msg.offset, err);
+                        resp_msg.err = Errno::Eintr;
+                        msg.response(resp_msg);
+                        return;
+                    }
+                }
+            }
+            let mut buf = Vec::<u8>::new();
+            if rf.scan.header.is_none() {
+                // the first write of the new segment file.
+                let header = SegmentHeader::new(msg.id0, msg.id1, msg.max_size);
+                buf = header.encode();
+                rf.scan.header = Some(header);
+                rf.scan.valid_size = HEADER_SIZE;
+            }
+            let (records_buf, records) = SegmentFile::encode_records(msg.ino, msg.offset, rf.scan.data_size,
+                msg.data.as_slice(), rf.scan.valid_size);
+            buf.extend_from_slice(&records_buf);
+            let ret = rf.file.write_all(buf.as_slice());
             match ret {
-                Ok(ret) => {
-                    resp_msg.nwrite = ret as u32;
+                Ok(_) => {
+                    rf.scan.push(records);
+                    resp_msg.nwrite = msg.data.len() as u32;
                     resp_msg.err = Errno::Esucc;
                     msg.response(resp_msg);
                     return;
                 }
                 Err(err) => {
                     println!("failed to write(id0: {}, id1: {}) with offset: {}, err: {}", msg.id0, msg.id1, msg.offset, err);
+                    // drop the part written, so that the next write starts at a record boundary.
+                    if let Err(err) = rf.file.set_len(rf.scan.file_size) {
+                        println!("failed to cut off the partial write of (id0: {}, id1: {}), err: {}", msg.id0, msg.id1, err);
+                    }
+                    if rf.scan.file_size == 0 {
+                        rf.scan.header = None;
+                        rf.scan.valid_size = 0;
+                    }
                     resp_msg.err = Errno::Eintr;
                     msg.response(resp_msg);
                     return;
@@ -186,25 +252,23 @@ impl DiskIoWorker {
         let d = NumberOp::to_u128(msg.id0, msg.id1);
         // open the file first.
         if !self.handles.contains_key(&d) {
-            let name = self.to_file_name(msg.id0, msg.id1, &msg.dir);
-            let ret = OpenOptions::new().create(true).read(true).append(true).open(&name);
-            match ret {
-                Ok(f) => {
-                    self.handles.insert(d, FileHandleRef::new(f));
-                }
-                Err(err) => {
-                    println!("do_write: failed to open({}), err: {}", name, err);
-                    let mut resp_msg = MsgFileReadData{
-                        data: None,
-                        err: Errno::Eintr,
-                    };
-                    resp_msg.err = Errno::Eintr;
-                    msg.response(resp_msg);
-                    return;
-                }
+            let ret = self.open_handle(msg.id0, msg.id1, &msg.dir);
+            if !ret.is_success() {
+                println!("do_read: failed to open seg(id0: {}, id1: {}), err: {:?}", msg.id0, msg.id1, ret);
+                let resp_msg = MsgFileReadData{
+                    data: None,
+                    err: ret,
+                };
+                msg.response(resp_msg);
+                return;
             }
         }
         if let Some(h) = self.handles.get_mut(&d) {
+            if h.scan.header.is_some() {
+                let resp_msg = DiskIoWorker::read_records(h, msg);
+                msg.response(resp_msg);
+                return;
+            }
             let ret = h.file.seek(SeekFrom::Start(msg.offset));
             match ret {
                 Ok(ret) => {
@@ -220,15 +284,15 @@ impl DiskIoWorker {
                     return;
                 }
             }
-            let mut total = 0;
+            let mut total: usize = 0;
             let mut errno = Errno::Esucc;
-            let mut resp_data: Vec<u8> = Vec::new();
-            loop {
-                let mut data: [u8; 4<<10] = [0; 4<<10];
+            // read into the response directly, and never beyond the size wanted.
+            let mut resp_data: Vec<u8> = vec![0; msg.size as usize];
+            while total < resp_data.len() {
                 //On a successful read, the number of read bytes is returned. 
                 //If the supplied buffer is not empty and the function returns Ok(0),
                 //then the source has reached an "end-of-file" event.
-                let ret = h.file.read(&mut data[..]);
+                let ret = h.file.read(&mut resp_data[total..]);
                 match ret {
                     Ok(ret) => {
                         if ret == 0 {
@@ -236,10 +300,8 @@ impl DiskIoWorker {
                             errno = Errno::Eeof;
                             break;
                         }
-                        total += ret as u32;
-                        let vdata = data[..ret].to_vec();
-                        resp_data.extend(vdata);
-                        if total >= msg.size {
+                        total += ret;
+                        if total >= resp_data.len() {
                             println!("do_read: finish to read {} data for {:?}.", total, msg);
                             break;
                         }
@@ -251,6 +313,7 @@ impl DiskIoWorker {
                     }
                 }
             } // loop
+            resp_data.truncate(total);
             if errno.is_eof() || errno.is_success() {
                 let resp_msg = MsgFileReadData{
                     data: Some(resp_data),
@@ -264,6 +327,7 @@ impl DiskIoWorker {
                 err: errno,
             };
             msg.response(resp_msg);
+            return;
         } // if
         // file handle not found.
         println!("do_read: cannot find file handle for id0: {}, id1: {}", msg.id0, msg.id1);
@@ -274,6 +338,102 @@ impl DiskIoWorker {
         msg.response(resp_msg);
     }
 
+    // read the records which cover the range, and check their data against the checksums.
+    // the data is shorter with Eeof if the range is beyond the data of the segment, or reaches
+    // the lost data.
+    fn read_records(h: &mut FileHandleRef, msg: &MsgFileReadOp) -> MsgFileReadData {
+        let end = msg.offset + msg.size as u64;
+        let mut resp_data = Vec::<u8>::with_capacity(msg.size as usize);
+        let mut errno = Errno::Esucc;
+        let mut addr = msg.offset;
+        let mut idx = h.scan.find(addr).unwrap_or(h.scan.records.len());
+        while addr < end {
+            let r = match h.scan.records.get(idx) {
+                Some(r) => r.clone(),
+                None => {
+                    // eof happens.
+                    errno = Errno::Eeof;
+                    break;
+                }
+            };
+            if r.is_lost() {
+                println!("do_read: the record at {} is lost for {:?}", r.pos, msg);
+                errno = Errno::Eeof;
+                break;
+            }
+            let mut data = vec![0u8; r.len as usize];
+            let ret = h.file.seek(SeekFrom::Start(r.data_pos())).and_then(|_| h.file.read_exact(&mut data));
+            if let Err(err) = ret {
+                if err.kind() == std::io::ErrorKind::UnexpectedEof {
+                    println!("do_read: the record at {} is cut short for {:?}", r.pos, msg);
+                    errno = Errno::Eeof;
+                    break;
+                }
+                println!("do_read: failed to read{:?} with err: {}", msg, err);
+                return MsgFileReadData{
+                    data: None,
+                    err: Errno::Eintr,
+                };
+            }
+            if crc32c(&data) != r.crc {
+                println!("do_read: the record(ino: {}, offset: {}, seg_addr: {}, len: {}) at {} fails the checksum for {:?}",
+                r.ino, r.offset, r.seg_addr, r.len, r.pos, msg);
+                return MsgFileReadData{
+                    data: None,
+                    err: Errno::Ecorrupt,
+                };
+            }
+            let start = (addr - r.seg_addr) as usize;
+            let stop = (std::cmp::min(end, r.seg_end_addr()) - r.seg_addr) as usize;
+            resp_data.extend_from_slice(&data[start..stop]);
+            addr = r.seg_addr + stop as u64;
+            idx += 1;
+        }
+        MsgFileReadData{
+            data: Some(resp_data),
+            err: errno,
+        }
+    }
+
+    // close the segment file no matter how many times it is opened, and remove it.
+    fn do_del(&mut self, msg: &MsgFileDelOp) {
+        let id = NumberOp::to_u128(msg.id0, msg.id1);
+        self.handles.remove(&id);
+        let name = self.to_file_name(msg.id0, msg.id1, &msg.dir);
+        let ret = std::fs::remove_file(&name);
+        match ret {
+            Ok(_) => {
+                msg.response(Errno::Esucc);
+            }
+            Err(err) => {
+                if err.kind() == std::io::ErrorKind::NotFound {
+                    msg.response(Errno::Esucc);
+                    return;
+                }
+                println!("do_del: failed to remove {}, err: {}", name, err);
+                msg.response(Errno::Eintr);
+            }
+        }
+    }
+
+    // move the file from over the segment file, the handle of the old file is dropped, so that
+    // the next io opens the new one.
+    fn do_replace(&mut self, msg: &MsgFileReplaceOp) {
+        let id = NumberOp::to_u128(msg.id0, msg.id1);
+        self.handles.remove(&id);
+        let name = self.to_file_name(msg.id0, msg.id1, &msg.dir);
+        let ret = std::fs::rename(&msg.from, &name);
+        match ret {
+            Ok(_) => {
+                msg.response(Errno::Esucc);
+            }
+            Err(err) => {
+                println!("do_replace: failed to move {} to {}, err: {}", msg.from, name, err);
+                msg.response(Errno::Eintr);
+            }
+        }
+    }
+
     fn do_close(&mut self, msg: &MsgFileCloseOp){
         let id = NumberOp::to_u128(msg.id0, msg.id1);
         if let Some(f) = self.handles.get_mut(&id) {
@@ -1,15 +1,19 @@
 use std::fs::File;
+use crate::segment_format::SegmentScan;
 
 pub struct FileHandleRef{
     pub file: File,
     pub handle_ref: Ref,
+    // the format and the records of the segment file.
+    pub scan: SegmentScan,
 }
 
 impl FileHandleRef {
-    pub fn new(f: File) -> Self{
+    pub fn new(f: File, scan: SegmentScan) -> Self{
         FileHandleRef{
             file: f,
             handle_ref: Ref::new(),
+            scan: scan,
         }
     }
 
@@ -5,4 +5,5 @@ pub mod io_thread_pool;
 pub mod disk_io_worker;
 pub mod backend_storage;
 pub mod backend_store_mgr;
+pub mod segment_format;
 mod file_handle_ref;
\ No newline at end of file
@@ -0,0 +1,421 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+// the layout of a segment file, all the integers are little endian:
+// header (64 bytes): magic "YIGFSSEG", version u32, header size u32, id0 u64, id1 u64,
+// max_size u64, reserved, crc32c of the bytes before it u32.
+// record header (40 bytes): magic u32, len u32, ino u64, file offset u64, seg_addr u64,
+// crc32c of the data u32, crc32c of the bytes before it u32, and then len bytes of data.
+// the segment address of a block is the offset of its data among the data of all the records,
+// so the addresses kept in the meta server don't change with the format.
+pub const SEGMENT_MAGIC: &[u8; 8] = b"YIGFSSEG";
+pub const SEGMENT_VERSION: u32 = 1;
+pub const HEADER_SIZE: u64 = 64;
+pub const RECORD_MAGIC: u32 = 0x4b4c4259;
+pub const RECORD_HEADER_SIZE: u64 = 40;
+// the data of a write is split into the records of at most this size.
+pub const RECORD_MAX_DATA: usize = 65536;
+// the ino of the records which take the place of the data lost from the segment, such as the
+// tail cut by crash. the reads stop at them, so the data is read from elsewhere or fails.
+pub const LOST_INO: u64 = u64::MAX;
+
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            if crc & 1 == 1 {
+                crc = (crc >> 1) ^ 0x82F63B78;
+            } else {
+                crc >>= 1;
+            }
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// the crc32c (castagnoli) of data.
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut crc: u32 = !0;
+    for b in data {
+        crc = CRC32C_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+fn get_u32(buf: &[u8], pos: usize) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&buf[pos..pos + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn get_u64(buf: &[u8], pos: usize) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&buf[pos..pos + 8]);
+    u64::from_le_bytes(b)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentHeader {
+    pub version: u32,
+    pub id0: u64,
+    pub id1: u64,
+    pub max_size: u64,
+}
+
+impl SegmentHeader {
+    pub fn new(id0: u64, id1: u64, max_size: u64) -> Self {
+        SegmentHeader{
+            version: SEGMENT_VERSION,
+            id0: id0,
+            id1: id1,
+            max_size: max_size,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(HEADER_SIZE as usize);
+        buf.extend_from_slice(SEGMENT_MAGIC);
+        buf.extend_from_slice(&self.version.to_le_bytes());
+        buf.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        buf.extend_from_slice(&self.id0.to_le_bytes());
+        buf.extend_from_slice(&self.id1.to_le_bytes());
+        buf.extend_from_slice(&self.max_size.to_le_bytes());
+        buf.resize(HEADER_SIZE as usize - 4, 0);
+        let crc = crc32c(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    // decode the header, returns None if buf doesn't start with the magic.
+    pub fn decode(buf: &[u8]) -> Result<Option<SegmentHeader>, String> {
+        if buf.len() < SEGMENT_MAGIC.len() || &buf[0..SEGMENT_MAGIC.len()] != SEGMENT_MAGIC {
+            return Ok(None);
+        }
+        if buf.len() < HEADER_SIZE as usize {
+            return Err(format!("the segment header is cut short at {} bytes", buf.len()));
+        }
+        let end = HEADER_SIZE as usize - 4;
+        if crc32c(&buf[0..end]) != get_u32(buf, end) {
+            return Err(String::from("the segment header fails the checksum"));
+        }
+        let version = get_u32(buf, 8);
+        if version != SEGMENT_VERSION {
+            return Err(format!("unsupported segment version {}", version));
+        }
+        if get_u32(buf, 12) as u64 != HEADER_SIZE {
+            return Err(format!("invalid segment header size {}", get_u32(buf, 12)));
+        }
+        Ok(Some(SegmentHeader{
+            version: version,
+            id0: get_u64(buf, 16),
+            id1: get_u64(buf, 24),
+            max_size: get_u64(buf, 32),
+        }))
+    }
+}
+
+// BlockRecord is the header of a record, the data follows it in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockRecord {
+    pub ino: u64,
+    // the file offset of the data.
+    pub offset: u64,
+    // the segment address of the data.
+    pub seg_addr: u64,
+    pub len: u32,
+    // the crc32c of the data.
+    pub crc: u32,
+    // the position of the record in the segment file.
+    pub pos: u64,
+}
+
+impl BlockRecord {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(RECORD_HEADER_SIZE as usize);
+        buf.extend_from_slice(&RECORD_MAGIC.to_le_bytes());
+        buf.extend_from_slice(&self.len.to_le_bytes());
+        buf.extend_from_slice(&self.ino.to_le_bytes());
+        buf.extend_from_slice(&self.offset.to_le_bytes());
+        buf.extend_from_slice(&self.seg_addr.to_le_bytes());
+        buf.extend_from_slice(&self.crc.to_le_bytes());
+        let crc = crc32c(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    // decode the record header at pos of the file, returns None if it is invalid.
+    pub fn decode(buf: &[u8], pos: u64) -> Option<BlockRecord> {
+        if buf.len() < RECORD_HEADER_SIZE as usize || get_u32(buf, 0) != RECORD_MAGIC {
+            return None;
+        }
+        let end = RECORD_HEADER_SIZE as usize - 4;
+        if crc32c(&buf[0..end]) != get_u32(buf, end) {
+            return None;
+        }
+        Some(BlockRecord{
+            ino: get_u64(buf, 8),
+            offset: get_u64(buf, 16),
+            seg_addr: get_u64(buf, 24),
+            len: get_u32(buf, 4),
+            crc: get_u32(buf, 32),
+            pos: pos,
+        })
+    }
+
+    // the position of the data in the segment file.
+    pub fn data_pos(&self) -> u64 {
+        self.pos + RECORD_HEADER_SIZE
+    }
+
+    // the position right after the record in the segment file.
+    pub fn end_pos(&self) -> u64 {
+        self.data_pos() + self.len as u64
+    }
+
+    // the segment address right after the data.
+    pub fn seg_end_addr(&self) -> u64 {
+        self.seg_addr + self.len as u64
+    }
+
+    // whether the record takes the place of the lost data.
+    pub fn is_lost(&self) -> bool {
+        self.ino == LOST_INO
+    }
+}
+
+// ScannedBlock is a block of a file rebuilt from the contiguous records of the segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedBlock {
+    pub ino: u64,
+    pub offset: u64,
+    pub seg_start_addr: u64,
+    pub seg_end_addr: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SegmentScan {
+    // None for the segment file in the legacy raw format, or the empty file.
+    pub header: Option<SegmentHeader>,
+    // the valid records in order.
+    pub records: Vec<BlockRecord>,
+    // the size of the data in the valid records, the size of the file in the legacy format.
+    pub data_size: u64,
+    // the end of the last valid record in the file.
+    pub valid_size: u64,
+    pub file_size: u64,
+    // the records whose data fails the checksum, they are only checked if verify is set.
+    pub corrupt: Vec<BlockRecord>,
+}
+
+impl SegmentScan {
+    // whether the file has a tail which is not a complete record, such as a write cut by crash.
+    pub fn is_torn(&self) -> bool {
+        self.valid_size < self.file_size
+    }
+
+    // whether the file is in the legacy raw format.
+    pub fn is_raw(&self) -> bool {
+        self.header.is_none() && self.file_size > 0 && !self.is_torn()
+    }
+
+    // the index of the record which contains the segment address, None if it is beyond the data.
+    pub fn find(&self, seg_addr: u64) -> Option<usize> {
+        let idx = self.records.partition_point(|r| r.seg_end_addr() <= seg_addr);
+        if idx < self.records.len() {
+            return Some(idx);
+        }
+        None
+    }
+
+    // add the records appended to the file.
+    pub fn push(&mut self, records: Vec<BlockRecord>) {
+        for r in records {
+            self.data_size = r.seg_end_addr();
+            self.valid_size = r.end_pos();
+            self.records.push(r);
+        }
+        self.file_size = self.valid_size;
+    }
+
+    // the blocks of the files in the segment, the records are merged if they are contiguous in
+    // both the file and the segment. the records filling the holes with zero or the lost data
+    // are skipped.
+    pub fn blocks(&self) -> Vec<ScannedBlock> {
+        let mut blocks = Vec::<ScannedBlock>::new();
+        for r in &self.records {
+            if r.ino == 0 || r.is_lost() {
+                continue;
+            }
+            if let Some(last) = blocks.last_mut() {
+                if last.ino == r.ino && last.seg_end_addr == r.seg_addr
+                    && last.offset + (last.seg_end_addr - last.seg_start_addr) == r.offset {
+                    last.seg_end_addr = r.seg_end_addr();
+                    continue;
+                }
+            }
+            blocks.push(ScannedBlock{
+                ino: r.ino,
+                offset: r.offset,
+                seg_start_addr: r.seg_addr,
+                seg_end_addr: r.seg_end_addr(),
+            });
+        }
+        blocks
+    }
+}
+
+pub struct SegmentFile {}
+
+impl SegmentFile {
+    pub fn scan(path: &String, verify: bool) -> Result<SegmentScan, String> {
+        let mut f = match File::open(path) {
+            Ok(ret) => ret,
+            Err(err) => {
+                return Err(format!("failed to open {}, err: {}", path, err));
+            }
+        };
+        SegmentFile::scan_file(&mut f, verify).map_err(|err| format!("failed to scan {}, err: {}", path, err))
+    }
+
+    // scan the records of the file until the first invalid one. the data of the records is
+    // checked against the checksums if verify is set.
+    pub fn scan_file(f: &mut File, verify: bool) -> Result<SegmentScan, String> {
+        let mut scan: SegmentScan = Default::default();
+        scan.file_size = match f.metadata() {
+            Ok(ret) => ret.len(),
+            Err(err) => {
+                return Err(format!("failed to stat, err: {}", err));
+            }
+        };
+        let mut buf = vec![0u8; std::cmp::min(HEADER_SIZE, scan.file_size) as usize];
+        if let Err(err) = f.seek(SeekFrom::Start(0)).and_then(|_| f.read_exact(&mut buf)) {
+            return Err(format!("failed to read header, err: {}", err));
+        }
+        if buf.len() < HEADER_SIZE as usize {
+            let n = std::cmp::min(buf.len(), SEGMENT_MAGIC.len());
+            if buf[0..n] == SEGMENT_MAGIC[0..n] {
+                // the header itself is cut short.
+                return Ok(scan);
+            }
+        }
+        scan.header = SegmentHeader::decode(&buf)?;
+        if scan.header.is_none() {
+            scan.data_size = scan.file_size;
+            scan.valid_size = scan.file_size;
+            return Ok(scan);
+        }
+        scan.valid_size = HEADER_SIZE;
+        let mut pos = HEADER_SIZE;
+        let mut hbuf = vec![0u8; RECORD_HEADER_SIZE as usize];
+        while pos + RECORD_HEADER_SIZE <= scan.file_size {
+            if let Err(err) = f.seek(SeekFrom::Start(pos)).and_then(|_| f.read_exact(&mut hbuf)) {
+                return Err(format!("failed to read record at {}, err: {}", pos, err));
+            }
+            let r = match BlockRecord::decode(&hbuf, pos) {
+                Some(ret) => ret,
+                None => {
+                    break;
+                }
+            };
+            if r.end_pos() > scan.file_size || r.seg_addr != scan.data_size {
+                break;
+            }
+            if verify {
+                let data = SegmentFile::read_data(f, &r)?;
+                if crc32c(&data) != r.crc {
+                    scan.corrupt.push(r.clone());
+                }
+            }
+            pos = r.end_pos();
+            scan.data_size = r.seg_end_addr();
+            scan.valid_size = pos;
+            scan.records.push(r);
+        }
+        Ok(scan)
+    }
+
+    // read the data of the record without checking it.
+    pub fn read_data(f: &mut File, r: &BlockRecord) -> Result<Vec<u8>, String> {
+        let mut data = vec![0u8; r.len as usize];
+        if let Err(err) = f.seek(SeekFrom::Start(r.data_pos())).and_then(|_| f.read_exact(&mut data)) {
+            return Err(format!("failed to read the data of record at {}, err: {}", r.pos, err));
+        }
+        Ok(data)
+    }
+
+    // encode data of ino at the file offset into the records placed at pos of the segment file
+    // with seg_addr, returns the bytes to append and the records.
+    pub fn encode_records(ino: u64, offset: u64, seg_addr: u64, data: &[u8], pos: u64) -> (Vec<u8>, Vec<BlockRecord>) {
+        let mut buf = Vec::<u8>::with_capacity(data.len() + (data.len() / RECORD_MAX_DATA + 1) * RECORD_HEADER_SIZE as usize);
+        let mut records = Vec::<BlockRecord>::new();
+        for (i, chunk) in data.chunks(RECORD_MAX_DATA).enumerate() {
+            let skip = (i * RECORD_MAX_DATA) as u64;
+            let r = BlockRecord{
+                ino: ino,
+                offset: offset + skip,
+                seg_addr: seg_addr + skip,
+                len: chunk.len() as u32,
+                crc: crc32c(chunk),
+                pos: pos + buf.len() as u64,
+            };
+            buf.extend_from_slice(&r.encode());
+            buf.extend_from_slice(chunk);
+            records.push(r);
+        }
+        (buf, records)
+    }
+
+    // cut off or extend the data of the segment file to size, and sync it. the data is cut at
+    // the segment address, and extended by the lost records, so the lost data is never read as
+    // zero. the file in the legacy format is resized as is.
+    pub fn resize(path: &String, id0: u64, id1: u64, max_size: u64, size: u64) -> Result<(), String> {
+        let mut scan = SegmentFile::scan(path, false)?;
+        let mut f = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(ret) => ret,
+            Err(err) => {
+                return Err(format!("failed to open {}, err: {}", path, err));
+            }
+        };
+        if scan.is_raw() {
+            if let Err(err) = f.set_len(size) {
+                return Err(format!("failed to resize {} to {}, err: {}", path, size, err));
+            }
+            return f.sync_all().map_err(|err| format!("failed to sync {}, err: {}", path, err));
+        }
+        let mut tail = Vec::<u8>::new();
+        let mut cut = scan.valid_size;
+        if size < scan.data_size {
+            // keep the part of the record cut in the middle with a new checksum.
+            let idx = scan.find(size).unwrap_or(scan.records.len());
+            let r = scan.records[idx].clone();
+            cut = r.pos;
+            if size > r.seg_addr {
+                let data = SegmentFile::read_data(&mut f, &r)?;
+                let (buf, _) = SegmentFile::encode_records(r.ino, r.offset, r.seg_addr, &data[0..(size - r.seg_addr) as usize], r.pos);
+                tail = buf;
+            }
+        } else if size > scan.data_size {
+            if scan.header.is_none() {
+                cut = 0;
+                tail = SegmentHeader::new(id0, id1, max_size).encode();
+                scan.data_size = 0;
+            }
+            let zero = vec![0u8; (size - scan.data_size) as usize];
+            let (buf, _) = SegmentFile::encode_records(LOST_INO, 0, scan.data_size, &zero, cut + tail.len() as u64);
+            tail.extend_from_slice(&buf);
+        }
+        let ret = f.set_len(cut).and_then(|_| f.seek(SeekFrom::Start(cut))).and_then(|_| f.write_all(&tail));
+        if let Err(err) = ret {
+            return Err(format!("failed to resize {} to {}, err: {}", path, size, err));
+        }
+        f.sync_all().map_err(|err| format!("failed to sync {}, err: {}", path, err))
+    }
+}
@@ -32,6 +32,42 @@ pub struct MsgFileDelOp {
     pub resp_sender: Sender<Errno>,
 }
 
+impl MsgFileDelOp{
+    pub fn response(&self, err: Errno){
+        let ret = self.resp_sender.send(err);
+        match ret {
+            Ok(_) => {}
+            Err(err) => {
+                println!("failed to send response for del(id0: {}, id1: {}), err: {}",
+                self.id0, self.id1, err);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MsgFileReplaceOp {
+    pub id0: u64,
+    pub id1: u64,
+    pub dir: String,
+    // the file which replaces the segment file.
+    pub from: String,
+    pub resp_sender: Sender<Errno>,
+}
+
+impl MsgFileReplaceOp{
+    pub fn response(&self, err: Errno){
+        let ret = self.resp_sender.send(err);
+        match ret {
+            Ok(_) => {}
+            Err(err) => {
+                println!("failed to send response for replace(id0: {}, id1: {}), err: {}",
+                self.id0, self.id1, err);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MsgFileCloseOp{
     pub id0: u64,
@@ -53,6 +89,8 @@ pub struct MsgFileWriteOp {
     pub dir: String,
     // maximum size of this segment
     pub max_size: u64,
+    // the file which the data belongs to.
+    pub ino: u64,
     // file offset, not the offset in the segment.
     pub offset: u64,
     pub data: Vec<u8>,
@@ -107,5 +145,6 @@ pub enum MsgFileOp {
     OpWrite(MsgFileWriteOp),
     OpRead(MsgFileReadOp),
     OpClose(MsgFileCloseOp),
-    //OpDel(MsgFileDelOp),
+    OpDel(MsgFileDelOp),
+    OpReplace(MsgFileReplaceOp),
 }
\ No newline at end of file
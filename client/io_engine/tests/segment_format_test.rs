@@ -0,0 +1,114 @@
+use std::io::Write;
+use io_engine::segment_format::{HEADER_SIZE, RECORD_HEADER_SIZE, RECORD_MAX_DATA, ScannedBlock,
+    SegmentFile, SegmentHeader, crc32c};
+
+fn new_dir(name: &str) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!("yigfs_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).map_err(|err| format!("failed to create {:?}, err: {}", dir, err))?;
+    Ok(dir.display().to_string())
+}
+
+// write a segment file with the header and the writes of (ino, offset, data).
+fn write_segment(path: &String, writes: &[(u64, u64, Vec<u8>)]) -> Result<(), String> {
+    let mut content = SegmentHeader::new(1, 2, 1 << 20).encode();
+    let mut seg_addr: u64 = 0;
+    for (ino, offset, data) in writes {
+        let (buf, _) = SegmentFile::encode_records(*ino, *offset, seg_addr, data, content.len() as u64);
+        content.extend_from_slice(&buf);
+        seg_addr += data.len() as u64;
+    }
+    std::fs::write(path, content).map_err(|err| format!("failed to write {}, err: {}", path, err))
+}
+
+#[test]
+fn test_segment_format_encode() -> Result<(), String> {
+    if crc32c(b"123456789") != 0xE3069283 {
+        return Err(format!("got crc32c {:x}", crc32c(b"123456789")));
+    }
+    let header = SegmentHeader::new(7, 8, 4096);
+    let buf = header.encode();
+    if buf.len() != HEADER_SIZE as usize {
+        return Err(format!("got header of {} bytes", buf.len()));
+    }
+    if SegmentHeader::decode(&buf)? != Some(header) {
+        return Err(String::from("the header is changed by decode"));
+    }
+    let mut bad = buf.clone();
+    bad[20] ^= 1;
+    if SegmentHeader::decode(&bad).is_ok() {
+        return Err(String::from("the corrupted header is decoded"));
+    }
+    if SegmentHeader::decode(b"hello world")? != None {
+        return Err(String::from("the raw data is decoded as a header"));
+    }
+    // the large write is split into records.
+    let data = vec![1u8; RECORD_MAX_DATA + 10];
+    let (buf, records) = SegmentFile::encode_records(3, 100, 50, &data, HEADER_SIZE);
+    if records.len() != 2 || buf.len() != data.len() + 2 * RECORD_HEADER_SIZE as usize {
+        return Err(format!("got records {:?} in {} bytes", records, buf.len()));
+    }
+    if records[1].offset != 100 + RECORD_MAX_DATA as u64 || records[1].seg_addr != 50 + RECORD_MAX_DATA as u64
+        || records[1].pos != records[0].end_pos() || records[1].len != 10 {
+        return Err(format!("got the second record {:?}", records[1]));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_segment_format_scan() -> Result<(), String> {
+    let dir = new_dir("segment_format_scan")?;
+    let path = format!("{}/1.2.seg", dir);
+    write_segment(&path, &[(5, 0, b"hello ".to_vec()), (5, 6, b"world".to_vec()), (6, 0, b"foo".to_vec())])?;
+    let scan = SegmentFile::scan(&path, true)?;
+    if scan.header.is_none() || scan.records.len() != 3 || scan.data_size != 14 || scan.is_torn() || !scan.corrupt.is_empty() {
+        return Err(format!("got scan {:?}", scan));
+    }
+    // the block map is rebuilt from the records.
+    let want = vec![
+        ScannedBlock{ino: 5, offset: 0, seg_start_addr: 0, seg_end_addr: 11},
+        ScannedBlock{ino: 6, offset: 0, seg_start_addr: 11, seg_end_addr: 14},
+    ];
+    if scan.blocks() != want {
+        return Err(format!("got blocks {:?}", scan.blocks()));
+    }
+
+    // a write cut by crash leaves a torn tail.
+    let mut f = std::fs::OpenOptions::new().append(true).open(&path).map_err(|err| format!("failed to open {}, err: {}", path, err))?;
+    let (buf, _) = SegmentFile::encode_records(6, 3, 14, b"bar", scan.valid_size);
+    f.write_all(&buf[0..buf.len() - 1]).map_err(|err| format!("failed to write {}, err: {}", path, err))?;
+    let scan = SegmentFile::scan(&path, false)?;
+    if !scan.is_torn() || scan.data_size != 14 || scan.records.len() != 3 {
+        return Err(format!("got scan {:?} with the torn tail", scan));
+    }
+
+    // cut in the middle of a record, and then extend with zero.
+    SegmentFile::resize(&path, 1, 2, 1 << 20, 8)?;
+    let scan = SegmentFile::scan(&path, true)?;
+    if scan.is_torn() || scan.data_size != 8 || scan.records.len() != 2 || !scan.corrupt.is_empty() {
+        return Err(format!("got scan {:?} after cut", scan));
+    }
+    SegmentFile::resize(&path, 1, 2, 1 << 20, 20)?;
+    let scan = SegmentFile::scan(&path, true)?;
+    if scan.data_size != 20 || scan.blocks().len() != 1 || !scan.corrupt.is_empty() {
+        return Err(format!("got scan {:?} after extend", scan));
+    }
+    // the extended data is marked lost.
+    if !scan.records.iter().skip(2).all(|r| r.is_lost()) || scan.records[2].seg_addr != 8 {
+        return Err(format!("got records {:?} after extend", scan.records));
+    }
+
+    // the file in the legacy format is kept as is.
+    let legacy = format!("{}/3.4.seg", dir);
+    std::fs::write(&legacy, b"hello world").map_err(|err| format!("failed to write {}, err: {}", legacy, err))?;
+    let scan = SegmentFile::scan(&legacy, true)?;
+    if !scan.is_raw() || scan.data_size != 11 {
+        return Err(format!("got scan {:?} of the legacy file", scan));
+    }
+    SegmentFile::resize(&legacy, 3, 4, 1 << 20, 5)?;
+    if std::fs::read(&legacy).map_err(|err| format!("failed to read {}, err: {}", legacy, err))? != b"hello".to_vec() {
+        return Err(String::from("the legacy file is not cut as is"));
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+use common::config::{MetaServerConfig, S3Config};
+use common::http_client::HttpMethod;
+use common::uuid;
+use s3::signature::{AwsCredentials, SignedRequest};
+
+// the service name used in the credential scope of the v4 signature.
+pub static META_SERVICE_NAME: &str = "yigfs";
+// the headers used by the token auth, the meta server rejects the request
+// if x-yigfs-date is out of its allowed time window or x-yigfs-nonce is replayed.
+pub static HEADER_DATE: &str = "x-yigfs-date";
+pub static HEADER_NONCE: &str = "x-yigfs-nonce";
+pub static HEADER_SIGNATURE: &str = "x-yigfs-signature";
+
+#[derive(Debug, PartialEq)]
+pub enum AuthType {
+    // the requests are sent without any credentials.
+    None,
+    // the requests are signed by aws signature v4 with the ak/sk of the bucket.
+    V4,
+    // the requests carry the bearer token and a hmac of the request made by the token.
+    Token,
+}
+
+impl AuthType {
+    pub fn parse(s: &String) -> Result<AuthType, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "none" => {
+                Ok(AuthType::None)
+            }
+            "v4" => {
+                Ok(AuthType::V4)
+            }
+            "token" => {
+                Ok(AuthType::Token)
+            }
+            _ => {
+                Err(format!("unknown auth_type: {}", s))
+            }
+        }
+    }
+}
+
+pub struct MetaAuth {
+    auth_type: AuthType,
+    region: String,
+    ak: String,
+    sk: String,
+    token: String,
+}
+
+impl MetaAuth {
+    pub fn new(meta_cfg: &MetaServerConfig, s3_cfg: &S3Config) -> Result<MetaAuth, String> {
+        let auth_type = AuthType::parse(&meta_cfg.auth_type)?;
+        match auth_type {
+            AuthType::V4 => {
+                if s3_cfg.ak.is_empty() || s3_cfg.sk.is_empty() {
+                    return Err(format!("v4 auth requires the ak and sk of bucket {}", s3_cfg.bucket));
+                }
+            }
+            AuthType::Token => {
+                if meta_cfg.auth_token.is_empty() {
+                    return Err(format!("token auth requires the auth_token"));
+                }
+            }
+            AuthType::None => {}
+        }
+        Ok(MetaAuth{
+            auth_type: auth_type,
+            region: s3_cfg.region.clone(),
+            ak: s3_cfg.ak.clone(),
+            sk: s3_cfg.sk.clone(),
+            token: meta_cfg.auth_token.clone(),
+        })
+    }
+
+    pub fn auth_type(&self) -> &AuthType {
+        &self.auth_type
+    }
+
+    // generate the headers which authenticate the request.
+    // every call generates the new date & nonce, so the signed headers cannot be replayed.
+    pub fn sign(&self, method: &HttpMethod, url: &String, body: &[u8]) -> Result<BTreeMap<String, Vec<Vec<u8>>>, String> {
+        match self.auth_type {
+            AuthType::None => {
+                Ok(BTreeMap::new())
+            }
+            AuthType::V4 => {
+                self.sign_v4(method, url, body)
+            }
+            AuthType::Token => {
+                self.sign_token(method, url, body)
+            }
+        }
+    }
+
+    fn sign_v4(&self, method: &HttpMethod, url: &String, body: &[u8]) -> Result<BTreeMap<String, Vec<Vec<u8>>>, String> {
+        let (host, path) = MetaAuth::split_url(url)?;
+        let mut request = SignedRequest::new(MetaAuth::method_name(method), META_SERVICE_NAME, &self.region, &path, &host);
+        // the meta server always talks json.
+        request.add_header("content-type", "application/json");
+        request.add_header(HEADER_NONCE, &uuid::uuid_string());
+        let creds = AwsCredentials::new(&self.ak, &self.sk);
+        request.sign(&creds, body);
+        Ok(request.headers)
+    }
+
+    fn sign_token(&self, method: &HttpMethod, url: &String, body: &[u8]) -> Result<BTreeMap<String, Vec<Vec<u8>>>, String> {
+        let (_, path) = MetaAuth::split_url(url)?;
+        let date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let nonce = uuid::uuid_string();
+        let string_to_sign = format!("{}\n{}\n{}\n{}\n{}",
+            MetaAuth::method_name(method), path, date, nonce, hex::encode(Sha256::digest(body)));
+        let mut mac = Hmac::<Sha256>::new_varkey(self.token.as_bytes())
+            .map_err(|err| format!("failed to create hmac, err: {}", err))?;
+        mac.update(string_to_sign.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let mut headers: BTreeMap<String, Vec<Vec<u8>>> = BTreeMap::new();
+        headers.insert(String::from("content-type"), vec![b"application/json".to_vec()]);
+        headers.insert(String::from(HEADER_DATE), vec![date.into_bytes()]);
+        headers.insert(String::from(HEADER_NONCE), vec![nonce.into_bytes()]);
+        headers.insert(String::from(HEADER_SIGNATURE), vec![signature.into_bytes()]);
+        Ok(headers)
+    }
+
+    // split the url into host(with port) and path.
+    fn split_url(url: &String) -> Result<(String, String), String> {
+        let uri: http::Uri;
+        match url.parse::<http::Uri>() {
+            Ok(ret) => {
+                uri = ret;
+            }
+            Err(err) => {
+                return Err(format!("failed to parse url: {}, err: {}", url, err));
+            }
+        }
+        let host: String;
+        match uri.authority() {
+            Some(a) => {
+                host = String::from(a.as_str());
+            }
+            None => {
+                return Err(format!("url {} has no host", url));
+            }
+        }
+        Ok((host, String::from(uri.path())))
+    }
+
+    fn method_name(method: &HttpMethod) -> &'static str {
+        match method {
+            HttpMethod::Get => "GET",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Post => "POST",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Head => "HEAD",
+        }
+    }
+}
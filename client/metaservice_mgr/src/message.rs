@@ -32,6 +32,23 @@ pub struct RespReadDir{
     pub files: Vec<RespDirEntry>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespDirEntryPlus {
+    pub ino: u64,
+    #[serde(rename(serialize = "type", deserialize = "type"))]
+    pub dir_entry_type: u8,
+    #[serde(rename(serialize = "file_name", deserialize = "file_name"))]
+    pub name: String,
+    pub attr: MsgFileAttr,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespReadDirPlus{
+    pub result: RespResult,
+    pub offset: i64,
+    pub files: Vec<RespDirEntryPlus>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ReqDirFileAttr {
     pub region: String,
@@ -98,6 +115,19 @@ pub struct RespFileAttr {
     pub attr: MsgFileAttr,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReqFileAttrs{
+    pub region: String,
+    pub bucket: String,
+    pub inos: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespFileAttrs {
+    pub result: RespResult,
+    pub files: Vec<MsgFileAttr>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ReqMount{
     pub region: String,
@@ -126,6 +156,63 @@ pub struct ReqFileLeader {
 pub struct MsgLeaderInfo {
     pub zone: String,
     pub leader: String,
+    // 0 means the leader has no lease.
+    #[serde(default)]
+    pub lease_epoch: u64,
+    // the remaining seconds of the lease.
+    #[serde(default)]
+    pub lease_ttl: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReqZoneMachines {
+    pub region: String,
+    pub bucket: String,
+    pub zone: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MsgMachineInfo {
+    pub zone: String,
+    pub machine: String,
+    pub status: i32,
+    pub weight: u32,
+    // the number of files led by the machine.
+    #[serde(default)]
+    pub load: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespZoneMachines {
+    pub result: RespResult,
+    #[serde(default)]
+    pub machines: Vec<MsgMachineInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReqFileLease {
+    pub region: String,
+    pub bucket: String,
+    pub zone: String,
+    pub machine: String,
+    pub ino: u64,
+    pub generation: u64,
+    // the epoch of the lease to renew or release, 0 to acquire.
+    #[serde(default)]
+    pub lease_epoch: u64,
+    #[serde(default)]
+    pub lease_secs: u64,
+    // the unreachable leader to transfer the lease from.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub from_leader: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespFileLease {
+    pub result: RespResult,
+    #[serde(default)]
+    pub leader_info: MsgLeaderInfo,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -147,6 +234,10 @@ pub struct ReqFileCreate {
     pub uid: u32,
     pub gid: u32, 
     pub perm: u32,
+    // the machine proposed to lead the new file, the meta server decides if it is empty.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub leader: String,
 }
 
 
@@ -215,6 +306,9 @@ pub struct MsgSegment {
     pub max_size: u64,
     pub leader: String,
     pub blocks: Vec<MsgBlock>,
+    // the data in [0, backend_size) of the segment is uploaded to the backend store.
+    #[serde(default)]
+    pub backend_size: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -246,6 +340,42 @@ pub struct RespGetSegments {
     pub segments: Vec<MsgSegment>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReqSegmentInfo {
+    pub region: String,
+    pub bucket: String,
+    pub zone: String,
+    pub seg_id0: u64,
+    pub seg_id1: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MsgSegmentBlock {
+    pub ino: u64,
+    pub generation: u64,
+    pub offset: u64,
+    pub seg_start_addr: u64,
+    pub seg_end_addr: u64,
+    pub size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RespSegmentInfo {
+    pub result: RespResult,
+    #[serde(default)]
+    pub seg_id0: u64,
+    #[serde(default)]
+    pub seg_id1: u64,
+    #[serde(default)]
+    pub max_size: u64,
+    #[serde(default)]
+    pub leader: String,
+    #[serde(default)]
+    pub backend_size: u64,
+    #[serde(default)]
+    pub blocks: Vec<MsgSegmentBlock>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ReqUpdateSegments{
     pub region: String,
@@ -253,6 +383,9 @@ pub struct ReqUpdateSegments{
     pub zone: String,
     pub ino: u64,
     pub generation: u64,
+    // the meta server rejects the update from the leader whose lease is taken over.
+    #[serde(default)]
+    pub lease_epoch: u64,
     pub segments: Vec<MsgSegment>,
 }
 
@@ -269,6 +402,8 @@ pub struct ReqAddBlock{
     pub machine: String,
     pub ino: u64,
     pub generation: u64,
+    #[serde(default)]
+    pub lease_epoch: u64,
     pub segment: MsgSegment,
 }
 
@@ -1,17 +1,43 @@
-use crate::types::{DirEntry, FileLeader, NewFileInfo, SetFileAttr, Segment};
+use crate::types::{DirEntry, DirEntryPlus, FileLeader, FileLease, MachineInfo, NewFileInfo, SetFileAttr, Segment, SegmentInfo};
 use crate::types::FileAttr;
 use common::error::Errno;
 
-pub trait MetaServiceMgr {
+pub trait MetaServiceMgr: Send + Sync {
     fn mount(&self, uid: u32, gid: u32) -> Result<(), Errno>;
-    fn read_dir(&self, ino: u64, offset: i64)->Result<Vec<DirEntry>, Errno>;
+    // read one page of the entries after the cursor offset, 0 is the beginning of the dir.
+    // returns the entries and the cursor for the next page, the page is empty at the end of dir.
+    fn read_dir(&self, ino: u64, offset: i64)->Result<(Vec<DirEntry>, i64), Errno>;
+    // read the entries of the dir together with their attrs, the same paging as read_dir.
+    // returns Enotsupp if the meta server doesn't support it.
+    fn read_dir_plus(&self, ino: u64, offset: i64)->Result<(Vec<DirEntryPlus>, i64), Errno>;
     fn read_dir_file_attr(&self, ino: u64, name: &String) -> Result<FileAttr, Errno>;
     fn read_file_attr(&self, ino: u64) -> Result<FileAttr, Errno>;
+    // read the attrs of the inos in one request, the missing inos are skipped.
+    fn read_file_attrs(&self, inos: &Vec<u64>) -> Result<Vec<FileAttr>, Errno>;
     fn set_file_attr(&self, attr: &SetFileAttr) -> Result<FileAttr, Errno>;
-    fn new_ino_leader(&self, parent: u64, name: &String, uid: u32, gid: u32, perm: u32) -> Result<NewFileInfo, Errno>;
+    // create the file and propose leader to lead it, the meta server chooses the leader if it is empty.
+    fn new_ino_leader(&self, parent: u64, name: &String, uid: u32, gid: u32, perm: u32, leader: &String) -> Result<NewFileInfo, Errno>;
     fn get_file_leader(&self, ino: u64) -> Result<FileLeader, Errno>;
-    fn get_file_segments(&self, ino: u64, offset: Option<u64>, size: Option<i64>) -> Result<Vec<Segment>, Errno>;
+    // the segment apis take the generation of ino, so that the blocks of a deleted file
+    // are never attached to the new file which reuses the ino.
+    fn get_file_segments(&self, ino: u64, generation: u64, offset: Option<u64>, size: Option<i64>) -> Result<Vec<Segment>, Errno>;
+    // the blocks of all the files in the segment, returns Enoent if no file refers to the segment,
+    // or Enotsupp if the meta server doesn't support it.
+    fn get_segment_info(&self, seg_id0: u64, seg_id1: u64) -> Result<SegmentInfo, Errno>;
     fn get_machine_id(&self) -> String;
-    fn add_file_block(&self, ino: u64, seg: &Segment) -> Errno;
-    fn update_file_segments(&self, ino: u64, segs: &Vec<Segment>) -> Errno;
+    // list the machines of the bucket in all the zones, returns Enotsupp if the meta server doesn't support it.
+    fn get_zone_machines(&self) -> Result<Vec<MachineInfo>, Errno>;
+    // lease_epoch is the epoch of the leader lease, the meta server returns Estale if the lease is taken over.
+    fn add_file_block(&self, ino: u64, generation: u64, lease_epoch: u64, seg: &Segment) -> Errno;
+    fn update_file_segments(&self, ino: u64, generation: u64, lease_epoch: u64, segs: &Vec<Segment>) -> Errno;
+    // acquire the leader lease of ino for this machine. if the lease is held by another machine
+    // and not expired yet, the returned lease shows the current holder.
+    fn acquire_file_lease(&self, ino: u64, generation: u64, lease_secs: u64) -> Result<FileLease, Errno>;
+    // renew the lease, returns Estale if the lease has been taken over by others.
+    fn renew_file_lease(&self, ino: u64, generation: u64, lease_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno>;
+    fn release_file_lease(&self, ino: u64, generation: u64, lease_epoch: u64) -> Errno;
+    // ask the meta server to transfer the leadership of ino from the unreachable from_leader
+    // to this machine. from_epoch is the lease epoch of from_leader, returns Estale if the
+    // leadership has been changed since then.
+    fn transfer_file_leader(&self, ino: u64, generation: u64, from_leader: &String, from_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno>;
 }
\ No newline at end of file
@@ -3,9 +3,10 @@ extern crate tokio;
 mod message;
 
 use std::sync::Arc;
-use crate::{mgr, types::{Block, FileLeader, NewFileInfo, Segment, SetFileAttr}};
-use crate::types::DirEntry;
+use crate::{mgr, types::{Block, FileLeader, FileLease, MachineInfo, NewFileInfo, Segment, SegmentBlock, SegmentInfo, SetFileAttr}};
+use crate::types::{DirEntry, DirEntryPlus};
 use crate::types::FileAttr;
+use crate::auth::MetaAuth;
 use common::http_client;
 use common::http_client::RespText;
 use common::config::Config;
@@ -13,9 +14,17 @@ use common::json;
 use common::error::Errno;
 use common::http_client::HttpMethod;
 use common::runtime::Executor;
-use message::{MsgBlock, MsgFileAttr, MsgSegment, MsgSetFileAttr, ReqAddBlock, ReqDirFileAttr, ReqFileAttr, ReqFileCreate, ReqFileLeader, ReqGetSegments, ReqMount, ReqReadDir, ReqSetFileAttr, RespAddBock, RespDirFileAttr, RespFileAttr, RespFileCreate, RespFileLeader, RespGetSegments, RespReadDir, RespSetFileAttr};
+use message::{MsgBlock, MsgFileAttr, MsgSegment, MsgSetFileAttr, ReqAddBlock, ReqDirFileAttr, ReqFileAttr, ReqFileAttrs, ReqFileCreate, ReqFileLeader, ReqGetSegments, ReqMount, ReqReadDir, ReqSetFileAttr, RespAddBock, RespDirFileAttr, RespFileAttr, RespFileAttrs, RespFileCreate, RespFileLeader, RespGetSegments, RespReadDir, RespReadDirPlus, RespSetFileAttr};
+
+use self::message::{ReqFileLease, ReqSegmentInfo, ReqUpdateSegments, ReqZoneMachines, RespFileLease, RespSegmentInfo, RespUpdateSegments, RespZoneMachines};
+
+// the lease of file leader is held by another machine.
+const ERR_LEASE_HELD: i64 = 40015;
+// the lease epoch is stale, the lease is expired or taken over by another machine.
+const ERR_LEASE_STALE: i64 = 40016;
+// the segment is not referred by any file.
+const ERR_NO_TARGET_SEGMENT: i64 = 40010;
 
-use self::message::{ReqUpdateSegments, RespUpdateSegments};
 pub struct MetaServiceMgrImpl{
     http_client: Arc<http_client::HttpClient>,
     meta_server_url: String,
@@ -24,6 +33,7 @@ pub struct MetaServiceMgrImpl{
     zone: String,
     machine: String,
     exec: Executor,
+    auth: MetaAuth,
 }
 
 impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
@@ -51,7 +61,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         let url = format!("{}/v1/dir", self.meta_server_url);
         let resp : RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &req_json.as_bytes(), &HttpMethod::Put, false));
+        let ret = self.send_request(&url, &req_json.as_bytes(), &HttpMethod::Put);
         match ret {
             Ok(ret) => {
                 resp = ret;
@@ -69,7 +79,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         }
         Ok(())
     }
-    fn read_dir(&self, ino: u64, offset: i64)->Result<Vec<DirEntry>, Errno>{
+    fn read_dir(&self, ino: u64, offset: i64)->Result<(Vec<DirEntry>, i64), Errno>{
         let mut entrys = Vec::new();
         let ret = self.read_dir_files(ino, offset);
         match ret {
@@ -77,7 +87,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
                 if dirs.result.err_code != 0 {
                     if dirs.result.err_code == 40003 {
                         println!("no files found in bucket {} with ino: {}, offset: {}", self.bucket, ino, offset);
-                        return Err(Errno::Enoent);
+                        return Ok((entrys, offset));
                     }
                     println!("got error when read_dir_files for ino: {}, offset: {}, err: {}",
                     ino, offset, dirs.result.err_msg);
@@ -91,7 +101,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
                     };
                     entrys.push(entry);
                 }
-                return Ok(entrys);
+                return Ok((entrys, MetaServiceMgrImpl::next_dir_offset(offset, dirs.offset)));
             }
             Err(error) => {
                 println!("failed to read meta for ino: {}, offset: {}, err: {}",
@@ -101,6 +111,104 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         }
     }
 
+    fn read_dir_plus(&self, ino: u64, offset: i64)->Result<(Vec<DirEntryPlus>, i64), Errno>{
+        let dirs : Box<RespReadDirPlus>;
+        let ret = self.read_dir_files_plus(ino, offset);
+        match ret {
+            Ok(ret) => {
+                dirs = ret;
+            }
+            Err(err) => {
+                if err.is_enotsupp() {
+                    return Err(err);
+                }
+                println!("failed to read_dir_plus for ino: {}, offset: {}, err: {:?}", ino, offset, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if dirs.result.err_code != 0 {
+            if dirs.result.err_code == 40003 {
+                println!("no files found in bucket {} with ino: {}, offset: {}", self.bucket, ino, offset);
+                return Ok((Vec::new(), offset));
+            }
+            println!("got error when read_dir_plus for ino: {}, offset: {}, err: {}",
+            ino, offset, dirs.result.err_msg);
+            return Err(Errno::Eintr);
+        }
+        let mut entrys = Vec::new();
+        for i in &dirs.files {
+            let entry = DirEntryPlus{
+                entry: DirEntry{
+                    ino: i.ino,
+                    file_type: i.dir_entry_type.into(),
+                    name: i.name.clone(),
+                },
+                attr: self.to_file_attr(&i.attr),
+            };
+            entrys.push(entry);
+        }
+        return Ok((entrys, MetaServiceMgrImpl::next_dir_offset(offset, dirs.offset)));
+    }
+
+    fn read_file_attrs(&self, inos: &Vec<u64>) -> Result<Vec<FileAttr>, Errno>{
+        let req = ReqFileAttrs{
+            region: self.region.clone(),
+            bucket: self.bucket.clone(),
+            inos: inos.clone(),
+        };
+        let body: String;
+        let ret = json::encode_to_str::<ReqFileAttrs>(&req);
+        match ret {
+            Ok(ret) => {
+                body = ret;
+            }
+            Err(err) => {
+                println!("read_file_attrs: failed to encode {:?}, err: {}", req, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let url = format!("{}/v1/file/attrs", self.meta_server_url);
+        let resp_text: RespText;
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Get);
+        match ret {
+            Ok(ret) => {
+                resp_text = ret;
+            }
+            Err(err) => {
+                println!("read_file_attrs: failed to send req to {}, err: {}", url, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp_text.status == 404 {
+            return Err(Errno::Enotsupp);
+        }
+        if resp_text.status >= 300 {
+            println!("read_file_attrs: got status: {} for {} inos, resp: {}", resp_text.status, inos.len(), resp_text.body);
+            return Err(Errno::Eintr);
+        }
+        let resp: RespFileAttrs;
+        let ret = json::decode_from_str::<RespFileAttrs>(&resp_text.body);
+        match ret {
+            Ok(ret) => {
+                resp = ret;
+            }
+            Err(err) => {
+                println!("read_file_attrs: got invalid resp: {}, err: {}", resp_text.body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp.result.err_code != 0 {
+            println!("read_file_attrs: failed for {} inos, err_code: {}, err_msg: {}",
+            inos.len(), resp.result.err_code, resp.result.err_msg);
+            return Err(Errno::Eintr);
+        }
+        let mut attrs = Vec::new();
+        for a in &resp.files {
+            attrs.push(self.to_file_attr(a));
+        }
+        Ok(attrs)
+    }
+
     fn read_file_attr(&self, ino: u64) -> Result<FileAttr, Errno>{
         let attr : MsgFileAttr;
         let ret = self.read_file_attr(ino);
@@ -147,7 +255,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         let url = format!("{}/v1/file/attr", self.meta_server_url);
         let resp_text : RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &req_str.as_bytes(), &HttpMethod::Put, false));
+        let ret = self.send_request(&url, &req_str.as_bytes(), &HttpMethod::Put);
         match ret {
             Ok(ret) => {
                 resp_text = ret;
@@ -220,7 +328,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         }
         let url = format!("{}/v1/file/leader", self.meta_server_url);
         let resp : RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &body.as_bytes(), &HttpMethod::Get, false));
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Get);
         match ret {
             Ok(ret) => {
                 resp = ret;
@@ -254,10 +362,12 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
             zone: resp_leader.leader_info.zone,
             leader: resp_leader.leader_info.leader,
             ino: ino,
+            lease_epoch: resp_leader.leader_info.lease_epoch,
+            lease_ttl: resp_leader.leader_info.lease_ttl,
         })
     }
 
-    fn new_ino_leader(&self, parent: u64, name: &String, uid: u32, gid: u32, perm: u32) -> Result<NewFileInfo, Errno> {
+    fn new_ino_leader(&self, parent: u64, name: &String, uid: u32, gid: u32, perm: u32, leader: &String) -> Result<NewFileInfo, Errno> {
         let req_file_create = ReqFileCreate{
             zone: self.zone.clone(),
             machine: self.machine.clone(),
@@ -268,6 +378,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
             uid: uid,
             gid: gid,
             perm: perm,
+            leader: leader.clone(),
         };
         let body : String;
         let ret = json::encode_to_str::<ReqFileCreate>(&req_file_create);
@@ -282,7 +393,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         }
         let url = format!("{}/v1/dir/file", self.meta_server_url);
         let resp: RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &body.as_bytes(), &HttpMethod::Put, false));
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Put);
         match ret {
             Ok(ret) => {
                 resp = ret;
@@ -318,18 +429,20 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
                 zone: resp_file_created.leader_info.zone,
                 leader: resp_file_created.leader_info.leader,
                 ino: resp_file_created.file_info.ino,
+                lease_epoch: resp_file_created.leader_info.lease_epoch,
+                lease_ttl: resp_file_created.leader_info.lease_ttl,
             },
             attr: self.to_file_attr(&resp_file_created.file_info),
         })
     }
 
-    fn get_file_segments(&self, ino: u64, offset: Option<u64>, size: Option<i64>) -> Result<Vec<Segment>, Errno>{
+    fn get_file_segments(&self, ino: u64, generation: u64, offset: Option<u64>, size: Option<i64>) -> Result<Vec<Segment>, Errno>{
         let req_get_segments = ReqGetSegments{
             zone: self.zone.clone(),
             region: self.region.clone(),
             bucket: self.bucket.clone(),
             ino: ino,
-            generation: 0,
+            generation: generation,
             offset: offset,
             size: size,
         };
@@ -346,7 +459,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         }
         let url = format!("{}/v1/file/segments", self.meta_server_url);
         let resp_text: RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &body.as_bytes(), &HttpMethod::Get, false));
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Get);
         match ret  {
             Ok(ret) => {
                 resp_text = ret;
@@ -383,6 +496,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
             segment.seg_id1 = s.seg_id1;
             segment.max_size = s.max_size;
             segment.leader = s.leader;
+            segment.backend_size = s.backend_size;
             for b in s.blocks {
                 let block = Block{
                     offset: b.offset,
@@ -397,17 +511,163 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
         Ok(segments)
     }
 
+    fn get_segment_info(&self, seg_id0: u64, seg_id1: u64) -> Result<SegmentInfo, Errno> {
+        let req = ReqSegmentInfo{
+            region: self.region.clone(),
+            bucket: self.bucket.clone(),
+            zone: self.zone.clone(),
+            seg_id0: seg_id0,
+            seg_id1: seg_id1,
+        };
+        let body: String;
+        let ret = json::encode_to_str::<ReqSegmentInfo>(&req);
+        match ret {
+            Ok(ret) => {
+                body = ret;
+            }
+            Err(err) => {
+                println!("get_segment_info: failed to encode {:?}, err: {}", req, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let url = format!("{}/v1/segment/info", self.meta_server_url);
+        let resp_text: RespText;
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Get);
+        match ret {
+            Ok(ret) => {
+                resp_text = ret;
+            }
+            Err(err) => {
+                println!("get_segment_info: failed to send {}, err: {}", body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp_text.status == 404 {
+            println!("get_segment_info: meta server doesn't support segment info, req: {}", body);
+            return Err(Errno::Enotsupp);
+        }
+        if resp_text.status >= 300 {
+            println!("get_segment_info: got status {} for {}, resp: {}", resp_text.status, body, resp_text.body);
+            return Err(Errno::Eintr);
+        }
+        let resp: RespSegmentInfo;
+        let ret = json::decode_from_str::<RespSegmentInfo>(&resp_text.body);
+        match ret {
+            Ok(ret) => {
+                resp = ret;
+            }
+            Err(err) => {
+                println!("get_segment_info: failed to decode {}, err: {}", resp_text.body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp.result.err_code != 0 {
+            if resp.result.err_code == ERR_NO_TARGET_SEGMENT {
+                return Err(Errno::Enoent);
+            }
+            println!("get_segment_info: failed for {}, err_code: {}, err_msg: {}",
+            body, resp.result.err_code, resp.result.err_msg);
+            return Err(Errno::Eintr);
+        }
+        let mut info = SegmentInfo{
+            seg_id0: resp.seg_id0,
+            seg_id1: resp.seg_id1,
+            max_size: resp.max_size,
+            leader: resp.leader,
+            backend_size: resp.backend_size,
+            blocks: Vec::new(),
+        };
+        for b in resp.blocks {
+            info.blocks.push(SegmentBlock{
+                ino: b.ino,
+                generation: b.generation,
+                offset: b.offset,
+                seg_start_addr: b.seg_start_addr,
+                seg_end_addr: b.seg_end_addr,
+                size: b.size,
+            });
+        }
+        Ok(info)
+    }
+
     fn get_machine_id(&self) -> String {
         self.machine.clone()
     }
 
-    fn add_file_block(&self, ino: u64, seg: &Segment) -> Errno {
+    fn get_zone_machines(&self) -> Result<Vec<MachineInfo>, Errno> {
+        let req = ReqZoneMachines{
+            region: self.region.clone(),
+            bucket: self.bucket.clone(),
+            zone: self.zone.clone(),
+        };
+        let body: String;
+        let ret = json::encode_to_str::<ReqZoneMachines>(&req);
+        match ret {
+            Ok(ret) => {
+                body = ret;
+            }
+            Err(err) => {
+                println!("get_zone_machines: failed to encode {:?}, err: {}", req, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let url = format!("{}/v1/zone/machines", self.meta_server_url);
+        let resp_text: RespText;
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Get);
+        match ret {
+            Ok(ret) => {
+                resp_text = ret;
+            }
+            Err(err) => {
+                println!("get_zone_machines: failed to send {}, err: {}", body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp_text.status == 404 {
+            println!("get_zone_machines: meta server doesn't support listing machines, req: {}", body);
+            return Err(Errno::Enotsupp);
+        }
+        if resp_text.status >= 300 {
+            println!("get_zone_machines: got status {} for {}, resp: {}", resp_text.status, body, resp_text.body);
+            return Err(Errno::Eintr);
+        }
+        let resp: RespZoneMachines;
+        let ret = json::decode_from_str::<RespZoneMachines>(&resp_text.body);
+        match ret {
+            Ok(ret) => {
+                resp = ret;
+            }
+            Err(err) => {
+                println!("get_zone_machines: failed to decode {}, err: {}", resp_text.body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp.result.err_code != 0 {
+            println!("get_zone_machines: failed for {}, err_code: {}, err_msg: {}",
+            body, resp.result.err_code, resp.result.err_msg);
+            return Err(Errno::Eintr);
+        }
+        let mut machines = Vec::<MachineInfo>::new();
+        for m in resp.machines {
+            machines.push(MachineInfo{
+                zone: m.zone,
+                machine: m.machine,
+                status: m.status,
+                weight: m.weight,
+                load: m.load,
+            });
+        }
+        Ok(machines)
+    }
+
+    fn add_file_block(&self, ino: u64, generation: u64, lease_epoch: u64, seg: &Segment) -> Errno {
         let mut s = MsgSegment{
             seg_id0: seg.seg_id0,
             seg_id1: seg.seg_id1,
             max_size: seg.max_size,
             leader: seg.leader.clone(),
             blocks: Vec::new(),
+            backend_size: seg.backend_size,
         };
         for b in &seg.blocks {
             let bl = MsgBlock {
@@ -425,7 +685,8 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
             zone: self.zone.clone(),
             machine: self.machine.clone(),
             ino: ino,
-            generation: 0,
+            generation: generation,
+            lease_epoch: lease_epoch,
             segment: s,
         };
 
@@ -443,7 +704,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         let url = format!("{}/v1/file/block", self.meta_server_url);
         let resp_text: RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &body.as_bytes(), &HttpMethod::Put, false));
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Put);
         match ret {
             Ok(ret) => {
                 resp_text = ret;
@@ -475,12 +736,15 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         if resp.result.err_code != 0 {
             println!("add_file_block: failed to add file block for {}, err: {}", body, resp.result.err_msg);
+            if resp.result.err_code == ERR_LEASE_STALE {
+                return Errno::Estale;
+            }
             return Errno::Eintr;
         }
         return Errno::Esucc;
     }
 
-    fn update_file_segments(&self, ino: u64, segs: &Vec<Segment>) -> Errno{
+    fn update_file_segments(&self, ino: u64, generation: u64, lease_epoch: u64, segs: &Vec<Segment>) -> Errno{
         let mut vs: Vec<MsgSegment> = Vec::new();
         for s in segs {
             vs.push(MetaServiceMgrImpl::to_msg_segment(s));
@@ -491,7 +755,8 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
             bucket: self.bucket.clone(),
             zone: self.zone.clone(),
             ino: ino,
-            generation: 0,
+            generation: generation,
+            lease_epoch: lease_epoch,
             segments: vs,
         };
 
@@ -509,7 +774,7 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         let url = format!("{}/v1/file/segments", self.meta_server_url);
         let resp_text: RespText;
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &body.as_bytes(), &HttpMethod::Put, false));
+        let ret = self.send_request(&url, &body.as_bytes(), &HttpMethod::Put);
         match ret {
             Ok(ret) => {
                 resp_text = ret;
@@ -541,16 +806,44 @@ impl mgr::MetaServiceMgr for MetaServiceMgrImpl{
 
         if resp.result.err_code != 0 {
             println!("update_file_segments: failed to add file block for {}, err: {}", body, resp.result.err_msg);
+            if resp.result.err_code == ERR_LEASE_STALE {
+                return Errno::Estale;
+            }
             return Errno::Eintr;
         }
 
         return Errno::Esucc;
     }
+
+    fn acquire_file_lease(&self, ino: u64, generation: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        self.send_lease_request("lease", ino, generation, 0, lease_secs, &String::new(), &HttpMethod::Put)
+    }
+
+    fn renew_file_lease(&self, ino: u64, generation: u64, lease_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        self.send_lease_request("lease", ino, generation, lease_epoch, lease_secs, &String::new(), &HttpMethod::Post)
+    }
+
+    fn release_file_lease(&self, ino: u64, generation: u64, lease_epoch: u64) -> Errno {
+        let ret = self.send_lease_request("lease", ino, generation, lease_epoch, 0, &String::new(), &HttpMethod::Delete);
+        match ret {
+            Ok(_) => {
+                return Errno::Esucc;
+            }
+            Err(err) => {
+                return err;
+            }
+        }
+    }
+
+    fn transfer_file_leader(&self, ino: u64, generation: u64, from_leader: &String, from_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        self.send_lease_request("transfer", ino, generation, from_epoch, lease_secs, from_leader, &HttpMethod::Post)
+    }
 }
 
 impl MetaServiceMgrImpl {
     pub fn new(meta_cfg: &Config, exec: &Executor) -> Result<MetaServiceMgrImpl, String> {
-        let http_client = Arc::new(http_client::HttpClient::new(3));
+        let http_client = Arc::new(http_client::HttpClient::new_with_tls(3, &meta_cfg.metaserver_config.tls)?);
+        let auth = MetaAuth::new(&meta_cfg.metaserver_config, &meta_cfg.s3_config)?;
         Ok(MetaServiceMgrImpl{
             http_client: http_client,
             meta_server_url: meta_cfg.metaserver_config.meta_server.clone(),
@@ -559,9 +852,115 @@ impl MetaServiceMgrImpl {
             zone: meta_cfg.zone_config.zone.clone(),
             machine: meta_cfg.zone_config.machine.clone(),
             exec: exec.clone(),
+            auth: auth,
         })
     }
 
+    // all the requests to meta server must be sent by this function, so that they are authenticated.
+    fn send_request(&self, url: &String, body: &[u8], method: &HttpMethod) -> Result<RespText, String> {
+        let ret = self.exec.get_runtime().block_on(self.http_client.request_with_signer(url, body, method,
+            || self.auth.sign(method, url, body)));
+        if let Ok(resp) = &ret {
+            if resp.status == 401 || resp.status == 403 {
+                println!("request to {} is rejected by meta server with status: {}, auth_type: {:?}",
+                url, resp.status, self.auth.auth_type());
+            }
+        }
+        return ret;
+    }
+
+    // for /v1/file/leader/lease, PUT acquires, POST renews and DELETE releases the lease of file leader.
+    // POST /v1/file/leader/transfer takes over the lease of the unreachable from_leader.
+    fn send_lease_request(&self, path: &str, ino: u64, generation: u64, lease_epoch: u64, lease_secs: u64,
+        from_leader: &String, method: &HttpMethod) -> Result<FileLease, Errno> {
+        let req = ReqFileLease{
+            region: self.region.clone(),
+            bucket: self.bucket.clone(),
+            zone: self.zone.clone(),
+            machine: self.machine.clone(),
+            ino: ino,
+            generation: generation,
+            lease_epoch: lease_epoch,
+            lease_secs: lease_secs,
+            from_leader: from_leader.clone(),
+        };
+        let body: String;
+        let ret = json::encode_to_str::<ReqFileLease>(&req);
+        match ret {
+            Ok(ret) => {
+                body = ret;
+            }
+            Err(err) => {
+                println!("file_lease: failed to encode req: {:?}, err: {}", req, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let action = match method {
+            _ if path == "transfer" => "transfer",
+            HttpMethod::Put => "acquire",
+            HttpMethod::Post => "renew",
+            _ => "release",
+        };
+        let url = format!("{}/v1/file/leader/{}", self.meta_server_url, path);
+        let resp_text: RespText;
+        let ret = self.send_request(&url, &body.as_bytes(), method);
+        match ret {
+            Ok(ret) => {
+                resp_text = ret;
+            }
+            Err(err) => {
+                println!("file_lease: failed to send {} req to {} with body: {}, err: {}",
+                action, url, body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        if resp_text.status == 404 {
+            println!("file_lease: meta server doesn't support leader lease, req: {}", body);
+            return Err(Errno::Enotsupp);
+        }
+        if resp_text.status >= 300 {
+            println!("file_lease: got status {} for {} req: {}, resp: {}",
+            resp_text.status, action, body, resp_text.body);
+            return Err(Errno::Eintr);
+        }
+        let resp: RespFileLease;
+        let ret = json::decode_from_str::<RespFileLease>(&resp_text.body);
+        match ret {
+            Ok(ret) => {
+                resp = ret;
+            }
+            Err(err) => {
+                println!("file_lease: failed to decode body: {}, err: {}", resp_text.body, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        // the holder of the lease is returned in leader_info if it is held by others.
+        if resp.result.err_code != 0 && resp.result.err_code != ERR_LEASE_HELD {
+            println!("file_lease: failed to {} lease for {}, err_code: {}, err_msg: {}",
+            action, body, resp.result.err_code, resp.result.err_msg);
+            if resp.result.err_code == ERR_LEASE_STALE {
+                return Err(Errno::Estale);
+            }
+            return Err(Errno::Eintr);
+        }
+        Ok(FileLease{
+            ino: ino,
+            generation: generation,
+            leader: resp.leader_info.leader,
+            epoch: resp.leader_info.lease_epoch,
+            ttl: resp.leader_info.lease_ttl,
+        })
+    }
+
+    // the meta server returns the last listed ino as the cursor of the next page.
+    // keep the cursor at the end of dir, so that the listing never goes backward.
+    fn next_dir_offset(offset: i64, resp_offset: i64) -> i64 {
+        if resp_offset > offset {
+            return resp_offset;
+        }
+        return offset;
+    }
+
     fn to_msg_block(b: &Block) -> MsgBlock {
         MsgBlock{
             offset: b.offset,
@@ -578,6 +977,7 @@ impl MetaServiceMgrImpl {
             max_size: s.max_size,
             leader: s.leader.clone(),
             blocks: Vec::new(),
+            backend_size: s.backend_size,
         };
         for b in &s.blocks {
             m.blocks.push(MetaServiceMgrImpl::to_msg_block(b));
@@ -622,7 +1022,7 @@ impl MetaServiceMgrImpl {
         }
         let resp : RespText;
         let url = format!("{}/v1/file/attr", self.meta_server_url);
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &req_body.as_bytes(), &HttpMethod::Get, false));
+        let ret = self.send_request(&url, &req_body.as_bytes(), &HttpMethod::Get);
         match ret {
             Ok(ret) => {
                 resp = ret;
@@ -672,7 +1072,7 @@ impl MetaServiceMgrImpl {
         }
         let resp_text : RespText;
         let url = format!("{}/v1/dir/file/attr", self.meta_server_url);
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &req_child_file_attr_json.as_bytes(), &HttpMethod::Get, false));
+        let ret = self.send_request(&url, &req_child_file_attr_json.as_bytes(), &HttpMethod::Get);
         match ret {
             Ok(resp) => {
                 resp_text = resp;
@@ -722,7 +1122,7 @@ impl MetaServiceMgrImpl {
 
         let resp_body :String;
         let url = format!("{}/v1/dir/files", self.meta_server_url);
-        let ret = self.exec.get_runtime().block_on(self.http_client.request(&url, &req_read_dir_json.as_bytes(), &HttpMethod::Get, false));
+        let ret = self.send_request(&url, &req_read_dir_json.as_bytes(), &HttpMethod::Get);
         match ret {
             Ok(text) => {
                 if text.status >= 300 {
@@ -745,7 +1145,56 @@ impl MetaServiceMgrImpl {
             }
         }
     }
-}
 
+    fn read_dir_files_plus(&self, ino: u64, offset: i64) -> Result<Box<RespReadDirPlus>, Errno>{
+        let req_read_dir = ReqReadDir{
+            region: self.region.clone(),
+            bucket:self.bucket.clone(),
+            ino: ino,
+            offset: offset,
+        };
+        let req_read_dir_json: String;
+        let ret = json::encode_to_str::<ReqReadDir>(&req_read_dir);
+        match ret {
+            Ok(ret) => {
+                req_read_dir_json = ret;
+            }
+            Err(error) => {
+                println!("faied to convert {:?} to json, err: {}", req_read_dir, error);
+                return Err(Errno::Eintr);
+            }
+        }
 
+        let resp_body :String;
+        let url = format!("{}/v1/dir/files/attrs", self.meta_server_url);
+        let ret = self.send_request(&url, &req_read_dir_json.as_bytes(), &HttpMethod::Get);
+        match ret {
+            Ok(text) => {
+                // the old meta server has no such api.
+                if text.status == 404 {
+                    return Err(Errno::Enotsupp);
+                }
+                if text.status >= 300 {
+                    println!("got resp {} from {}, body: {}", text.status, url, text.body);
+                    return Err(Errno::Eintr);
+                }
+                resp_body = text.body;
+            }
+            Err(error) => {
+                println!("failed to get response for {}, err: {}", url, error);
+                return Err(Errno::Eintr);
+            }
+        }
 
+        let resp_read_dir = json::decode_from_str::<RespReadDirPlus>(&resp_body);
+        match resp_read_dir {
+            Ok(resp_read_dir) => {
+                return Ok(Box::new(resp_read_dir));
+            }
+            Err(error) => {
+                println!("failed to decode from {}, err: {}", resp_body, error);
+                return Err(Errno::Eintr);
+            }
+        }
+    }
+}
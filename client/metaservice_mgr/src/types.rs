@@ -33,6 +33,12 @@ pub struct DirEntry{
 }
 
 #[derive (Debug)]
+pub struct DirEntryPlus{
+    pub entry: DirEntry,
+    pub attr: FileAttr,
+}
+
+#[derive (Debug, Clone)]
 pub struct FileAttr {
     /// Inode number
     pub ino: u64,
@@ -69,7 +75,49 @@ pub struct FileLeader {
     pub zone: String,
     pub leader: String,
     pub ino: u64,
+    // the epoch of the leader lease, 0 if the meta server doesn't grant lease.
+    pub lease_epoch: u64,
+    // the remaining seconds of the leader lease.
+    pub lease_ttl: i64,
+}
+
+impl FileLeader {
+    // other machines can take over the file if the lease of leader expires.
+    pub fn is_lease_expired(&self) -> bool {
+        self.lease_epoch > 0 && self.lease_ttl <= 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileLease {
+    pub ino: u64,
+    pub generation: u64,
+    // the machine which holds the lease.
+    pub leader: String,
+    // increased every time the lease is granted to a new leader.
+    pub epoch: u64,
+    // the remaining seconds of the lease.
+    pub ttl: i64,
+}
+// the status of the machine which is up in zone.
+pub const MACHINE_UP: i32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct MachineInfo {
+    pub zone: String,
+    pub machine: String,
+    pub status: i32,
+    pub weight: u32,
+    // the number of files led by the machine.
+    pub load: u64,
+}
+
+impl MachineInfo {
+    pub fn is_up(&self) -> bool {
+        self.status == MACHINE_UP
+    }
 }
+
 #[derive(Debug)]
 pub struct NewFileInfo {
     pub leader_info: FileLeader,
@@ -95,16 +143,18 @@ pub struct SetFileAttr {
     pub gid: Option<u32>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Segment {
     pub seg_id0: u64,
     pub seg_id1: u64,
     pub max_size: u64,
     pub leader: String,
     pub blocks: Vec<Block>,
+    // the data in [0, backend_size) of the segment is sealed and uploaded to the backend store.
+    pub backend_size: u64,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Block {
     pub offset: u64,
     pub seg_start_addr: u64,
@@ -112,3 +162,24 @@ pub struct Block {
     pub size: i64,
 }
 
+// SegmentInfo is the blocks of all the files stored in one segment.
+#[derive(Debug, Default, Clone)]
+pub struct SegmentInfo {
+    pub seg_id0: u64,
+    pub seg_id1: u64,
+    pub max_size: u64,
+    pub leader: String,
+    pub backend_size: u64,
+    pub blocks: Vec<SegmentBlock>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SegmentBlock {
+    pub ino: u64,
+    pub generation: u64,
+    pub offset: u64,
+    pub seg_start_addr: u64,
+    pub seg_end_addr: u64,
+    pub size: i64,
+}
+
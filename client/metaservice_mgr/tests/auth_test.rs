@@ -0,0 +1,108 @@
+use common::config::{MetaServerConfig, S3Config};
+use common::http_client::HttpMethod;
+use metaservice_mgr::auth::{AuthType, MetaAuth, HEADER_NONCE, HEADER_SIGNATURE};
+
+fn new_configs(auth_type: &str, token: &str) -> (MetaServerConfig, S3Config) {
+    let meta_cfg = MetaServerConfig{
+        meta_server: String::from("https://127.0.0.1:9088"),
+        auth_type: String::from(auth_type),
+        auth_token: String::from(token),
+        tls: Default::default(),
+    };
+    let s3_cfg = S3Config{
+        region: String::from("cn-bj-1"),
+        server: String::from("s3.test.com"),
+        bucket: String::from("mybucket"),
+        ak: String::from("hahahaha"),
+        sk: String::from("hehehehe"),
+    };
+    (meta_cfg, s3_cfg)
+}
+
+#[test]
+fn test_auth_none() -> Result<(), String> {
+    let (meta_cfg, s3_cfg) = new_configs("", "");
+    let auth = MetaAuth::new(&meta_cfg, &s3_cfg)?;
+    if *auth.auth_type() != AuthType::None {
+        return Err(format!("got auth_type: {:?} for empty setting", auth.auth_type()));
+    }
+    let headers = auth.sign(&HttpMethod::Get, &meta_cfg.meta_server, b"{}")?;
+    if !headers.is_empty() {
+        return Err(format!("got headers: {:?} for none auth", headers));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_auth_v4() -> Result<(), String> {
+    let (meta_cfg, s3_cfg) = new_configs("v4", "");
+    let auth = MetaAuth::new(&meta_cfg, &s3_cfg)?;
+    let url = format!("{}/v1/file/attr", meta_cfg.meta_server);
+    let headers = auth.sign(&HttpMethod::Put, &url, b"{\"ino\":1}")?;
+    let authorization = headers.get("authorization").ok_or(String::from("no authorization header"))?;
+    let value = String::from_utf8(authorization[0].clone()).map_err(|err| format!("{}", err))?;
+    if !value.starts_with("AWS4-HMAC-SHA256 Credential=hahahaha/") || !value.contains("/yigfs/aws4_request") {
+        return Err(format!("got invalid authorization: {}", value));
+    }
+    // date and nonce must be signed for replay protection.
+    if !value.contains("x-amz-date") || !value.contains(HEADER_NONCE) {
+        return Err(format!("date or nonce is not signed: {}", value));
+    }
+    // the same request must not generate the same signature twice.
+    let again = auth.sign(&HttpMethod::Put, &url, b"{\"ino\":1}")?;
+    if again.get("authorization") == headers.get("authorization") {
+        return Err(format!("got the same signature for two requests"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_auth_v4_requires_keys() -> Result<(), String> {
+    let (meta_cfg, mut s3_cfg) = new_configs("v4", "");
+    s3_cfg.sk = String::new();
+    match MetaAuth::new(&meta_cfg, &s3_cfg) {
+        Ok(_) => {
+            return Err(format!("v4 auth is created without sk"));
+        }
+        Err(_) => {
+            return Ok(());
+        }
+    }
+}
+
+#[test]
+fn test_auth_token() -> Result<(), String> {
+    let (meta_cfg, s3_cfg) = new_configs("token", "my-token");
+    let auth = MetaAuth::new(&meta_cfg, &s3_cfg)?;
+    let url = format!("{}/v1/dir/files", meta_cfg.meta_server);
+    let headers = auth.sign(&HttpMethod::Get, &url, b"{}")?;
+    // the token is the signing key, it must never be sent.
+    if headers.contains_key("authorization") {
+        return Err(format!("got authorization header: {:?}", headers.get("authorization")));
+    }
+    for (k, v) in &headers {
+        for value in v {
+            if String::from_utf8_lossy(value).contains("my-token") {
+                return Err(format!("header {} leaks the token", k));
+            }
+        }
+    }
+    let signature = headers.get(HEADER_SIGNATURE).ok_or(String::from("no signature header"))?;
+    if signature[0].len() != 64 {
+        return Err(format!("got invalid signature: {:?}", signature));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_auth_unknown_type() -> Result<(), String> {
+    let (meta_cfg, s3_cfg) = new_configs("basic", "");
+    match MetaAuth::new(&meta_cfg, &s3_cfg) {
+        Ok(_) => {
+            return Err(format!("unknown auth_type is accepted"));
+        }
+        Err(_) => {
+            return Ok(());
+        }
+    }
+}
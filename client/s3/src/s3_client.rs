@@ -7,6 +7,7 @@ use crate::signature::SignedRequest;
 use crate::types::S3ObjectInfo;
 use crate::types::AppendS3ObjectResp;
 
+use common::config::TlsConfig;
 use common::http_client::HttpClient;
 use common::http_client::HttpMethod;
 use common::error::Errno;
@@ -20,6 +21,10 @@ pub struct S3Client {
     pub ak: String,
     // sk
     pub sk: String,
+    // http or https
+    pub scheme: String,
+    // tls settings for https
+    pub tls: TlsConfig,
 }
 
 impl S3Client {
@@ -29,6 +34,32 @@ impl S3Client {
             endpoint: endpoint.to_string(),
             ak : ak.to_string(),
             sk: sk.to_string(),
+            scheme: String::from("http"),
+            tls: Default::default(),
+        }
+    }
+
+    // create the client which talks to the endpoint by https with the tls settings.
+    pub fn new_with_tls(region: &str, endpoint: &str, ak: &str, sk: &str, tls: &TlsConfig) -> S3Client {
+        S3Client {
+            region: region.to_string(),
+            endpoint: endpoint.to_string(),
+            ak : ak.to_string(),
+            sk: sk.to_string(),
+            scheme: String::from("https"),
+            tls: tls.clone(),
+        }
+    }
+
+    fn new_http_client(&self, retry_times: u32) -> Result<HttpClient, Errno> {
+        match HttpClient::new_with_tls(retry_times, &self.tls) {
+            Ok(ret) => {
+                return Ok(ret);
+            }
+            Err(err) => {
+                println!("Failed to create http client for {}, err: {}", self.endpoint, err);
+                return Err(Errno::Eintr);
+            }
         }
     }
 
@@ -36,7 +67,7 @@ impl S3Client {
         let path = format!("/{}/{}", bucket, object);
 
         // create url
-        let url = String::from("http://") + &self.endpoint + &path;
+        let url = self.scheme.clone() + "://" + &self.endpoint + &path;
 
         let body = Vec::new();
         let aws_credentials = AwsCredentials::new(&self.ak, &self.sk);
@@ -46,7 +77,7 @@ impl S3Client {
 
         // set the head object req header, then send it.
         let retry_times = 3;
-        let mut client = HttpClient::new(retry_times);
+        let mut client = self.new_http_client(retry_times)?;
         client.set_headers(request.headers);
         
         let resp = client.request(&url, &body, &HttpMethod::Head, true).await;
@@ -93,7 +124,7 @@ impl S3Client {
         // create url
         let params = String::from("?append");
         let path = format!("/{}/{}", bucket, object);
-        let url = String::from("http://") + &self.endpoint + &path + &params;
+        let url = self.scheme.clone() + "://" + &self.endpoint + &path + &params;
         // add the position to url
         let final_url = &format!("{}&position={}", url, append_position);
 
@@ -109,7 +140,7 @@ impl S3Client {
 
         // set the append object req header, then send it.
         let retry_times = 3;
-        let mut client = HttpClient::new(retry_times);
+        let mut client = self.new_http_client(retry_times)?;
         client.set_headers(request.headers);
         
         let resp = client.request(&final_url, &body, &HttpMethod::Post, true).await;
@@ -148,7 +179,7 @@ impl S3Client {
         // create url
         let params = String::from("?append");
         let path = format!("/{}/{}", bucket, object);
-        let url = String::from("http://") + &self.endpoint + &path + &params;
+        let url = self.scheme.clone() + "://" + &self.endpoint + &path + &params;
         // add the position to url
         let final_url = &format!("{}&position={}", url, append_position);
 
@@ -164,7 +195,7 @@ impl S3Client {
 
         // set the append object req header, then send it.
         let retry_times = 3;
-        let mut client = HttpClient::new(retry_times);
+        let mut client = self.new_http_client(retry_times)?;
         client.set_headers(request.headers);
 
         let resp = client.request(&final_url, data, &HttpMethod::Post, true).await;
@@ -203,7 +234,7 @@ impl S3Client {
         let path = format!("/{}/{}", bucket, object);
 
         // create url
-        let url = String::from("http://") + &self.endpoint + &path;
+        let url = self.scheme.clone() + "://" + &self.endpoint + &path;
 
         let body = Vec::new();
         let aws_credentials = AwsCredentials::new(&self.ak, &self.sk);
@@ -218,7 +249,7 @@ impl S3Client {
 
         // set the get object req header, then send it.
         let retry_times = 3;
-        let mut client = HttpClient::new(retry_times);
+        let mut client = self.new_http_client(retry_times)?;
         client.set_headers(request.headers);
         
         let resp = client.request(&url, &body, &HttpMethod::Get, true).await;
@@ -243,4 +274,41 @@ impl S3Client {
             }
         }
     }
+
+    // delete the object, it succeeds if the object doesn't exist.
+    pub async fn delete_object(&self, bucket: &str, object: &str) -> Result<(), Errno> {
+        let path = format!("/{}/{}", bucket, object);
+
+        // create url
+        let url = self.scheme.clone() + "://" + &self.endpoint + &path;
+
+        let body = Vec::new();
+        let aws_credentials = AwsCredentials::new(&self.ak, &self.sk);
+        //sign the request
+        let mut request = SignedRequest::new("DELETE", "s3", &self.region, &path, &self.endpoint);
+        request.sign(&aws_credentials, &body);
+
+        // set the delete object req header, then send it.
+        let retry_times = 3;
+        let mut client = self.new_http_client(retry_times)?;
+        client.set_headers(request.headers);
+
+        let resp = client.request(&url, &body, &HttpMethod::Delete, true).await;
+        match resp {
+            Ok(resp) => {
+                if resp.status == 403 {
+                    return Err(Errno::Eaccess)
+                } else if resp.status == 404 {
+                    return Ok(())
+                } else if resp.status >= 300 {
+                    println!("Failed to delete object, resp status is: {}, body is: {}", resp.status, resp.body);
+                    return Err(Errno::Eintr)
+                }
+                return Ok(());
+            }
+            Err(_) => {
+                return Err(Errno::Eintr)
+            }
+        }
+    }
 }
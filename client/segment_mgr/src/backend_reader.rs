@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use common::error::Errno;
+use io_engine::backend_storage::BackendStore;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::Segment;
+use crate::extent_map::{Extent, ExtentMap};
+
+// BackendReader reads the sealed data of the files led by other machines from the
+// backend store directly, without going through their leaders.
+// the segments of a file are got from the meta server on the first read after it is opened,
+// and reused until the file is written or closed on this machine.
+pub struct BackendReader {
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    backend_store: Arc<dyn BackendStore>,
+    // ino -> (generation, segments).
+    segs: Mutex<HashMap<u64, (u64, Arc<Vec<Segment>>)>>,
+}
+
+impl BackendReader {
+    pub fn new(meta_service_mgr: Arc<dyn MetaServiceMgr>, backend_store: Arc<dyn BackendStore>) -> Self {
+        BackendReader{
+            meta_service_mgr: meta_service_mgr,
+            backend_store: backend_store,
+            segs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // drop the cached segments of ino, the next read gets them from the meta server again.
+    pub fn invalidate(&self, ino: u64) {
+        self.segs.lock().unwrap().remove(&ino);
+    }
+
+    fn get_segments(&self, ino: u64, generation: u64) -> Result<Arc<Vec<Segment>>, Errno> {
+        if let Some((g, segs)) = self.segs.lock().unwrap().get(&ino) {
+            if *g == generation {
+                return Ok(segs.clone());
+            }
+        }
+        let ret = self.meta_service_mgr.get_file_segments(ino, generation, None, None);
+        match ret {
+            Ok(ret) => {
+                let segs = Arc::new(ret);
+                self.segs.lock().unwrap().insert(ino, (generation, segs.clone()));
+                Ok(segs)
+            }
+            Err(err) => {
+                println!("get_segments: failed to get_file_segments for ino: {}, err: {:?}", ino, err);
+                Err(err)
+            }
+        }
+    }
+
+    // read the data of [offset, offset + size) until the first byte which is not sealed.
+    // the holes between the sealed extents are filled with zero. the returned data may be
+    // shorter than size, the rest must be read from the leader, because the data written
+    // after the last update of segments is only known by the leader.
+    pub fn read(&self, ino: u64, generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let segs = self.get_segments(ino, generation)?;
+        let extents = BackendReader::resolve(&segs, offset, offset + size as u64);
+        let mut data = Vec::<u8>::new();
+        for (e, sealed_size) in extents {
+            if sealed_size == 0 {
+                break;
+            }
+            // zero-fill the hole before the extent.
+            data.resize((e.start - offset) as usize, 0);
+            let ret = self.backend_store.read(e.id0, e.id1, e.seg_addr, sealed_size as u32);
+            match ret {
+                Ok(ret) => {
+                    let n = ret.len();
+                    data.extend(ret);
+                    if n < sealed_size as usize {
+                        println!("read: got {} bytes for seg(id0: {}, id1: {}) of ino: {} from backend, want: {}",
+                        n, e.id0, e.id1, ino, sealed_size);
+                        break;
+                    }
+                }
+                Err(err) => {
+                    println!("read: failed to read seg(id0: {}, id1: {}) of ino: {} from backend, offset: {}, err: {:?}",
+                    e.id0, e.id1, ino, e.seg_addr, err);
+                    return Err(err);
+                }
+            }
+            // the rest of the extent is not sealed.
+            if sealed_size < e.size() {
+                break;
+            }
+        }
+        Ok(data)
+    }
+
+    // resolve the blocks into the extents visible in [start, end), together with the size of
+    // the sealed part at the beginning of each extent.
+    fn resolve(segs: &Vec<Segment>, start: u64, end: u64) -> Vec<(Extent, u64)> {
+        let mut m = ExtentMap::new();
+        // the segments are in the order they are created, and the blocks in one segment are
+        // appended in the order of writes, so the later blocks shadow the earlier ones.
+        for s in segs {
+            let mut blocks: Vec<_> = s.blocks.iter().filter(|b| b.size > 0).collect();
+            blocks.sort_by(|a, b| a.seg_start_addr.cmp(&b.seg_start_addr));
+            for b in blocks {
+                m.insert(b.offset, b.size as u64, s.seg_id0, s.seg_id1, b.seg_start_addr);
+            }
+        }
+        let mut extents = Vec::<(Extent, u64)>::new();
+        for e in m.lookup(start, end) {
+            let mut sealed_size: u64 = 0;
+            if let Some(s) = segs.iter().find(|s| s.seg_id0 == e.id0 && s.seg_id1 == e.id1) {
+                if s.backend_size > e.seg_addr {
+                    sealed_size = std::cmp::min(s.backend_size - e.seg_addr, e.size());
+                }
+            }
+            extents.push((e, sealed_size));
+        }
+        extents
+    }
+}
@@ -0,0 +1,51 @@
+use crate::extent_map::ExtentMap;
+use crate::types::Segment;
+
+// SegmentUsage is how much of the data written into a segment is still visible in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentUsage {
+    pub id0: u64,
+    pub id1: u64,
+    // the size written into the segment.
+    pub used: u64,
+    // the size not overwritten by the later writes.
+    pub live: u64,
+}
+
+impl SegmentUsage {
+    pub fn live_ratio(&self) -> f64 {
+        if self.used == 0 {
+            return 0.0;
+        }
+        self.live as f64 / self.used as f64
+    }
+}
+
+pub struct Compaction {}
+
+impl Compaction {
+    // the usage of the segments of a file, in the order of the segments.
+    pub fn usage(segs: &Vec<Segment>) -> Vec<SegmentUsage> {
+        let mut usages: Vec<SegmentUsage> = segs.iter().map(|s| SegmentUsage{
+            id0: s.seg_id0,
+            id1: s.seg_id1,
+            used: s.blocks.iter().map(|b| b.seg_end_addr).max().unwrap_or(0),
+            live: 0,
+        }).collect();
+        for e in ExtentMap::from_segments(segs).lookup(0, u64::MAX) {
+            if let Some(u) = usages.iter_mut().find(|u| u.id0 == e.id0 && u.id1 == e.id1) {
+                u.live += e.size();
+            }
+        }
+        usages
+    }
+
+    // the segments whose live ratio is below min_live_ratio. the last segment is still being
+    // written, so it is never chosen.
+    pub fn victims(segs: &Vec<Segment>, min_live_ratio: f64) -> Vec<SegmentUsage> {
+        let mut usages = Compaction::usage(segs);
+        usages.pop();
+        usages.retain(|u| u.live_ratio() < min_live_ratio);
+        usages
+    }
+}
@@ -0,0 +1,224 @@
+extern crate crossbeam_channel;
+
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crossbeam_channel::{Sender, Receiver, bounded, select, tick};
+use common::config::DirHealthConfig;
+use crate::dir_placement::DirUsage;
+
+// the file written and read back by the probe in every data dir.
+pub const PROBE_FILE: &str = ".yigfs_probe";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirState {
+    Healthy = 0,
+    // the free space is below the watermark, no new segment is placed in it.
+    Full = 1,
+    // the probe fails or too many io fail, the dir is not used for the new segments, and the
+    // sealed data in it is read from the backend store.
+    Failed = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirStatus {
+    pub dir: String,
+    pub state: DirState,
+    pub total: u64,
+    pub free: u64,
+    // the io and the failed io since the last check.
+    pub ios: u64,
+    pub errors: u64,
+    // why the dir is not healthy.
+    pub reason: String,
+}
+
+// DirHealth checks the data dirs in the background by a write probe, the free space and the
+// error rate of the io on the segments.
+pub struct DirHealth {
+    min_free_ratio: f64,
+    max_error_ratio: f64,
+    min_ios: u64,
+    interval: Duration,
+    status: Mutex<Vec<DirStatus>>,
+    stop_tx: Mutex<Option<Sender<u32>>>,
+    check_th: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DirHealth {
+    pub fn new(dirs: &Vec<String>, cfg: &DirHealthConfig, min_free_ratio: f64) -> Self {
+        let status = dirs.iter().map(|d| DirStatus{
+            dir: d.clone(),
+            state: DirState::Healthy,
+            total: 0,
+            free: 0,
+            ios: 0,
+            errors: 0,
+            reason: String::new(),
+        }).collect();
+        DirHealth{
+            min_free_ratio: min_free_ratio,
+            max_error_ratio: cfg.max_error_ratio,
+            min_ios: cfg.min_ios,
+            interval: Duration::from_secs(cfg.check_interval_secs),
+            status: Mutex::new(status),
+            stop_tx: Mutex::new(None),
+            check_th: Mutex::new(None),
+        }
+    }
+
+    // start checking the dirs every interval, it is not started if the interval is 0.
+    pub fn start(health: &Arc<DirHealth>) {
+        if health.interval == Duration::from_secs(0) {
+            return;
+        }
+        let (stop_tx, stop_rx) = bounded::<u32>(1);
+        let h = health.clone();
+        *health.stop_tx.lock().unwrap() = Some(stop_tx);
+        *health.check_th.lock().unwrap() = Some(thread::spawn(move || h.work(stop_rx)));
+    }
+
+    pub fn stop(&self) {
+        if let Some(tx) = self.stop_tx.lock().unwrap().take() {
+            let ret = tx.send(1);
+            if let Err(err) = ret {
+                println!("DirHealth: failed to send stop signal, err: {}", err);
+            }
+        }
+        if let Some(th) = self.check_th.lock().unwrap().take() {
+            if th.join().is_err() {
+                println!("DirHealth: failed to join the check thread");
+            }
+        }
+    }
+
+    pub fn is_healthy(&self, idx: usize) -> bool {
+        match self.status.lock().unwrap().get(idx) {
+            Some(s) => s.state == DirState::Healthy,
+            None => false,
+        }
+    }
+
+    // whether the segments in the dir can be read or written, the full dir is still usable.
+    pub fn is_usable(&self, idx: usize) -> bool {
+        match self.status.lock().unwrap().get(idx) {
+            Some(s) => s.state != DirState::Failed,
+            None => false,
+        }
+    }
+
+    pub fn status(&self) -> Vec<DirStatus> {
+        self.status.lock().unwrap().clone()
+    }
+
+    // record the result of an io on the dir, the dir fails once its error rate is too high.
+    pub fn record(&self, idx: usize, ok: bool) {
+        let mut status = self.status.lock().unwrap();
+        if let Some(s) = status.get_mut(idx) {
+            s.ios += 1;
+            if ok {
+                return;
+            }
+            s.errors += 1;
+            if s.state != DirState::Failed && self.too_many_errors(s) {
+                s.state = DirState::Failed;
+                s.reason = format!("{} of {} io failed", s.errors, s.ios);
+                println!("DirHealth: data dir {} fails, {}", s.dir, s.reason);
+            }
+        }
+    }
+
+    // check all the dirs, and start a new window of the error rate.
+    pub fn check(&self) {
+        let dirs: Vec<String> = self.status.lock().unwrap().iter().map(|s| s.dir.clone()).collect();
+        for (idx, dir) in dirs.iter().enumerate() {
+            let probe = DirHealth::probe(dir);
+            let usage = DirUsage::statfs(dir);
+            let mut status = self.status.lock().unwrap();
+            let s = &mut status[idx];
+            let mut state = DirState::Healthy;
+            let mut reason = String::new();
+            if let Ok((total, free)) = &usage {
+                s.total = *total;
+                s.free = *free;
+                if (*free as f64) < *total as f64 * self.min_free_ratio {
+                    state = DirState::Full;
+                    reason = format!("free space {} of {} is below the watermark", free, total);
+                }
+            }
+            if self.too_many_errors(s) {
+                state = DirState::Failed;
+                reason = format!("{} of {} io failed", s.errors, s.ios);
+            }
+            if let Err(err) = usage {
+                state = DirState::Failed;
+                reason = err;
+            }
+            if let Err(err) = probe {
+                state = DirState::Failed;
+                reason = err;
+            }
+            if s.state != state {
+                println!("DirHealth: data dir {} changes from {:?} to {:?}, {}", s.dir, s.state, state, reason);
+            }
+            s.state = state;
+            s.reason = reason;
+            s.ios = 0;
+            s.errors = 0;
+        }
+    }
+
+    // write a small file into dir, sync it and read it back.
+    pub fn probe(dir: &String) -> Result<(), String> {
+        let name = format!("{}/{}", dir, PROBE_FILE);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let content = format!("yigfs probe {}", now);
+        let ret = OpenOptions::new().create(true).read(true).write(true).truncate(true).open(&name).and_then(|mut f| {
+            f.write_all(content.as_bytes())?;
+            f.sync_all()?;
+            f.seek(SeekFrom::Start(0))?;
+            let mut got = String::new();
+            f.read_to_string(&mut got)?;
+            Ok(got)
+        });
+        let _ = fs::remove_file(&name);
+        match ret {
+            Ok(got) => {
+                if got != content {
+                    return Err(format!("probe {} read back {:?}, want {:?}", name, got, content));
+                }
+                Ok(())
+            }
+            Err(err) => Err(format!("probe {} failed, err: {}", name, err)),
+        }
+    }
+
+    fn too_many_errors(&self, s: &DirStatus) -> bool {
+        s.ios >= self.min_ios && s.errors as f64 > s.ios as f64 * self.max_error_ratio
+    }
+
+    fn work(&self, stop_rx: Receiver<u32>) {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(ticker) -> _ => {
+                    self.check();
+                },
+                recv(stop_rx) -> msg => {
+                    match msg {
+                        Ok(_) => {
+                            println!("DirHealth got stop signal, stop the loop...");
+                        }
+                        Err(err) => {
+                            println!("DirHealth recv invalid stop signal with err: {} and stop the loop...", err);
+                        }
+                    }
+                    break;
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+extern crate libc;
+
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CString;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use common::numbers::NumberOp;
+use hash_ring::HashRing;
+
+// DirUsage is the state of a data dir when a new segment is placed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirUsage {
+    // the index of the data dir in segment_configs.
+    pub idx: usize,
+    // the total and free bytes of the file system of the dir, 0 if unknown.
+    pub total: u64,
+    pub free: u64,
+    // the number of segments placed in the dir.
+    pub segments: u64,
+}
+
+impl DirUsage {
+    // the total and free bytes of the file system of dir.
+    pub fn statfs(dir: &String) -> Result<(u64, u64), String> {
+        let path = match CString::new(dir.as_bytes()) {
+            Ok(ret) => ret,
+            Err(err) => {
+                return Err(format!("invalid dir {}, err: {}", dir, err));
+            }
+        };
+        let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+        let ret = unsafe { libc::statvfs(path.as_ptr(), &mut st) };
+        if ret != 0 {
+            return Err(format!("failed to statvfs {}, err: {}", dir, std::io::Error::last_os_error()));
+        }
+        let frsize = st.f_frsize as u64;
+        Ok((st.f_blocks as u64 * frsize, st.f_bavail as u64 * frsize))
+    }
+
+    pub fn used_ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.total - std::cmp::min(self.free, self.total)) as f64 / self.total as f64
+    }
+}
+
+// DirPlacement chooses the data dir of a new segment among the candidates, which are the dirs
+// below their segment number limit and above their free space watermark.
+pub trait DirPlacement: Send + Sync {
+    fn name(&self) -> &str;
+    // returns the idx of the chosen dir, candidates is never empty.
+    fn choose(&self, id0: u64, id1: u64, candidates: &Vec<DirUsage>) -> usize;
+}
+
+// create the placement policy by name: "hash", "weight", "least_used" or "round_robin".
+pub fn new_dir_placement(policy: &str) -> Result<Box<dyn DirPlacement>, String> {
+    match policy {
+        "" | "hash" => Ok(Box::new(HashDirPlacement{})),
+        "weight" => Ok(Box::new(WeightDirPlacement{})),
+        "least_used" => Ok(Box::new(LeastUsedDirPlacement{})),
+        "round_robin" => Ok(Box::new(RoundRobinDirPlacement{
+            next: AtomicUsize::new(0),
+        })),
+        _ => Err(format!("unknown dir placement policy: {}", policy)),
+    }
+}
+
+// HashDirPlacement maps the segment id to the dirs by the consistent hash with equal weights.
+pub struct HashDirPlacement {}
+
+impl HashDirPlacement {
+    // the dir chosen for the segment among dirs.
+    pub fn ring_choose(id0: u64, id1: u64, dirs: Vec<usize>) -> usize {
+        let first = dirs[0];
+        let ring: HashRing<usize> = HashRing::new(dirs, 10);
+        let id = NumberOp::to_u128(id0, id1);
+        match ring.get_node(id.to_string()) {
+            Some(idx) => *idx,
+            None => first,
+        }
+    }
+}
+
+impl DirPlacement for HashDirPlacement {
+    fn name(&self) -> &str {
+        "hash"
+    }
+
+    fn choose(&self, id0: u64, id1: u64, candidates: &Vec<DirUsage>) -> usize {
+        HashDirPlacement::ring_choose(id0, id1, candidates.iter().map(|c| c.idx).collect())
+    }
+}
+
+// WeightDirPlacement spreads the segments by the size of the file systems, so that a 8T disk
+// gets 8 times the segments of a 1T disk.
+pub struct WeightDirPlacement {}
+
+impl DirPlacement for WeightDirPlacement {
+    fn name(&self) -> &str {
+        "weight"
+    }
+
+    fn choose(&self, id0: u64, id1: u64, candidates: &Vec<DirUsage>) -> usize {
+        let mut total: u128 = 0;
+        for c in candidates {
+            total += std::cmp::max(c.total, 1) as u128;
+        }
+        let mut hasher = DefaultHasher::new();
+        id0.hash(&mut hasher);
+        id1.hash(&mut hasher);
+        let mut point = hasher.finish() as u128 % total;
+        for c in candidates {
+            let w = std::cmp::max(c.total, 1) as u128;
+            if point < w {
+                return c.idx;
+            }
+            point -= w;
+        }
+        candidates[candidates.len() - 1].idx
+    }
+}
+
+// LeastUsedDirPlacement chooses the dir with the lowest used ratio of its file system.
+pub struct LeastUsedDirPlacement {}
+
+impl DirPlacement for LeastUsedDirPlacement {
+    fn name(&self) -> &str {
+        "least_used"
+    }
+
+    fn choose(&self, _id0: u64, _id1: u64, candidates: &Vec<DirUsage>) -> usize {
+        let mut best = &candidates[0];
+        for c in candidates {
+            if c.used_ratio() < best.used_ratio() {
+                best = c;
+            }
+        }
+        best.idx
+    }
+}
+
+// RoundRobinDirPlacement chooses the dirs in turn.
+pub struct RoundRobinDirPlacement {
+    next: AtomicUsize,
+}
+
+impl DirPlacement for RoundRobinDirPlacement {
+    fn name(&self) -> &str {
+        "round_robin"
+    }
+
+    fn choose(&self, _id0: u64, _id1: u64, candidates: &Vec<DirUsage>) -> usize {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        candidates[n % candidates.len()].idx
+    }
+}
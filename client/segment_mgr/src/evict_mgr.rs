@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use common::config::LeaderConfig;
+use crate::dir_placement::DirUsage;
+
+// EvictFile is a local segment file which may be evicted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictFile {
+    pub id0: u64,
+    pub id1: u64,
+    // the index of the data dir of the segment.
+    pub dir: usize,
+    pub size: u64,
+    // when the segment is read or written the last time, and how many times it is.
+    pub last_access: SystemTime,
+    pub hits: u64,
+}
+
+// the usage of a data dir by the segment files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirCacheUsage {
+    pub dir: String,
+    // the bytes used and allowed in the dir.
+    pub used: u64,
+    pub limit: u64,
+    pub segments: u64,
+}
+
+impl DirCacheUsage {
+    pub fn used_ratio(&self) -> f64 {
+        if self.limit == 0 {
+            return 0.0;
+        }
+        self.used as f64 / self.limit as f64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EvictReport {
+    // the usage of the dirs before the eviction.
+    pub usage: Vec<DirCacheUsage>,
+    // the (id0, id1) of the evicted segments.
+    pub evicted: Vec<(u64, u64)>,
+    pub bytes: u64,
+}
+
+// EvictPolicy orders the uploaded segments of a dir, the first ones are evicted first.
+pub trait EvictPolicy: Send + Sync {
+    fn name(&self) -> &str;
+    fn sort(&self, files: &mut Vec<EvictFile>);
+}
+
+// create the eviction policy by name: "lru" or "lfu".
+pub fn new_evict_policy(policy: &str) -> Result<Box<dyn EvictPolicy>, String> {
+    match policy {
+        "" | "lru" => Ok(Box::new(LruEvictPolicy{})),
+        "lfu" => Ok(Box::new(LfuEvictPolicy{})),
+        _ => Err(format!("unknown evict policy: {}", policy)),
+    }
+}
+
+// LruEvictPolicy evicts the segments least recently used first.
+pub struct LruEvictPolicy {}
+
+impl EvictPolicy for LruEvictPolicy {
+    fn name(&self) -> &str {
+        "lru"
+    }
+
+    fn sort(&self, files: &mut Vec<EvictFile>) {
+        files.sort_by_key(|f| f.last_access);
+    }
+}
+
+// LfuEvictPolicy evicts the segments least frequently used first, and the least recently used
+// first among the ones used as often.
+pub struct LfuEvictPolicy {}
+
+impl EvictPolicy for LfuEvictPolicy {
+    fn name(&self) -> &str {
+        "lfu"
+    }
+
+    fn sort(&self, files: &mut Vec<EvictFile>) {
+        files.sort_by_key(|f| (f.hits, f.last_access));
+    }
+}
+
+// EvictMgr tracks the access of the local segments, and chooses the segments to evict from the
+// data dirs above the high watermark. only the segments in the backend store are evicted, they
+// are fetched back when they are read again.
+pub struct EvictMgr {
+    policy: Box<dyn EvictPolicy>,
+    capacity: u64,
+    high_watermark: f64,
+    low_watermark: f64,
+    // (id0, id1) -> (last access, hits).
+    access: Mutex<HashMap<(u64, u64), (SystemTime, u64)>>,
+}
+
+impl EvictMgr {
+    pub fn new(cfg: &LeaderConfig) -> Self {
+        let policy = match new_evict_policy(&cfg.evict_policy) {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("EvictMgr: {}, evict the segments by lru", err);
+                Box::new(LruEvictPolicy{})
+            }
+        };
+        EvictMgr{
+            policy: policy,
+            capacity: cfg.evict_capacity,
+            high_watermark: cfg.evict_high_watermark,
+            low_watermark: cfg.evict_low_watermark,
+            access: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &str {
+        self.policy.name()
+    }
+
+    // record a read or write of the segment.
+    pub fn access(&self, id0: u64, id1: u64) {
+        let mut access = self.access.lock().unwrap();
+        let a = access.entry((id0, id1)).or_insert((SystemTime::now(), 0));
+        a.0 = SystemTime::now();
+        a.1 += 1;
+    }
+
+    // the segment is removed, its access is dropped.
+    pub fn forget(&self, id0: u64, id1: u64) {
+        self.access.lock().unwrap().remove(&(id0, id1));
+    }
+
+    // fill the access of the file, the file not accessed since start is used when it is modified.
+    pub fn fill_access(&self, f: &mut EvictFile) {
+        if let Some((last_access, hits)) = self.access.lock().unwrap().get(&(f.id0, f.id1)) {
+            f.last_access = *last_access;
+            f.hits = *hits;
+        }
+    }
+
+    // the usage of the dirs by files. the limit of a dir is the capacity if it is set, and the
+    // usage of its file system is used otherwise.
+    pub fn usage(&self, dirs: &Vec<String>, files: &Vec<EvictFile>) -> Vec<DirCacheUsage> {
+        let mut usage: Vec<DirCacheUsage> = dirs.iter().map(|d| DirCacheUsage{
+            dir: d.clone(),
+            ..Default::default()
+        }).collect();
+        for f in files {
+            if let Some(u) = usage.get_mut(f.dir) {
+                u.used += f.size;
+                u.segments += 1;
+            }
+        }
+        if self.capacity > 0 {
+            for u in usage.iter_mut() {
+                u.limit = self.capacity;
+            }
+            return usage;
+        }
+        for u in usage.iter_mut() {
+            match DirUsage::statfs(&u.dir) {
+                Ok((total, free)) => {
+                    u.limit = total;
+                    u.used = total - std::cmp::min(free, total);
+                }
+                Err(err) => {
+                    println!("EvictMgr: {}", err);
+                }
+            }
+        }
+        usage
+    }
+
+    // choose the files to evict from the dirs whose usage is above the high watermark, until the
+    // usage is below the low watermark. the files are skipped if evictable returns false.
+    pub fn plan<F: Fn(&EvictFile) -> bool>(&self, usage: &Vec<DirCacheUsage>, files: &Vec<EvictFile>, evictable: F) -> Vec<EvictFile> {
+        let mut victims = Vec::<EvictFile>::new();
+        for (idx, u) in usage.iter().enumerate() {
+            if u.limit == 0 || u.used_ratio() <= self.high_watermark {
+                continue;
+            }
+            let target = (u.limit as f64 * self.low_watermark) as u64;
+            let mut candidates: Vec<EvictFile> = files.iter().filter(|f| f.dir == idx).cloned().collect();
+            self.policy.sort(&mut candidates);
+            let mut used = u.used;
+            for f in candidates {
+                if used <= target {
+                    break;
+                }
+                if !evictable(&f) {
+                    continue;
+                }
+                used -= std::cmp::min(f.size, used);
+                victims.push(f);
+            }
+            if used > target {
+                println!("EvictMgr: dir {} is still above the watermark with {} of {} bytes used, no more segments can be evicted",
+                u.dir, used, u.limit);
+            }
+        }
+        victims
+    }
+}
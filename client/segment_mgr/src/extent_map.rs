@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use crate::types::Segment;
+
+// Extent maps [start, end) of the file to [seg_addr, seg_addr + end - start) of the segment (id0, id1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extent {
+    pub start: u64,
+    pub end: u64,
+    pub id0: u64,
+    pub id1: u64,
+    pub seg_addr: u64,
+}
+
+impl Extent {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    // the part of the extent in [start, end), it must overlap with the extent.
+    fn clip(&self, start: u64, end: u64) -> Extent {
+        let s = std::cmp::max(self.start, start);
+        let e = std::cmp::min(self.end, end);
+        Extent{
+            start: s,
+            end: e,
+            id0: self.id0,
+            id1: self.id1,
+            seg_addr: self.seg_addr + (s - self.start),
+        }
+    }
+
+    // whether e follows this extent both in the file and in the same segment.
+    fn is_followed_by(&self, e: &Extent) -> bool {
+        self.end == e.start && self.id0 == e.id0 && self.id1 == e.id1 && self.seg_addr + self.size() == e.seg_addr
+    }
+}
+
+// FileExtents is the extents of a file in the range being read.
+#[derive(Debug, Default)]
+pub struct FileExtents {
+    pub generation: u64,
+    // the end of the last extent of the file, the holes before it are read as zero.
+    pub size: u64,
+    pub extents: Vec<Extent>,
+}
+
+// ExtentMap keeps the non-overlapping extents of a file keyed by their start offset.
+// the later writes shadow the earlier ones, and the ranges without extent are holes.
+#[derive(Debug, Default, Clone)]
+pub struct ExtentMap {
+    extents: BTreeMap<u64, Extent>,
+}
+
+impl ExtentMap {
+    pub fn new() -> Self {
+        ExtentMap{
+            extents: BTreeMap::new(),
+        }
+    }
+
+    // the segments are in the order they are created, and the blocks in one segment are
+    // appended in the order of writes, so the later blocks shadow the earlier ones.
+    pub fn from_segments(segs: &Vec<Segment>) -> Self {
+        let mut m = ExtentMap::new();
+        for s in segs {
+            let mut blocks: Vec<_> = s.blocks.iter().filter(|b| b.size > 0).collect();
+            blocks.sort_by(|a, b| a.seg_start_addr.cmp(&b.seg_start_addr));
+            for b in blocks {
+                m.insert(b.offset, b.size as u64, s.seg_id0, s.seg_id1, b.seg_start_addr);
+            }
+        }
+        m
+    }
+
+    // map [start, start + size) of the file to seg_addr of the segment (id0, id1).
+    pub fn insert(&mut self, start: u64, size: u64, id0: u64, id1: u64, seg_addr: u64) {
+        if size == 0 {
+            return;
+        }
+        let end = start + size;
+        let mut new_extent = Extent{
+            start: start,
+            end: end,
+            id0: id0,
+            id1: id1,
+            seg_addr: seg_addr,
+        };
+        // remove the overlapped extents, and keep their parts outside of [start, end).
+        let overlapped = self.overlapped_keys(start, end);
+        for k in overlapped {
+            if let Some(e) = self.extents.remove(&k) {
+                if e.start < start {
+                    let left = e.clip(e.start, start);
+                    self.extents.insert(left.start, left);
+                }
+                if e.end > end {
+                    let right = e.clip(end, e.end);
+                    self.extents.insert(right.start, right);
+                }
+            }
+        }
+        // merge with the neighbours which are continuous in the same segment.
+        let prev = self.extents.range(..start).next_back().map(|(k, e)| (*k, e.is_followed_by(&new_extent)));
+        if let Some((k, true)) = prev {
+            if let Some(e) = self.extents.remove(&k) {
+                new_extent.start = e.start;
+                new_extent.seg_addr = e.seg_addr;
+            }
+        }
+        let next = self.extents.get(&end).map(|e| new_extent.is_followed_by(e));
+        if let Some(true) = next {
+            if let Some(e) = self.extents.remove(&end) {
+                new_extent.end = e.end;
+            }
+        }
+        self.extents.insert(new_extent.start, new_extent);
+    }
+
+    // the extents in [start, end) sorted by the file offset, the extents are clipped
+    // to the range, and the gaps between them are holes.
+    pub fn lookup(&self, start: u64, end: u64) -> Vec<Extent> {
+        let mut v = Vec::<Extent>::new();
+        for k in self.overlapped_keys(start, end) {
+            if let Some(e) = self.extents.get(&k) {
+                v.push(e.clip(start, end));
+            }
+        }
+        v
+    }
+
+    // the end of the last extent.
+    pub fn size(&self) -> u64 {
+        match self.extents.values().next_back() {
+            Some(e) => e.end,
+            None => 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.extents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extents.is_empty()
+    }
+
+    fn overlapped_keys(&self, start: u64, end: u64) -> Vec<u64> {
+        let mut keys = Vec::<u64>::new();
+        if start >= end {
+            return keys;
+        }
+        // the extent before start may cover it.
+        if let Some((k, e)) = self.extents.range(..start).next_back() {
+            if e.end > start {
+                keys.push(*k);
+            }
+        }
+        for (k, _) in self.extents.range(start..end) {
+            keys.push(*k);
+        }
+        keys
+    }
+}
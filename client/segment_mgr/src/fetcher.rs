@@ -0,0 +1,196 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use common::error::Errno;
+use io_engine::backend_storage::BackendStore;
+use io_engine::segment_format::{SegmentFile, SegmentHeader};
+use crate::types::Segment;
+
+// the sealed data is fetched from the backend store in chunks of this size.
+const FETCH_CHUNK: u64 = 4 << 20;
+
+#[derive(Debug, Clone, Default)]
+pub struct FetchStats {
+    // the segments fetched, and the bytes of them.
+    pub fetched: u64,
+    pub bytes: u64,
+    pub failed: u64,
+}
+
+// SegmentFetcher fetches the sealed data of the segments which are missing locally from the
+// backend store back into their data dirs. the concurrent fetches of one segment are done once,
+// the others wait for it and then read the fetched file.
+pub struct SegmentFetcher {
+    backend_store: Arc<dyn BackendStore>,
+    fetching: Mutex<HashMap<(u64, u64), Arc<Mutex<()>>>>,
+    stats: Mutex<FetchStats>,
+}
+
+impl SegmentFetcher {
+    pub fn new(backend_store: Arc<dyn BackendStore>) -> Self {
+        SegmentFetcher{
+            backend_store: backend_store,
+            fetching: Mutex::new(HashMap::new()),
+            stats: Mutex::new(FetchStats::default()),
+        }
+    }
+
+    // fetch [0, backend_size) of seg of ino into the segment file at path, unless the file has
+    // it already. the data is written into a temporary file in the segment format first, and then
+    // moved over path by install.
+    pub fn fetch<F: Fn(&String) -> Errno>(&self, ino: u64, seg: &Segment, backend_size: u64, path: &String, install: F) -> Errno {
+        let key = (seg.seg_id0, seg.seg_id1);
+        let lock = self.fetching.lock().unwrap().entry(key).or_insert_with(|| Arc::new(Mutex::new(()))).clone();
+        let ret = {
+            let _g = lock.lock().unwrap();
+            self.fetch_locked(ino, seg, backend_size, path, install)
+        };
+        // the last one waiting for the segment removes its entry.
+        let mut fetching = self.fetching.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            fetching.remove(&key);
+        }
+        ret
+    }
+
+    pub fn stats(&self) -> FetchStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn fetch_locked<F: Fn(&String) -> Errno>(&self, ino: u64, seg: &Segment, backend_size: u64, path: &String, install: F) -> Errno {
+        let mut local: u64 = 0;
+        if Path::new(path).is_file() {
+            match SegmentFile::scan(path, false) {
+                Ok(scan) => {
+                    local = scan.data_size;
+                }
+                Err(err) => {
+                    println!("fetch: failed to scan the local file, err: {}", err);
+                    return Errno::Eintr;
+                }
+            }
+        }
+        // fetched by the one before.
+        if local >= backend_size {
+            return Errno::Esucc;
+        }
+        // the local file has data which may not be in the backend store, it is never replaced.
+        if local > 0 {
+            println!("fetch: {} has {} bytes of the sealed {} bytes, it is not fetched", path, local, backend_size);
+            return Errno::Eexists;
+        }
+        let ret = self.download(ino, seg, backend_size, path, install);
+        let mut stats = self.stats.lock().unwrap();
+        if ret.is_success() {
+            stats.fetched += 1;
+            stats.bytes += backend_size;
+            println!("fetch: fetched {} bytes of segment(id0: {}, id1: {}) of ino: {} into {}",
+            backend_size, seg.seg_id0, seg.seg_id1, ino, path);
+        } else {
+            stats.failed += 1;
+        }
+        ret
+    }
+
+    // the chunks are encoded and written into the temporary file as they are read, so the
+    // segment is never held in memory as a whole.
+    fn download<F: Fn(&String) -> Errno>(&self, ino: u64, seg: &Segment, backend_size: u64, path: &String, install: F) -> Errno {
+        let tmp = format!("{}.fetch", path);
+        let mut f = match File::create(&tmp) {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("fetch: failed to create {}, err: {}", tmp, err);
+                return Errno::Eintr;
+            }
+        };
+        let ret = self.write_fetched(&mut f, ino, seg, backend_size, &tmp);
+        if !ret.is_success() {
+            let _ = fs::remove_file(&tmp);
+            return ret;
+        }
+        let ret = install(&tmp);
+        if !ret.is_success() {
+            let _ = fs::remove_file(&tmp);
+        }
+        ret
+    }
+
+    fn write_fetched(&self, f: &mut File, ino: u64, seg: &Segment, backend_size: u64, tmp: &String) -> Errno {
+        let header = SegmentHeader::new(seg.seg_id0, seg.seg_id1, seg.max_size).encode();
+        if let Err(err) = f.write_all(&header) {
+            println!("fetch: failed to write {}, err: {}", tmp, err);
+            return Errno::Eintr;
+        }
+        let mut file_pos = header.len() as u64;
+        let blocks = SegmentFetcher::sorted_blocks(seg);
+        let mut pos: u64 = 0;
+        while pos < backend_size {
+            let n = cmp::min(FETCH_CHUNK, backend_size - pos);
+            let ret = self.backend_store.read(seg.seg_id0, seg.seg_id1, pos, n as u32);
+            let d = match ret {
+                Ok(d) => d,
+                Err(err) => {
+                    println!("fetch: failed to read segment(id0: {}, id1: {}) at {} from backend store, err: {:?}",
+                    seg.seg_id0, seg.seg_id1, pos, err);
+                    return err;
+                }
+            };
+            if d.len() as u64 != n {
+                println!("fetch: segment(id0: {}, id1: {}) in backend store is truncated, want [{}, {}), got {} bytes",
+                seg.seg_id0, seg.seg_id1, pos, pos + n, d.len());
+                return Errno::Eintr;
+            }
+            let content = SegmentFetcher::encode_range(ino, &blocks, pos, &d, file_pos);
+            if let Err(err) = f.write_all(&content) {
+                println!("fetch: failed to write {}, err: {}", tmp, err);
+                return Errno::Eintr;
+            }
+            file_pos += content.len() as u64;
+            pos += n;
+        }
+        if let Err(err) = f.sync_all() {
+            println!("fetch: failed to sync {}, err: {}", tmp, err);
+            return Errno::Eintr;
+        }
+        Errno::Esucc
+    }
+
+    // the blocks of the segment as (seg_start_addr, seg_end_addr, offset) in the order of the
+    // segment addresses.
+    fn sorted_blocks(seg: &Segment) -> Vec<(u64, u64, u64)> {
+        let mut blocks: Vec<(u64, u64, u64)> = seg.blocks.iter().map(|b| (b.seg_start_addr, b.seg_end_addr, b.offset)).collect();
+        blocks.sort();
+        blocks
+    }
+
+    // encode the data at the segment address start into the records placed at pos of the file,
+    // with the records of the blocks, the data not referred by the blocks goes into the records of
+    // ino 0.
+    fn encode_range(ino: u64, blocks: &Vec<(u64, u64, u64)>, start: u64, data: &[u8], pos: u64) -> Vec<u8> {
+        let mut content = Vec::<u8>::new();
+        let end = start + data.len() as u64;
+        let mut addr = start;
+        for (b_start, b_stop, offset) in blocks {
+            let from = cmp::max(addr, *b_start);
+            let to = cmp::min(*b_stop, end);
+            if from >= to {
+                continue;
+            }
+            if addr < from {
+                let (buf, _) = SegmentFile::encode_records(0, 0, addr, &data[(addr - start) as usize..(from - start) as usize], pos + content.len() as u64);
+                content.extend_from_slice(&buf);
+            }
+            let (buf, _) = SegmentFile::encode_records(ino, offset + from - b_start, from, &data[(from - start) as usize..(to - start) as usize], pos + content.len() as u64);
+            content.extend_from_slice(&buf);
+            addr = to;
+        }
+        if addr < end {
+            let (buf, _) = SegmentFile::encode_records(0, 0, addr, &data[(addr - start) as usize..], pos + content.len() as u64);
+            content.extend_from_slice(&buf);
+        }
+        content
+    }
+}
@@ -1,18 +1,21 @@
 extern crate crossbeam_channel;
 
 use std::collections::HashMap;
+use std::sync::Mutex;
 use std::thread;
 use std::thread::JoinHandle;
 use crossbeam_channel::{Sender, Receiver, bounded, select};
 use common::error::Errno;
 use common::defer;
-use crate::types::{Block, FileHandle, MsgAddBlock, MsgAddSegment, MsgFileHandleOp, MsgGetLastSegment, MsgQueryHandle, Segment};
+use crate::types::{Block, FileHandle, MsgAddBlock, MsgAddSegment, MsgDelSegments, MsgFileHandleOp, MsgSetBackendSize, MsgGetExtents,
+    MsgGetLastSegment, MsgQueryHandle, Segment};
+use crate::extent_map::{ExtentMap, FileExtents};
 
 pub struct FileHandleMgr {
     //for update file handle.
     handle_op_tx: Sender<MsgFileHandleOp>,
     stop_tx: Sender<u32>,
-    handle_mgr_th: Option<JoinHandle<()>>,
+    handle_mgr_th: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl FileHandleMgr {
@@ -22,6 +25,7 @@ impl FileHandleMgr {
         
         let mut handle_mgr = HandleMgr{
             handles: HashMap::<u64, FileHandle>::new(),
+            extents: HashMap::<u64, ExtentMap>::new(),
             handle_op_rx: rx,
             stop_rx: stop_rx,
         };
@@ -29,13 +33,13 @@ impl FileHandleMgr {
         let mgr = FileHandleMgr{
             handle_op_tx: tx,
             stop_tx: stop_tx,
-            handle_mgr_th: Some(thread::spawn(move || handle_mgr.start())),
+            handle_mgr_th: Mutex::new(Some(thread::spawn(move || handle_mgr.start()))),
         };
 
         return mgr;
     }
 
-    pub fn stop(&mut self){
+    pub fn stop(&self){
         let ret = self.stop_tx.send(1);
         match ret {
             Ok(_) => {}
@@ -44,7 +48,7 @@ impl FileHandleMgr {
             }
         }
         // join the HandleMgr thread.
-        if let Some(h) = self.handle_mgr_th.take() {
+        if let Some(h) = self.handle_mgr_th.lock().unwrap().take() {
             let ret = h.join();
             match ret {
                 Ok(_) => {
@@ -113,6 +117,68 @@ impl FileHandleMgr {
         }
     }
 
+    // remove the segments from the handle of ino, the extents in them must be shadowed by others.
+    pub fn del_segments(&self, ino: u64, segs: &Vec<(u64, u64)>) -> Errno {
+        let msg = MsgFileHandleOp::DelSegments(MsgDelSegments{
+            ino: ino,
+            segs: segs.clone(),
+        });
+        let ret = self.handle_op_tx.send(msg);
+        match ret {
+            Ok(_) => {
+                return Errno::Esucc;
+            }
+            Err(err) => {
+                println!("failed to del segments for ino: {}, err: {}", ino, err);
+                return Errno::Eintr;
+            }
+        }
+    }
+
+    // set the backend_size of the segment in the handle of ino after it is uploaded.
+    pub fn set_backend_size(&self, ino: u64, id0: u64, id1: u64, backend_size: u64) -> Errno {
+        let msg = MsgFileHandleOp::SetBackendSize(MsgSetBackendSize{
+            ino: ino,
+            id0: id0,
+            id1: id1,
+            backend_size: backend_size,
+        });
+        let ret = self.handle_op_tx.send(msg);
+        match ret {
+            Ok(_) => {
+                return Errno::Esucc;
+            }
+            Err(err) => {
+                println!("failed to set backend_size of segment(id0: {}, id1: {}) for ino: {}, err: {}",
+                id0, id1, ino, err);
+                return Errno::Eintr;
+            }
+        }
+    }
+
+    // the inos of all the handles.
+    pub fn get_inos(&self) -> Result<Vec<u64>, Errno> {
+        let (tx, rx) = bounded::<Vec<u64>>(1);
+        let ret = self.handle_op_tx.send(MsgFileHandleOp::GetInos(tx));
+        match ret {
+            Ok(_) => {}
+            Err(err) => {
+                println!("get_inos: failed to send query, err: {}", err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                return Ok(ret);
+            }
+            Err(err) => {
+                println!("get_inos: failed to recv inos, err: {}", err);
+                return Err(Errno::Eintr);
+            }
+        }
+    }
+
     pub fn del(&self, ino: u64) -> Errno {
         let msg = MsgFileHandleOp::Del(ino);
         let ret = self.handle_op_tx.send(msg);
@@ -127,7 +193,8 @@ impl FileHandleMgr {
         }
     }
 
-    // Vec[0]: id0; Vec[1]: id1; Vec[2]: max_size of segment.
+    // Vec[0]: id0; Vec[1]: id1; Vec[2]: max_size of segment; Vec[3]: generation of the handle;
+    // Vec[4]: lease epoch of the handle.
     pub fn get_last_segment(&self, ino: u64) -> Result<Vec<u64>, Errno> {
         let (tx, rx) = bounded::<Vec<u64>>(1);
         let query = MsgGetLastSegment{
@@ -159,6 +226,46 @@ impl FileHandleMgr {
         }
     }
 
+    // returns the extents of ino in [offset, offset + size).
+    pub fn get_extents(&self, ino: u64, offset: u64, size: u32) -> Result<FileExtents, Errno> {
+        let (tx, rx) = bounded::<Option<FileExtents>>(1);
+        let query = MsgGetExtents{
+            ino: ino,
+            offset: offset,
+            size: size,
+            tx: tx,
+        };
+        defer!{
+            let rxc = rx.clone();
+            drop(rxc);
+        };
+        let ret = self.handle_op_tx.send(MsgFileHandleOp::GetExtents(query));
+        match ret {
+            Ok(_) => {}
+            Err(err) => {
+                println!("get_extents: failed to send query for ino: {}, err: {}", ino, err);
+                return Err(Errno::Eintr);
+            }
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                match ret {
+                    Some(ret) => {
+                        return Ok(ret);
+                    }
+                    None => {
+                        return Err(Errno::Enoent);
+                    }
+                }
+            }
+            Err(err) => {
+                println!("get_extents: failed to recv extents for ino: {}, err: {}", ino, err);
+                return Err(Errno::Eintr);
+            }
+        }
+    }
+
     pub fn get(&self, ino: u64) -> Result<FileHandle, Errno>{
         let (tx, rx) = bounded::<Option<FileHandle>>(1);
         let query = MsgQueryHandle{
@@ -231,6 +338,8 @@ impl FileHandleMgr {
 struct HandleMgr {
     // ino-->FileHandle
     handles: HashMap<u64, FileHandle>,
+    // ino-->the extents of the file, it is updated together with the handle.
+    extents: HashMap<u64, ExtentMap>,
     handle_op_rx: Receiver<MsgFileHandleOp>,
     stop_rx: Receiver<u32>,
 }
@@ -268,7 +377,22 @@ impl HandleMgr {
                         }
                         MsgFileHandleOp::GetLastSegment(m) => {
                             self.get_last_segment(&m);
-                        }                        
+                        }
+                        MsgFileHandleOp::GetExtents(m) => {
+                            self.get_extents(&m);
+                        }
+                        MsgFileHandleOp::DelSegments(m) => {
+                            self.del_segments(&m);
+                        }
+                        MsgFileHandleOp::SetBackendSize(m) => {
+                            self.set_backend_size(&m);
+                        }
+                        MsgFileHandleOp::GetInos(tx) => {
+                            let inos: Vec<u64> = self.handles.keys().cloned().collect();
+                            if let Err(err) = tx.send(inos) {
+                                println!("get_inos: failed to send inos, err: {}", err);
+                            }
+                        }
                     }
                 },
                 recv(self.stop_rx) -> msg => {
@@ -292,6 +416,7 @@ impl HandleMgr {
     }
 
     fn add(&mut self, handle: FileHandle) {
+        self.extents.insert(handle.ino, ExtentMap::from_segments(&handle.segments));
         self.handles.insert(handle.ino, handle);
     }
 
@@ -304,18 +429,67 @@ impl HandleMgr {
 
     fn add_block(&mut self, msg: &MsgAddBlock) {
         if let Some(h) = self.handles.get_mut(&msg.ino) {
+            // the block is written by the stale handle of the reused ino.
+            if h.generation != msg.block.generation {
+                println!("add_block: drop block of ino: {} with generation: {}, the handle generation is: {}",
+                msg.ino, msg.block.generation, h.generation);
+                return;
+            }
             for s in &mut h.segments {
                 if s.seg_id0 != msg.id0 || s.seg_id1 != msg.id1 {
                     continue;
                 }
-                s.add_block(msg.ino, msg.block.offset, msg.block.seg_start_addr, msg.block.size);
+                s.add_block(msg.ino, msg.block.generation, msg.block.offset, msg.block.seg_start_addr, msg.block.size);
+                if let Some(m) = self.extents.get_mut(&msg.ino) {
+                    m.insert(msg.block.offset, msg.block.size as u64, msg.id0, msg.id1, msg.block.seg_start_addr);
+                }
                 return;
             }
         }
     }
 
+    fn del_segments(&mut self, msg: &MsgDelSegments) {
+        if let Some(h) = self.handles.get_mut(&msg.ino) {
+            h.segments.retain(|s| !msg.segs.contains(&(s.seg_id0, s.seg_id1)));
+            self.extents.insert(msg.ino, ExtentMap::from_segments(&h.segments));
+        }
+    }
+
+    fn set_backend_size(&mut self, msg: &MsgSetBackendSize) {
+        if let Some(h) = self.handles.get_mut(&msg.ino) {
+            for s in &mut h.segments {
+                if s.seg_id0 == msg.id0 && s.seg_id1 == msg.id1 {
+                    s.backend_size = msg.backend_size;
+                }
+            }
+        }
+    }
+
     fn del(&mut self, ino: u64) {
         self.handles.remove(&ino);
+        self.extents.remove(&ino);
+    }
+
+    fn get_extents(&self, msg: &MsgGetExtents) {
+        let mut ret: Option<FileExtents> = None;
+        if let Some(h) = self.handles.get(&msg.ino) {
+            let mut e = FileExtents{
+                generation: h.generation,
+                ..Default::default()
+            };
+            if let Some(m) = self.extents.get(&msg.ino) {
+                e.size = m.size();
+                e.extents = m.lookup(msg.offset, msg.offset + msg.size as u64);
+            }
+            ret = Some(e);
+        }
+        let r = msg.tx.send(ret);
+        match r {
+            Ok(_) => {}
+            Err(err) => {
+                println!("get_extents: failed to send extents for ino: {}, err: {}", msg.ino, err);
+            }
+        }
     }
     
     fn get_last_segment(&self, msg: &MsgGetLastSegment) {
@@ -324,6 +498,8 @@ impl HandleMgr {
         let mut id0: u64 = 0;
         let mut id1: u64 = 0;
         let mut max_size: u64 = 0;
+        let mut generation: u64 = 0;
+        let mut lease_epoch: u64 = 0;
         let tx = msg.tx.clone();
         defer! {
             drop(tx);
@@ -335,12 +511,16 @@ impl HandleMgr {
                 id0 = l.seg_id0;
                 id1 = l.seg_id1;
                 max_size = l.max_size;
+                generation = h.generation;
+                lease_epoch = h.lease_epoch;
             }
         }
         if found {
             v.push(id0);
             v.push(id1);
             v.push(max_size);
+            v.push(generation);
+            v.push(lease_epoch);
         }
         let ret = msg.tx.send(v);
         match ret {
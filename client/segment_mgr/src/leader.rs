@@ -1,15 +1,20 @@
 use common::error::Errno;
 use crate::types::BlockIo;
-pub trait Leader {
+pub trait Leader: Send + Sync {
     // open the segment for io
-    fn open(&self, ino: u64) -> Errno;
+    // all the apis take the generation of ino, and return Estale if the handle
+    // of ino belongs to another generation.
+    fn open(&self, ino: u64, generation: u64) -> Errno;
     // write the block into the segment file.
-    // all the blocks are appended to the segment file.
-    fn write(&self, ino: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno>;
+    // all the blocks are appended to the segment file, the data larger than the space
+    // left in the segment is split into blocks across several segments.
+    fn write(&self, ino: u64, generation: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno>;
     // read the data into Vec<u8>
-    fn read(&self, ino: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno>;
+    fn read(&self, ino: u64, generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno>;
+    // flush the buffered writes of ino into the segment file.
+    fn flush(&self, ino: u64, generation: u64) -> Errno;
     // close the file handle specified by ino.
-    fn close(&self, ino: u64) -> Errno;
+    fn close(&self, ino: u64, generation: u64) -> Errno;
     // release this leader.
-    fn release(&mut self);
+    fn release(&self);
 }
\ No newline at end of file
@@ -1,34 +1,120 @@
-use std::rc::Rc;
-use crossbeam_channel::bounded;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, Weak};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use crossbeam_channel::{Sender, Receiver, bounded, select, tick};
 use common::runtime::Executor;
+use common::config::LeaderConfig;
 use common::error::Errno;
 use io_engine::types::{MsgFileOpenOp, MsgFileReadOp, MsgFileOp, MsgFileWriteOp, 
-    MsgFileWriteResp, MsgFileCloseOp, MsgFileReadData};
+    MsgFileWriteResp, MsgFileCloseOp, MsgFileDelOp, MsgFileReadData, MsgFileReplaceOp};
 use io_engine::io_thread_pool::IoThreadPool;
 use io_engine::disk_io_worker::DiskIoWorkerFactory;
 use io_engine::backend_storage::BackendStore;
+use io_engine::segment_format::SegmentFile;
 use crate::leader::Leader;
 use crate::file_handle::FileHandleMgr;
 use crate::types::{FileHandle, Block, BlockIo, Segment};
 use crate::segment_mgr::SegmentMgr;
+use crate::lease_mgr::LeaseMgr;
+use crate::extent_map::{Extent, ExtentMap, FileExtents};
+use crate::write_buffer::WriteBuffer;
+use crate::readahead::{ReadPattern, ReadCache};
+use crate::compaction::Compaction;
+use crate::uploader::{SegmentUpload, UploadState, Uploader};
+use crate::fetcher::{FetchStats, SegmentFetcher};
+use crate::evict_mgr::{EvictFile, EvictMgr, EvictReport};
+
+// the data is read ahead in chunks of this size, so that it is evicted from the cache gradually.
+const READAHEAD_CHUNK: u64 = 1 << 20;
+// the live data is copied in chunks of this size during compaction.
+const COMPACT_CHUNK: u64 = 4 << 20;
+// how often the idle segments are sealed and the sealed segments are uploaded.
+const UPLOAD_INTERVAL: Duration = Duration::from_millis(100);
+// the number of the locks the files are hashed to by ino.
+const INO_LOCKS: u64 = 64;
+
+// the write buffer of one file, it is locked during the flush so that the reads
+// see the buffered data either in the buffer or in the segment. it is also locked
+// by the writes and the compaction of the file, so that they don't interleave.
+type BufferRef = Arc<Mutex<Option<WriteBuffer>>>;
+
+// read [start, end) of ino into the read cache.
+struct ReadaheadReq {
+    ino: u64,
+    generation: u64,
+    start: u64,
+    end: u64,
+}
 
 pub struct LeaderLocal {
     machine: String,
     disk_io_pool: IoThreadPool,
-    backend_store: Box<dyn BackendStore>,
+    backend_store: Arc<dyn BackendStore>,
     exec: Executor,
-    segment_mgr: Rc<SegmentMgr>,
+    segment_mgr: Arc<SegmentMgr>,
+    lease_mgr: Arc<LeaseMgr>,
     handle_mgr: FileHandleMgr,
+    // the handles of the closed files during the compaction, they are not seen by the file operations.
+    gc_handle_mgr: FileHandleMgr,
+    // held by open and close, and by the changes of the closed files, so that they don't interleave.
+    ino_locks: Vec<Mutex<()>>,
+    write_buffer_size: usize,
+    flush_interval: Duration,
+    buffers: Mutex<HashMap<u64, BufferRef>>,
+    // the errors of the background flushes which drop the buffered data, returned by the
+    // next flush or close of the file.
+    flush_errors: Mutex<HashMap<u64, Errno>>,
+    readahead_window: u64,
+    read_patterns: Mutex<HashMap<u64, ReadPattern>>,
+    read_cache: Mutex<ReadCache>,
+    readahead_tx: Sender<ReadaheadReq>,
+    // the segments whose live ratio is below it are compacted every gc_interval.
+    gc_min_live_ratio: f64,
+    gc_interval: Duration,
+    // held by the reads of the segments, so that the compacted segments are removed after them.
+    segments_lock: RwLock<()>,
+    // the worker flushes the buffers older than flush_interval and reads ahead in background.
+    worker_rx: Mutex<Option<(Receiver<u32>, Receiver<ReadaheadReq>)>>,
+    stop_tx: Sender<u32>,
+    worker_th: Mutex<Option<JoinHandle<()>>>,
+    // the sealed segments are uploaded to the backend store by the upload thread.
+    uploader: Uploader,
+    upload_bytes_per_sec: u64,
+    upload_chunk_size: u64,
+    upload_stop_tx: Mutex<Option<Sender<u32>>>,
+    upload_th: Mutex<Option<JoinHandle<()>>>,
+    // fetches the sealed segments missing locally back from the backend store.
+    fetcher: SegmentFetcher,
+    // the uploaded segments are evicted from the data dirs above the watermark every evict_interval.
+    evict_mgr: EvictMgr,
+    evict_interval: Duration,
 }
 
 impl Leader for LeaderLocal {
-    fn open(&self, ino: u64) -> Errno {
+    fn open(&self, ino: u64, generation: u64) -> Errno {
+        let _l = self.lock_ino(ino);
         let segments : Vec<Segment>;
         let ret = self.handle_mgr.get(ino);
         match ret {
             Ok(ret) => {
-                println!("open: got handle for ino: {}, leader: {}", ino, ret.leader);
-                return Errno::Esucc;
+                if ret.generation == generation {
+                    if ret.lease_epoch == 0 || self.lease_mgr.is_valid(ino, ret.lease_epoch) {
+                        println!("open: got handle for ino: {}, generation: {}, leader: {}", ino, generation, ret.leader);
+                        return Errno::Esucc;
+                    }
+                    // the file may be written by another leader after the lease is lost, reload the segments.
+                    println!("open: the lease of ino: {} with epoch: {} is lost, reopen it", ino, ret.lease_epoch);
+                }
+                if ret.generation > generation {
+                    println!("open: ino: {} with generation: {} is stale, the handle generation is: {}",
+                    ino, generation, ret.generation);
+                    return Errno::Estale;
+                }
+                // the ino is reused after the old file is deleted, drop the old handle.
+                println!("open: replace the stale handle of ino: {} with generation: {} by generation: {}",
+                ino, ret.generation, generation);
             }
             Err(err) => {
                 if !err.is_enoent() {
@@ -37,7 +123,27 @@ impl Leader for LeaderLocal {
                 }
             }
         }
-        let ret = self.segment_mgr.get_file_segments(ino, &self.machine);
+        // the lease must be acquired before loading the segments, so that no one else can change them.
+        let mut lease_epoch: u64 = 0;
+        let ret = self.lease_mgr.acquire(ino, generation);
+        match ret {
+            Ok((lease, _)) => {
+                if lease.leader != self.machine {
+                    println!("open: ino: {} is led by {} with lease epoch: {}, ttl: {}",
+                    ino, lease.leader, lease.epoch, lease.ttl);
+                    return Errno::Eexists;
+                }
+                lease_epoch = lease.epoch;
+            }
+            Err(err) => {
+                // the meta server doesn't support lease, the leader is kept without lease.
+                if !err.is_enotsupp() {
+                    println!("open: failed to acquire lease for ino: {}, err: {:?}", ino, err);
+                    return err;
+                }
+            }
+        }
+        let ret = self.segment_mgr.get_file_segments(ino, generation, &self.machine);
         match ret {
             Ok(ret) => {
                 segments = ret;
@@ -80,111 +186,282 @@ impl Leader for LeaderLocal {
             }
         }
 
+        for (i, seg) in segments.iter().enumerate() {
+            let size = seg.blocks.iter().map(|b| b.seg_end_addr).max().unwrap_or(0);
+            self.uploader.track(seg.seg_id0, seg.seg_id1, ino, generation, size, seg.backend_size, i + 1 == segments.len());
+        }
         let file_handle = FileHandle {
             ino: ino,
+            generation: generation,
             leader: self.machine.clone(),
+            lease_epoch: lease_epoch,
             segments: segments,
         };
+        // the writes buffered by the old handle can't be written any more.
+        self.buffers.lock().unwrap().remove(&ino);
         self.handle_mgr.add(&file_handle);
 
         return Errno::Esucc;
     }
 
-    fn read(&self, ino: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
-        let file_handle : FileHandle;
-        let ret = self.handle_mgr.get(ino);
+    fn read(&self, ino: u64, generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        // take the buffered writes before reading the segments, if they are flushed in between,
+        // the segments and the buffer have the same data.
+        let pending = self.get_buffered(ino, generation);
+        self.readahead(ino, generation, offset, size);
+        let _r = self.segments_lock.read().unwrap();
+        let file_extents: FileExtents;
+        let ret = self.handle_mgr.get_extents(ino, offset, size);
         match ret{
             Ok(ret) => {
-                file_handle = ret;
+                file_extents = ret;
             }
             Err(err) => {
-                println!("read: failed to get file_handle for ino: {}, err: {:?}", ino, err);
+                println!("read: failed to get extents for ino: {}, err: {:?}", ino, err);
                 return Err(err);
             }
         }
-        let mut start = offset;
-        let mut total_read = size;
+        if file_extents.generation != generation {
+            println!("read: ino: {} with generation: {} is stale, the handle generation is: {}",
+            ino, generation, file_extents.generation);
+            return Err(Errno::Estale);
+        }
+        // the holes are filled with zero, and the data ends at the end of the last extent of the file.
         let mut data = Vec::<u8>::new();
-        for s in &file_handle.segments {
-            for b in &s.blocks {
-                if b.offset <= start && start <= (b.offset + b.size as u64) {
-                    let mut to_read = total_read;
-                    if to_read >= b.size as u32 {
-                        to_read = b.size as u32;
-                    }
-                    // read the data.
-                    let seg_dir = self.segment_mgr.get_segment_dir(s.seg_id0, s.seg_id1);
-                    let (tx, rx) = bounded::<MsgFileReadData>(1);
-                    let msg = MsgFileReadOp{
-                        id0: s.seg_id0,
-                        id1: s.seg_id1,
-                        dir: seg_dir,
-                        offset: start,
-                        size: to_read,
-                        data_sender: tx,
-                    };
-                    let worker = self.disk_io_pool.get_thread(s.seg_id0, s.seg_id1);
-                    let ret = worker.do_io(MsgFileOp::OpRead(msg));
-                    if !ret.is_success(){
-                        println!("read: failed to read data for ino: {}, offset: {}, err: {:?}", ino, start, ret);
-                        return Err(ret);
-                    }
-                    let ret = rx.recv();
-                    match ret {
-                        Ok(ret) => {
-                            if ret.err.is_success() {
-                                if let Some(d) = ret.data{
-                                    let l = d.len() as u32;
-                                    total_read -= l as u32;
-                                    start += l as u64;
-                                    data.extend(d);
-                                }
-                            } else if ret.err.is_eof() {
-                                println!("read: got eof for ino: {}, offset: {}, start: {}", ino, offset, start);
-                                return Ok(data);
-                            } else {
-                                println!("read: got error when read data for ino: {}, offset: {}, start: {}, err: {:?}",
-                                ino, offset, start, ret.err);
-                                return Err(ret.err);
-                            }
-                        }
-                        Err(err) => {
-                            println!("read: failed to got response for ino: {}, offset: {}, start: {}, err: {}", 
-                            ino, offset, start, err);
-                        }
-                    }
-                    if total_read == 0 {
-                        println!("read: finished for ino: {}, offset: {}", ino, offset);
-                        return Ok(data);
-                    }
-                }
+        for e in &file_extents.extents {
+            data.resize((e.start - offset) as usize, 0);
+            self.evict_mgr.access(e.id0, e.id1);
+            let cached = self.read_cache.lock().unwrap().get(e.id0, e.id1, e.seg_addr, e.size() as u32);
+            let d = match cached {
+                Some(d) => d,
+                None => self.read_extent(&self.handle_mgr, ino, e.id0, e.id1, e.seg_addr, e.size() as u32)?,
+            };
+            if d.len() < e.size() as usize {
+                println!("read: segment(id0: {}, id1: {}) of ino: {} is truncated, want [{}, {}), got {} bytes",
+                e.id0, e.id1, ino, e.seg_addr, e.seg_addr + e.size(), d.len());
+                return Err(Errno::Eintr);
             }
+            data.extend_from_slice(&d[..e.size() as usize]);
+        }
+        if file_extents.size > offset {
+            let end = std::cmp::min(file_extents.size, offset + size as u64);
+            data.resize((end - offset) as usize, 0);
+        }
+        if let Some(b) = &pending {
+            b.overlay(offset, size, &mut data);
         }
         return Ok(data);
     }
 
-    fn write(&self, ino: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno> {
+    fn write(&self, ino: u64, generation: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno> {
+        let buf_ref = self.get_buffer(ino);
+        let mut buf = buf_ref.lock().unwrap();
+        if self.write_buffer_size == 0 || data.len() >= self.write_buffer_size {
+            // the buffered writes are written first, so that they are shadowed by this one.
+            let ret = self.flush_buffer(ino, &mut buf);
+            if !ret.is_success() {
+                return Err(ret);
+            }
+            return self.write_blocks(&self.handle_mgr, ino, generation, offset, data);
+        }
+        let mut appended = false;
+        if let Some(b) = buf.as_mut() {
+            if b.is_next(generation, offset) && b.data.len() + data.len() <= self.write_buffer_size {
+                b.data.extend_from_slice(data);
+                appended = true;
+            }
+        }
+        if !appended {
+            let ret = self.flush_buffer(ino, &mut buf);
+            if !ret.is_success() {
+                return Err(ret);
+            }
+            // the handle is checked when the buffer is started, and again when it is flushed.
+            self.check_handle(&self.handle_mgr, ino, generation)?;
+            *buf = Some(WriteBuffer::new(generation, offset, data));
+        }
+        if buf.as_ref().map_or(false, |b| b.data.len() >= self.write_buffer_size) {
+            let ret = self.flush_buffer(ino, &mut buf);
+            if !ret.is_success() {
+                return Err(ret);
+            }
+        }
+        // the buffered write has no address in the segment until it is flushed.
+        Ok(BlockIo{
+            size: data.len() as u32,
+            ..Default::default()
+        })
+    }
+
+    fn flush(&self, ino: u64, generation: u64) -> Errno {
+        if let Some(err) = self.flush_errors.lock().unwrap().remove(&ino) {
+            println!("flush: the buffered writes of ino: {} are lost in background, err: {:?}", ino, err);
+            return err;
+        }
+        let buf_ref: BufferRef;
+        match self.buffers.lock().unwrap().get(&ino) {
+            Some(b) => {
+                buf_ref = b.clone();
+            }
+            None => {
+                return Errno::Esucc;
+            }
+        }
+        let mut buf = buf_ref.lock().unwrap();
+        if let Some(b) = buf.as_ref() {
+            if b.generation != generation {
+                println!("flush: ino: {} with generation: {} is stale, the buffer generation is: {}",
+                ino, generation, b.generation);
+                return Errno::Estale;
+            }
+        }
+        self.flush_buffer(ino, &mut buf)
+    }
+
+    fn close(&self, ino: u64, generation: u64) -> Errno {
+        // first we should update the segments into meta server.
+        // second we should close all the file handles for the ino.
+        // the buffered writes must be in the segments before they are updated.
+        let _l = self.lock_ino(ino);
+        let ret = self.flush(ino, generation);
+        if !ret.is_success() {
+            println!("close: failed to flush ino: {}, err: {:?}", ino, ret);
+            return ret;
+        }
+        let handle: FileHandle;
+        let ret = self.handle_mgr.get(ino);
+        match ret {
+            Ok(ret) => {
+                handle = ret;
+            }
+            Err(err) => {
+                println!("close: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                return err;
+            }
+        }
+        if handle.generation != generation {
+            println!("close: ino: {} with generation: {} is stale, the handle generation is: {}",
+            ino, generation, handle.generation);
+            return Errno::Estale;
+        }
+        // update the segments into meta server.
+        if !handle.segments.is_empty() {
+            let ret = self.segment_mgr.update_segments(ino, handle.generation, handle.lease_epoch, &handle.segments);
+            if !ret.is_success(){
+                println!("close: failed to update segments for ino: {}, err: {:?}", ino, ret);
+                if ret.is_estale() {
+                    // the meta server rejects the stale leader, drop the handle so that it will be reopened.
+                    self.handle_mgr.del(ino);
+                    self.buffers.lock().unwrap().remove(&ino);
+                    self.lease_mgr.release(ino);
+                }
+                return ret;
+            }
+        }
+        // close the segments file handles.
+        let ret = self.close_segments(ino, &handle.segments);
+        if !ret.is_success() {
+            return ret;
+        }
+
+        let err = self.handle_mgr.del(ino);
+        self.buffers.lock().unwrap().remove(&ino);
+        self.flush_errors.lock().unwrap().remove(&ino);
+        self.read_patterns.lock().unwrap().remove(&ino);
+        if handle.lease_epoch != 0 {
+            let ret = self.lease_mgr.release(ino);
+            if !ret.is_success() {
+                println!("close: failed to release lease for ino: {}, err: {:?}", ino, ret);
+            }
+        }
+        return err;
+    }
+
+    fn release(&self) {
+        if let Some(tx) = self.upload_stop_tx.lock().unwrap().take() {
+            let ret = tx.send(1);
+            if let Err(err) = ret {
+                println!("release: failed to stop the upload thread, err: {}", err);
+            }
+        }
+        if let Some(h) = self.upload_th.lock().unwrap().take() {
+            if h.join().is_err() {
+                println!("release: failed to join the upload thread");
+            }
+        }
+        if let Some(h) = self.worker_th.lock().unwrap().take() {
+            let ret = self.stop_tx.send(1);
+            if let Err(err) = ret {
+                println!("release: failed to stop the worker, err: {}", err);
+            }
+            if h.join().is_err() {
+                println!("release: failed to join the worker");
+            }
+        }
+        // the files may be still open, write the buffers before the handles are gone.
+        let bufs: Vec<(u64, BufferRef)> = self.buffers.lock().unwrap().drain().collect();
+        for (ino, b) in bufs {
+            let ret = self.flush_buffer(ino, &mut b.lock().unwrap());
+            if !ret.is_success() {
+                println!("release: failed to flush ino: {}, err: {:?}", ino, ret);
+            }
+        }
+        self.handle_mgr.stop();
+        self.gc_handle_mgr.stop();
+    }
+}
+
+impl LeaderLocal {
+    // check the handle of ino can be written, returns the last segment of it.
+    fn check_handle(&self, handles: &FileHandleMgr, ino: u64, generation: u64) -> Result<Vec<u64>, Errno> {
         let last_segment: Vec<u64>;
-        let ret = self.handle_mgr.get_last_segment(ino);
+        let ret = handles.get_last_segment(ino);
         match ret {
             Ok(ret) => {
                 last_segment = ret;
             }
             Err(err) => {
-                println!("write: failed to get_last_segment for ino: {}, err: {:?}", ino, err);
+                println!("check_handle: failed to get_last_segment for ino: {}, err: {:?}", ino, err);
                 return Err(err);
             }
         }
         if last_segment.is_empty() {
-            println!("write: failed to get_last_segment for ino: {}, no segments found.", ino);
+            println!("check_handle: failed to get_last_segment for ino: {}, no segments found.", ino);
             return Err(Errno::Enoent);
         }
+        if last_segment[3] != generation {
+            println!("check_handle: ino: {} with generation: {} is stale, the handle generation is: {}",
+            ino, generation, last_segment[3]);
+            return Err(Errno::Estale);
+        }
+        // reject the writes once the lease is lost, the file may be led by another machine now.
+        if last_segment[4] != 0 && !self.lease_mgr.is_valid(ino, last_segment[4]) {
+            println!("check_handle: the lease of ino: {} with epoch: {} is not valid any more", ino, last_segment[4]);
+            return Err(Errno::Estale);
+        }
+        Ok(last_segment)
+    }
+
+    // write data into the segments without buffering.
+    fn write_blocks(&self, handles: &FileHandleMgr, ino: u64, generation: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno> {
+        let last_segment = self.check_handle(handles, ino, generation)?;
         let mut id0 = last_segment[0];
         let mut id1 = last_segment[1];
         let mut seg_max_size = last_segment[2];
+        // the sealed segment takes no more data, the write goes into a new segment.
+        if self.uploader.is_sealed(id0, id1) {
+            seg_max_size = 0;
+        }
+        // the data is split into blocks across the space left in the current segment and the new segments.
+        // pos is the size written, and piece_size is the max size of the next block.
+        let mut pos: usize = 0;
+        let mut piece_size: usize = data.len();
+        let mut first: Option<BlockIo> = None;
         //println!("write: seg(id0: {}, id1: {}, max_size: {}, ino: {}, offset: {})", id0, id1, seg_max_size, ino, offset);
-        loop {
+        while pos < data.len() {
             //println!("write: seg(id0: {}, id1: {}, max_size: {})", id0, id1, seg_max_size);
+            let piece_end = std::cmp::min(data.len(), pos + piece_size);
+            let piece_offset = offset + pos as u64;
             let worker = self.disk_io_pool.get_thread(id0, id1);
             let seg_dir = self.segment_mgr.get_segment_dir(id0, id1);
             let (tx, rx) = bounded::<MsgFileWriteResp>(1);
@@ -192,9 +469,10 @@ impl Leader for LeaderLocal {
                 id0: id0,
                 id1: id1,
                 max_size: seg_max_size,
+                ino: ino,
                 dir: seg_dir.clone(),
-                offset: offset, // the file offset is not used currently.
-                data: data.to_vec(),
+                offset: piece_offset, // the file offset is kept in the records of the segment.
+                data: data[pos..piece_end].to_vec(),
                 resp_sender: tx,
             };
             let ret = worker.do_io(MsgFileOp::OpWrite(msg));
@@ -208,90 +486,645 @@ impl Leader for LeaderLocal {
                 Ok(r) => {
                     if !r.err.is_success() {
                         if r.err.is_enospc() {
+                            // the offset is the current size of the segment, fill the space left first.
+                            if r.offset < seg_max_size {
+                                piece_size = (seg_max_size - r.offset) as usize;
+                                continue;
+                            }
                             println!("write: segment(id0: {}, id1: {}, dir: {}) has no space left for ino: {} with offset: {}",
-                            id0, id1, seg_dir, ino, offset);
+                            id0, id1, seg_dir, ino, piece_offset);
+                            self.uploader.seal(id0, id1);
                             let seg = self.segment_mgr.new_segment(&String::from(""));
-                            self.handle_mgr.add_segment(ino, &seg);
+                            if seg.max_size == 0 {
+                                println!("write: the new segment(id0: {}, id1: {}) has no space for ino: {}",
+                                seg.seg_id0, seg.seg_id1, ino);
+                                return Err(Errno::Enospc);
+                            }
+                            handles.add_segment(ino, &seg);
                             id0 = seg.seg_id0;
                             id1 = seg.seg_id1;
                             seg_max_size = seg.max_size;
+                            piece_size = seg_max_size as usize;
                             println!("write: add new segment(id0: {}, id1: {}) for ino: {} with offset: {}",
-                        id0, id1, ino, offset);
+                        id0, id1, ino, piece_offset);
                             continue;
                         }
                         println!("write: failed to write segment(id0: {}, id1: {}) for ino: {} with offset: {}, err: {:?}",
-                        id0, id1, ino, offset, r.err);
-                        return Err(r.err);
+                        id0, id1, ino, piece_offset, r.err);
+                        self.segment_mgr.record_io(id0, id1, false);
+                        if self.segment_mgr.is_segment_dir_usable(id0, id1) {
+                            return Err(r.err);
+                        }
+                        // the dir fails, write into a new segment in another dir. the data written
+                        // is still uploaded from the failed dir if it can be read.
+                        self.uploader.seal(id0, id1);
+                        let seg = self.segment_mgr.new_segment(&String::from(""));
+                        if seg.max_size == 0 {
+                            println!("write: no data dir has space for ino: {} after dir {} fails", ino, seg_dir);
+                            return Err(r.err);
+                        }
+                        handles.add_segment(ino, &seg);
+                        id0 = seg.seg_id0;
+                        id1 = seg.seg_id1;
+                        seg_max_size = seg.max_size;
+                        piece_size = seg_max_size as usize;
+                        println!("write: data dir {} fails, add new segment(id0: {}, id1: {}) for ino: {}",
+                        seg_dir, id0, id1, ino);
+                        continue;
+                    }
+                    self.segment_mgr.record_io(id0, id1, true);
+                    if r.nwrite == 0 {
+                        println!("write: wrote nothing into segment(id0: {}, id1: {}) for ino: {} with offset: {}",
+                        id0, id1, ino, piece_offset);
+                        return Err(Errno::Eintr);
                     }
                     // write block success.
                     let b = Block {
                         ino: ino,
-                        generation: 0,
-                        offset: offset,
+                        generation: generation,
+                        offset: piece_offset,
                         seg_start_addr: r.offset,
                         seg_end_addr: r.offset + r.nwrite as u64,
                         size: r.nwrite as i64,
                     };
-                    let ret = self.handle_mgr.add_block(ino, id0, id1, &b);
+                    let ret = handles.add_block(ino, id0, id1, &b);
                     if !ret.is_success() {
-                        println!("write: failed to add_block{:?} for ino: {} with offset: {}, err: {:?}", b, ino, offset, ret);
+                        println!("write: failed to add_block{:?} for ino: {} with offset: {}, err: {:?}", b, ino, piece_offset, ret);
                         return Err(ret);
                     }
-                    
-                    return Ok(BlockIo{
-                        id0: id0,
-                        id1: id1,
-                        offset: r.offset,
-                        size: r.nwrite,
-                    });
-                    // currently, will update the segments in close api.
-                    // upload the block to meta server.
-                    /*let ret = self.segment_mgr.upload_block(ino, id0, id1, &b);
-                    if ret.is_success(){
-                        return Ok(BlockIo{
+                    self.uploader.touch(id0, id1, ino, generation, b.seg_end_addr);
+                    self.evict_mgr.access(id0, id1);
+                    if first.is_none() {
+                        first = Some(BlockIo{
                             id0: id0,
                             id1: id1,
                             offset: r.offset,
-                            size: r.nwrite,
+                            size: 0,
                         });
                     }
-                    println!("write: failed to upload block{:?} for ino: {}, err: {:?}", b, ino, ret);
-                    return Err(ret);*/
+                    pos += r.nwrite as usize;
+                    piece_size = data.len() - pos;
+                    // currently, will update the segments in close api.
                 }
                 Err(err) => {
                     println!("write: failed to get response for seg(id0: {}, id1: {}) of ino: {} with offset: {}, err: {}", 
-                        id0, id1, ino, offset, err);
+                        id0, id1, ino, piece_offset, err);
                     return Err(Errno::Eintr);
                 }
             }
-            
         }
+        // the first block is returned with the total size written.
+        let mut b = first.unwrap_or(BlockIo{
+            id0: id0,
+            id1: id1,
+            offset: 0,
+            size: 0,
+        });
+        b.size = pos as u32;
+        return Ok(b);
     }
 
-    fn close(&self, ino: u64) -> Errno {
-        // first we should update the segments into meta server.
-        // second we should close all the file handles for the ino.
+    // read the extent of ino from the segment in its data dir. if the local segment file is
+    // missing, the sealed segment is fetched back from the backend store into the dir first. if
+    // the dir fails, or the local read still fails or is short, the sealed data is read from the
+    // backend store directly.
+    fn read_extent(&self, handles: &FileHandleMgr, ino: u64, id0: u64, id1: u64, seg_addr: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let usable = self.segment_mgr.is_segment_dir_usable(id0, id1);
+        let backend_size = self.get_backend_size(handles, ino, id0, id1);
+        let mut local: Option<Result<Vec<u8>, Errno>> = None;
+        if usable {
+            let mut ret = self.read_segment(id0, id1, seg_addr, size);
+            if let Ok(d) = &ret {
+                if d.len() >= size as usize {
+                    return ret;
+                }
+            }
+            if seg_addr + size as u64 <= backend_size && self.fetch_segment(handles, ino, id0, id1, backend_size).is_success() {
+                ret = self.read_segment(id0, id1, seg_addr, size);
+                if let Ok(d) = &ret {
+                    if d.len() >= size as usize {
+                        return ret;
+                    }
+                }
+            }
+            local = Some(ret);
+        }
+        if seg_addr + size as u64 <= backend_size {
+            let ret = self.backend_store.read(id0, id1, seg_addr, size);
+            match ret {
+                Ok(d) => {
+                    println!("read_extent: read seg(id0: {}, id1: {}), offset: {}, size: {} of ino: {} from backend store",
+                    id0, id1, seg_addr, size, ino);
+                    return Ok(d);
+                }
+                Err(err) => {
+                    println!("read_extent: failed to read seg(id0: {}, id1: {}), offset: {} from backend store, err: {:?}",
+                    id0, id1, seg_addr, err);
+                }
+            }
+        }
+        match local {
+            Some(ret) => ret,
+            // the data is not sealed, try the failed dir anyway.
+            None => self.read_segment(id0, id1, seg_addr, size),
+        }
+    }
+
+    // fetch the sealed data of the segment of ino into its data dir if the local file is missing.
+    fn fetch_segment(&self, handles: &FileHandleMgr, ino: u64, id0: u64, id1: u64, backend_size: u64) -> Errno {
+        let seg: Segment;
+        let ret = handles.get(ino);
+        match ret {
+            Ok(h) => {
+                match h.segments.iter().find(|s| s.seg_id0 == id0 && s.seg_id1 == id1) {
+                    Some(s) => {
+                        seg = s.copy();
+                    }
+                    None => {
+                        return Errno::Enoent;
+                    }
+                }
+            }
+            Err(err) => {
+                return err;
+            }
+        }
+        let path = self.segment_mgr.get_segment_file(id0, id1);
+        self.fetcher.fetch(ino, &seg, backend_size, &path, |from| self.replace_segment(id0, id1, from))
+    }
+
+    // move the file from over the segment file through the disk worker of the segment.
+    fn replace_segment(&self, id0: u64, id1: u64, from: &String) -> Errno {
+        let (tx, rx) = bounded::<Errno>(1);
+        let msg = MsgFileReplaceOp{
+            id0: id0,
+            id1: id1,
+            dir: self.segment_mgr.get_segment_dir(id0, id1),
+            from: from.clone(),
+            resp_sender: tx,
+        };
+        let worker = self.disk_io_pool.get_thread(id0, id1);
+        let ret = worker.do_io(MsgFileOp::OpReplace(msg));
+        if !ret.is_success() {
+            return ret;
+        }
+        match rx.recv() {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("replace_segment: failed to get response for seg(id0: {}, id1: {}), err: {}", id0, id1, err);
+                Errno::Eintr
+            }
+        }
+    }
+
+    // the size of the segment of ino sealed in the backend store.
+    fn get_backend_size(&self, handles: &FileHandleMgr, ino: u64, id0: u64, id1: u64) -> u64 {
+        match handles.get(ino) {
+            Ok(handle) => {
+                handle.segments.iter().find(|s| s.seg_id0 == id0 && s.seg_id1 == id1).map_or(0, |s| s.backend_size)
+            }
+            Err(_) => 0,
+        }
+    }
+
+    // read [seg_addr, seg_addr + size) of the segment, the returned data may be shorter at the end of segment.
+    fn read_segment(&self, id0: u64, id1: u64, seg_addr: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let seg_dir = self.segment_mgr.get_segment_dir(id0, id1);
+        let (tx, rx) = bounded::<MsgFileReadData>(1);
+        let msg = MsgFileReadOp{
+            id0: id0,
+            id1: id1,
+            dir: seg_dir,
+            offset: seg_addr,
+            size: size,
+            data_sender: tx,
+        };
+        let worker = self.disk_io_pool.get_thread(id0, id1);
+        let ret = worker.do_io(MsgFileOp::OpRead(msg));
+        if !ret.is_success(){
+            println!("read_segment: failed to send read msg for seg(id0: {}, id1: {}), offset: {}, err: {:?}",
+            id0, id1, seg_addr, ret);
+            return Err(ret);
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                if ret.err.is_success() || ret.err.is_eof() {
+                    self.segment_mgr.record_io(id0, id1, true);
+                    return Ok(ret.data.unwrap_or_default());
+                }
+                println!("read_segment: failed to read seg(id0: {}, id1: {}), offset: {}, err: {:?}",
+                id0, id1, seg_addr, ret.err);
+                self.segment_mgr.record_io(id0, id1, false);
+                return Err(ret.err);
+            }
+            Err(err) => {
+                println!("read_segment: failed to get response for seg(id0: {}, id1: {}), offset: {}, err: {}",
+                id0, id1, seg_addr, err);
+                return Err(Errno::Eintr);
+            }
+        }
+    }
+
+    // the buffer of ino, it is created if not exists.
+    fn get_buffer(&self, ino: u64) -> BufferRef {
+        self.buffers.lock().unwrap().entry(ino).or_insert_with(|| Arc::new(Mutex::new(None))).clone()
+    }
+
+    // a copy of the buffered writes of ino.
+    fn get_buffered(&self, ino: u64, generation: u64) -> Option<WriteBuffer> {
+        let buf_ref = self.buffers.lock().unwrap().get(&ino).cloned()?;
+        let buf = buf_ref.lock().unwrap();
+        match buf.as_ref() {
+            Some(b) if b.generation == generation => Some(b.clone()),
+            _ => None,
+        }
+    }
+
+    // write the buffered data into the segment as one block. the data is kept in the buffer
+    // if it fails, and written again by the next flush. it is dropped only if the handle is
+    // closed or stale, which can't write it any more.
+    fn flush_buffer(&self, ino: u64, buf: &mut Option<WriteBuffer>) -> Errno {
+        if let Some(b) = buf.take() {
+            let ret = self.write_blocks(&self.handle_mgr, ino, b.generation, b.offset, &b.data);
+            if let Err(err) = ret {
+                println!("flush_buffer: failed to write [{}, {}) of ino: {}, err: {:?}",
+                b.offset, b.end(), ino, err);
+                if !err.is_estale() && !err.is_enoent() {
+                    *buf = Some(b);
+                }
+                return err;
+            }
+        }
+        Errno::Esucc
+    }
+
+    fn flush_expired(&self) {
+        let bufs: Vec<(u64, BufferRef)> = self.buffers.lock().unwrap().iter().map(|(k, v)| (*k, v.clone())).collect();
+        for (ino, b) in bufs {
+            let mut buf = b.lock().unwrap();
+            if buf.as_ref().map_or(false, |b| b.since.elapsed() >= self.flush_interval) {
+                let ret = self.flush_buffer(ino, &mut buf);
+                if !ret.is_success() {
+                    println!("flush_expired: failed to flush ino: {}, err: {:?}", ino, ret);
+                    // the kept buffer is flushed again later, the dropped one is reported by the next flush.
+                    if buf.is_none() {
+                        self.flush_errors.lock().unwrap().insert(ino, ret);
+                    }
+                }
+            }
+        }
+    }
+
+    // record the read of ino, and read ahead of it in background if the reads are sequential.
+    fn readahead(&self, ino: u64, generation: u64, offset: u64, size: u32) {
+        if self.readahead_window == 0 {
+            return;
+        }
+        let ret = self.read_patterns.lock().unwrap().entry(ino).or_insert_with(ReadPattern::new)
+        .on_read(offset, size, self.readahead_window);
+        if let Some((start, end)) = ret {
+            let req = ReadaheadReq{
+                ino: ino,
+                generation: generation,
+                start: start,
+                end: end,
+            };
+            // the readahead is skipped if the worker is busy.
+            if self.readahead_tx.try_send(req).is_err() {
+                println!("readahead: skip [{}, {}) of ino: {}, the worker is busy", start, end, ino);
+            }
+        }
+    }
+
+    fn do_readahead(&self, req: &ReadaheadReq) {
+        let _r = self.segments_lock.read().unwrap();
+        let ret = self.handle_mgr.get_extents(req.ino, req.start, (req.end - req.start) as u32);
+        let file_extents = match ret {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("do_readahead: failed to get extents for ino: {}, err: {:?}", req.ino, err);
+                return;
+            }
+        };
+        if file_extents.generation != req.generation {
+            return;
+        }
+        for e in &file_extents.extents {
+            let mut addr = e.seg_addr;
+            let end = e.seg_addr + e.size();
+            while addr < end {
+                let n = std::cmp::min(READAHEAD_CHUNK, end - addr);
+                if !self.read_cache.lock().unwrap().contains(e.id0, e.id1, addr, n as u32) {
+                    let ret = self.read_segment(e.id0, e.id1, addr, n as u32);
+                    match ret {
+                        Ok(d) => {
+                            if d.len() as u64 != n {
+                                return;
+                            }
+                            self.read_cache.lock().unwrap().insert(e.id0, e.id1, addr, d);
+                        }
+                        Err(err) => {
+                            println!("do_readahead: failed to read seg(id0: {}, id1: {}) of ino: {}, offset: {}, err: {:?}",
+                            e.id0, e.id1, req.ino, addr, err);
+                            return;
+                        }
+                    }
+                }
+                addr += n;
+            }
+        }
+    }
+
+    // copy the live data in the segments of ino whose live ratio is below gc_min_live_ratio into
+    // a new segment, update the segments in meta server and remove the old segment files.
+    pub fn compact(&self, ino: u64, generation: u64) -> Errno {
+        let buf_ref = self.get_buffer(ino);
+        let mut buf = buf_ref.lock().unwrap();
+        let ret = self.flush_buffer(ino, &mut buf);
+        if !ret.is_success() {
+            return ret;
+        }
+        self.compact_handle(&self.handle_mgr, ino, generation)
+    }
+
+    // compact the segments of the handle of ino in handles.
+    fn compact_handle(&self, handles: &FileHandleMgr, ino: u64, generation: u64) -> Errno {
         let handle: FileHandle;
-        let ret = self.handle_mgr.get(ino);
+        let ret = handles.get(ino);
         match ret {
             Ok(ret) => {
                 handle = ret;
             }
             Err(err) => {
-                println!("close: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                println!("compact: failed to get file handle for ino: {}, err: {:?}", ino, err);
                 return err;
             }
         }
-        // update the segments into meta server.
-        if !handle.segments.is_empty() {
-            let ret = self.segment_mgr.update_segments(ino, &handle.segments);
-            if !ret.is_success(){
-                println!("close: failed to update segments for ino: {}, err: {:?}", ino, ret);
-                return ret;
+        if let Err(err) = self.check_handle(handles, ino, generation) {
+            return err;
+        }
+        let victims = Compaction::victims(&handle.segments, self.gc_min_live_ratio);
+        if victims.is_empty() {
+            return Errno::Esucc;
+        }
+        let ids: Vec<(u64, u64)> = victims.iter().map(|v| (v.id0, v.id1)).collect();
+        let extents: Vec<Extent> = ExtentMap::from_segments(&handle.segments).lookup(0, u64::MAX)
+        .into_iter().filter(|e| ids.contains(&(e.id0, e.id1))).collect();
+        let copied: u64;
+        let ret = self.copy_extents(handles, ino, generation, &extents);
+        match ret {
+            Ok(ret) => {
+                copied = ret;
+            }
+            Err(err) => {
+                println!("compact: failed to copy the extents of ino: {}, err: {:?}", ino, err);
+                self.drop_new_segments(handles, ino, &handle.segments);
+                return err;
             }
         }
-        // close the segments file handles.
-        for s in &handle.segments {
+        let ret = handles.del_segments(ino, &ids);
+        if !ret.is_success() {
+            return ret;
+        }
+        let ret = handles.get(ino);
+        match ret {
+            Ok(ret) => {
+                // the old segments are kept if the meta server still refers to them.
+                let err = self.segment_mgr.update_segments(ino, generation, ret.lease_epoch, &ret.segments);
+                if !err.is_success() {
+                    println!("compact: failed to update segments for ino: {}, err: {:?}", ino, err);
+                    return err;
+                }
+            }
+            Err(err) => {
+                println!("compact: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                return err;
+            }
+        }
+        // wait for the reads of the old segments.
+        let _w = self.segments_lock.write().unwrap();
+        for v in &victims {
+            // the object of the segment is in the backend store once any of it is uploaded.
+            let backend_size = handle.segments.iter().find(|s| s.seg_id0 == v.id0 && s.seg_id1 == v.id1)
+            .map_or(0, |s| s.backend_size);
+            let uploaded = backend_size > 0 || match self.uploader.state(v.id0, v.id1) {
+                Some(UploadState::Uploading) | Some(UploadState::Uploaded) | Some(UploadState::Failed) => true,
+                _ => false,
+            };
+            let ret = self.del_segment(v.id0, v.id1);
+            if !ret.is_success() {
+                println!("compact: failed to remove segment(id0: {}, id1: {}) of ino: {}, err: {:?}",
+                v.id0, v.id1, ino, ret);
+            }
+            if uploaded {
+                let ret = self.backend_store.delete(v.id0, v.id1);
+                if !ret.is_success() {
+                    println!("compact: failed to delete segment(id0: {}, id1: {}) of ino: {} from backend store, err: {:?}",
+                    v.id0, v.id1, ino, ret);
+                }
+            }
+        }
+        println!("compact: copied {} bytes of ino: {} from {} segments", copied, ino, victims.len());
+        Errno::Esucc
+    }
+
+    // copy the extents into a new segment, so they shadow the old data. the new segment is
+    // allocated after the first chunk is read, no segment is added if nothing is copied.
+    fn copy_extents(&self, handles: &FileHandleMgr, ino: u64, generation: u64, extents: &Vec<Extent>) -> Result<u64, Errno> {
+        let mut allocated = false;
+        let mut copied: u64 = 0;
+        for e in extents {
+            let mut pos: u64 = 0;
+            while pos < e.size() {
+                let n = std::cmp::min(COMPACT_CHUNK, e.size() - pos);
+                let d = self.read_extent(handles, ino, e.id0, e.id1, e.seg_addr + pos, n as u32)?;
+                if d.len() as u64 != n {
+                    println!("compact: segment(id0: {}, id1: {}) of ino: {} is truncated, want [{}, {}), got {} bytes",
+                    e.id0, e.id1, ino, e.seg_addr + pos, e.seg_addr + pos + n, d.len());
+                    return Err(Errno::Eintr);
+                }
+                if !allocated {
+                    let seg = self.segment_mgr.new_segment(&String::from(""));
+                    if seg.max_size == 0 {
+                        println!("compact: the new segment(id0: {}, id1: {}) has no space for ino: {}",
+                        seg.seg_id0, seg.seg_id1, ino);
+                        return Err(Errno::Enospc);
+                    }
+                    let ret = handles.add_segment(ino, &seg);
+                    if !ret.is_success() {
+                        return Err(ret);
+                    }
+                    println!("compact: copy the live data of ino: {} into segment(id0: {}, id1: {})",
+                    ino, seg.seg_id0, seg.seg_id1);
+                    allocated = true;
+                }
+                self.write_blocks(handles, ino, generation, e.start + pos, &d)?;
+                pos += n;
+            }
+            copied += e.size();
+        }
+        Ok(copied)
+    }
+
+    // drop the segments added to the handle of ino since it had the segments old, such as after
+    // the compaction fails, so that the old data is read again.
+    fn drop_new_segments(&self, handles: &FileHandleMgr, ino: u64, old: &Vec<Segment>) {
+        let cur: Vec<Segment>;
+        let ret = handles.get(ino);
+        match ret {
+            Ok(ret) => {
+                cur = ret.segments;
+            }
+            Err(err) => {
+                println!("drop_new_segments: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                return;
+            }
+        }
+        let ids: Vec<(u64, u64)> = cur.iter()
+        .filter(|s| !old.iter().any(|o| o.seg_id0 == s.seg_id0 && o.seg_id1 == s.seg_id1))
+        .map(|s| (s.seg_id0, s.seg_id1)).collect();
+        if ids.is_empty() {
+            return;
+        }
+        let ret = handles.del_segments(ino, &ids);
+        if !ret.is_success() {
+            println!("drop_new_segments: failed to del segments {:?} of ino: {}, err: {:?}", ids, ino, ret);
+            return;
+        }
+        for (id0, id1) in &ids {
+            let ret = self.del_segment(*id0, *id1);
+            if !ret.is_success() {
+                println!("drop_new_segments: failed to remove segment(id0: {}, id1: {}) of ino: {}, err: {:?}",
+                id0, id1, ino, ret);
+            }
+        }
+    }
+
+    // compact the opened files, and the closed files whose segments are on this machine.
+    pub fn gc(&self) {
+        let inos: Vec<u64>;
+        let ret = self.handle_mgr.get_inos();
+        match ret {
+            Ok(ret) => {
+                inos = ret;
+            }
+            Err(err) => {
+                println!("gc: failed to get the opened files, err: {:?}", err);
+                return;
+            }
+        }
+        let mut opened = Vec::<(u64, u64)>::new();
+        for ino in &inos {
+            if let Ok(h) = self.handle_mgr.get(*ino) {
+                for s in &h.segments {
+                    opened.push((s.seg_id0, s.seg_id1));
+                }
+                let ret = self.compact(*ino, h.generation);
+                if !ret.is_success() {
+                    println!("gc: failed to compact ino: {}, err: {:?}", ino, ret);
+                }
+            }
+        }
+        // the closed files are found by the local segments not used by the opened files.
+        let mut closed = Vec::<(u64, u64)>::new();
+        for (id0, id1, _) in self.segment_mgr.segment_files() {
+            if opened.contains(&(id0, id1)) {
+                continue;
+            }
+            let ret = self.segment_mgr.get_segment_inos(id0, id1);
+            match ret {
+                Ok(ret) => {
+                    for f in ret {
+                        if !inos.contains(&f.0) && !closed.contains(&f) {
+                            closed.push(f);
+                        }
+                    }
+                }
+                Err(err) => {
+                    println!("gc: failed to get the files of segment(id0: {}, id1: {}), err: {:?}", id0, id1, err);
+                }
+            }
+        }
+        for (ino, generation) in closed {
+            let ret = self.compact_closed(ino, generation);
+            if !ret.is_success() {
+                println!("gc: failed to compact closed ino: {}, generation: {}, err: {:?}", ino, generation, ret);
+            }
+        }
+    }
+
+    // compact the file which is not opened, it is skipped if it is led by others. the segments are
+    // loaded from meta server under the lease into a handle of gc_handle_mgr, so the file operations
+    // never see it, and the file can't be opened until the compaction is done.
+    pub fn compact_closed(&self, ino: u64, generation: u64) -> Errno {
+        let _l = self.lock_ino(ino);
+        if self.handle_mgr.get(ino).is_ok() {
+            return self.compact(ino, generation);
+        }
+        let mut lease_epoch: u64 = 0;
+        let mut granted = false;
+        let ret = self.lease_mgr.acquire(ino, generation);
+        match ret {
+            Ok((lease, new)) => {
+                if lease.leader != self.machine {
+                    println!("compact_closed: ino: {} is led by {} with lease epoch: {}", ino, lease.leader, lease.epoch);
+                    return Errno::Esucc;
+                }
+                lease_epoch = lease.epoch;
+                granted = new;
+            }
+            Err(err) => {
+                if !err.is_enotsupp() {
+                    println!("compact_closed: failed to acquire lease for ino: {}, err: {:?}", ino, err);
+                    return err;
+                }
+            }
+        }
+        let err: Errno;
+        let ret = self.segment_mgr.get_file_segments(ino, generation, &self.machine);
+        match ret {
+            Ok(segments) => {
+                let handle = FileHandle {
+                    ino: ino,
+                    generation: generation,
+                    leader: self.machine.clone(),
+                    lease_epoch: lease_epoch,
+                    segments: segments,
+                };
+                self.gc_handle_mgr.add(&handle);
+                let mut ret = self.compact_handle(&self.gc_handle_mgr, ino, generation);
+                if let Ok(h) = self.gc_handle_mgr.get(ino) {
+                    let e = self.close_segments(ino, &h.segments);
+                    if !e.is_success() && ret.is_success() {
+                        ret = e;
+                    }
+                }
+                self.gc_handle_mgr.del(ino);
+                err = ret;
+            }
+            Err(e) => {
+                println!("compact_closed: failed to get_file_segments for ino: {}, err: {:?}", ino, e);
+                err = e;
+            }
+        }
+        if granted {
+            let ret = self.lease_mgr.release(ino);
+            if !ret.is_success() {
+                println!("compact_closed: failed to release lease for ino: {}, err: {:?}", ino, ret);
+            }
+        }
+        err
+    }
+
+    // the lock of the files hashed to the same slot as ino.
+    fn lock_ino(&self, ino: u64) -> MutexGuard<'_, ()> {
+        self.ino_locks[(ino % INO_LOCKS) as usize].lock().unwrap()
+    }
+
+    // close the segment files of ino in the disk workers, so that their data is synced.
+    fn close_segments(&self, ino: u64, segments: &Vec<Segment>) -> Errno {
+        for s in segments {
             for b in &s.blocks {
                 println!("ino: {}, seg: {}, {}, block: offset: {}, size: {}",
                 ino, s.seg_id0, s.seg_id1, b.offset, b.size);
@@ -309,26 +1142,427 @@ impl Leader for LeaderLocal {
                 return ret;
             }
         }
+        Errno::Esucc
+    }
 
-        let err = self.handle_mgr.del(ino);
-        return err;
+    // remove the segment file, it must not be referred by any file.
+    fn del_segment(&self, id0: u64, id1: u64) -> Errno {
+        self.read_cache.lock().unwrap().remove_segment(id0, id1);
+        self.uploader.forget(id0, id1);
+        self.evict_mgr.forget(id0, id1);
+        let ret = self.remove_segment_file(id0, id1);
+        if ret.is_success() {
+            self.segment_mgr.remove_segment(id0, id1);
+        }
+        ret
     }
 
-    fn release(&mut self) {
-        self.handle_mgr.stop();
+    // remove the segment file through the disk worker of the segment, so that its handle is closed.
+    fn remove_segment_file(&self, id0: u64, id1: u64) -> Errno {
+        let (tx, rx) = bounded::<Errno>(1);
+        let msg = MsgFileDelOp{
+            id0: id0,
+            id1: id1,
+            dir: self.segment_mgr.get_segment_dir(id0, id1),
+            resp_sender: tx,
+        };
+        let worker = self.disk_io_pool.get_thread(id0, id1);
+        let ret = worker.do_io(MsgFileOp::OpDel(msg));
+        if !ret.is_success() {
+            return ret;
+        }
+        match rx.recv() {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("remove_segment_file: failed to get response for seg(id0: {}, id1: {}), err: {}", id0, id1, err);
+                Errno::Eintr
+            }
+        }
     }
-}
 
-impl LeaderLocal {
-    pub fn new(machine: &String, thr_num: u32, exec: &Executor, mgr: Rc<SegmentMgr>, backend: Box<dyn BackendStore>) -> Self {
+    // evict the uploaded segments from the data dirs whose usage is above the high watermark.
+    pub fn evict(&self) -> EvictReport {
+        let dirs: Vec<String> = self.segment_mgr.dir_status().iter().map(|s| s.dir.clone()).collect();
+        let mut files = Vec::<EvictFile>::new();
+        let mut paths = HashMap::<(u64, u64), String>::new();
+        for (id0, id1, path) in self.segment_mgr.segment_files() {
+            // the segment is evicted already, or left empty by the open after it is evicted.
+            let md = match std::fs::metadata(&path) {
+                Ok(md) => md,
+                Err(_) => {
+                    continue;
+                }
+            };
+            if md.len() == 0 {
+                continue;
+            }
+            let seg_dir = self.segment_mgr.get_segment_dir(id0, id1);
+            let dir = match dirs.iter().position(|d| *d == seg_dir) {
+                Some(idx) => idx,
+                None => {
+                    continue;
+                }
+            };
+            let mut f = EvictFile{
+                id0: id0,
+                id1: id1,
+                dir: dir,
+                size: md.len(),
+                last_access: md.modified().unwrap_or(UNIX_EPOCH),
+                hits: 0,
+            };
+            self.evict_mgr.fill_access(&mut f);
+            files.push(f);
+            paths.insert((id0, id1), path);
+        }
+        let mut report = EvictReport{
+            usage: self.evict_mgr.usage(&dirs, &files),
+            ..Default::default()
+        };
+        let victims = self.evict_mgr.plan(&report.usage, &files, |f| {
+            paths.get(&(f.id0, f.id1)).map_or(false, |p| self.is_evictable(f.id0, f.id1, p))
+        });
+        for f in &victims {
+            let ret = self.remove_segment_file(f.id0, f.id1);
+            if !ret.is_success() {
+                println!("evict: failed to remove segment(id0: {}, id1: {}), err: {:?}", f.id0, f.id1, ret);
+                continue;
+            }
+            report.evicted.push((f.id0, f.id1));
+            report.bytes += f.size;
+        }
+        if !report.evicted.is_empty() {
+            println!("evict: evicted {} segments with {} bytes by {}: {:?}",
+            report.evicted.len(), report.bytes, self.evict_mgr.policy(), report.evicted);
+        }
+        report
+    }
+
+    // whether no more data is written into the segment, and all of its data is in the backend
+    // store. the upload is only trusted after the meta server records the backend_size, so that
+    // the evicted data can be fetched back.
+    fn is_evictable(&self, id0: u64, id1: u64, path: &String) -> bool {
+        if let Some(state) = self.uploader.state(id0, id1) {
+            if state != UploadState::Uploaded {
+                return false;
+            }
+        }
+        let size = match SegmentFile::scan(path, false) {
+            Ok(scan) => scan.data_size,
+            Err(err) => {
+                println!("is_evictable: {}", err);
+                return false;
+            }
+        };
+        match self.segment_mgr.get_backend_size(id0, id1) {
+            Ok(backend_size) => size > 0 && size <= backend_size,
+            Err(_) => false,
+        }
+    }
+
+    // start the worker which flushes the buffers, reads ahead, compacts and evicts the segments,
+    // it stops when the leader is released or dropped.
+    pub fn start_worker(leader: &Arc<LeaderLocal>) {
+        if leader.write_buffer_size == 0 && leader.readahead_window == 0 && leader.gc_interval == Duration::from_secs(0)
+            && leader.evict_interval == Duration::from_secs(0) {
+            return;
+        }
+        let stop_rx: Receiver<u32>;
+        let readahead_rx: Receiver<ReadaheadReq>;
+        match leader.worker_rx.lock().unwrap().take() {
+            Some(rx) => {
+                stop_rx = rx.0;
+                readahead_rx = rx.1;
+            }
+            None => {
+                println!("start_worker: the worker is started already");
+                return;
+            }
+        }
+        let weak = Arc::downgrade(leader);
+        let interval = std::cmp::max(leader.flush_interval / 2, Duration::from_millis(1));
+        let h = thread::spawn(move || LeaderLocal::work(weak, interval, stop_rx, readahead_rx));
+        *leader.worker_th.lock().unwrap() = Some(h);
+    }
+
+    fn work(leader: Weak<LeaderLocal>, interval: Duration, stop_rx: Receiver<u32>, readahead_rx: Receiver<ReadaheadReq>) {
+        let ticker = tick(interval);
+        let mut last_gc = Instant::now();
+        let mut last_evict = Instant::now();
+        loop {
+            select! {
+                recv(ticker) -> _ => {
+                    match leader.upgrade() {
+                        Some(l) => {
+                            l.flush_expired();
+                            if l.gc_interval > Duration::from_secs(0) && last_gc.elapsed() >= l.gc_interval {
+                                l.gc();
+                                last_gc = Instant::now();
+                            }
+                            if l.evict_interval > Duration::from_secs(0) && last_evict.elapsed() >= l.evict_interval {
+                                l.evict();
+                                last_evict = Instant::now();
+                            }
+                        }
+                        None => {
+                            break;
+                        }
+                    }
+                },
+                recv(readahead_rx) -> msg => {
+                    if let (Ok(req), Some(l)) = (msg, leader.upgrade()) {
+                        l.do_readahead(&req);
+                    }
+                },
+                recv(stop_rx) -> _ => {
+                    println!("worker got stop signal, stop the loop...");
+                    break;
+                },
+            }
+        }
+    }
+
+    // start the thread which seals the idle segments and uploads the sealed segments, it stops
+    // when the leader is released or dropped.
+    pub fn start_uploader(leader: &Arc<LeaderLocal>) {
+        let (stop_tx, stop_rx) = bounded::<u32>(1);
+        let weak = Arc::downgrade(leader);
+        *leader.upload_stop_tx.lock().unwrap() = Some(stop_tx);
+        let h = thread::spawn(move || LeaderLocal::upload_work(weak, stop_rx));
+        *leader.upload_th.lock().unwrap() = Some(h);
+    }
+
+    fn upload_work(leader: Weak<LeaderLocal>, stop_rx: Receiver<u32>) {
+        let ticker = tick(UPLOAD_INTERVAL);
+        loop {
+            select! {
+                recv(ticker) -> _ => {
+                    match leader.upgrade() {
+                        Some(l) => {
+                            if !l.upload_sealed(&stop_rx) {
+                                println!("upload thread got stop signal during upload, stop the loop...");
+                                break;
+                            }
+                        }
+                        None => {
+                            break;
+                        }
+                    }
+                },
+                recv(stop_rx) -> _ => {
+                    println!("upload thread got stop signal, stop the loop...");
+                    break;
+                },
+            }
+        }
+    }
+
+    // seal the idle segments and upload all the sealed segments, returns false if it is stopped.
+    fn upload_sealed(&self, stop_rx: &Receiver<u32>) -> bool {
+        self.uploader.seal_idle();
+        while let Some(u) = self.uploader.next() {
+            match self.upload_segment(&u, stop_rx) {
+                Some(ret) => {
+                    self.uploader.finish(u.id0, u.id1, ret);
+                }
+                None => {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // append the data of the segment after the uploaded part to the backend store, and record the
+    // backend_size of it in meta server. returns None if it is stopped.
+    fn upload_segment(&self, u: &SegmentUpload, stop_rx: &Receiver<u32>) -> Option<Errno> {
+        if u.uploaded < u.size {
+            let ret = self.backend_store.open(u.id0, u.id1);
+            if !ret.is_success() {
+                println!("upload_segment: failed to open segment(id0: {}, id1: {}) in backend store, err: {:?}",
+                u.id0, u.id1, ret);
+                return Some(ret);
+            }
+            let start = Instant::now();
+            let mut pos = u.uploaded;
+            while pos < u.size {
+                let n = std::cmp::min(self.upload_chunk_size, u.size - pos);
+                let d: Vec<u8>;
+                let ret = self.read_segment(u.id0, u.id1, pos, n as u32);
+                match ret {
+                    Ok(ret) => {
+                        d = ret;
+                    }
+                    Err(err) => {
+                        return Some(err);
+                    }
+                }
+                if d.len() as u64 != n {
+                    println!("upload_segment: segment(id0: {}, id1: {}) is truncated, want [{}, {}), got {} bytes",
+                    u.id0, u.id1, pos, pos + n, d.len());
+                    return Some(Errno::Eintr);
+                }
+                let ret = self.backend_store.write(u.id0, u.id1, pos, &d);
+                if !ret.is_success() {
+                    println!("upload_segment: failed to write segment(id0: {}, id1: {}) at {} into backend store, err: {:?}",
+                    u.id0, u.id1, pos, ret);
+                    return Some(ret);
+                }
+                pos += n;
+                self.uploader.progress(u.id0, u.id1, pos);
+                if !self.throttle_upload(start, pos - u.uploaded, stop_rx) {
+                    return None;
+                }
+            }
+            let ret = self.backend_store.close(u.id0, u.id1);
+            if !ret.is_success() {
+                println!("upload_segment: failed to close segment(id0: {}, id1: {}) in backend store, err: {:?}",
+                u.id0, u.id1, ret);
+                return Some(ret);
+            }
+        }
+        // the data is in the backend store, a failed commit is retried without uploading it again.
+        Some(self.commit_upload(u.ino, u.generation, u.id0, u.id1, u.size))
+    }
+
+    // wait until bytes uploaded since start are within upload_bytes_per_sec, returns false if it is stopped.
+    fn throttle_upload(&self, start: Instant, bytes: u64, stop_rx: &Receiver<u32>) -> bool {
+        if self.upload_bytes_per_sec == 0 {
+            return true;
+        }
+        let want = Duration::from_secs_f64(bytes as f64 / self.upload_bytes_per_sec as f64);
+        let elapsed = start.elapsed();
+        if want <= elapsed {
+            return true;
+        }
+        stop_rx.recv_timeout(want - elapsed).is_err()
+    }
+
+    // record the backend_size of the segment of ino in the handle and in meta server. the segments
+    // of the closed file are loaded from meta server under the lease, the file can't be opened
+    // until they are updated.
+    fn commit_upload(&self, ino: u64, generation: u64, id0: u64, id1: u64, backend_size: u64) -> Errno {
+        let _l = self.lock_ino(ino);
+        let ret = self.handle_mgr.get(ino);
+        match ret {
+            Ok(h) => {
+                // the ino is reused or the segment is compacted, no one refers to it any more.
+                if h.generation != generation || !h.segments.iter().any(|s| s.seg_id0 == id0 && s.seg_id1 == id1) {
+                    return Errno::Esucc;
+                }
+                let ret = self.handle_mgr.set_backend_size(ino, id0, id1, backend_size);
+                if !ret.is_success() {
+                    return ret;
+                }
+                let ret = self.handle_mgr.get(ino);
+                match ret {
+                    Ok(h) => {
+                        return self.segment_mgr.update_segments(ino, generation, h.lease_epoch, &h.segments);
+                    }
+                    Err(err) => {
+                        println!("commit_upload: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                        return err;
+                    }
+                }
+            }
+            Err(err) => {
+                if !err.is_enoent() {
+                    println!("commit_upload: failed to get file handle for ino: {}, err: {:?}", ino, err);
+                    return err;
+                }
+            }
+        }
+        let mut lease_epoch: u64 = 0;
+        let mut granted = false;
+        let ret = self.lease_mgr.acquire(ino, generation);
+        match ret {
+            Ok((lease, new)) => {
+                if lease.leader != self.machine {
+                    println!("commit_upload: ino: {} is led by {} with lease epoch: {}", ino, lease.leader, lease.epoch);
+                    return Errno::Eexists;
+                }
+                lease_epoch = lease.epoch;
+                granted = new;
+            }
+            Err(err) => {
+                if !err.is_enotsupp() {
+                    println!("commit_upload: failed to acquire lease for ino: {}, err: {:?}", ino, err);
+                    return err;
+                }
+            }
+        }
+        let mut ret = self.segment_mgr.get_file_segments(ino, generation, &self.machine);
+        if let Ok(segments) = &mut ret {
+            for s in segments.iter_mut() {
+                if s.seg_id0 == id0 && s.seg_id1 == id1 {
+                    s.backend_size = backend_size;
+                }
+            }
+        }
+        let err = match ret {
+            Ok(segments) => self.segment_mgr.update_segments(ino, generation, lease_epoch, &segments),
+            Err(err) => {
+                println!("commit_upload: failed to get_file_segments for ino: {}, err: {:?}", ino, err);
+                err
+            }
+        };
+        // the lease held before is not released, it belongs to whoever acquired it.
+        if granted {
+            let ret = self.lease_mgr.release(ino);
+            if !ret.is_success() {
+                println!("commit_upload: failed to release lease for ino: {}, err: {:?}", ino, ret);
+            }
+        }
+        err
+    }
+
+    // the upload state of the segments.
+    pub fn upload_status(&self) -> Vec<SegmentUpload> {
+        self.uploader.status()
+    }
+
+    // the segments fetched back from the backend store.
+    pub fn fetch_stats(&self) -> FetchStats {
+        self.fetcher.stats()
+    }
+
+    pub fn new(machine: &String, cfg: &LeaderConfig, thr_num: u32, exec: &Executor, mgr: Arc<SegmentMgr>,
+        lease_mgr: Arc<LeaseMgr>, backend: Arc<dyn BackendStore>) -> Self {
+        let (stop_tx, stop_rx) = bounded::<u32>(1);
+        let (readahead_tx, readahead_rx) = bounded::<ReadaheadReq>(16);
         LeaderLocal {
             machine: machine.clone(),
             disk_io_pool: IoThreadPool::new(thr_num, &String::from("Disk"), 
             exec, &DiskIoWorkerFactory::new()),
-            backend_store: backend,
+            backend_store: backend.clone(),
             exec: exec.clone(),
             segment_mgr: mgr,
+            lease_mgr: lease_mgr,
             handle_mgr: FileHandleMgr::create(),
+            gc_handle_mgr: FileHandleMgr::create(),
+            ino_locks: (0..INO_LOCKS).map(|_| Mutex::new(())).collect(),
+            write_buffer_size: cfg.write_buffer_size,
+            flush_interval: Duration::from_millis(cfg.write_flush_ms),
+            buffers: Mutex::new(HashMap::new()),
+            flush_errors: Mutex::new(HashMap::new()),
+            readahead_window: cfg.readahead_window,
+            read_patterns: Mutex::new(HashMap::new()),
+            read_cache: Mutex::new(ReadCache::new(cfg.read_cache_size)),
+            readahead_tx: readahead_tx,
+            gc_min_live_ratio: cfg.gc_min_live_ratio,
+            gc_interval: Duration::from_secs(cfg.gc_interval_secs),
+            segments_lock: RwLock::new(()),
+            worker_rx: Mutex::new(Some((stop_rx, readahead_rx))),
+            stop_tx: stop_tx,
+            worker_th: Mutex::new(None),
+            uploader: Uploader::new(cfg),
+            upload_bytes_per_sec: cfg.upload_bytes_per_sec,
+            upload_chunk_size: std::cmp::max(cfg.upload_chunk_size, 1),
+            upload_stop_tx: Mutex::new(None),
+            upload_th: Mutex::new(None),
+            fetcher: SegmentFetcher::new(backend.clone()),
+            evict_mgr: EvictMgr::new(cfg),
+            evict_interval: Duration::from_secs(cfg.evict_interval_secs),
         }
     }
 }
\ No newline at end of file
@@ -1,13 +1,26 @@
-
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use common::runtime::Executor;
+use common::config::{LeaderConfig, ScrubConfig};
+use common::error::Errno;
 use io_engine::backend_storage::BackendStore;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::FileLeader;
 
 use crate::{leader::Leader, segment_mgr::SegmentMgr};
 use crate::leader_local::LeaderLocal;
+use crate::lease_mgr::LeaseMgr;
 use crate::leader_not_support::LeaderNotSupport;
+use crate::leader_peer::LeaderPeer;
+use crate::leader_server::LeaderServer;
+use crate::backend_reader::BackendReader;
+use crate::leader_placement::LeaderPlacement;
+use crate::dir_health::DirStatus;
+use crate::scrubber::{ScrubReport, Scrubber};
+use crate::uploader::SegmentUpload;
+use crate::fetcher::FetchStats;
+use crate::evict_mgr::EvictReport;
 
 #[derive(Debug)]
 enum LeaderType {
@@ -17,35 +30,246 @@ enum LeaderType {
 }
 pub struct LeaderMgr {
     machine: String,
-    leaders: HashMap<u8, Box<dyn Leader>>,
+    leaders: HashMap<u8, Arc<dyn Leader>>,
+    local: Arc<LeaderLocal>,
+    // renews the leases of the files led by this machine in background.
+    lease_mgr: Arc<LeaseMgr>,
+    // serves the io from the peers for the files led by this machine.
+    server: Option<LeaderServer>,
+    // reads the sealed data of the files led by other machines from the backend store.
+    backend_reader: BackendReader,
+    // chooses the leader of the new files.
+    placement: LeaderPlacement,
+    leader_cfg: LeaderConfig,
+    segment_mgr: Arc<SegmentMgr>,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    backend_store: Arc<dyn BackendStore>,
+    // verifies the local segment files in background.
+    scrubber: Option<Arc<Scrubber>>,
 }
 
 impl LeaderMgr {
-    pub fn new(machine: &String, thr_num: u32, exec: &Executor, seg_mgr: Rc<SegmentMgr>, backend_store: Box<dyn BackendStore>) -> Self {
-        let mut leaders = HashMap::<u8, Box<dyn Leader>>::new();
-        leaders.insert(LeaderType::Unknown as u8, Box::new(LeaderNotSupport::new()));
-        leaders.insert(LeaderType::Local as u8, Box::new(LeaderLocal::new(machine, thr_num, exec, seg_mgr, backend_store)));
+    pub fn new(machine: &String, leader_cfg: &LeaderConfig, thr_num: u32, exec: &Executor, seg_mgr: Arc<SegmentMgr>,
+        meta_service_mgr: Arc<dyn MetaServiceMgr>, backend_store: Box<dyn BackendStore>) -> Self {
+        let lease_mgr = Arc::new(LeaseMgr::new(machine, leader_cfg, meta_service_mgr.clone()));
+        let backend_store: Arc<dyn BackendStore> = Arc::from(backend_store);
+        let backend_reader = BackendReader::new(meta_service_mgr.clone(), backend_store.clone());
+        let placement = LeaderPlacement::new(machine, leader_cfg, meta_service_mgr.clone());
+        let mut leaders = HashMap::<u8, Arc<dyn Leader>>::new();
+        leaders.insert(LeaderType::Unknown as u8, Arc::new(LeaderNotSupport::new()));
+        leaders.insert(LeaderType::Peer as u8, Arc::new(LeaderPeer::new(machine, leader_cfg, meta_service_mgr.clone())));
+        let local = Arc::new(LeaderLocal::new(machine, leader_cfg, thr_num, exec, seg_mgr.clone(),
+            lease_mgr.clone(), backend_store.clone()));
+        LeaderLocal::start_worker(&local);
+        LeaderLocal::start_uploader(&local);
+        leaders.insert(LeaderType::Local as u8, local.clone());
         LeaderMgr{
             machine: machine.clone(),
             leaders: leaders,
+            local: local,
+            lease_mgr: lease_mgr,
+            server: None,
+            backend_reader: backend_reader,
+            placement: placement,
+            leader_cfg: leader_cfg.clone(),
+            segment_mgr: seg_mgr,
+            meta_service_mgr: meta_service_mgr,
+            backend_store: backend_store,
+            scrubber: None,
+        }
+    }
+
+    // start the leader rpc server on bind_addr, 0.0.0.0:<peer_port> is used if it is empty.
+    pub fn start_server(&mut self, bind_addr: &String) -> Errno {
+        let mut addr = bind_addr.clone();
+        if addr.is_empty() {
+            addr = format!("0.0.0.0:{}", self.leader_cfg.peer_port);
+        }
+        let local = self.get_leader(&self.machine).clone();
+        let ret = LeaderServer::start(&addr, &self.machine, &self.leader_cfg.rpc_token, self.leader_cfg.rpc_server_conns,
+            self.leader_cfg.rpc_peer_concurrency, local, self.meta_service_mgr.clone());
+        match ret {
+            Ok(ret) => {
+                self.server = Some(ret);
+                return Errno::Esucc;
+            }
+            Err(err) => {
+                println!("start_server: failed to start leader server on {}, err: {}", addr, err);
+                return Errno::Eintr;
+            }
         }
     }
+
+    // start scrubbing the local segment files in background.
+    pub fn start_scrubber(&mut self, cfg: &ScrubConfig) {
+        let scrubber = Arc::new(Scrubber::new(cfg, self.segment_mgr.clone(), self.meta_service_mgr.clone(),
+            self.backend_store.clone()));
+        Scrubber::start(&scrubber);
+        self.scrubber = Some(scrubber);
+    }
+
+    // the report of the last scrub of the local segment files.
+    pub fn scrub_report(&self) -> Option<ScrubReport> {
+        self.scrubber.as_ref().and_then(|s| s.last_report())
+    }
+
     pub fn stop(&mut self){
-        for (k, l) in &mut self.leaders {
+        if let Some(s) = self.scrubber.take() {
+            s.stop();
+            println!("scrubber is stopped.");
+        }
+        if let Some(mut s) = self.server.take() {
+            s.stop();
+        }
+        for (k, l) in &self.leaders {
             l.release();
             println!("leader of {:?} is stopped.", k);
         }
         self.leaders.clear();
+        self.lease_mgr.stop();
+        println!("lease mgr is stopped.");
     }
-    pub fn get_leader(&self, leader: &String) -> &Box<dyn Leader> {
+    pub fn get_leader(&self, leader: &String) -> &Arc<dyn Leader> {
         let mut leader_type = LeaderType::Unknown as u8;
         if *leader == self.machine {
             leader_type = LeaderType::Local as u8;
-        } else if !leader.is_empty(){// peer is not supported yet.
+        } else if !leader.is_empty(){
             leader_type = LeaderType::Peer as u8;
         }
 
+        if let Some(l) = self.leaders.get(&leader_type) {
+            return l;
+        }
         // will not crash here, because unknown is always in the hashmap.
-        self.leaders.get(&leader_type).unwrap()
+        self.leaders.get(&(LeaderType::Unknown as u8)).unwrap()
+    }
+
+    // the upload state of the segments of the local leader.
+    pub fn upload_status(&self) -> Vec<SegmentUpload> {
+        self.local.upload_status()
+    }
+
+    // the segments fetched back from the backend store by the local leader.
+    pub fn fetch_stats(&self) -> FetchStats {
+        self.local.fetch_stats()
+    }
+
+    // evict the uploaded segments of the local leader from the data dirs above the watermark.
+    pub fn evict(&self) -> EvictReport {
+        self.local.evict()
+    }
+
+    // compact the segments of ino led by this machine, see LeaderLocal::compact.
+    pub fn compact(&self, ino: u64, generation: u64) -> Errno {
+        self.local.compact(ino, generation)
+    }
+
+    // compact the files of the local leader, including the closed ones.
+    pub fn gc(&self) {
+        self.local.gc()
     }
-}
\ No newline at end of file
+
+    // the state of the data dirs of the segments on this machine.
+    pub fn dir_status(&self) -> Vec<DirStatus> {
+        self.segment_mgr.dir_status()
+    }
+
+    // choose the machine to lead the new file name under parent.
+    pub fn choose_leader(&self, parent: u64, name: &String) -> String {
+        self.placement.choose(parent, name)
+    }
+
+    // drop the segments of ino cached for reading from the backend store, called when the file
+    // is opened, written or closed on this machine.
+    pub fn invalidate_segments(&self, ino: u64) {
+        self.backend_reader.invalidate(ino);
+    }
+
+    // read ino led by leader. if the file is led by another machine, the sealed data is read from
+    // the backend store directly, and only the rest is read from the leader.
+    pub fn read(&self, leader: &String, ino: u64, generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        if *leader == self.machine || leader.is_empty() {
+            return self.get_leader(leader).read(ino, generation, offset, size);
+        }
+        let mut data: Vec<u8>;
+        let ret = self.backend_reader.read(ino, generation, offset, size);
+        match ret {
+            Ok(ret) => {
+                data = ret;
+            }
+            Err(err) => {
+                println!("read: failed to read ino: {}, offset: {} from backend, read from leader {}, err: {:?}",
+                ino, offset, leader, err);
+                return self.get_leader(leader).read(ino, generation, offset, size);
+            }
+        }
+        let n = data.len();
+        if n >= size as usize {
+            return Ok(data);
+        }
+        let ret = self.get_leader(leader).read(ino, generation, offset + n as u64, size - n as u32);
+        match ret {
+            Ok(ret) => {
+                data.extend(ret);
+                return Ok(data);
+            }
+            Err(err) => {
+                println!("read: failed to read ino: {}, offset: {} from leader {}, err: {:?}",
+                ino, offset + n as u64, leader, err);
+                return Err(err);
+            }
+        }
+    }
+
+    // returns the machine to serve ino. the file is taken over by this machine only if the
+    // lease of its leader is expired, an unreachable leader with a valid lease may still
+    // be writing the file. the io to an unreachable leader asks the meta server to
+    // transfer the file by takeover instead.
+    pub fn resolve_leader(&self, ino: u64, generation: u64, file_leader: &FileLeader) -> String {
+        if file_leader.leader == self.machine || file_leader.leader.is_empty() {
+            return file_leader.leader.clone();
+        }
+        if !file_leader.is_lease_expired() {
+            return file_leader.leader.clone();
+        }
+        println!("resolve_leader: the lease of leader {} for ino: {} is expired, epoch: {}, ttl: {}, take it over",
+        file_leader.leader, ino, file_leader.lease_epoch, file_leader.lease_ttl);
+        let ret = self.takeover(ino, generation, file_leader);
+        match ret {
+            Ok(ret) => {
+                return ret;
+            }
+            Err(err) => {
+                println!("resolve_leader: failed to take over ino: {} from {}, err: {:?}",
+                ino, file_leader.leader, err);
+                return file_leader.leader.clone();
+            }
+        }
+    }
+
+    // ask the meta server to transfer the leadership of ino to this machine, and rebuild
+    // the file handle from the segments in meta server. returns the new leader of ino,
+    // which may be another machine if it takes over the file first, or the old leader
+    // if its lease is not expired yet.
+    pub fn takeover(&self, ino: u64, generation: u64, file_leader: &FileLeader) -> Result<String, Errno> {
+        let ret = self.lease_mgr.transfer(ino, generation, &file_leader.leader, file_leader.lease_epoch);
+        match ret {
+            Ok(lease) => {
+                if lease.leader != self.machine {
+                    return Ok(lease.leader);
+                }
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
+        let ret = self.get_leader(&self.machine).open(ino, generation);
+        if !ret.is_success() {
+            println!("takeover: failed to open ino: {} after taking over from {}, err: {:?}",
+            ino, file_leader.leader, ret);
+            return Err(ret);
+        }
+        println!("takeover: ino: {} is taken over from {}", ino, file_leader.leader);
+        Ok(self.machine.clone())
+    }
+}
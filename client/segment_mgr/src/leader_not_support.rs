@@ -6,23 +6,27 @@ pub struct LeaderNotSupport {
 }
 
 impl Leader for LeaderNotSupport {
-    fn open(&self, _ino: u64) -> Errno {
+    fn open(&self, _ino: u64, _generation: u64) -> Errno {
         Errno::Enotsupp
     }
 
-    fn read(&self, _ino: u64, _offset: u64, _size: u32) -> Result<Vec<u8>, Errno>{
+    fn read(&self, _ino: u64, _generation: u64, _offset: u64, _size: u32) -> Result<Vec<u8>, Errno>{
         Err(Errno::Enotsupp)
     }
 
-    fn write(&self, _ino: u64, _offset: u64, _data: &[u8]) -> Result<BlockIo, Errno> {
+    fn write(&self, _ino: u64, _generation: u64, _offset: u64, _data: &[u8]) -> Result<BlockIo, Errno> {
         Err(Errno::Enotsupp)
     }
 
-    fn close(&self, _ino: u64)->Errno{
+    fn flush(&self, _ino: u64, _generation: u64)->Errno{
         Errno::Enotsupp
     }
 
-    fn release(&mut self){}
+    fn close(&self, _ino: u64, _generation: u64)->Errno{
+        Errno::Enotsupp
+    }
+
+    fn release(&self){}
 }
 
 impl LeaderNotSupport{
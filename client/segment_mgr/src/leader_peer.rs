@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use common::config::LeaderConfig;
+use common::error::Errno;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use crate::leader::Leader;
+use crate::leader_rpc::{RpcOp, RpcRequest, RpcResponse, max_response_data, peer_addr, read_frame, sign_request,
+    write_frame};
+use crate::types::BlockIo;
+
+// ConnPool keeps the idle connections to the peers.
+struct ConnPool {
+    max_idle: usize,
+    timeout: Duration,
+    // peer addr -> idle connections.
+    conns: Mutex<HashMap<String, Vec<TcpStream>>>,
+}
+
+impl ConnPool {
+    fn new(max_idle: usize, timeout: Duration) -> Self {
+        ConnPool{
+            max_idle: max_idle,
+            timeout: timeout,
+            conns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // returns the connection and whether it is reused from the pool.
+    fn get(&self, addr: &String) -> io::Result<(TcpStream, bool)> {
+        if let Some(v) = self.conns.lock().unwrap().get_mut(addr) {
+            if let Some(c) = v.pop() {
+                return Ok((c, true));
+            }
+        }
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, format!("no address for {}", addr));
+        for a in addr.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&a, self.timeout) {
+                Ok(c) => {
+                    c.set_read_timeout(Some(self.timeout))?;
+                    c.set_write_timeout(Some(self.timeout))?;
+                    c.set_nodelay(true)?;
+                    return Ok((c, false));
+                }
+                Err(err) => {
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    fn put(&self, addr: &String, conn: TcpStream) {
+        let mut conns = self.conns.lock().unwrap();
+        let v = conns.entry(addr.clone()).or_insert(Vec::new());
+        if v.len() < self.max_idle {
+            v.push(conn);
+        }
+    }
+
+    fn clear(&self, addr: &String) {
+        self.conns.lock().unwrap().remove(addr);
+    }
+}
+
+// LeaderPeer forwards the io of the files led by other machines to their leaders.
+pub struct LeaderPeer {
+    machine: String,
+    // the shared token to sign the requests.
+    token: String,
+    peer_port: u16,
+    retries: u32,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    // ino -> the machine which leads it.
+    leaders: Mutex<HashMap<u64, String>>,
+    pool: ConnPool,
+}
+
+impl Leader for LeaderPeer {
+    fn open(&self, ino: u64, generation: u64) -> Errno {
+        // the leader may be changed since last open.
+        self.leaders.lock().unwrap().remove(&ino);
+        let req = RpcRequest{
+            op: RpcOp::Open as u8,
+            ino: ino,
+            generation: generation,
+            ..Default::default()
+        };
+        let ret = self.call(req, &[], true);
+        match ret {
+            Ok((resp, _)) => {
+                let err = Errno::from_code(resp.errno);
+                if !err.is_success() {
+                    println!("open: peer failed to open ino: {}, err: {:?}", ino, err);
+                    self.leaders.lock().unwrap().remove(&ino);
+                }
+                return err;
+            }
+            Err(err) => {
+                return err;
+            }
+        }
+    }
+
+    fn read(&self, ino: u64, generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let req = RpcRequest{
+            op: RpcOp::Read as u8,
+            ino: ino,
+            generation: generation,
+            offset: offset,
+            size: size,
+            ..Default::default()
+        };
+        let (resp, data) = self.call(req, &[], true)?;
+        let err = Errno::from_code(resp.errno);
+        if !err.is_success() {
+            println!("read: peer failed to read ino: {}, offset: {}, err: {:?}", ino, offset, err);
+            return Err(err);
+        }
+        Ok(data)
+    }
+
+    fn write(&self, ino: u64, generation: u64, offset: u64, data: &[u8]) -> Result<BlockIo, Errno> {
+        let req = RpcRequest{
+            op: RpcOp::Write as u8,
+            ino: ino,
+            generation: generation,
+            offset: offset,
+            size: data.len() as u32,
+            ..Default::default()
+        };
+        // the write is appended to the segment, so it is not retried once it may be received by the peer.
+        let (resp, _) = self.call(req, data, false)?;
+        let err = Errno::from_code(resp.errno);
+        if !err.is_success() {
+            println!("write: peer failed to write ino: {}, offset: {}, err: {:?}", ino, offset, err);
+            return Err(err);
+        }
+        Ok(BlockIo{
+            id0: resp.id0,
+            id1: resp.id1,
+            offset: resp.offset,
+            size: resp.size,
+        })
+    }
+
+    fn flush(&self, ino: u64, generation: u64) -> Errno {
+        let req = RpcRequest{
+            op: RpcOp::Flush as u8,
+            ino: ino,
+            generation: generation,
+            ..Default::default()
+        };
+        let ret = self.call(req, &[], true);
+        match ret {
+            Ok((resp, _)) => {
+                return Errno::from_code(resp.errno);
+            }
+            Err(err) => {
+                return err;
+            }
+        }
+    }
+
+    fn close(&self, ino: u64, generation: u64) -> Errno {
+        let req = RpcRequest{
+            op: RpcOp::Close as u8,
+            ino: ino,
+            generation: generation,
+            ..Default::default()
+        };
+        let ret = self.call(req, &[], true);
+        self.leaders.lock().unwrap().remove(&ino);
+        match ret {
+            Ok((resp, _)) => {
+                return Errno::from_code(resp.errno);
+            }
+            Err(err) => {
+                return err;
+            }
+        }
+    }
+
+    fn release(&self) {
+        self.leaders.lock().unwrap().clear();
+        self.pool.conns.lock().unwrap().clear();
+    }
+}
+
+impl LeaderPeer {
+    pub fn new(machine: &String, cfg: &LeaderConfig, meta_service_mgr: Arc<dyn MetaServiceMgr>) -> Self {
+        LeaderPeer{
+            machine: machine.clone(),
+            token: cfg.rpc_token.clone(),
+            peer_port: cfg.peer_port,
+            retries: cfg.rpc_retries,
+            meta_service_mgr: meta_service_mgr,
+            leaders: Mutex::new(HashMap::new()),
+            pool: ConnPool::new(cfg.rpc_pool_size, Duration::from_millis(cfg.rpc_timeout_ms)),
+        }
+    }
+
+    fn get_file_leader(&self, ino: u64) -> Result<String, Errno> {
+        if let Some(l) = self.leaders.lock().unwrap().get(&ino) {
+            return Ok(l.clone());
+        }
+        let ret = self.meta_service_mgr.get_file_leader(ino);
+        match ret {
+            Ok(ret) => {
+                self.leaders.lock().unwrap().insert(ino, ret.leader.clone());
+                return Ok(ret.leader);
+            }
+            Err(err) => {
+                println!("get_file_leader: failed to get leader of ino: {}, err: {:?}", ino, err);
+                return Err(err);
+            }
+        }
+    }
+
+    // send the request to the leader of ino and wait for the response. returns Eunreach if the
+    // leader can't be connected after retries. retry is true if the request can be sent again
+    // after the peer may have received it.
+    fn call(&self, mut req: RpcRequest, data: &[u8], retry: bool) -> Result<(RpcResponse, Vec<u8>), Errno> {
+        let leader = self.get_file_leader(req.ino)?;
+        req.machine = self.machine.clone();
+        let addr = peer_addr(&leader, self.peer_port);
+        for i in 0..(self.retries + 1) {
+            if i > 0 {
+                thread::sleep(Duration::from_millis(100 * i as u64));
+            }
+            let (mut conn, reused) = match self.pool.get(&addr) {
+                Ok(c) => c,
+                Err(err) => {
+                    println!("call: failed to connect to leader {} for ino: {}, err: {}", addr, req.ino, err);
+                    continue;
+                }
+            };
+            if let Err(err) = sign_request(&self.token, &mut req, data) {
+                println!("call: failed to sign op: {} for ino: {}, err: {}", req.op, req.ino, err);
+                return Err(Errno::Eintr);
+            }
+            if let Err(err) = write_frame(&mut conn, &req, data) {
+                println!("call: failed to send op: {} to {} for ino: {}, err: {}", req.op, addr, req.ino, err);
+                // the part sent may be applied by the peer, unless the idle connection was closed before.
+                let closed = reused && LeaderPeer::is_closed(&err);
+                if closed {
+                    self.pool.clear(&addr);
+                }
+                if !retry && !closed {
+                    return Err(Errno::Eintr);
+                }
+                continue;
+            }
+            let ret = read_frame::<TcpStream, RpcResponse, _>(&mut conn, |_| max_response_data(&req));
+            match ret {
+                Ok(ret) => {
+                    self.pool.put(&addr, conn);
+                    return Ok(ret);
+                }
+                Err(err) => {
+                    println!("call: failed to recv op: {} from {} for ino: {}, err: {}", req.op, addr, req.ino, err);
+                    // the idle connection may be closed by the peer before the request arrives.
+                    let closed = reused && err.kind() == io::ErrorKind::UnexpectedEof;
+                    if closed {
+                        self.pool.clear(&addr);
+                    }
+                    if !retry && !closed {
+                        return Err(Errno::Eintr);
+                    }
+                }
+            }
+        }
+        println!("call: leader {} of ino: {} is unreachable after {} retries", addr, req.ino, self.retries);
+        self.leaders.lock().unwrap().remove(&req.ino);
+        Err(Errno::Eunreach)
+    }
+
+    // the errors of sending into a connection closed by the peer.
+    fn is_closed(err: &io::Error) -> bool {
+        match err.kind() {
+            io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use common::config::LeaderConfig;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::MachineInfo;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PlacementPolicy {
+    // this machine leads all the files it creates.
+    Local = 0,
+    // the machines in the same zone, chosen by weight.
+    Zone = 1,
+    // the machines in all the zones, chosen by weight.
+    Weight = 2,
+    // the least loaded machine in the same zone.
+    Load = 3,
+}
+
+impl PlacementPolicy {
+    pub fn from_str(s: &str) -> Result<PlacementPolicy, String> {
+        match s {
+            "" | "local" => Ok(PlacementPolicy::Local),
+            "zone" => Ok(PlacementPolicy::Zone),
+            "weight" => Ok(PlacementPolicy::Weight),
+            "load" => Ok(PlacementPolicy::Load),
+            _ => Err(format!("unknown placement policy: {}", s)),
+        }
+    }
+}
+
+struct MachineCache {
+    machines: Vec<MachineInfo>,
+    refreshed: Option<Instant>,
+}
+
+// LeaderPlacement chooses the leader of the new files by the placement policy.
+// the zone of this machine is the one it is registered with in the meta server.
+pub struct LeaderPlacement {
+    machine: String,
+    policy: PlacementPolicy,
+    refresh_interval: Duration,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    cache: Mutex<MachineCache>,
+}
+
+impl LeaderPlacement {
+    pub fn new(machine: &String, cfg: &LeaderConfig, meta_service_mgr: Arc<dyn MetaServiceMgr>) -> Self {
+        let policy = match PlacementPolicy::from_str(&cfg.placement_policy) {
+            Ok(p) => p,
+            Err(err) => {
+                println!("LeaderPlacement: {}, lead the new files locally", err);
+                PlacementPolicy::Local
+            }
+        };
+        LeaderPlacement{
+            machine: machine.clone(),
+            policy: policy,
+            refresh_interval: Duration::from_secs(cfg.placement_refresh_secs),
+            meta_service_mgr: meta_service_mgr,
+            cache: Mutex::new(MachineCache{
+                machines: Vec::new(),
+                refreshed: None,
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> PlacementPolicy {
+        self.policy
+    }
+
+    // choose the leader of the new file name under parent. this machine is chosen if
+    // no other machine is eligible or the machines can't be got from the meta server.
+    // the zone policies never place the files out of the zone, so this machine is also
+    // chosen if it isn't registered in any zone.
+    pub fn choose(&self, parent: u64, name: &String) -> String {
+        if self.policy == PlacementPolicy::Local {
+            return self.machine.clone();
+        }
+        let mut cache = self.cache.lock().unwrap();
+        self.refresh(&mut cache);
+        let zone = cache.machines.iter().find(|m| m.machine == self.machine).map(|m| m.zone.clone());
+        if zone.is_none() && self.policy != PlacementPolicy::Weight && !cache.machines.is_empty() {
+            println!("LeaderPlacement: machine {} is not registered in any zone, lead the new file {} under {} locally",
+            self.machine, name, parent);
+            return self.machine.clone();
+        }
+        let mut candidates: Vec<usize> = Vec::new();
+        for (i, m) in cache.machines.iter().enumerate() {
+            if !m.is_up() {
+                continue;
+            }
+            if self.policy != PlacementPolicy::Weight && zone.as_ref() != Some(&m.zone) {
+                continue;
+            }
+            candidates.push(i);
+        }
+        if candidates.is_empty() {
+            return self.machine.clone();
+        }
+        let idx = match self.policy {
+            PlacementPolicy::Load => {
+                LeaderPlacement::least_loaded(&cache.machines, &candidates, &self.machine)
+            }
+            _ => {
+                LeaderPlacement::by_weight(&cache.machines, &candidates, parent, name)
+            }
+        };
+        // count the new file, so that the files created before next refresh are spread.
+        cache.machines[idx].load += 1;
+        cache.machines[idx].machine.clone()
+    }
+
+    fn refresh(&self, cache: &mut MachineCache) {
+        if let Some(t) = cache.refreshed {
+            if t.elapsed() < self.refresh_interval {
+                return;
+            }
+        }
+        let ret = self.meta_service_mgr.get_zone_machines();
+        match ret {
+            Ok(ret) => {
+                cache.machines = ret;
+            }
+            Err(err) => {
+                // keep the old machines, and try again after the interval.
+                println!("LeaderPlacement: failed to get the machines of zones, err: {:?}", err);
+            }
+        }
+        cache.refreshed = Some(Instant::now());
+    }
+
+    // the same file name is always mapped to the same machine if the machines are not changed.
+    fn by_weight(machines: &Vec<MachineInfo>, candidates: &Vec<usize>, parent: u64, name: &String) -> usize {
+        let mut total: u64 = 0;
+        for i in candidates {
+            total += std::cmp::max(machines[*i].weight, 1) as u64;
+        }
+        let mut hasher = DefaultHasher::new();
+        parent.hash(&mut hasher);
+        name.hash(&mut hasher);
+        let mut point = hasher.finish() % total;
+        for i in candidates {
+            let w = std::cmp::max(machines[*i].weight, 1) as u64;
+            if point < w {
+                return *i;
+            }
+            point -= w;
+        }
+        candidates[candidates.len() - 1]
+    }
+
+    // the load is relative to the weight, this machine wins the tie to save the forwarding.
+    fn least_loaded(machines: &Vec<MachineInfo>, candidates: &Vec<usize>, local: &String) -> usize {
+        let mut best = candidates[0];
+        for i in candidates {
+            let (a, b) = (&machines[*i], &machines[best]);
+            // a.load / a.weight < b.load / b.weight
+            let lhs = a.load as u128 * std::cmp::max(b.weight, 1) as u128;
+            let rhs = b.load as u128 * std::cmp::max(a.weight, 1) as u128;
+            if lhs < rhs || (lhs == rhs && a.machine == *local) {
+                best = *i;
+            }
+        }
+        best
+    }
+}
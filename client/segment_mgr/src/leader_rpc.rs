@@ -0,0 +1,173 @@
+extern crate serde;
+
+use std::io;
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+use common::json;
+use common::uuid;
+
+// the protocol between the peer leaders, every request & response is one frame:
+// magic(u32) | header_len(u32) | data_len(u32) | json header | data
+// all the integers are big endian.
+pub const RPC_MAGIC: u32 = 0x59494746;
+const MAX_HEADER_LEN: u32 = 64 * 1024;
+// the max data of a write request, which is the max write size of fuse.
+pub const MAX_WRITE_LEN: u32 = 16 * 1024 * 1024;
+// the requests signed out of this window are rejected.
+pub const RPC_AUTH_WINDOW_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RpcOp {
+    Open = 1,
+    Read = 2,
+    Write = 3,
+    Close = 4,
+    Flush = 5,
+}
+
+impl RpcOp {
+    pub fn from_u8(op: u8) -> Option<RpcOp> {
+        match op {
+            1 => Some(RpcOp::Open),
+            2 => Some(RpcOp::Read),
+            3 => Some(RpcOp::Write),
+            4 => Some(RpcOp::Close),
+            5 => Some(RpcOp::Flush),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RpcRequest {
+    pub op: u8,
+    pub ino: u64,
+    pub generation: u64,
+    pub offset: u64,
+    // the size to read.
+    pub size: u32,
+    // the machine which sends the request.
+    #[serde(default)]
+    pub machine: String,
+    // the unix seconds when the request is signed.
+    #[serde(default)]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub nonce: String,
+    // hmac-sha256 of the request and the sha256 of data by the shared token.
+    #[serde(default)]
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RpcResponse {
+    // the code of Errno.
+    pub errno: i32,
+    // the block written by write.
+    pub id0: u64,
+    pub id1: u64,
+    pub offset: u64,
+    pub size: u32,
+}
+
+// the address of leader rpc server for machine, the machine may carry its own port,
+// such as "127.0.0.1:9099", otherwise the peer_port is used.
+pub fn peer_addr(machine: &String, peer_port: u16) -> String {
+    if machine.contains(':') {
+        return machine.clone();
+    }
+    format!("{}:{}", machine, peer_port)
+}
+
+// the max data carried by req, only the write request has data.
+pub fn max_request_data(req: &RpcRequest) -> u32 {
+    if RpcOp::from_u8(req.op) == Some(RpcOp::Write) {
+        return MAX_WRITE_LEN;
+    }
+    0
+}
+
+// the max data of the response to req, only the read response has data up to the size to read.
+pub fn max_response_data(req: &RpcRequest) -> u32 {
+    if RpcOp::from_u8(req.op) == Some(RpcOp::Read) {
+        return req.size;
+    }
+    0
+}
+
+pub fn now_secs() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
+
+// sign the request by the token shared by the machines of the cluster.
+pub fn sign_request(token: &String, req: &mut RpcRequest, data: &[u8]) -> Result<(), String> {
+    req.timestamp = now_secs();
+    req.nonce = uuid::uuid_string();
+    let mut mac = Hmac::<Sha256>::new_varkey(token.as_bytes())
+        .map_err(|err| format!("failed to create hmac, err: {}", err))?;
+    mac.update(string_to_sign(req, data).as_bytes());
+    req.signature = hex::encode(mac.finalize().into_bytes());
+    Ok(())
+}
+
+// only checks the signature, the timestamp & nonce are checked by the server.
+pub fn verify_signature(token: &String, req: &RpcRequest, data: &[u8]) -> bool {
+    let signature = match hex::decode(&req.signature) {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    let mut mac = match Hmac::<Sha256>::new_varkey(token.as_bytes()) {
+        Ok(m) => m,
+        Err(_) => return false,
+    };
+    mac.update(string_to_sign(req, data).as_bytes());
+    mac.verify(&signature).is_ok()
+}
+
+fn string_to_sign(req: &RpcRequest, data: &[u8]) -> String {
+    format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}", req.op, req.ino, req.generation, req.offset, req.size,
+        req.machine, req.timestamp, req.nonce, hex::encode(Sha256::digest(data)))
+}
+
+pub fn write_frame<W: Write, T: Default + Serialize>(w: &mut W, header: &T, data: &[u8]) -> io::Result<()> {
+    let h = json::encode_to_str::<T>(header).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut buf = Vec::<u8>::with_capacity(12 + h.len() + data.len());
+    buf.extend_from_slice(&RPC_MAGIC.to_be_bytes());
+    buf.extend_from_slice(&(h.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(h.as_bytes());
+    buf.extend_from_slice(data);
+    w.write_all(&buf)?;
+    w.flush()
+}
+
+// read one frame, the data is rejected before it is read if it is longer than max_data_len of the header.
+pub fn read_frame<R: Read, T: Default + for<'de> Deserialize<'de>, F: Fn(&T) -> u32>(r: &mut R, max_data_len: F)
+    -> io::Result<(T, Vec<u8>)> {
+    let mut prefix = [0u8; 12];
+    r.read_exact(&mut prefix)?;
+    let magic = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]);
+    let header_len = u32::from_be_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]);
+    let data_len = u32::from_be_bytes([prefix[8], prefix[9], prefix[10], prefix[11]]);
+    if magic != RPC_MAGIC || header_len > MAX_HEADER_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("invalid frame, magic: {:x}, header_len: {}, data_len: {}", magic, header_len, data_len)));
+    }
+    let mut h = vec![0u8; header_len as usize];
+    r.read_exact(&mut h)?;
+    let h = String::from_utf8(h).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let header = json::decode_from_str::<T>(&h).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if data_len > max_data_len(&header) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("invalid frame, data_len: {} is more than {}", data_len, max_data_len(&header))));
+    }
+    let mut data = vec![0u8; data_len as usize];
+    r.read_exact(&mut data)?;
+    Ok((header, data))
+}
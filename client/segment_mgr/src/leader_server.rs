@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crossbeam_channel::{Receiver, Sender};
+use common::defer;
+use common::error::Errno;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use crate::leader::Leader;
+use crate::leader_rpc::{RPC_AUTH_WINDOW_SECS, RpcOp, RpcRequest, RpcResponse, max_request_data, now_secs,
+    read_frame, verify_signature, write_frame};
+
+// the connection from peer is closed if no request comes in this duration.
+const CONN_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+// purge the expired nonces once there are so many.
+const MAX_NONCES: usize = 100000;
+// the leader of an ino checked with the meta server is trusted for this duration.
+const LEADER_CHECK_TTL: Duration = Duration::from_secs(10);
+
+struct ServerCtx {
+    machine: String,
+    leader: Arc<dyn Leader>,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    // ino -> (generation, checked time), the inos confirmed to be led by this machine.
+    led_inos: Mutex<HashMap<u64, (u64, Instant)>>,
+    token: String,
+    stopped: AtomicBool,
+    max_peer_requests: usize,
+    // peer machine -> the requests being served.
+    peer_requests: Mutex<HashMap<String, usize>>,
+    peer_cond: Condvar,
+    // nonce -> timestamp, the nonces seen in the auth window to reject the replayed requests.
+    nonces: Mutex<HashMap<String, i64>>,
+}
+
+// LeaderServer serves the open/read/write/close requests from the peers for the inos
+// led by this machine, and dispatches them into the local leader.
+// the connections are served by a fixed number of workers, the new connection is
+// closed if all the workers are busy.
+pub struct LeaderServer {
+    addr: SocketAddr,
+    ctx: Arc<ServerCtx>,
+    accept_th: Option<JoinHandle<()>>,
+}
+
+impl LeaderServer {
+    pub fn start(bind_addr: &String, machine: &String, token: &String, max_conns: usize, max_peer_requests: usize,
+        leader: Arc<dyn Leader>, meta_service_mgr: Arc<dyn MetaServiceMgr>) -> Result<LeaderServer, String> {
+        if token.is_empty() {
+            return Err(String::from("the token of leader rpc is not set"));
+        }
+        let listener = TcpListener::bind(bind_addr).map_err(|err| format!("failed to bind {}, err: {}", bind_addr, err))?;
+        let addr = listener.local_addr().map_err(|err| format!("failed to get local addr of {}, err: {}", bind_addr, err))?;
+        let ctx = Arc::new(ServerCtx{
+            machine: machine.clone(),
+            leader: leader,
+            meta_service_mgr: meta_service_mgr,
+            led_inos: Mutex::new(HashMap::new()),
+            token: token.clone(),
+            stopped: AtomicBool::new(false),
+            max_peer_requests: std::cmp::max(max_peer_requests, 1),
+            peer_requests: Mutex::new(HashMap::new()),
+            peer_cond: Condvar::new(),
+            nonces: Mutex::new(HashMap::new()),
+        });
+        // the workers exit once the accept loop stops and drops the sender.
+        let (tx, rx) = crossbeam_channel::bounded::<TcpStream>(0);
+        for _ in 0..std::cmp::max(max_conns, 1) {
+            let rx = rx.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || LeaderServer::work(rx, ctx));
+        }
+        let accept_ctx = ctx.clone();
+        println!("leader server is listening on {} with {} workers", addr, std::cmp::max(max_conns, 1));
+        Ok(LeaderServer{
+            addr: addr,
+            ctx: ctx,
+            accept_th: Some(thread::spawn(move || LeaderServer::accept(listener, accept_ctx, tx))),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(&mut self) {
+        self.ctx.stopped.store(true, Ordering::SeqCst);
+        // wake up the accept loop.
+        let mut addr = self.addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip([127, 0, 0, 1].into());
+        }
+        let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+        if let Some(h) = self.accept_th.take() {
+            let ret = h.join();
+            match ret {
+                Ok(_) => {
+                    println!("LeaderServer has stopped.");
+                }
+                Err(_) => {
+                    println!("LeaderServer failes to stop, join failed");
+                }
+            }
+        }
+    }
+
+    fn accept(listener: TcpListener, ctx: Arc<ServerCtx>, conns: Sender<TcpStream>) {
+        for conn in listener.incoming() {
+            if ctx.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            match conn {
+                Ok(conn) => {
+                    // hand the connection to an idle worker, or close it if all of them are busy.
+                    if let Err(err) = conns.try_send(conn) {
+                        let peer = err.into_inner().peer_addr().map(|a| a.to_string()).unwrap_or_default();
+                        println!("leader server: all the workers are busy, close the connection from {}", peer);
+                    }
+                }
+                Err(err) => {
+                    println!("leader server failed to accept, err: {}", err);
+                }
+            }
+        }
+    }
+
+    fn work(conns: Receiver<TcpStream>, ctx: Arc<ServerCtx>) {
+        for conn in conns.iter() {
+            if ctx.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            LeaderServer::serve_conn(conn, &ctx);
+        }
+    }
+
+    fn serve_conn(mut conn: TcpStream, ctx: &Arc<ServerCtx>) {
+        let peer = match conn.peer_addr() {
+            Ok(a) => a.to_string(),
+            Err(_) => String::from("unknown"),
+        };
+        if let Err(err) = conn.set_read_timeout(Some(CONN_IDLE_TIMEOUT)) {
+            println!("serve_conn: failed to set read timeout for {}, err: {}", peer, err);
+            return;
+        }
+        let _ = conn.set_nodelay(true);
+        loop {
+            if ctx.stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            let (req, data) = match read_frame::<TcpStream, RpcRequest, _>(&mut conn, max_request_data) {
+                Ok(r) => r,
+                Err(err) => {
+                    if err.kind() != io::ErrorKind::UnexpectedEof {
+                        println!("serve_conn: failed to read request from {}, err: {}", peer, err);
+                    }
+                    break;
+                }
+            };
+            let (resp, out) = ctx.serve(&req, &data);
+            if let Err(err) = write_frame(&mut conn, &resp, &out) {
+                println!("serve_conn: failed to send response of op: {} for ino: {} to {}, err: {}",
+                req.op, req.ino, peer, err);
+                break;
+            }
+        }
+    }
+}
+
+impl ServerCtx {
+    fn serve(&self, req: &RpcRequest, data: &[u8]) -> (RpcResponse, Vec<u8>) {
+        let mut resp = RpcResponse::default();
+        let err = self.authenticate(req, data);
+        if !err.is_success() {
+            println!("serve: reject op: {} for ino: {} from {}, err: {:?}", req.op, req.ino, req.machine, err);
+            resp.errno = err as i32;
+            return (resp, Vec::new());
+        }
+        // the peer may send the requests of an ino led by another machine now.
+        let err = self.check_leader(req.ino, req.generation, RpcOp::from_u8(req.op) == Some(RpcOp::Open));
+        if !err.is_success() {
+            resp.errno = err as i32;
+            return (resp, Vec::new());
+        }
+        self.acquire_peer(&req.machine);
+        defer!{
+            self.release_peer(&req.machine);
+        };
+        let mut out = Vec::new();
+        let err = match RpcOp::from_u8(req.op) {
+            Some(RpcOp::Open) => {
+                self.leader.open(req.ino, req.generation)
+            }
+            Some(RpcOp::Read) => {
+                match self.leader.read(req.ino, req.generation, req.offset, req.size) {
+                    Ok(d) => {
+                        out = d;
+                        Errno::Esucc
+                    }
+                    Err(err) => err,
+                }
+            }
+            Some(RpcOp::Write) => {
+                match self.leader.write(req.ino, req.generation, req.offset, data) {
+                    Ok(b) => {
+                        resp.id0 = b.id0;
+                        resp.id1 = b.id1;
+                        resp.offset = b.offset;
+                        resp.size = b.size;
+                        Errno::Esucc
+                    }
+                    Err(err) => err,
+                }
+            }
+            Some(RpcOp::Flush) => {
+                self.leader.flush(req.ino, req.generation)
+            }
+            Some(RpcOp::Close) => {
+                self.leader.close(req.ino, req.generation)
+            }
+            None => {
+                println!("serve: got unknown op: {} from {}", req.op, req.machine);
+                Errno::Enotsupp
+            }
+        };
+        resp.errno = err as i32;
+        (resp, out)
+    }
+
+    fn authenticate(&self, req: &RpcRequest, data: &[u8]) -> Errno {
+        if !verify_signature(&self.token, req, data) {
+            return Errno::Eaccess;
+        }
+        let now = now_secs();
+        if (now - req.timestamp).abs() > RPC_AUTH_WINDOW_SECS {
+            return Errno::Eaccess;
+        }
+        let mut nonces = self.nonces.lock().unwrap();
+        if nonces.contains_key(&req.nonce) {
+            return Errno::Eaccess;
+        }
+        if nonces.len() >= MAX_NONCES {
+            nonces.retain(|_, ts| (now - *ts).abs() <= RPC_AUTH_WINDOW_SECS);
+        }
+        nonces.insert(req.nonce.clone(), req.timestamp);
+        Errno::Esucc
+    }
+
+    // check that ino is led by this machine before dispatching its requests into the local leader.
+    // open always asks the meta server, the others trust the result within LEADER_CHECK_TTL.
+    fn check_leader(&self, ino: u64, generation: u64, force: bool) -> Errno {
+        if !force {
+            let led = self.led_inos.lock().unwrap();
+            if let Some((g, checked)) = led.get(&ino) {
+                if *g == generation && checked.elapsed() < LEADER_CHECK_TTL {
+                    return Errno::Esucc;
+                }
+            }
+        }
+        let ret = self.meta_service_mgr.get_file_leader(ino);
+        match ret {
+            Ok(ret) => {
+                if ret.leader != self.machine {
+                    println!("check_leader: ino: {} is led by {}, not {}", ino, ret.leader, self.machine);
+                    self.led_inos.lock().unwrap().remove(&ino);
+                    return Errno::Eexists;
+                }
+            }
+            Err(err) => {
+                println!("check_leader: failed to get leader of ino: {}, err: {:?}", ino, err);
+                return err;
+            }
+        }
+        self.led_inos.lock().unwrap().insert(ino, (generation, Instant::now()));
+        Errno::Esucc
+    }
+
+    // wait until the requests being served for the peer are less than the limit.
+    fn acquire_peer(&self, machine: &String) {
+        let mut peers = self.peer_requests.lock().unwrap();
+        loop {
+            let n = *peers.get(machine).unwrap_or(&0);
+            if n < self.max_peer_requests {
+                peers.insert(machine.clone(), n + 1);
+                return;
+            }
+            peers = self.peer_cond.wait(peers).unwrap();
+        }
+    }
+
+    fn release_peer(&self, machine: &String) {
+        let mut peers = self.peer_requests.lock().unwrap();
+        let n = *peers.get(machine).unwrap_or(&1);
+        if n <= 1 {
+            peers.remove(machine);
+        } else {
+            peers.insert(machine.clone(), n - 1);
+        }
+        self.peer_cond.notify_all();
+    }
+}
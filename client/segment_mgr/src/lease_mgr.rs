@@ -0,0 +1,248 @@
+extern crate crossbeam_channel;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crossbeam_channel::{Sender, Receiver, bounded, select, tick};
+use common::error::Errno;
+use common::config::LeaderConfig;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::FileLease;
+
+struct LocalLease {
+    lease: FileLease,
+    // the lease is treated as expired locally after deadline, even if the renew
+    // request is still in flight, so that the leader stops writing before others take over.
+    deadline: Instant,
+}
+
+// LeaseMgr holds the leader leases of the files opened on this machine, and renews them
+// in the background before they expire.
+pub struct LeaseMgr {
+    machine: String,
+    lease_secs: u64,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    leases: Arc<Mutex<HashMap<u64, LocalLease>>>,
+    stop_tx: Sender<u32>,
+    renew_th: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl LeaseMgr {
+    pub fn new(machine: &String, cfg: &LeaderConfig, meta_service_mgr: Arc<dyn MetaServiceMgr>) -> Self {
+        let (stop_tx, stop_rx) = bounded::<u32>(1);
+        let leases = Arc::new(Mutex::new(HashMap::<u64, LocalLease>::new()));
+        let mut renew_interval = cfg.renew_interval_secs;
+        if renew_interval == 0 || renew_interval >= cfg.lease_secs {
+            renew_interval = cfg.lease_secs / 3 + 1;
+        }
+        let mut renewer = LeaseRenewer{
+            lease_secs: cfg.lease_secs,
+            interval: Duration::from_secs(renew_interval),
+            meta_service_mgr: meta_service_mgr.clone(),
+            leases: leases.clone(),
+            stop_rx: stop_rx,
+        };
+        LeaseMgr{
+            machine: machine.clone(),
+            lease_secs: cfg.lease_secs,
+            meta_service_mgr: meta_service_mgr,
+            leases: leases,
+            stop_tx: stop_tx,
+            renew_th: Mutex::new(Some(thread::spawn(move || renewer.start()))),
+        }
+    }
+
+    // acquire the lease of ino. the returned lease may be held by another machine,
+    // the caller must check the leader of it. the flag is true if the lease is granted
+    // by this call, false if this machine holds it already.
+    pub fn acquire(&self, ino: u64, generation: u64) -> Result<(FileLease, bool), Errno> {
+        if let Some(lease) = self.get(ino, generation) {
+            return Ok((lease, false));
+        }
+        let now = Instant::now();
+        let lease: FileLease;
+        let ret = self.meta_service_mgr.acquire_file_lease(ino, generation, self.lease_secs);
+        match ret {
+            Ok(ret) => {
+                lease = ret;
+            }
+            Err(err) => {
+                println!("acquire: failed to acquire lease for ino: {}, generation: {}, err: {:?}",
+                ino, generation, err);
+                return Err(err);
+            }
+        }
+        Ok((self.hold(now, lease), true))
+    }
+
+    // take over the lease of ino from the unreachable leader.
+    pub fn transfer(&self, ino: u64, generation: u64, from_leader: &String, from_epoch: u64) -> Result<FileLease, Errno> {
+        let now = Instant::now();
+        let lease: FileLease;
+        let ret = self.meta_service_mgr.transfer_file_leader(ino, generation, from_leader, from_epoch, self.lease_secs);
+        match ret {
+            Ok(ret) => {
+                lease = ret;
+            }
+            Err(err) => {
+                println!("transfer: failed to transfer lease of ino: {} from {} with epoch: {}, err: {:?}",
+                ino, from_leader, from_epoch, err);
+                return Err(err);
+            }
+        }
+        Ok(self.hold(now, lease))
+    }
+
+    // get the unexpired lease of ino held by this machine.
+    pub fn get(&self, ino: u64, generation: u64) -> Option<FileLease> {
+        let leases = self.leases.lock().unwrap();
+        if let Some(l) = leases.get(&ino) {
+            if l.lease.generation == generation && l.deadline > Instant::now() {
+                return Some(l.lease.clone());
+            }
+        }
+        None
+    }
+
+    // the writes of a leader must be rejected if its lease epoch is not valid any more.
+    pub fn is_valid(&self, ino: u64, epoch: u64) -> bool {
+        let leases = self.leases.lock().unwrap();
+        if let Some(l) = leases.get(&ino) {
+            return l.lease.epoch == epoch && l.deadline > Instant::now();
+        }
+        false
+    }
+
+    pub fn release(&self, ino: u64) -> Errno {
+        let l = self.leases.lock().unwrap().remove(&ino);
+        if let Some(l) = l {
+            return self.meta_service_mgr.release_file_lease(ino, l.lease.generation, l.lease.epoch);
+        }
+        Errno::Esucc
+    }
+
+    pub fn stop(&self) {
+        let ret = self.stop_tx.send(1);
+        match ret {
+            Ok(_) => {}
+            Err(err) => {
+                println!("failed to stop lease mgr, err: {}", err);
+            }
+        }
+        if let Some(h) = self.renew_th.lock().unwrap().take() {
+            let ret = h.join();
+            match ret {
+                Ok(_) => {
+                    println!("LeaseRenewer has stopped.");
+                }
+                Err(_) => {
+                    println!("LeaseRenewer failes to stop, join failed");
+                }
+            }
+        }
+        // release all the leases, so that others can take over the files at once.
+        let inos: Vec<u64> = self.leases.lock().unwrap().keys().cloned().collect();
+        for ino in inos {
+            let ret = self.release(ino);
+            if !ret.is_success() {
+                println!("stop: failed to release lease for ino: {}, err: {:?}", ino, ret);
+            }
+        }
+    }
+
+    // keep the lease if it is granted to this machine.
+    fn hold(&self, start: Instant, lease: FileLease) -> FileLease {
+        if lease.leader != self.machine {
+            println!("the lease of ino: {} is held by {} with epoch: {}, ttl: {}",
+            lease.ino, lease.leader, lease.epoch, lease.ttl);
+            return lease;
+        }
+        self.leases.lock().unwrap().insert(lease.ino, LocalLease{
+            lease: lease.clone(),
+            deadline: LeaseMgr::deadline(start, self.lease_secs, lease.ttl),
+        });
+        lease
+    }
+
+    // start is the time the request is sent, so the local deadline is never later than the one of meta server.
+    fn deadline(start: Instant, lease_secs: u64, ttl: i64) -> Instant {
+        let mut secs = lease_secs;
+        if ttl > 0 && (ttl as u64) < secs {
+            secs = ttl as u64;
+        }
+        start + Duration::from_secs(secs)
+    }
+}
+
+struct LeaseRenewer {
+    lease_secs: u64,
+    interval: Duration,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    leases: Arc<Mutex<HashMap<u64, LocalLease>>>,
+    stop_rx: Receiver<u32>,
+}
+
+impl LeaseRenewer {
+    fn start(&mut self) {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(ticker) -> _ => {
+                    self.renew_all();
+                },
+                recv(self.stop_rx) -> msg => {
+                    match msg {
+                        Ok(_) => {
+                            println!("LeaseRenewer got stop signal, stop the loop...");
+                            break;
+                        }
+                        Err(err) => {
+                            println!("LeaseRenewer recv invalid stop signal with err: {} and stop the loop...", err);
+                            break;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn renew_all(&self) {
+        let leases: Vec<FileLease> = self.leases.lock().unwrap().values().map(|l| l.lease.clone()).collect();
+        for l in leases {
+            let now = Instant::now();
+            let ret = self.meta_service_mgr.renew_file_lease(l.ino, l.generation, l.epoch, self.lease_secs);
+            match ret {
+                Ok(ret) => {
+                    let mut leases = self.leases.lock().unwrap();
+                    if let Some(local) = leases.get_mut(&l.ino) {
+                        // the lease may be released and acquired again during renew.
+                        if local.lease.epoch == l.epoch {
+                            local.lease.ttl = ret.ttl;
+                            local.deadline = LeaseMgr::deadline(now, self.lease_secs, ret.ttl);
+                        }
+                    }
+                }
+                Err(err) => {
+                    if err.is_estale() {
+                        println!("renew: the lease of ino: {} with epoch: {} is taken over by others",
+                        l.ino, l.epoch);
+                        let mut leases = self.leases.lock().unwrap();
+                        let taken = match leases.get(&l.ino) {
+                            Some(local) => local.lease.epoch == l.epoch,
+                            None => false,
+                        };
+                        if taken {
+                            leases.remove(&l.ino);
+                        }
+                        continue;
+                    }
+                    // keep the lease, it expires at the deadline if the meta server is unreachable.
+                    println!("renew: failed to renew lease for ino: {} with epoch: {}, err: {:?}",
+                    l.ino, l.epoch, err);
+                }
+            }
+        }
+    }
+}
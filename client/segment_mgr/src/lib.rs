@@ -3,5 +3,23 @@ pub mod segment_mgr;
 pub mod leader_mgr;
 pub mod leader;
 pub mod file_handle;
+pub mod lease_mgr;
+pub mod leader_rpc;
+pub mod leader_server;
+pub mod backend_reader;
+pub mod leader_placement;
+pub mod extent_map;
+pub mod write_buffer;
+pub mod readahead;
+pub mod compaction;
+pub mod recovery;
+pub mod segment_index;
+pub mod dir_placement;
+pub mod dir_health;
 mod leader_local;
-mod leader_not_support;
\ No newline at end of file
+mod leader_not_support;
+mod leader_peer;
+pub mod scrubber;
+pub mod uploader;
+pub mod fetcher;
+pub mod evict_mgr;
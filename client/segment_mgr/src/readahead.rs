@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+// ReadPattern detects the sequential reads of a file, and decides the range to read ahead.
+#[derive(Debug, Default)]
+pub struct ReadPattern {
+    // the end of the last read.
+    next: u64,
+    // how many reads follow each other.
+    seq: u32,
+    // the end of the data read ahead.
+    ra_end: u64,
+}
+
+impl ReadPattern {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // record the read of [offset, offset + size), returns the range to read ahead if the reads
+    // are sequential and less than half of window is read ahead of them.
+    pub fn on_read(&mut self, offset: u64, size: u32, window: u64) -> Option<(u64, u64)> {
+        let end = offset + size as u64;
+        if offset == self.next {
+            self.seq += 1;
+        } else {
+            self.seq = 0;
+            self.ra_end = 0;
+        }
+        self.next = end;
+        if self.seq == 0 || window == 0 {
+            return None;
+        }
+        if self.ra_end < end {
+            self.ra_end = end;
+        }
+        if self.ra_end - end >= window / 2 {
+            return None;
+        }
+        let start = self.ra_end;
+        self.ra_end = end + window;
+        Some((start, self.ra_end))
+    }
+}
+
+struct CacheEntry {
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+// ReadCache keeps the data read ahead by the segment address. the data written into the segments
+// is never changed, so the cached data is valid as long as the segment exists.
+pub struct ReadCache {
+    capacity: u64,
+    size: u64,
+    tick: u64,
+    // keyed by (id0, id1, seg_addr).
+    entries: BTreeMap<(u64, u64, u64), CacheEntry>,
+}
+
+impl ReadCache {
+    pub fn new(capacity: u64) -> Self {
+        ReadCache{
+            capacity: capacity,
+            size: 0,
+            tick: 0,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    // cache the data of the segment (id0, id1) at seg_addr, the least recently used data
+    // is evicted if the cache is full.
+    pub fn insert(&mut self, id0: u64, id1: u64, seg_addr: u64, data: Vec<u8>) {
+        if data.is_empty() || data.len() as u64 > self.capacity {
+            return;
+        }
+        self.tick += 1;
+        self.size += data.len() as u64;
+        let old = self.entries.insert((id0, id1, seg_addr), CacheEntry{
+            data: data,
+            last_used: self.tick,
+        });
+        if let Some(old) = old {
+            self.size -= old.data.len() as u64;
+        }
+        while self.size > self.capacity {
+            let lru = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| *k);
+            match lru {
+                Some(k) => {
+                    if let Some(e) = self.entries.remove(&k) {
+                        self.size -= e.data.len() as u64;
+                    }
+                }
+                None => {
+                    break;
+                }
+            }
+        }
+    }
+
+    // the data of [seg_addr, seg_addr + size) in the segment (id0, id1), it may be spread
+    // in several entries. returns None if any part of it is not cached.
+    pub fn get(&mut self, id0: u64, id1: u64, seg_addr: u64, size: u32) -> Option<Vec<u8>> {
+        let end = seg_addr + size as u64;
+        let mut data = Vec::<u8>::with_capacity(size as usize);
+        let mut keys = Vec::<(u64, u64, u64)>::new();
+        let mut pos = seg_addr;
+        while pos < end {
+            let (k, e) = self.entries.range(..=(id0, id1, pos)).next_back()?;
+            if k.0 != id0 || k.1 != id1 || k.2 + e.data.len() as u64 <= pos {
+                return None;
+            }
+            let n = std::cmp::min(k.2 + e.data.len() as u64, end) - pos;
+            let start = (pos - k.2) as usize;
+            data.extend_from_slice(&e.data[start..start + n as usize]);
+            keys.push(*k);
+            pos += n;
+        }
+        self.tick += 1;
+        for k in keys {
+            if let Some(e) = self.entries.get_mut(&k) {
+                e.last_used = self.tick;
+            }
+        }
+        Some(data)
+    }
+
+    // whether [seg_addr, seg_addr + size) of the segment (id0, id1) is cached in one entry.
+    pub fn contains(&self, id0: u64, id1: u64, seg_addr: u64, size: u32) -> bool {
+        match self.entries.range(..=(id0, id1, seg_addr)).next_back() {
+            Some((k, e)) => {
+                k.0 == id0 && k.1 == id1 && k.2 + e.data.len() as u64 >= seg_addr + size as u64
+            }
+            None => false,
+        }
+    }
+
+    // remove the cached data of the segment (id0, id1).
+    pub fn remove_segment(&mut self, id0: u64, id1: u64) {
+        let keys: Vec<(u64, u64, u64)> = self.entries.range((id0, id1, 0)..=(id0, id1, u64::MAX)).map(|(k, _)| *k).collect();
+        for k in keys {
+            if let Some(e) = self.entries.remove(&k) {
+                self.size -= e.data.len() as u64;
+            }
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
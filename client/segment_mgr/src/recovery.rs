@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+use metaservice_mgr::types::SegmentInfo;
+
+// the sub dir of a data dir which keeps the segment files not referred by any file.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+// SegmentTail is a segment file whose size doesn't match the end of the blocks in the meta server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentTail {
+    pub id0: u64,
+    pub id1: u64,
+    // the size of the data found on disk.
+    pub file_size: u64,
+    // the end of the last block in the meta server, the file is resized to it.
+    pub end: u64,
+}
+
+// LostBlock is the part of a block which is missing in a segment file cut short by the crash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LostBlock {
+    pub id0: u64,
+    pub id1: u64,
+    pub ino: u64,
+    pub generation: u64,
+    // the file offset and size of the missing part.
+    pub offset: u64,
+    pub size: u64,
+    // whether the missing part is sealed and can be read from the backend store.
+    pub in_backend: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    // the number of segment files scanned.
+    pub scanned: u64,
+    // the segment files not referred by any file.
+    pub orphans: Vec<String>,
+    // the new paths of the orphans moved into the quarantine dirs.
+    pub quarantined: Vec<String>,
+    // the segment files with the data written after the last block known by the meta server,
+    // which never reached the meta server and is cut off.
+    pub trimmed: Vec<SegmentTail>,
+    // the segment files shorter than the blocks in the meta server, they are extended with the
+    // lost records so that the later writes don't take the addresses of the blocks, and the
+    // lost data is read from the backend store instead of as zero.
+    pub truncated: Vec<SegmentTail>,
+    pub lost: Vec<LostBlock>,
+}
+
+impl RecoveryReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphans.is_empty() && self.trimmed.is_empty() && self.truncated.is_empty()
+    }
+}
+
+pub struct Recovery {}
+
+impl Recovery {
+    // parse the segment ids from the segment file name "{id0}.{id1}.seg".
+    pub fn parse_file_name(name: &str) -> Option<(u64, u64)> {
+        let ids = name.strip_suffix(".seg")?;
+        let mut parts = ids.split('.');
+        let id0 = parts.next()?.parse::<u64>().ok()?;
+        let id1 = parts.next()?.parse::<u64>().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((id0, id1))
+    }
+
+    // list the segment files in dir, the other files and the sub dirs are skipped.
+    pub fn list_segment_files(dir: &String) -> Result<Vec<(u64, u64, String)>, String> {
+        let mut files = Vec::<(u64, u64, String)>::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(ret) => ret,
+            Err(err) => {
+                return Err(format!("failed to read dir {}, err: {}", dir, err));
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(ret) => ret,
+                Err(err) => {
+                    return Err(format!("failed to read entry of dir {}, err: {}", dir, err));
+                }
+            };
+            match entry.file_type() {
+                Ok(t) => {
+                    if !t.is_file() {
+                        continue;
+                    }
+                }
+                Err(err) => {
+                    return Err(format!("failed to get type of {:?}, err: {}", entry.path(), err));
+                }
+            }
+            let name = entry.file_name();
+            if let Some((id0, id1)) = name.to_str().and_then(Recovery::parse_file_name) {
+                files.push((id0, id1, entry.path().to_string_lossy().to_string()));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    // the end of the last block of the segment.
+    pub fn blocks_end(info: &SegmentInfo) -> u64 {
+        info.blocks.iter().map(|b| b.seg_end_addr).max().unwrap_or(0)
+    }
+
+    // the parts of the blocks beyond file_size, which are lost with the tail of the file.
+    pub fn lost_blocks(info: &SegmentInfo, file_size: u64) -> Vec<LostBlock> {
+        let mut lost = Vec::<LostBlock>::new();
+        for b in &info.blocks {
+            if b.seg_end_addr <= file_size {
+                continue;
+            }
+            let start = std::cmp::max(b.seg_start_addr, file_size);
+            lost.push(LostBlock{
+                id0: info.seg_id0,
+                id1: info.seg_id1,
+                ino: b.ino,
+                generation: b.generation,
+                offset: b.offset + (start - b.seg_start_addr),
+                size: b.seg_end_addr - start,
+                in_backend: info.backend_size >= b.seg_end_addr,
+            });
+        }
+        lost
+    }
+
+    // move the segment file into the quarantine dir of dir, returns the new path.
+    pub fn quarantine(dir: &String, path: &String) -> Result<String, String> {
+        let qdir = format!("{}/{}", dir, QUARANTINE_DIR);
+        if let Err(err) = fs::create_dir_all(&qdir) {
+            return Err(format!("failed to create {}, err: {}", qdir, err));
+        }
+        let name = match Path::new(path).file_name() {
+            Some(ret) => ret.to_string_lossy().to_string(),
+            None => {
+                return Err(format!("invalid segment file {}", path));
+            }
+        };
+        let target = format!("{}/{}", qdir, name);
+        if let Err(err) = fs::rename(path, &target) {
+            return Err(format!("failed to move {} to {}, err: {}", path, target, err));
+        }
+        Ok(target)
+    }
+}
@@ -0,0 +1,362 @@
+extern crate crossbeam_channel;
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crossbeam_channel::{Sender, Receiver, RecvTimeoutError, bounded, select, tick};
+use common::config::ScrubConfig;
+use io_engine::backend_storage::BackendStore;
+use io_engine::segment_format::{BlockRecord, SegmentFile, SegmentScan, crc32c};
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::SegmentInfo;
+use crate::segment_mgr::SegmentMgr;
+
+// the segments are verified and compared with the backend store in batches of this size.
+const SCRUB_CHUNK: u64 = 1 << 20;
+
+// ScrubBlock is the part of a file found bad by the scrubber.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrubBlock {
+    pub id0: u64,
+    pub id1: u64,
+    pub ino: u64,
+    // the file offset and size of the bad part.
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScrubReport {
+    // the number of segment files scrubbed.
+    pub scanned: u64,
+    // the bytes read from the disks and the backend store.
+    pub bytes: u64,
+    // the blocks failing the checksum which are rewritten by the data in the backend store.
+    pub repaired: Vec<ScrubBlock>,
+    // the blocks failing the checksum which are not sealed, or whose backend copy is bad too.
+    pub unrecoverable: Vec<ScrubBlock>,
+    // the sealed blocks which differ from their backend copy, the local data of the segment files
+    // in the current format passes the checksum, so the backend copy is bad.
+    pub mismatched: Vec<ScrubBlock>,
+    // whether the scrub is stopped before all the segments are scrubbed.
+    pub interrupted: bool,
+}
+
+// Scrubber walks the local segment files in background at a limited rate, verifies the data
+// against the checksums of the records, and compares the sealed data with the backend store.
+pub struct Scrubber {
+    segment_mgr: Arc<SegmentMgr>,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
+    backend_store: Arc<dyn BackendStore>,
+    interval: Duration,
+    bytes_per_sec: u64,
+    last_report: Mutex<Option<ScrubReport>>,
+    stop_tx: Sender<u32>,
+    stop_rx: Receiver<u32>,
+    scrub_th: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Scrubber {
+    pub fn new(cfg: &ScrubConfig, segment_mgr: Arc<SegmentMgr>, meta_service_mgr: Arc<dyn MetaServiceMgr>,
+        backend_store: Arc<dyn BackendStore>) -> Self {
+        let (stop_tx, stop_rx) = bounded::<u32>(1);
+        Scrubber{
+            segment_mgr: segment_mgr,
+            meta_service_mgr: meta_service_mgr,
+            backend_store: backend_store,
+            interval: Duration::from_secs(cfg.interval_secs),
+            bytes_per_sec: cfg.bytes_per_sec,
+            last_report: Mutex::new(None),
+            stop_tx: stop_tx,
+            stop_rx: stop_rx,
+            scrub_th: Mutex::new(None),
+        }
+    }
+
+    // scrub all the segments every interval, it is not started if the interval is 0.
+    pub fn start(scrubber: &Arc<Scrubber>) {
+        if scrubber.interval == Duration::from_secs(0) {
+            return;
+        }
+        let s = scrubber.clone();
+        *scrubber.scrub_th.lock().unwrap() = Some(thread::spawn(move || s.work()));
+    }
+
+    pub fn stop(&self) {
+        let ret = self.stop_tx.try_send(1);
+        if let Err(err) = ret {
+            println!("Scrubber: failed to send stop signal, err: {}", err);
+        }
+        if let Some(th) = self.scrub_th.lock().unwrap().take() {
+            if th.join().is_err() {
+                println!("Scrubber: failed to join the scrub thread");
+            }
+        }
+    }
+
+    // the report of the last finished scrub.
+    pub fn last_report(&self) -> Option<ScrubReport> {
+        self.last_report.lock().unwrap().clone()
+    }
+
+    // scrub all the local segment files once.
+    pub fn scrub(&self) -> ScrubReport {
+        let mut report: ScrubReport = Default::default();
+        let start = Instant::now();
+        for (id0, id1, path) in self.segment_mgr.segment_files() {
+            if !self.scrub_segment(id0, id1, &path, start, &mut report) {
+                println!("Scrubber: stopped after {} segments", report.scanned);
+                report.interrupted = true;
+                break;
+            }
+        }
+        println!("Scrubber: scrubbed {} segments with {} bytes in {:?}, repaired: {:?}, unrecoverable: {:?}, mismatched: {:?}",
+        report.scanned, report.bytes, start.elapsed(), report.repaired, report.unrecoverable, report.mismatched);
+        *self.last_report.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    // returns false if the scrubber is stopped.
+    fn scrub_segment(&self, id0: u64, id1: u64, path: &String, start: Instant, report: &mut ScrubReport) -> bool {
+        let scan: SegmentScan;
+        match SegmentFile::scan(path, false) {
+            Ok(ret) => {
+                scan = ret;
+            }
+            Err(err) => {
+                // the segment may be removed after it is listed.
+                println!("Scrubber: skip seg(id0: {}, id1: {}), {}", id0, id1, err);
+                return true;
+            }
+        }
+        let mut info: Option<SegmentInfo> = None;
+        let ret = self.meta_service_mgr.get_segment_info(id0, id1);
+        match ret {
+            Ok(ret) => {
+                info = Some(ret);
+            }
+            Err(err) => {
+                if !err.is_enoent() && !err.is_enotsupp() {
+                    println!("Scrubber: failed to get_segment_info for seg(id0: {}, id1: {}), err: {:?}", id0, id1, err);
+                }
+            }
+        }
+        let backend_size = info.as_ref().map_or(0, |i| i.backend_size);
+        let mut f = match File::open(path) {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("Scrubber: skip seg(id0: {}, id1: {}), failed to open {}, err: {}", id0, id1, path, err);
+                return true;
+            }
+        };
+        report.scanned += 1;
+        if scan.header.is_none() {
+            return self.compare_raw(id0, id1, &mut f, scan.data_size, &info, start, report);
+        }
+        let mut i: usize = 0;
+        while i < scan.records.len() {
+            let batch_start = scan.records[i].seg_addr;
+            let mut j = i + 1;
+            while j < scan.records.len() && scan.records[j].seg_end_addr() - batch_start <= SCRUB_CHUNK {
+                j += 1;
+            }
+            let batch = &scan.records[i..j];
+            let batch_end = batch[batch.len() - 1].seg_end_addr();
+            // the sealed part of the batch in the backend store.
+            let mut backend: Option<Vec<u8>> = None;
+            let sealed_end = std::cmp::min(batch_end, backend_size);
+            if sealed_end > batch_start {
+                let ret = self.backend_store.read(id0, id1, batch_start, (sealed_end - batch_start) as u32);
+                match ret {
+                    Ok(ret) => {
+                        report.bytes += ret.len() as u64;
+                        backend = Some(ret);
+                    }
+                    Err(err) => {
+                        println!("Scrubber: failed to read seg(id0: {}, id1: {}), offset: {}, size: {} from backend store, err: {:?}",
+                        id0, id1, batch_start, sealed_end - batch_start, err);
+                    }
+                }
+            }
+            for r in batch {
+                report.bytes += r.len as u64;
+                let local = match SegmentFile::read_data(&mut f, r) {
+                    Ok(data) => {
+                        if crc32c(&data) == r.crc {
+                            Some(data)
+                        } else {
+                            None
+                        }
+                    }
+                    Err(err) => {
+                        println!("Scrubber: seg(id0: {}, id1: {}), {}", id0, id1, err);
+                        None
+                    }
+                };
+                let copy = backend.as_ref().and_then(|b| {
+                    let start = (r.seg_addr - batch_start) as usize;
+                    let end = (r.seg_end_addr() - batch_start) as usize;
+                    if end <= b.len() {
+                        return Some(&b[start..end]);
+                    }
+                    None
+                });
+                let block = ScrubBlock{
+                    id0: id0,
+                    id1: id1,
+                    ino: r.ino,
+                    offset: r.offset,
+                    size: r.len as u64,
+                };
+                match local {
+                    Some(data) => {
+                        if let Some(copy) = copy {
+                            if copy != data.as_slice() {
+                                println!("Scrubber: the backend copy of {:?} differs from the local data", block);
+                                report.mismatched.push(block);
+                            }
+                        }
+                    }
+                    None => {
+                        match copy {
+                            Some(copy) if crc32c(copy) == r.crc => {
+                                match Scrubber::repair(path, r, copy) {
+                                    Ok(_) => {
+                                        println!("Scrubber: repaired {:?} from backend store", block);
+                                        report.repaired.push(block);
+                                    }
+                                    Err(err) => {
+                                        println!("Scrubber: failed to repair {:?}, {}", block, err);
+                                        report.unrecoverable.push(block);
+                                    }
+                                }
+                            }
+                            _ => {
+                                println!("Scrubber: {:?} fails the checksum and can't be repaired", block);
+                                report.unrecoverable.push(block);
+                            }
+                        }
+                    }
+                }
+            }
+            if !self.throttle(start, report.bytes) {
+                return false;
+            }
+            i = j;
+        }
+        true
+    }
+
+    // compare the sealed data of the segment file in the legacy format with the backend store,
+    // the bad parts are reported by the blocks in the meta server.
+    fn compare_raw(&self, id0: u64, id1: u64, f: &mut File, size: u64, info: &Option<SegmentInfo>,
+        start: Instant, report: &mut ScrubReport) -> bool {
+        let info = match info {
+            Some(ret) => ret,
+            None => {
+                return true;
+            }
+        };
+        let end = std::cmp::min(size, info.backend_size);
+        let mut addr: u64 = 0;
+        while addr < end {
+            let n = std::cmp::min(SCRUB_CHUNK, end - addr);
+            let mut local = vec![0u8; n as usize];
+            if let Err(err) = f.seek(SeekFrom::Start(addr)).and_then(|_| f.read_exact(&mut local)) {
+                println!("Scrubber: failed to read seg(id0: {}, id1: {}) at {}, err: {}", id0, id1, addr, err);
+                return true;
+            }
+            report.bytes += n;
+            let ret = self.backend_store.read(id0, id1, addr, n as u32);
+            match ret {
+                Ok(copy) => {
+                    report.bytes += copy.len() as u64;
+                    if copy != local {
+                        for b in info.blocks.iter().filter(|b| b.seg_start_addr < addr + n && b.seg_end_addr > addr) {
+                            let s = std::cmp::max(b.seg_start_addr, addr);
+                            let e = std::cmp::min(b.seg_end_addr, addr + n);
+                            let block = ScrubBlock{
+                                id0: id0,
+                                id1: id1,
+                                ino: b.ino,
+                                offset: b.offset + (s - b.seg_start_addr),
+                                size: e - s,
+                            };
+                            println!("Scrubber: the backend copy of {:?} differs from the local data", block);
+                            report.mismatched.push(block);
+                        }
+                    }
+                }
+                Err(err) => {
+                    println!("Scrubber: failed to read seg(id0: {}, id1: {}), offset: {} from backend store, err: {:?}",
+                    id0, id1, addr, err);
+                }
+            }
+            if !self.throttle(start, report.bytes) {
+                return false;
+            }
+            addr += n;
+        }
+        true
+    }
+
+    // rewrite the data of the record in place.
+    fn repair(path: &String, r: &BlockRecord, data: &[u8]) -> Result<(), String> {
+        let mut f = match OpenOptions::new().write(true).open(path) {
+            Ok(ret) => ret,
+            Err(err) => {
+                return Err(format!("failed to open {}, err: {}", path, err));
+            }
+        };
+        let ret = f.seek(SeekFrom::Start(r.data_pos())).and_then(|_| f.write_all(data)).and_then(|_| f.sync_data());
+        if let Err(err) = ret {
+            return Err(format!("failed to write {} at {}, err: {}", path, r.data_pos(), err));
+        }
+        Ok(())
+    }
+
+    // wait until the bytes read since start are within the rate, returns false if it is stopped.
+    fn throttle(&self, start: Instant, bytes: u64) -> bool {
+        let mut wait = Duration::from_secs(0);
+        if self.bytes_per_sec > 0 {
+            let want = Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+            let elapsed = start.elapsed();
+            if want > elapsed {
+                wait = want - elapsed;
+            }
+        }
+        match self.stop_rx.recv_timeout(wait) {
+            Ok(_) => false,
+            Err(RecvTimeoutError::Timeout) => true,
+            Err(RecvTimeoutError::Disconnected) => false,
+        }
+    }
+
+    fn work(&self) {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(ticker) -> _ => {
+                    let report = self.scrub();
+                    if report.interrupted {
+                        break;
+                    }
+                },
+                recv(self.stop_rx) -> msg => {
+                    match msg {
+                        Ok(_) => {
+                            println!("Scrubber got stop signal, stop the loop...");
+                        }
+                        Err(err) => {
+                            println!("Scrubber recv invalid stop signal with err: {} and stop the loop...", err);
+                        }
+                    }
+                    break;
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use common::numbers::NumberOp;
+use crate::recovery::Recovery;
+
+// the file in every data dir which records the segments placed in the dir.
+pub const INDEX_FILE: &str = "segments.idx";
+
+// SegmentMove is a segment moved by the rebalance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentMove {
+    pub id0: u64,
+    pub id1: u64,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Default)]
+pub struct RebalanceReport {
+    pub moved: Vec<SegmentMove>,
+    // the segments in the index without files, only their records are moved.
+    pub missing: u64,
+}
+
+// SegmentIndex remembers the data dir of every segment, so that the segments stay in their dirs
+// when the data dirs are changed. each data dir keeps a log of "+ id0 id1" and "- id0 id1"
+// records for the segments added into and removed from it, the log is compacted on load.
+pub struct SegmentIndex {
+    // segment id -> the index of its data dir.
+    dirs: HashMap<u128, usize>,
+    // the opened index file of each data dir.
+    logs: Vec<Option<File>>,
+    // the number of segments in each data dir.
+    counts: Vec<u64>,
+}
+
+impl SegmentIndex {
+    // load the index files of the data dirs, the segment files not in the index yet, such as
+    // the ones created before the index, are added into it.
+    pub fn load(data_dirs: &Vec<String>) -> Self {
+        let mut index = SegmentIndex{
+            dirs: HashMap::new(),
+            logs: Vec::new(),
+            counts: vec![0; data_dirs.len()],
+        };
+        for (idx, dir) in data_dirs.iter().enumerate() {
+            if let Err(err) = fs::create_dir_all(dir) {
+                println!("SegmentIndex: failed to create data dir {}, err: {}", dir, err);
+            }
+            let name = format!("{}/{}", dir, INDEX_FILE);
+            match fs::read_to_string(&name) {
+                Ok(content) => {
+                    for line in content.lines() {
+                        match SegmentIndex::parse_record(line) {
+                            Some((true, id)) => {
+                                index.dirs.insert(id, idx);
+                            }
+                            Some((false, id)) => {
+                                if index.dirs.get(&id) == Some(&idx) {
+                                    index.dirs.remove(&id);
+                                }
+                            }
+                            None => {
+                                println!("SegmentIndex: skip invalid record {:?} in {}", line, name);
+                            }
+                        }
+                    }
+                }
+                Err(err) => {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        println!("SegmentIndex: failed to read {}, err: {}", name, err);
+                    }
+                }
+            }
+        }
+        for (idx, dir) in data_dirs.iter().enumerate() {
+            match Recovery::list_segment_files(dir) {
+                Ok(files) => {
+                    for (id0, id1, _) in files {
+                        index.dirs.entry(NumberOp::to_u128(id0, id1)).or_insert(idx);
+                    }
+                }
+                Err(err) => {
+                    println!("SegmentIndex: {}", err);
+                }
+            }
+        }
+        for (idx, dir) in data_dirs.iter().enumerate() {
+            let name = format!("{}/{}", dir, INDEX_FILE);
+            let log = SegmentIndex::compact(&name, &index.dirs, idx);
+            index.logs.push(log);
+        }
+        for idx in index.dirs.values() {
+            index.counts[*idx] += 1;
+        }
+        index
+    }
+
+    pub fn get(&self, id0: u64, id1: u64) -> Option<usize> {
+        self.dirs.get(&NumberOp::to_u128(id0, id1)).copied()
+    }
+
+    // record that the segment is placed in the data dir idx, the record is synced before return.
+    pub fn add(&mut self, id0: u64, id1: u64, idx: usize) -> Result<(), String> {
+        let id = NumberOp::to_u128(id0, id1);
+        let old = self.dirs.get(&id).copied();
+        if old == Some(idx) {
+            return Ok(());
+        }
+        self.append(idx, &format!("+ {} {}\n", id0, id1))?;
+        self.dirs.insert(id, idx);
+        self.counts[idx] += 1;
+        if let Some(old) = old {
+            self.counts[old] -= 1;
+            self.append(old, &format!("- {} {}\n", id0, id1))?;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id0: u64, id1: u64) -> Result<(), String> {
+        let id = NumberOp::to_u128(id0, id1);
+        if let Some(idx) = self.dirs.remove(&id) {
+            self.counts[idx] -= 1;
+            self.append(idx, &format!("- {} {}\n", id0, id1))?;
+        }
+        Ok(())
+    }
+
+    // the number of segments in the data dir idx.
+    pub fn count(&self, idx: usize) -> u64 {
+        self.counts.get(idx).copied().unwrap_or(0)
+    }
+
+    // all the segments in the index with their data dirs.
+    pub fn segments(&self) -> Vec<(u64, u64, usize)> {
+        let mut segs: Vec<(u64, u64, usize)> = self.dirs.iter().map(|(id, idx)| {
+            let ids = NumberOp::from_u128(*id);
+            (ids[0], ids[1], *idx)
+        }).collect();
+        segs.sort();
+        segs
+    }
+
+    fn append(&mut self, idx: usize, record: &String) -> Result<(), String> {
+        let f = match self.logs.get_mut(idx) {
+            Some(Some(f)) => f,
+            _ => {
+                return Err(format!("the index file of data dir {} is not opened", idx));
+            }
+        };
+        if let Err(err) = f.write_all(record.as_bytes()) {
+            return Err(format!("failed to append {:?} to the index of data dir {}, err: {}", record, idx, err));
+        }
+        if let Err(err) = f.sync_data() {
+            return Err(format!("failed to sync the index of data dir {}, err: {}", idx, err));
+        }
+        Ok(())
+    }
+
+    // parse the record into (whether it is added, segment id).
+    fn parse_record(line: &str) -> Option<(bool, u128)> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        let id0 = parts[1].parse::<u64>().ok()?;
+        let id1 = parts[2].parse::<u64>().ok()?;
+        match parts[0] {
+            "+" => Some((true, NumberOp::to_u128(id0, id1))),
+            "-" => Some((false, NumberOp::to_u128(id0, id1))),
+            _ => None,
+        }
+    }
+
+    // rewrite the index file of data dir idx with only the segments in it, and open it for append.
+    fn compact(name: &String, dirs: &HashMap<u128, usize>, idx: usize) -> Option<File> {
+        let mut ids: Vec<Vec<u64>> = dirs.iter().filter(|(_, i)| **i == idx).map(|(id, _)| NumberOp::from_u128(*id)).collect();
+        ids.sort();
+        let mut content = String::new();
+        for id in ids {
+            content.push_str(&format!("+ {} {}\n", id[0], id[1]));
+        }
+        let tmp = format!("{}.tmp", name);
+        let ret = File::create(&tmp).and_then(|mut f| {
+            f.write_all(content.as_bytes())?;
+            f.sync_all()
+        }).and_then(|_| fs::rename(&tmp, name));
+        if let Err(err) = ret {
+            println!("SegmentIndex: failed to compact {}, err: {}", name, err);
+        }
+        match OpenOptions::new().create(true).append(true).open(name) {
+            Ok(f) => Some(f),
+            Err(err) => {
+                println!("SegmentIndex: failed to open {}, err: {}", name, err);
+                None
+            }
+        }
+    }
+}
@@ -1,29 +1,43 @@
 extern crate tokio;
 extern crate hash_ring;
 
-use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use std::path::Path;
 use crate::types::{Block, Segment, DataDir};
+use crate::recovery::{Recovery, RecoveryReport, SegmentTail};
+use crate::segment_index::{RebalanceReport, SegmentIndex, SegmentMove};
+use crate::dir_placement::{DirPlacement, DirUsage, HashDirPlacement, new_dir_placement};
+use crate::dir_health::{DirHealth, DirStatus};
 use common::{error::Errno, numbers::NumberOp};
 use common::config::Config;
 use metaservice_mgr::mgr::MetaServiceMgr;
 use metaservice_mgr::types::Segment as MetaSegment;
 use hash_ring::HashRing;
+use io_engine::segment_format::{SegmentFile, SegmentScan};
 
 pub struct SegmentMgr {
-    meta_service_mgr: Rc<dyn MetaServiceMgr>,
+    meta_service_mgr: Arc<dyn MetaServiceMgr>,
     // initialized during new() and later readonly.
     data_dirs: Vec<DataDir>,
     // key: u128 stands for segmentid; nodes usize stands for the index of data_dirs.
     // initialized during new() and later readonly.
     dirs_sharder: HashRing<usize>,
+    // the data dirs of the created segments, the hash ring is only used for the new segments,
+    // so that the segments are not lost when the data dirs are changed.
+    index: RwLock<SegmentIndex>,
+    // chooses the data dirs of the new segments.
+    placement: Box<dyn DirPlacement>,
+    min_free_ratio: f64,
+    // the failed dirs are not used for the new segments.
+    health: Arc<DirHealth>,
 }
 
 impl SegmentMgr {
     // make sure this function is threadsafe.
-    pub fn get_file_segments(&self, ino: u64, leader: &String)-> Result<Vec<Segment>, Errno> {
+    pub fn get_file_segments(&self, ino: u64, generation: u64, leader: &String)-> Result<Vec<Segment>, Errno> {
         let mut segments : Vec<Segment> = Vec::new();
         let segs : Vec<metaservice_mgr::types::Segment>;
-        let ret = self.meta_service_mgr.get_file_segments(ino, None, None);
+        let ret = self.meta_service_mgr.get_file_segments(ino, generation, None, None);
         match ret {
             Ok(ret) => {
                 segs = ret;
@@ -45,10 +59,11 @@ impl SegmentMgr {
             segment.seg_id1 = s.seg_id1;
             segment.max_size = s.max_size;
             segment.leader = s.leader;
+            segment.backend_size = s.backend_size;
             for b in s.blocks {
                 let block = Block{
                     ino: ino,
-                    generation: 0,
+                    generation: generation,
                     offset: b.offset,
                     seg_start_addr: b.seg_start_addr,
                     seg_end_addr: b.seg_end_addr,
@@ -69,12 +84,24 @@ impl SegmentMgr {
             l = self.meta_service_mgr.get_machine_id();
         }
         let mut seg = Segment::new(&l);
-        let idx = self.get_segment_dir_idx(seg.seg_id0, seg.seg_id1);
+        let mut index = self.index.write().unwrap();
+        let candidates = self.get_candidate_dirs(&index);
+        if candidates.is_empty() {
+            // the write to the segment fails with Enospc.
+            println!("new_segment: no data dir has space for seg(id0: {}, id1: {})", seg.seg_id0, seg.seg_id1);
+            seg.max_size = 0;
+            return seg;
+        }
+        let idx = self.placement.choose(seg.seg_id0, seg.seg_id1, &candidates);
+        let ret = index.add(seg.seg_id0, seg.seg_id1, idx);
+        if let Err(err) = ret {
+            println!("new_segment: failed to record seg(id0: {}, id1: {}) in the index, err: {}", seg.seg_id0, seg.seg_id1, err);
+        }
         seg.max_size = self.data_dirs[idx].size;
         seg
     }
 
-    pub fn create(cfg: &Config, mgr: Rc<dyn MetaServiceMgr>) -> Self {
+    pub fn create(cfg: &Config, mgr: Arc<dyn MetaServiceMgr>) -> Self {
         let mut dirs: Vec<DataDir> = Vec::new();
         let mut dir_idxs: Vec<usize> = Vec::new();
         let mut idx : usize = 0;
@@ -90,19 +117,36 @@ impl SegmentMgr {
         }
         
         let ring: HashRing<usize> = HashRing::new(dir_idxs, 10);
+        let dir_names: Vec<String> = dirs.iter().map(|d| d.dir.clone()).collect();
+        let index = SegmentIndex::load(&dir_names);
+        let health = Arc::new(DirHealth::new(&dir_names, &cfg.dir_health_config, cfg.dir_placement_config.min_free_ratio));
+        // the failed dirs are found before any segment is placed.
+        health.check();
+        DirHealth::start(&health);
+        let placement = match new_dir_placement(&cfg.dir_placement_config.policy) {
+            Ok(ret) => ret,
+            Err(err) => {
+                println!("SegmentMgr: {}, place the segments by hash", err);
+                Box::new(HashDirPlacement{})
+            }
+        };
         SegmentMgr{
             meta_service_mgr: mgr,
             data_dirs: dirs,
             dirs_sharder: ring,
+            index: RwLock::new(index),
+            placement: placement,
+            min_free_ratio: cfg.dir_placement_config.min_free_ratio,
+            health: health,
         }
     }
 
-    pub fn upload_block(&self, ino: u64, seg_id0: u64, seg_id1: u64, b: &Block)->Errno {
+    pub fn upload_block(&self, ino: u64, lease_epoch: u64, seg_id0: u64, seg_id1: u64, b: &Block)->Errno {
         let idx = self.get_segment_dir_idx(seg_id0, seg_id1);
         let data_dir = &self.data_dirs[idx];
         let mut seg = Segment::rich_new(seg_id0, seg_id1, data_dir.size, self.meta_service_mgr.get_machine_id());
-        seg.add_block(ino, b.offset,b.seg_start_addr, b.size);
-        let ret = self.meta_service_mgr.add_file_block(ino, &seg.to_meta_segment());
+        seg.add_block(ino, b.generation, b.offset,b.seg_start_addr, b.size);
+        let ret = self.meta_service_mgr.add_file_block(ino, b.generation, lease_epoch, &seg.to_meta_segment());
         return ret;
     }
 
@@ -111,12 +155,139 @@ impl SegmentMgr {
         self.data_dirs[idx].dir.clone()
     }
 
-    pub fn update_segments(&self, ino: u64, segs: &Vec<Segment>) -> Errno {
+    // the size of the segment in the backend store recorded in meta server.
+    pub fn get_backend_size(&self, id0: u64, id1: u64) -> Result<u64, Errno> {
+        let ret = self.meta_service_mgr.get_segment_info(id0, id1);
+        match ret {
+            Ok(info) => Ok(info.backend_size),
+            Err(err) => {
+                println!("get_backend_size: failed to get_segment_info for seg(id0: {}, id1: {}), err: {:?}", id0, id1, err);
+                Err(err)
+            }
+        }
+    }
+
+    // the files which have blocks in the segment recorded in meta server, as (ino, generation).
+    pub fn get_segment_inos(&self, id0: u64, id1: u64) -> Result<Vec<(u64, u64)>, Errno> {
+        let ret = self.meta_service_mgr.get_segment_info(id0, id1);
+        match ret {
+            Ok(info) => {
+                let mut inos = Vec::<(u64, u64)>::new();
+                for b in &info.blocks {
+                    if !inos.contains(&(b.ino, b.generation)) {
+                        inos.push((b.ino, b.generation));
+                    }
+                }
+                Ok(inos)
+            }
+            Err(err) => {
+                println!("get_segment_inos: failed to get_segment_info for seg(id0: {}, id1: {}), err: {:?}", id0, id1, err);
+                Err(err)
+            }
+        }
+    }
+
+    // the path of the segment file in its data dir.
+    pub fn get_segment_file(&self, id0: u64, id1: u64) -> String {
+        format!("{}/{}.{}.seg", self.get_segment_dir(id0, id1), id0, id1)
+    }
+
+    // record the result of the io on the segment for the health of its dir.
+    pub fn record_io(&self, id0: u64, id1: u64, ok: bool) {
+        let idx = self.get_segment_dir_idx(id0, id1);
+        self.health.record(idx, ok);
+    }
+
+    // whether the dir of the segment can be read or written.
+    pub fn is_segment_dir_usable(&self, id0: u64, id1: u64) -> bool {
+        let idx = self.get_segment_dir_idx(id0, id1);
+        self.health.is_usable(idx)
+    }
+
+    // the state of the data dirs.
+    pub fn dir_status(&self) -> Vec<DirStatus> {
+        self.health.status()
+    }
+
+    // the segment files in the usable data dirs, as (id0, id1, path).
+    pub fn segment_files(&self) -> Vec<(u64, u64, String)> {
+        let segs = self.index.read().unwrap().segments();
+        let mut files = Vec::<(u64, u64, String)>::new();
+        for (id0, id1, idx) in segs {
+            if !self.health.is_usable(idx) {
+                continue;
+            }
+            files.push((id0, id1, format!("{}/{}.{}.seg", self.data_dirs[idx].dir, id0, id1)));
+        }
+        files
+    }
+
+    // forget the segment after its file is deleted.
+    pub fn remove_segment(&self, id0: u64, id1: u64) {
+        let ret = self.index.write().unwrap().remove(id0, id1);
+        if let Err(err) = ret {
+            println!("remove_segment: failed to remove seg(id0: {}, id1: {}) from the index, err: {}", id0, id1, err);
+        }
+    }
+
+    // move the segments into the data dirs chosen by the hash ring, such as after the data dirs
+    // are added. it must be done before any segment is opened. the segment is copied and synced
+    // before the index is changed, so it is never lost if the rebalance is interrupted.
+    // only the "hash" placement maps the existing segments to the dirs, returns Enotsupp for others.
+    pub fn rebalance(&self) -> Result<RebalanceReport, Errno> {
+        if self.placement.name() != "hash" {
+            println!("rebalance: the segments placed by {} can't be rebalanced", self.placement.name());
+            return Err(Errno::Enotsupp);
+        }
+        let mut report: RebalanceReport = Default::default();
+        let mut index = self.index.write().unwrap();
+        for (id0, id1, idx) in index.segments() {
+            let target = self.get_ring_dir_idx(id0, id1);
+            if target == idx {
+                continue;
+            }
+            let from = format!("{}/{}.{}.seg", self.data_dirs[idx].dir, id0, id1);
+            let to = format!("{}/{}.{}.seg", self.data_dirs[target].dir, id0, id1);
+            let exists = Path::new(&from).is_file();
+            if exists {
+                let tmp = format!("{}.tmp", to);
+                let ret = std::fs::copy(&from, &tmp).and_then(|_| {
+                    std::fs::File::open(&tmp)?.sync_all()
+                }).and_then(|_| std::fs::rename(&tmp, &to));
+                if let Err(err) = ret {
+                    println!("rebalance: failed to copy {} to {}, err: {}", from, to, err);
+                    let _ = std::fs::remove_file(&tmp);
+                    return Err(Errno::Eintr);
+                }
+            } else {
+                report.missing += 1;
+            }
+            if let Err(err) = index.add(id0, id1, target) {
+                println!("rebalance: failed to move seg(id0: {}, id1: {}) in the index, err: {}", id0, id1, err);
+                return Err(Errno::Eintr);
+            }
+            if exists {
+                if let Err(err) = std::fs::remove_file(&from) {
+                    println!("rebalance: failed to remove {}, err: {}", from, err);
+                }
+                println!("rebalance: moved {} to {}", from, to);
+                report.moved.push(SegmentMove{
+                    id0: id0,
+                    id1: id1,
+                    from: from,
+                    to: to,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn update_segments(&self, ino: u64, generation: u64, lease_epoch: u64, segs: &Vec<Segment>) -> Errno {
         let mut ms = Vec::<MetaSegment>::new();
         for s in segs {
             ms.push(s.to_meta_segment());
         }
-        let ret = self.meta_service_mgr.update_file_segments(ino, &ms);
+        let ret = self.meta_service_mgr.update_file_segments(ino, generation, lease_epoch, &ms);
         if !ret.is_success() {
             println!("update_segments: failed to update segments for ino: {}, err: {:?}", ino, ret);
             return ret;
@@ -124,8 +295,143 @@ impl SegmentMgr {
         return ret;
     }
 
+    // reconcile the segment files in the data dirs with the meta server after a crash, it must
+    // be done before any segment is opened. the files not referred by any file are moved into
+    // the quarantine dirs if quarantine is true, or only reported. the data written after the
+    // last block known by the meta server is cut off, and the files shorter than their blocks
+    // are extended with the records marking the data lost.
+    pub fn recover(&self, quarantine: bool) -> Result<RecoveryReport, Errno> {
+        let mut report: RecoveryReport = Default::default();
+        for d in &self.data_dirs {
+            if !Path::new(&d.dir).is_dir() {
+                println!("recover: data dir {} doesn't exist, skip it", d.dir);
+                continue;
+            }
+            let files: Vec<(u64, u64, String)>;
+            let ret = Recovery::list_segment_files(&d.dir);
+            match ret {
+                Ok(ret) => {
+                    files = ret;
+                }
+                Err(err) => {
+                    println!("recover: {}", err);
+                    return Err(Errno::Eintr);
+                }
+            }
+            for (id0, id1, path) in files {
+                report.scanned += 1;
+                let ret = self.meta_service_mgr.get_segment_info(id0, id1);
+                let info = match ret {
+                    Ok(ret) => ret,
+                    Err(err) => {
+                        if err.is_enoent() {
+                            println!("recover: segment file {} is not referred by any file", path);
+                            report.orphans.push(path.clone());
+                            if quarantine {
+                                match Recovery::quarantine(&d.dir, &path) {
+                                    Ok(ret) => {
+                                        report.quarantined.push(ret);
+                                        self.remove_segment(id0, id1);
+                                    }
+                                    Err(err) => {
+                                        println!("recover: {}", err);
+                                        return Err(Errno::Eintr);
+                                    }
+                                }
+                            }
+                            continue;
+                        }
+                        println!("recover: failed to get_segment_info for {}, err: {:?}", path, err);
+                        return Err(err);
+                    }
+                };
+                let scan: SegmentScan;
+                match SegmentFile::scan(&path, false) {
+                    Ok(ret) => {
+                        scan = ret;
+                    }
+                    Err(err) => {
+                        println!("recover: {}", err);
+                        return Err(Errno::Eintr);
+                    }
+                }
+                // the size of the data in the segment, the torn tail of the records is not counted.
+                let file_size = scan.data_size;
+                let end = Recovery::blocks_end(&info);
+                if file_size == end && !scan.is_torn() {
+                    continue;
+                }
+                let tail = SegmentTail{
+                    id0: id0,
+                    id1: id1,
+                    file_size: file_size,
+                    end: end,
+                };
+                if file_size >= end {
+                    println!("recover: cut off the {} bytes not in meta server and the torn tail from {}", file_size - end, path);
+                    report.trimmed.push(tail);
+                } else {
+                    let lost = Recovery::lost_blocks(&info, file_size);
+                    println!("recover: segment file {} is truncated to {}, the blocks end at {}, lost: {:?}",
+                    path, file_size, end, lost);
+                    report.lost.extend(lost);
+                    report.truncated.push(tail);
+                }
+                if let Err(err) = SegmentFile::resize(&path, id0, id1, info.max_size, end) {
+                    println!("recover: {}", err);
+                    return Err(Errno::Eintr);
+                }
+            }
+        }
+        Ok(report)
+    }
+
     // private member functions.
+    // the dirs below their segment number limit, and with enough free space for a full segment.
+    fn get_candidate_dirs(&self, index: &SegmentIndex) -> Vec<DirUsage> {
+        let mut candidates = Vec::<DirUsage>::new();
+        for (idx, d) in self.data_dirs.iter().enumerate() {
+            let mut usage = DirUsage{
+                idx: idx,
+                total: 0,
+                free: 0,
+                segments: index.count(idx),
+            };
+            if d.num > 0 && usage.segments >= d.num as u64 {
+                continue;
+            }
+            if !self.health.is_healthy(idx) {
+                continue;
+            }
+            match DirUsage::statfs(&d.dir) {
+                Ok((total, free)) => {
+                    usage.total = total;
+                    usage.free = free;
+                    let watermark = (total as f64 * self.min_free_ratio) as u64;
+                    if free < d.size + watermark {
+                        continue;
+                    }
+                }
+                Err(err) => {
+                    // the space of the dir is unknown, it is still used.
+                    println!("get_candidate_dirs: {}", err);
+                }
+            }
+            candidates.push(usage);
+        }
+        candidates
+    }
+
     fn get_segment_dir_idx(&self, id0: u64, id1: u64) -> usize {
+        if let Some(idx) = self.index.read().unwrap().get(id0, id1) {
+            if idx < self.data_dirs.len() {
+                return idx;
+            }
+        }
+        self.get_ring_dir_idx(id0, id1)
+    }
+
+    fn get_ring_dir_idx(&self, id0: u64, id1: u64) -> usize {
         let id = NumberOp::to_u128(id0, id1);
         let idx = self.dirs_sharder.get_node(id.to_string());
         // will return an element because none will not occur.
@@ -134,4 +440,10 @@ impl SegmentMgr {
             None => {0}
         }
     }
-}
\ No newline at end of file
+}
+
+impl Drop for SegmentMgr {
+    fn drop(&mut self) {
+        self.health.stop();
+    }
+}
@@ -3,6 +3,7 @@ use crossbeam_channel::{Sender};
 use common::uuid;
 use metaservice_mgr::types::Block as MetaBlock;
 use metaservice_mgr::types::Segment as MetaSegment;
+use crate::extent_map::FileExtents;
 
 
 #[derive(Debug, Default)]
@@ -20,6 +21,8 @@ pub struct Segment {
     pub max_size: u64,
     pub leader: String,
     pub blocks: Vec<Block>,
+    // the data in [0, backend_size) is sealed and uploaded to the backend store.
+    pub backend_size: u64,
     // ino --> largest_offset
     file_largest_offsets: HashMap<u64, u64>,
 }
@@ -32,6 +35,7 @@ impl Default for Segment {
             max_size: 0,
             leader: Default::default(),
             blocks: Default::default(),
+            backend_size: 0,
             file_largest_offsets: HashMap::new(),
         }
     }
@@ -46,6 +50,7 @@ impl Segment {
             max_size: 0,
             leader: leader.clone(),
             blocks: Vec::<Block>::new(),
+            backend_size: 0,
             file_largest_offsets: HashMap::new(),
         }
     }
@@ -57,6 +62,7 @@ impl Segment {
             max_size: max_size,
             leader: leader,
             blocks: Vec::<Block>::new(),
+            backend_size: 0,
             file_largest_offsets: HashMap::new(),
         }
     }
@@ -68,6 +74,7 @@ impl Segment {
             max_size: self.max_size,
             leader: self.leader.clone(),
             blocks: Vec::<Block>::new(),
+            backend_size: self.backend_size,
             file_largest_offsets: HashMap::new(),
         };
         for b in &self.blocks{
@@ -79,10 +86,10 @@ impl Segment {
         return s;
     }
 
-    pub fn add_block(&mut self, ino: u64, offset: u64, seg_start_offset: u64, nwrite: i64) {
+    pub fn add_block(&mut self, ino: u64, generation: u64, offset: u64, seg_start_offset: u64, nwrite: i64) {
         let b = Block{
             ino: ino,
-            generation: 0,
+            generation: generation,
             offset: offset,
             seg_start_addr: seg_start_offset,
             seg_end_addr: seg_start_offset+nwrite as u64,
@@ -90,7 +97,8 @@ impl Segment {
         };
         for bb in &mut self.blocks {
             // original offset keeps the same, but we concatenate the two consecutive blocks.
-            if bb.offset + bb.size as u64 == offset && bb.seg_end_addr == seg_start_offset {
+            if bb.ino == ino && bb.generation == generation &&
+                bb.offset + bb.size as u64 == offset && bb.seg_end_addr == seg_start_offset {
                 bb.seg_end_addr = seg_start_offset + nwrite as u64;
                 bb.size += nwrite;
                 return;
@@ -146,6 +154,7 @@ impl Segment {
             max_size: self.max_size,
             leader: self.leader.clone(),
             blocks: Vec::new(),
+            backend_size: self.backend_size,
         };
         for b in &self.blocks {
             meta_seg.blocks.push(b.to_meta_block());
@@ -206,14 +215,18 @@ pub struct BlockIo {
     pub id1: u64,
     // note: this offset is the start addr in the segment file.
     pub offset: u64,
-    // the size of this block.
+    // the size of this block, or the total size of a write split into several blocks.
     pub size: u32,
 }
 
 #[derive(Debug)]
 pub struct FileHandle {
     pub ino: u64,
+    // the generation of ino, the handle is stale if the ino is reused by a newer generation.
+    pub generation: u64,
     pub leader: String,
+    // the epoch of the leader lease when the handle is opened, 0 if no lease is granted.
+    pub lease_epoch: u64,
     pub segments: Vec<Segment>,
 }
 
@@ -221,7 +234,9 @@ impl FileHandle {
     pub fn copy(&self)->Self {
         let mut handle = FileHandle{
             ino: self.ino,
+            generation: self.generation,
             leader: self.leader.clone(),
+            lease_epoch: self.lease_epoch,
             segments: Vec::<Segment>::new(),
         };
         for s in &self.segments {
@@ -230,10 +245,12 @@ impl FileHandle {
         return handle;
     }
     
-    pub fn new(ino: u64)->Self{
+    pub fn new(ino: u64, generation: u64)->Self{
         FileHandle{
             ino: ino,
+            generation: generation,
             leader: String::from(""),
+            lease_epoch: 0,
             segments: Vec::<Segment>::new(),
         }
     }
@@ -273,12 +290,37 @@ pub struct MsgGetLastSegment{
     pub tx: Sender<Vec<u64>>,
 }
 
+#[derive(Debug)]
+pub struct MsgGetExtents{
+    pub ino: u64,
+    pub offset: u64,
+    pub size: u32,
+    // the extents in [offset, offset + size).
+    pub tx: Sender<Option<FileExtents>>,
+}
+
 #[derive(Debug)]
 pub struct MsgAddSegment{
     pub ino: u64,
     pub seg: Segment,
 }
 
+#[derive(Debug)]
+pub struct MsgDelSegments{
+    pub ino: u64,
+    // the (id0, id1) of the segments.
+    pub segs: Vec<(u64, u64)>,
+}
+
+#[derive(Debug)]
+pub struct MsgSetBackendSize{
+    pub ino: u64,
+    pub id0: u64,
+    pub id1: u64,
+    // the size of the segment uploaded to the backend store.
+    pub backend_size: u64,
+}
+
 #[derive(Debug)]
 pub enum MsgFileHandleOp{
     Add(FileHandle),
@@ -287,4 +329,9 @@ pub enum MsgFileHandleOp{
     Get(MsgQueryHandle),
     GetLastSegment(MsgGetLastSegment),
     AddSegment(MsgAddSegment),
+    GetExtents(MsgGetExtents),
+    DelSegments(MsgDelSegments),
+    SetBackendSize(MsgSetBackendSize),
+    // get the inos of all the handles.
+    GetInos(Sender<Vec<u64>>),
 }
\ No newline at end of file
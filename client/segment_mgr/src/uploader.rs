@@ -0,0 +1,213 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use common::config::LeaderConfig;
+use common::error::Errno;
+
+// the max wait before a failed upload is retried.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadState {
+    // the segment is still written.
+    Open = 0,
+    // the segment takes no more data, and waits to be uploaded.
+    Sealed = 1,
+    Uploading = 2,
+    // all the data of the segment is in the backend store.
+    Uploaded = 3,
+    // the upload fails after all the retries, the segment is kept local only.
+    Failed = 4,
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentUpload {
+    pub id0: u64,
+    pub id1: u64,
+    // the file which the segment belongs to.
+    pub ino: u64,
+    pub generation: u64,
+    // the data size of the segment, and the size of it in the backend store.
+    pub size: u64,
+    pub uploaded: u64,
+    pub state: UploadState,
+    pub retries: u32,
+    // the error of the last failed upload.
+    pub err: String,
+    next_try: Instant,
+    last_write: Instant,
+}
+
+impl SegmentUpload {
+    fn new(id0: u64, id1: u64, ino: u64, generation: u64, size: u64, uploaded: u64) -> Self {
+        let now = Instant::now();
+        SegmentUpload{
+            id0: id0,
+            id1: id1,
+            ino: ino,
+            generation: generation,
+            size: size,
+            uploaded: uploaded,
+            state: UploadState::Open,
+            retries: 0,
+            err: String::new(),
+            next_try: now,
+            last_write: now,
+        }
+    }
+}
+
+// Uploader keeps the upload state of the segments. a segment is sealed when it is full or no
+// data is written into it for seal_idle, and then it is uploaded to the backend store by the
+// upload thread of the leader. the sealed segment takes no more data, the writes go into a new
+// segment.
+pub struct Uploader {
+    seal_idle: Duration,
+    max_retries: u32,
+    retry_wait: Duration,
+    segments: Mutex<HashMap<(u64, u64), SegmentUpload>>,
+}
+
+impl Uploader {
+    pub fn new(cfg: &LeaderConfig) -> Self {
+        Uploader{
+            seal_idle: Duration::from_secs(cfg.seal_idle_secs),
+            max_retries: cfg.upload_retries,
+            retry_wait: Duration::from_millis(cfg.upload_retry_ms),
+            segments: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // track the segment of the opened file, size is the data in it and uploaded is its size in the
+    // backend store. only the last segment of the file is written if none of it is uploaded, the
+    // others are sealed, so that the local file of an uploaded segment can be evicted.
+    pub fn track(&self, id0: u64, id1: u64, ino: u64, generation: u64, size: u64, uploaded: u64, last: bool) {
+        let mut segments = self.segments.lock().unwrap();
+        if let Some(u) = segments.get_mut(&(id0, id1)) {
+            u.ino = ino;
+            u.generation = generation;
+            return;
+        }
+        let mut u = SegmentUpload::new(id0, id1, ino, generation, size, uploaded);
+        if !last || uploaded > 0 {
+            u.state = if size > uploaded { UploadState::Sealed } else { UploadState::Uploaded };
+        }
+        segments.insert((id0, id1), u);
+    }
+
+    // record the data written into the segment up to end.
+    pub fn touch(&self, id0: u64, id1: u64, ino: u64, generation: u64, end: u64) {
+        let mut segments = self.segments.lock().unwrap();
+        let u = segments.entry((id0, id1)).or_insert_with(|| SegmentUpload::new(id0, id1, ino, generation, 0, 0));
+        if end > u.size {
+            u.size = end;
+        }
+        u.last_write = Instant::now();
+    }
+
+    // seal the segment, so that it is uploaded.
+    pub fn seal(&self, id0: u64, id1: u64) {
+        let mut segments = self.segments.lock().unwrap();
+        if let Some(u) = segments.get_mut(&(id0, id1)) {
+            if u.state == UploadState::Open {
+                println!("Uploader: seal segment(id0: {}, id1: {}) of ino: {} with size: {}", id0, id1, u.ino, u.size);
+                u.state = UploadState::Sealed;
+            }
+        }
+    }
+
+    // seal the segments which have new data but are not written for seal_idle.
+    pub fn seal_idle(&self) {
+        if self.seal_idle == Duration::from_secs(0) {
+            return;
+        }
+        let mut segments = self.segments.lock().unwrap();
+        for u in segments.values_mut() {
+            if u.state == UploadState::Open && u.size > u.uploaded && u.last_write.elapsed() >= self.seal_idle {
+                println!("Uploader: seal idle segment(id0: {}, id1: {}) of ino: {} with size: {}", u.id0, u.id1, u.ino, u.size);
+                u.state = UploadState::Sealed;
+            }
+        }
+    }
+
+    // whether the segment takes no more data.
+    pub fn is_sealed(&self, id0: u64, id1: u64) -> bool {
+        match self.segments.lock().unwrap().get(&(id0, id1)) {
+            Some(u) => u.state != UploadState::Open,
+            None => false,
+        }
+    }
+
+    // take the next sealed segment to upload, it is in Uploading state until finish.
+    pub fn next(&self) -> Option<SegmentUpload> {
+        let mut segments = self.segments.lock().unwrap();
+        let now = Instant::now();
+        for u in segments.values_mut() {
+            if u.state == UploadState::Sealed && u.next_try <= now {
+                u.state = UploadState::Uploading;
+                return Some(u.clone());
+            }
+        }
+        None
+    }
+
+    // record the data uploaded so far, the upload is resumed from it after a failure.
+    pub fn progress(&self, id0: u64, id1: u64, uploaded: u64) {
+        if let Some(u) = self.segments.lock().unwrap().get_mut(&(id0, id1)) {
+            u.uploaded = uploaded;
+        }
+    }
+
+    // finish the upload of the segment with err. the failed upload is retried later unless the
+    // retries are used up or the backend store doesn't support it.
+    pub fn finish(&self, id0: u64, id1: u64, err: Errno) {
+        let mut segments = self.segments.lock().unwrap();
+        let u = match segments.get_mut(&(id0, id1)) {
+            Some(u) => u,
+            None => {
+                return;
+            }
+        };
+        if err.is_success() {
+            // the data written while it is sealed is uploaded next time.
+            if u.uploaded < u.size {
+                u.state = UploadState::Sealed;
+                return;
+            }
+            u.state = UploadState::Uploaded;
+            u.retries = 0;
+            u.err = String::new();
+            println!("Uploader: uploaded segment(id0: {}, id1: {}) of ino: {} with size: {}", id0, id1, u.ino, u.uploaded);
+            return;
+        }
+        u.retries += 1;
+        if err.is_enotsupp() || u.retries > self.max_retries {
+            println!("Uploader: failed to upload segment(id0: {}, id1: {}) of ino: {} after {} tries, err: {:?}",
+            id0, id1, u.ino, u.retries, err);
+            u.state = UploadState::Failed;
+        } else {
+            let wait = cmp::min(self.retry_wait * 2u32.pow(cmp::min(u.retries - 1, 16)), MAX_RETRY_WAIT);
+            println!("Uploader: failed to upload segment(id0: {}, id1: {}) of ino: {}, retry in {:?}, err: {:?}",
+            id0, id1, u.ino, wait, err);
+            u.state = UploadState::Sealed;
+            u.next_try = Instant::now() + wait;
+        }
+        u.err = format!("{:?}", err);
+    }
+
+    // the segment is removed, its upload is dropped.
+    pub fn forget(&self, id0: u64, id1: u64) {
+        self.segments.lock().unwrap().remove(&(id0, id1));
+    }
+
+    pub fn state(&self, id0: u64, id1: u64) -> Option<UploadState> {
+        self.segments.lock().unwrap().get(&(id0, id1)).map(|u| u.state)
+    }
+
+    pub fn status(&self) -> Vec<SegmentUpload> {
+        let mut ret: Vec<SegmentUpload> = self.segments.lock().unwrap().values().cloned().collect();
+        ret.sort_by_key(|u| (u.id0, u.id1));
+        ret
+    }
+}
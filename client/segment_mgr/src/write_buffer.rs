@@ -0,0 +1,48 @@
+use std::time::Instant;
+
+// WriteBuffer absorbs the small sequential writes of a file, and they are written
+// into the segment as one block when the buffer is flushed.
+#[derive(Debug, Clone)]
+pub struct WriteBuffer {
+    pub generation: u64,
+    // the file offset of the first byte in data.
+    pub offset: u64,
+    pub data: Vec<u8>,
+    // the time of the first write in the buffer.
+    pub since: Instant,
+}
+
+impl WriteBuffer {
+    pub fn new(generation: u64, offset: u64, data: &[u8]) -> Self {
+        WriteBuffer{
+            generation: generation,
+            offset: offset,
+            data: data.to_vec(),
+            since: Instant::now(),
+        }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.offset + self.data.len() as u64
+    }
+
+    // whether the write at offset follows the buffered data.
+    pub fn is_next(&self, generation: u64, offset: u64) -> bool {
+        self.generation == generation && self.end() == offset
+    }
+
+    // copy the buffered data in [offset, offset + size) into data, which is read from offset.
+    // data is extended with zero if it ends before the buffered data.
+    pub fn overlay(&self, offset: u64, size: u32, data: &mut Vec<u8>) {
+        let start = std::cmp::max(self.offset, offset);
+        let end = std::cmp::min(self.end(), offset + size as u64);
+        if start >= end {
+            return;
+        }
+        if data.len() < (end - offset) as usize {
+            data.resize((end - offset) as usize, 0);
+        }
+        let src = &self.data[(start - self.offset) as usize..(end - self.offset) as usize];
+        data[(start - offset) as usize..(end - offset) as usize].copy_from_slice(src);
+    }
+}
@@ -0,0 +1,141 @@
+mod mock_meta;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use common::error::Errno;
+use io_engine::backend_storage::BackendStore;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{Block, Segment};
+use segment_mgr::backend_reader::BackendReader;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr};
+
+// MemBackend keeps the uploaded segments in memory.
+#[derive(Default)]
+struct MemBackend {
+    segs: Mutex<HashMap<(u64, u64), Vec<u8>>>,
+}
+
+impl BackendStore for MemBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn write(&self, id0: u64, id1: u64, _offset: u64, data: &[u8]) -> Errno {
+        self.segs.lock().unwrap().entry((id0, id1)).or_insert(Vec::new()).extend_from_slice(data);
+        Errno::Esucc
+    }
+    fn read(&self, id0: u64, id1: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let segs = self.segs.lock().unwrap();
+        let d = segs.get(&(id0, id1)).ok_or(Errno::Enoent)?;
+        let start = std::cmp::min(offset as usize, d.len());
+        let end = std::cmp::min(start + size as usize, d.len());
+        Ok(d[start..end].to_vec())
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn delete(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+}
+
+fn new_segment(id0: u64, backend_size: u64, blocks: &[(u64, u64, i64)]) -> Segment {
+    let mut seg = Segment{
+        seg_id0: id0,
+        seg_id1: id0,
+        max_size: 1024,
+        leader: String::from("a"),
+        blocks: Vec::new(),
+        backend_size: backend_size,
+    };
+    for (offset, seg_start_addr, size) in blocks {
+        seg.blocks.push(Block{
+            offset: *offset,
+            seg_start_addr: *seg_start_addr,
+            seg_end_addr: *seg_start_addr + *size as u64,
+            size: *size,
+        });
+    }
+    seg
+}
+
+fn new_reader(segs: &Vec<Segment>, backend: Arc<MemBackend>) -> Result<BackendReader, String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("b", server));
+    let ret = meta.update_file_segments(1, 1, 0, segs);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    Ok(BackendReader::new(meta, backend))
+}
+
+#[test]
+fn test_backend_read_sealed() -> Result<(), String> {
+    let backend = Arc::new(MemBackend::default());
+    backend.write(1, 1, 0, b"aaaaabbbbb");
+    backend.write(2, 2, 0, b"ccc");
+    // file: [0, 5) "aaaaa", [5, 10) hole, [10, 15) "bbbbb", and [2, 5) is overwritten by "ccc".
+    let segs = vec![
+        new_segment(1, 10, &[(0, 0, 5), (10, 5, 5)]),
+        new_segment(2, 3, &[(2, 0, 3)]),
+    ];
+    let reader = new_reader(&segs, backend)?;
+    let data = reader.read(1, 1, 1, 12).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    let mut want = b"accc".to_vec();
+    want.extend_from_slice(&[0; 5]);
+    want.extend_from_slice(b"bbb");
+    if data != want {
+        return Err(format!("got data: {:?}, want: {:?}", data, want));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_backend_read_unsealed_tail() -> Result<(), String> {
+    let backend = Arc::new(MemBackend::default());
+    backend.write(1, 1, 0, b"aaaaab");
+    // only the first 6 bytes of the segment are uploaded, the rest is on the leader.
+    let segs = vec![new_segment(1, 6, &[(0, 0, 5), (5, 5, 5)])];
+    let reader = new_reader(&segs, backend)?;
+    let data = reader.read(1, 1, 0, 10).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"aaaaab".to_vec() {
+        return Err(format!("got data: {:?} for the sealed prefix", data));
+    }
+    let data = reader.read(1, 1, 7, 3).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if !data.is_empty() {
+        return Err(format!("got data: {:?} for the unsealed range", data));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_backend_read_cached_segments() -> Result<(), String> {
+    let backend = Arc::new(MemBackend::default());
+    backend.write(1, 1, 0, b"aaaaa");
+    backend.write(2, 2, 0, b"bbbbb");
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("b", server));
+    let ret = meta.update_file_segments(1, 1, 0, &vec![new_segment(1, 5, &[(0, 0, 5)])]);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    let reader = BackendReader::new(meta.clone(), backend);
+    let data = reader.read(1, 1, 0, 5).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"aaaaa".to_vec() {
+        return Err(format!("got data: {:?} for the first read", data));
+    }
+    // the file is overwritten by another machine, the reads keep using the cached segments.
+    let ret = meta.update_file_segments(1, 1, 0, &vec![new_segment(2, 5, &[(0, 0, 5)])]);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    let data = reader.read(1, 1, 0, 5).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"aaaaa".to_vec() {
+        return Err(format!("got data: {:?} with the cached segments", data));
+    }
+    reader.invalidate(1);
+    let data = reader.read(1, 1, 0, 5).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"bbbbb".to_vec() {
+        return Err(format!("got data: {:?} after invalidate", data));
+    }
+    Ok(())
+}
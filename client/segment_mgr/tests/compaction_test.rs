@@ -0,0 +1,60 @@
+use segment_mgr::compaction::{Compaction, SegmentUsage};
+use segment_mgr::types::Segment;
+
+fn new_segment(id: u64, blocks: &[(u64, u64, i64)]) -> Segment {
+    let mut s = Segment::rich_new(id, id, 100, String::from("a"));
+    for (offset, seg_addr, size) in blocks {
+        s.add_block(1, 1, *offset, *seg_addr, *size);
+    }
+    s
+}
+
+#[test]
+fn test_compaction_usage() -> Result<(), String> {
+    // (file offset, segment addr, size)
+    let segs = vec![
+        new_segment(1, &[(0, 0, 40), (100, 40, 20)]),
+        new_segment(2, &[(10, 0, 20), (100, 20, 20)]),
+        new_segment(3, &[(30, 0, 5)]),
+        new_segment(4, &[]),
+    ];
+    let want = vec![
+        SegmentUsage{id0: 1, id1: 1, used: 60, live: 15},
+        SegmentUsage{id0: 2, id1: 2, used: 40, live: 40},
+        SegmentUsage{id0: 3, id1: 3, used: 5, live: 5},
+        SegmentUsage{id0: 4, id1: 4, used: 0, live: 0},
+    ];
+    let got = Compaction::usage(&segs);
+    if got != want {
+        return Err(format!("got usage: {:?}, want: {:?}", got, want));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compaction_victims() -> Result<(), String> {
+    let segs = vec![
+        new_segment(1, &[(0, 0, 40)]),
+        new_segment(2, &[]),
+        new_segment(3, &[(0, 0, 30)]),
+        new_segment(4, &[(0, 0, 40)]),
+    ];
+    // the first one has 0 live bytes, the second one is empty, the last one is never chosen.
+    let got: Vec<u64> = Compaction::victims(&segs, 0.5).iter().map(|u| u.id0).collect();
+    if got != vec![1, 2, 3] {
+        return Err(format!("got victims: {:?}", got));
+    }
+    let segs = vec![
+        new_segment(1, &[(0, 0, 40)]),
+        new_segment(2, &[(0, 0, 10)]),
+    ];
+    let got: Vec<u64> = Compaction::victims(&segs, 0.7).iter().map(|u| u.id0).collect();
+    if !got.is_empty() {
+        return Err(format!("got victims: {:?}, the live ratio of them is 0.75", got));
+    }
+    let got: Vec<u64> = Compaction::victims(&segs, 0.8).iter().map(|u| u.id0).collect();
+    if got != vec![1] {
+        return Err(format!("got victims: {:?}", got));
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use common::config::DirHealthConfig;
+use segment_mgr::dir_health::{DirHealth, DirState, PROBE_FILE};
+
+fn new_dir(name: &str) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!("yigfs_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).map_err(|err| format!("failed to create {:?}, err: {}", dir, err))?;
+    Ok(dir.display().to_string())
+}
+
+fn states(health: &DirHealth) -> Vec<DirState> {
+    health.status().iter().map(|s| s.state).collect()
+}
+
+#[test]
+fn test_dir_health_check() -> Result<(), String> {
+    let good = new_dir("dir_health_good")?;
+    let missing = format!("{}/missing", good);
+    let cfg = DirHealthConfig{
+        check_interval_secs: 0,
+        max_error_ratio: 0.5,
+        min_ios: 4,
+    };
+    let health = DirHealth::new(&vec![good.clone(), missing.clone()], &cfg, 0.0);
+    health.check();
+    if states(&health) != vec![DirState::Healthy, DirState::Failed] || !health.is_usable(0) || health.is_usable(1) {
+        return Err(format!("got status: {:?}", health.status()));
+    }
+    if std::path::Path::new(&format!("{}/{}", good, PROBE_FILE)).exists() {
+        return Err(format!("the probe file is left in {}", good));
+    }
+
+    // the dir fails once more than half of the io fail, but not before min_ios.
+    for _ in 0..2 {
+        health.record(0, false);
+    }
+    if !health.is_healthy(0) {
+        return Err(format!("the dir fails before min_ios: {:?}", health.status()));
+    }
+    health.record(0, true);
+    health.record(0, false);
+    if health.is_healthy(0) || health.is_usable(0) {
+        return Err(format!("the dir doesn't fail with 3 of 4 io failed: {:?}", health.status()));
+    }
+    // the dir stays failed for the window with the errors, and recovers after a clean window.
+    health.check();
+    if health.is_usable(0) {
+        return Err(format!("the dir recovers with the errors in the window: {:?}", health.status()));
+    }
+    health.check();
+    if !health.is_healthy(0) {
+        return Err(format!("the dir doesn't recover: {:?}", health.status()));
+    }
+    std::fs::create_dir_all(&missing).map_err(|err| format!("failed to create {}, err: {}", missing, err))?;
+    health.check();
+    if states(&health) != vec![DirState::Healthy, DirState::Healthy] {
+        return Err(format!("got status: {:?}", health.status()));
+    }
+
+    // the dir is full if its free space is below the watermark, it is still usable.
+    let health = DirHealth::new(&vec![good.clone()], &cfg, 1.0);
+    health.check();
+    let status = health.status();
+    if status[0].state != DirState::Full || status[0].total == 0 || health.is_healthy(0) || !health.is_usable(0) {
+        return Err(format!("got status: {:?}", status));
+    }
+    let _ = std::fs::remove_dir_all(&good);
+    Ok(())
+}
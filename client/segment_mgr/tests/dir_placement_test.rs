@@ -0,0 +1,134 @@
+mod mock_meta;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use common::config::Config;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use segment_mgr::dir_placement::{DirUsage, HashDirPlacement, new_dir_placement};
+use segment_mgr::segment_mgr::SegmentMgr;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr, new_test_config, new_test_dir};
+
+const TB: u64 = 1 << 40;
+
+fn new_usage(idx: usize, total: u64, free: u64) -> DirUsage {
+    DirUsage{
+        idx: idx,
+        total: total,
+        free: free,
+        segments: 0,
+    }
+}
+
+fn count_dirs(policy: &str, candidates: &Vec<DirUsage>, n: u64) -> Result<HashMap<usize, u64>, String> {
+    let p = new_dir_placement(policy)?;
+    let mut counts = HashMap::<usize, u64>::new();
+    for i in 0..n {
+        let idx = p.choose(i * 7919, i, candidates);
+        *counts.entry(idx).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+#[test]
+fn test_dir_placement_policies() -> Result<(), String> {
+    if new_dir_placement("unknown").is_ok() {
+        return Err(String::from("unknown policy is accepted"));
+    }
+    // the hash policy chooses the same dirs as before.
+    let all = vec![new_usage(0, TB, TB), new_usage(1, TB, TB), new_usage(2, TB, TB)];
+    let p = new_dir_placement("hash")?;
+    for i in 0..100 {
+        if p.choose(i, i + 1, &all) != HashDirPlacement::ring_choose(i, i + 1, vec![0, 1, 2]) {
+            return Err(format!("hash policy chooses another dir for seg(id0: {}, id1: {})", i, i + 1));
+        }
+    }
+    let counts = count_dirs("hash", &vec![new_usage(0, TB, TB), new_usage(2, TB, TB)], 1000)?;
+    if counts.contains_key(&1) {
+        return Err(format!("hash policy chooses the dir not in candidates: {:?}", counts));
+    }
+
+    // a 8T disk gets about 8 times the segments of a 1T disk.
+    let counts = count_dirs("weight", &vec![new_usage(0, TB, TB), new_usage(1, 8 * TB, TB)], 9000)?;
+    let (small, large) = (counts.get(&0).copied().unwrap_or(0), counts.get(&1).copied().unwrap_or(0));
+    if small == 0 || large < small * 6 || large > small * 10 {
+        return Err(format!("weight policy got counts: {:?}", counts));
+    }
+
+    // the dir with the lowest used ratio, rather than the most free bytes.
+    let candidates = vec![new_usage(0, 8 * TB, TB), new_usage(1, TB, TB / 2), new_usage(2, 8 * TB, 2 * TB)];
+    let counts = count_dirs("least_used", &candidates, 10)?;
+    if counts.get(&1) != Some(&10) {
+        return Err(format!("least_used policy got counts: {:?}", counts));
+    }
+
+    let p = new_dir_placement("round_robin")?;
+    let candidates = vec![new_usage(3, TB, TB), new_usage(5, TB, TB)];
+    let got: Vec<usize> = (0..4).map(|i| p.choose(i, i, &candidates)).collect();
+    if got != vec![3, 5, 3, 5] {
+        return Err(format!("round_robin policy got dirs: {:?}", got));
+    }
+    Ok(())
+}
+
+fn new_config(name: &str, nums: &[u32], policy: &str, min_free_ratio: f64) -> Result<(Config, Vec<String>), String> {
+    let base = new_test_dir(name)?;
+    let dirs: Vec<String> = (0..nums.len()).map(|i| format!("{}/d{}", base, i)).collect();
+    let segs: Vec<(String, u64, u32)> = dirs.iter().zip(nums).map(|(d, n)| (d.clone(), 1024, *n)).collect();
+    let cfg = new_test_config(&base, &segs, &format!(r#"
+[dir_placement_config]
+policy = "{}"
+min_free_ratio = {}
+"#, policy, min_free_ratio))?;
+    Ok((cfg, dirs))
+}
+
+fn new_segment_mgr(cfg: &Config) -> SegmentMgr {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("b", server));
+    SegmentMgr::create(cfg, meta)
+}
+
+#[test]
+fn test_dir_placement_limits() -> Result<(), String> {
+    // the dirs are full after 1 and 2 segments.
+    let (cfg, dirs) = new_config("dir_placement_num", &[1, 2], "round_robin", 0.0)?;
+    let mgr = new_segment_mgr(&cfg);
+    let mut counts = HashMap::<String, u64>::new();
+    let mut placed = Vec::<(u64, u64)>::new();
+    for _ in 0..3 {
+        let seg = mgr.new_segment(&String::from("b"));
+        if seg.max_size != 1024 {
+            return Err(format!("got max_size {} while the dirs are not full", seg.max_size));
+        }
+        *counts.entry(mgr.get_segment_dir(seg.seg_id0, seg.seg_id1)).or_insert(0) += 1;
+        placed.push((seg.seg_id0, seg.seg_id1));
+    }
+    if counts.get(&dirs[0]) != Some(&1) || counts.get(&dirs[1]) != Some(&2) {
+        return Err(format!("got segments of dirs: {:?}", counts));
+    }
+    let seg = mgr.new_segment(&String::from("b"));
+    if seg.max_size != 0 {
+        return Err(format!("got max_size {} while all the dirs are full", seg.max_size));
+    }
+    // the removed segment frees its place.
+    mgr.remove_segment(placed[0].0, placed[0].1);
+    let seg = mgr.new_segment(&String::from("b"));
+    if seg.max_size != 1024 {
+        return Err(format!("got max_size {} after a segment is removed", seg.max_size));
+    }
+    if mgr.rebalance().is_ok() {
+        return Err(String::from("the segments placed by round_robin are rebalanced"));
+    }
+
+    // no dir has the free space above the watermark.
+    let (cfg, _) = new_config("dir_placement_free", &[0], "weight", 1.0)?;
+    let mgr = new_segment_mgr(&cfg);
+    let seg = mgr.new_segment(&String::from("b"));
+    if seg.max_size != 0 {
+        return Err(format!("got max_size {} while the free space is below the watermark", seg.max_size));
+    }
+    for d in &dirs {
+        let _ = std::fs::remove_dir_all(d);
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, UNIX_EPOCH};
+use common::config::LeaderConfig;
+use segment_mgr::evict_mgr::{EvictFile, EvictMgr, new_evict_policy};
+
+fn new_file(id: u64, dir: usize, size: u64, secs: u64, hits: u64) -> EvictFile {
+    EvictFile{
+        id0: id,
+        id1: id,
+        dir: dir,
+        size: size,
+        last_access: UNIX_EPOCH + Duration::from_secs(secs),
+        hits: hits,
+    }
+}
+
+fn new_evict_mgr(policy: &str) -> EvictMgr {
+    let cfg = LeaderConfig{
+        evict_policy: String::from(policy),
+        evict_capacity: 100,
+        evict_high_watermark: 0.9,
+        evict_low_watermark: 0.5,
+        ..Default::default()
+    };
+    EvictMgr::new(&cfg)
+}
+
+fn ids(files: &Vec<EvictFile>) -> Vec<u64> {
+    files.iter().map(|f| f.id0).collect()
+}
+
+#[test]
+fn test_evict_mgr_plan() -> Result<(), String> {
+    if new_evict_policy("unknown").is_ok() {
+        return Err(String::from("unknown policy is accepted"));
+    }
+    // dir 0 is above the high watermark, and dir 1 is not.
+    let files = vec![
+        new_file(1, 0, 30, 10, 5),
+        new_file(2, 0, 30, 20, 1),
+        new_file(3, 0, 35, 30, 1),
+        new_file(4, 1, 80, 0, 0),
+    ];
+    let dirs = vec![String::from("/data0"), String::from("/data1")];
+    let lru = new_evict_mgr("lru");
+    let usage = lru.usage(&dirs, &files);
+    if usage[0].used != 95 || usage[0].limit != 100 || usage[0].segments != 3 || usage[1].used != 80 {
+        return Err(format!("got usage: {:?}", usage));
+    }
+    // the least recently used ones are evicted until the usage is below the low watermark.
+    let victims = lru.plan(&usage, &files, |_| true);
+    if ids(&victims) != vec![1, 2] {
+        return Err(format!("lru evicted {:?}", ids(&victims)));
+    }
+    let lfu = new_evict_mgr("lfu");
+    let victims = lfu.plan(&usage, &files, |_| true);
+    if ids(&victims) != vec![2, 3] {
+        return Err(format!("lfu evicted {:?}", ids(&victims)));
+    }
+    // the segments which are not evictable are skipped.
+    let victims = lru.plan(&usage, &files, |f| f.id0 != 1);
+    if ids(&victims) != vec![2, 3] {
+        return Err(format!("lru evicted {:?} without segment 1", ids(&victims)));
+    }
+    // the access since start takes the place of the modified time.
+    lru.access(3, 3);
+    let mut f = files[2].clone();
+    lru.fill_access(&mut f);
+    if f.hits != 1 || f.last_access <= files[2].last_access {
+        return Err(format!("got file {:?} after access", f));
+    }
+    Ok(())
+}
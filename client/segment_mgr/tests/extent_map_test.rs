@@ -0,0 +1,172 @@
+use segment_mgr::extent_map::ExtentMap;
+use segment_mgr::types::Segment;
+
+// xorshift64, so that the failed cases can be reproduced by the seed.
+struct Rand {
+    state: u64,
+}
+
+impl Rand {
+    fn new(seed: u64) -> Self {
+        Rand{
+            state: seed * 2 + 1,
+        }
+    }
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low)
+    }
+}
+
+// the writes of a file, appended into the segments like the leader does.
+struct Model {
+    // the expected content of the file, the holes are zero.
+    file: Vec<u8>,
+    // the data of the segments.
+    seg_data: Vec<Vec<u8>>,
+    segs: Vec<Segment>,
+    // the map updated by every write.
+    map: ExtentMap,
+    seg_size: u64,
+}
+
+impl Model {
+    fn new(seg_size: u64) -> Self {
+        Model{
+            file: Vec::new(),
+            seg_data: Vec::new(),
+            segs: Vec::new(),
+            map: ExtentMap::new(),
+            seg_size: seg_size,
+        }
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) {
+        let end = offset as usize + data.len();
+        if self.file.len() < end {
+            self.file.resize(end, 0);
+        }
+        self.file[offset as usize..end].copy_from_slice(data);
+        let full = match self.seg_data.last() {
+            Some(d) => d.len() + data.len() > self.seg_size as usize,
+            None => true,
+        };
+        if full {
+            let id = self.segs.len() as u64 + 1;
+            self.segs.push(Segment::rich_new(id, id, self.seg_size, String::from("a")));
+            self.seg_data.push(Vec::new());
+        }
+        let idx = self.segs.len() - 1;
+        let seg_addr = self.seg_data[idx].len() as u64;
+        self.seg_data[idx].extend_from_slice(data);
+        self.segs[idx].add_block(1, 1, offset, seg_addr, data.len() as i64);
+        let id = idx as u64 + 1;
+        self.map.insert(offset, data.len() as u64, id, id, seg_addr);
+    }
+
+    // read the file through the extent map like the leader does.
+    fn read(&self, m: &ExtentMap, offset: u64, size: u64) -> Result<Vec<u8>, String> {
+        let mut data = Vec::<u8>::new();
+        let mut last_end = offset;
+        for e in m.lookup(offset, offset + size) {
+            if e.start < last_end || e.end <= e.start || e.end > offset + size {
+                return Err(format!("got invalid extent: {:?} after {} in [{}, {})", e, last_end, offset, offset + size));
+            }
+            last_end = e.end;
+            data.resize((e.start - offset) as usize, 0);
+            let seg = &self.seg_data[e.id0 as usize - 1];
+            data.extend_from_slice(&seg[e.seg_addr as usize..(e.seg_addr + e.size()) as usize]);
+        }
+        // zero-fill the hole at the end of the range.
+        if m.size() > offset {
+            data.resize((std::cmp::min(m.size(), offset + size) - offset) as usize, 0);
+        }
+        Ok(data)
+    }
+
+    fn expect(&self, offset: u64, size: u64) -> Vec<u8> {
+        let start = std::cmp::min(offset as usize, self.file.len());
+        let end = std::cmp::min((offset + size) as usize, self.file.len());
+        self.file[start..end].to_vec()
+    }
+}
+
+fn check_model(seed: u64) -> Result<(), String> {
+    let mut r = Rand::new(seed);
+    let mut model = Model::new(r.range(32, 128));
+    let writes = r.range(1, 60);
+    for _ in 0..writes {
+        let offset = r.range(0, 256);
+        let size = r.range(1, 32);
+        let data: Vec<u8> = (0..size).map(|_| r.range(1, 256) as u8).collect();
+        model.write(offset, &data);
+    }
+    let rebuilt = ExtentMap::from_segments(&model.segs);
+    if model.map.size() != model.file.len() as u64 || rebuilt.size() != model.file.len() as u64 {
+        return Err(format!("seed: {}, got size: {} and {}, want: {}", seed, model.map.size(), rebuilt.size(),
+        model.file.len()));
+    }
+    for _ in 0..50 {
+        let offset = r.range(0, 300);
+        let size = r.range(0, 128);
+        let want = model.expect(offset, size);
+        for (i, m) in [&model.map, &rebuilt].iter().enumerate() {
+            let got = model.read(m, offset, size).map_err(|err| format!("seed: {}, {}", seed, err))?;
+            if got != want {
+                return Err(format!("seed: {}, map: {}, read [{}, {}) got: {:?}, want: {:?}", seed, i, offset, offset + size,
+                got, want));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_extent_map_random_writes() -> Result<(), String> {
+    for seed in 0..500 {
+        check_model(seed)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_extent_map_overwrite() -> Result<(), String> {
+    let mut m = ExtentMap::new();
+    m.insert(0, 10, 1, 1, 0);
+    m.insert(3, 4, 1, 1, 10);
+    let extents = m.lookup(0, 10);
+    let got: Vec<(u64, u64, u64)> = extents.iter().map(|e| (e.start, e.end, e.seg_addr)).collect();
+    if got != vec![(0, 3, 0), (3, 7, 10), (7, 10, 7)] {
+        return Err(format!("got extents: {:?} after overwrite", got));
+    }
+    // the hole between the extents is skipped.
+    m.insert(20, 5, 1, 1, 14);
+    let got: Vec<(u64, u64)> = m.lookup(8, 22).iter().map(|e| (e.start, e.end)).collect();
+    if got != vec![(8, 10), (20, 22)] {
+        return Err(format!("got extents: {:?} with hole", got));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_extent_map_merge() -> Result<(), String> {
+    let mut m = ExtentMap::new();
+    // the sequential writes into one segment are merged.
+    for i in 0..10 {
+        m.insert(i * 4, 4, 1, 1, i * 4);
+    }
+    if m.len() != 1 {
+        return Err(format!("got {} extents for the sequential writes", m.len()));
+    }
+    // the continuous writes in different segments are not merged.
+    m.insert(40, 4, 2, 2, 0);
+    if m.len() != 2 {
+        return Err(format!("got {} extents after write into another segment", m.len()));
+    }
+    Ok(())
+}
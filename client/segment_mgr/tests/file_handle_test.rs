@@ -3,7 +3,7 @@ use segment_mgr::types::{FileHandle, Segment};
 
 #[test]
 fn test_file_handle_mgr_start()->Result<(), String> {
-    let mut mgr = FileHandleMgr::create();
+    let mgr = FileHandleMgr::create();
     mgr.stop();
     let ret = mgr.get(1);
     match ret {
@@ -21,10 +21,12 @@ fn test_file_handle_mgr_start()->Result<(), String> {
 
 #[test]
 fn test_file_handle_mgr_add() -> Result<(), String>{
-    let mut mgr = FileHandleMgr::create();
+    let mgr = FileHandleMgr::create();
     let h1 = FileHandle{
         ino: 1,
+        generation: 0,
         leader: String::from(""),
+        lease_epoch: 0,
         segments: Vec::<Segment>::new(),
     };
     let ret = mgr.add(&h1);
@@ -53,10 +55,12 @@ fn test_file_handle_mgr_add() -> Result<(), String>{
 #[test]
 fn test_file_handle_mgr_del() -> Result<(), String>{
     let ino: u64 = 1;
-    let mut mgr = FileHandleMgr::create();
+    let mgr = FileHandleMgr::create();
     let h1 = FileHandle{
         ino: ino,
+        generation: 0,
         leader: String::from(""),
+        lease_epoch: 0,
         segments: Vec::<Segment>::new(),
     };
     let ret = mgr.add(&h1);
@@ -104,10 +108,12 @@ fn test_file_handle_mgr_del() -> Result<(), String>{
 #[test]
 fn test_file_handle_get_last_segment() -> Result<(), String>{
     let ino: u64 = 1;
-    let mut mgr = FileHandleMgr::create();
+    let mgr = FileHandleMgr::create();
     let h1 = FileHandle{
         ino: ino,
+        generation: 0,
         leader: String::from(""),
+        lease_epoch: 0,
         segments: Vec::<Segment>::new(),
     };
     let ret = mgr.add(&h1);
@@ -209,4 +215,43 @@ fn test_file_handle_get_last_segment() -> Result<(), String>{
     }
     mgr.stop();
     return Ok(());
-}
\ No newline at end of file
+}
+#[test]
+fn test_file_handle_stale_block() -> Result<(), String>{
+    let ino: u64 = 1;
+    let mgr = FileHandleMgr::create();
+    let mut h1 = FileHandle::new(ino, 2);
+    let seg = Segment::new(&String::from("local"));
+    h1.segments.push(seg.copy());
+    mgr.add(&h1);
+    // the block written by the deleted file of generation 1 must be dropped.
+    let mut b = Block{
+        ino: ino,
+        generation: 1,
+        offset: 0,
+        seg_start_addr: 0,
+        seg_end_addr: 5,
+        size: 5,
+    };
+    mgr.add_block(ino, seg.seg_id0, seg.seg_id1, &b);
+    b.generation = 2;
+    b.offset = 10;
+    mgr.add_block(ino, seg.seg_id0, seg.seg_id1, &b);
+    let ret = mgr.get(ino);
+    mgr.stop();
+    match ret {
+        Ok(ret) => {
+            if ret.generation != 2 {
+                return Err(format!("got invalid generation: {}", ret.generation));
+            }
+            let blocks = &ret.segments[0].blocks;
+            if blocks.len() != 1 || blocks[0].offset != 10 || blocks[0].generation != 2 {
+                return Err(format!("got invalid blocks: {:?}", blocks));
+            }
+        }
+        Err(err) => {
+            return Err(format!("failed to get handle, err: {:?}", err));
+        }
+    }
+    Ok(())
+}
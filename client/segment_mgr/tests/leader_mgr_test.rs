@@ -0,0 +1,1248 @@
+mod mock_meta;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use common::config::Config;
+use common::error::Errno;
+use common::runtime::Executor;
+use io_engine::backend_storage::BackendStore;
+use io_engine::segment_format::{RECORD_HEADER_SIZE, ScannedBlock, SegmentFile};
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{Block, Segment};
+use segment_mgr::leader::Leader;
+use segment_mgr::leader_mgr::LeaderMgr;
+use segment_mgr::leader_rpc::{MAX_WRITE_LEN, RpcOp, RpcRequest, RpcResponse, max_request_data, read_frame, sign_request,
+    write_frame};
+use segment_mgr::leader_server::LeaderServer;
+use segment_mgr::segment_mgr::SegmentMgr;
+use segment_mgr::types::BlockIo;
+use segment_mgr::uploader::UploadState;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr, new_test_config, new_test_dir};
+
+struct NoBackend {}
+
+impl BackendStore for NoBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+    fn write(&self, _id0: u64, _id1: u64, _offset: u64, _data: &[u8]) -> Errno {
+        Errno::Enotsupp
+    }
+    fn read(&self, _id0: u64, _id1: u64, _offset: u64, _size: u32) -> Result<Vec<u8>, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+    fn delete(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+}
+
+fn new_config(name: &str, peer_port: u16) -> Result<Config, String> {
+    let dir = new_test_dir(name)?;
+    new_test_config(&dir, &[(dir.clone(), 67108864, 0)], &format!(r#"
+[leader_config]
+lease_secs = 30
+renew_interval_secs = 10
+peer_port = {}
+rpc_token = "hahahaha"
+"#, peer_port))
+}
+
+fn new_leader_mgr(cfg: &Config, server: &Arc<Mutex<MockMetaServer>>, exec: &Executor) -> LeaderMgr {
+    new_leader_mgr_with_backend(cfg, server, exec, Box::new(NoBackend{}))
+}
+
+fn new_leader_mgr_with_backend(cfg: &Config, server: &Arc<Mutex<MockMetaServer>>, exec: &Executor,
+    backend: Box<dyn BackendStore>) -> LeaderMgr {
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("b", server.clone()));
+    let seg_mgr = Arc::new(SegmentMgr::create(cfg, meta.clone()));
+    LeaderMgr::new(&String::from("b"), &cfg.leader_config, cfg.disk_cache_config.thread_num,
+    exec, seg_mgr, meta, backend)
+}
+
+// the local leader "b" of a test with ino 1 opened.
+struct LocalLeader {
+    cfg: Config,
+    server: Arc<Mutex<MockMetaServer>>,
+    mgr: LeaderMgr,
+    leader: Arc<dyn Leader>,
+    _exec: Executor,
+}
+
+// open ino 1 on the local leader "b" with the config of name. the writes go into the segments
+// directly and nothing is read ahead, unless set_cfg turns them on.
+fn open_local_leader(name: &str, set_cfg: impl FnOnce(&mut Config), backend: Box<dyn BackendStore>) -> Result<LocalLeader, String> {
+    let mut cfg = new_config(name, 1)?;
+    cfg.leader_config.write_buffer_size = 0;
+    cfg.leader_config.readahead_window = 0;
+    set_cfg(&mut cfg);
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let exec = Executor::create();
+    let mgr = new_leader_mgr_with_backend(&cfg, &server, &exec, backend);
+    let leader = mgr.get_leader(&String::from("b")).clone();
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to open, err: {:?}", ret));
+    }
+    Ok(LocalLeader{
+        cfg: cfg,
+        server: server,
+        mgr: mgr,
+        leader: leader,
+        _exec: exec,
+    })
+}
+
+#[test]
+fn test_leader_takeover_expired() -> Result<(), String> {
+    // the leader rpc port of machine "127.0.0.1" is closed.
+    let port = TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?
+    .local_addr().map_err(|err| format!("failed to get addr, err: {}", err))?.port();
+    let cfg = new_config("takeover", port)?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta_a = MockMetaServiceMgr::new("127.0.0.1", server.clone());
+    // the lease of "127.0.0.1" expires at once.
+    let lease_a = meta_a.acquire_file_lease(1, 1, 0).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let file_leader = meta_a.get_file_leader(1).map_err(|err| format!("failed to get leader, err: {:?}", err))?;
+    let leader = mgr.resolve_leader(1, 1, &file_leader);
+    if leader != "b" {
+        return Err(format!("got leader: {} after the lease of leader is expired", leader));
+    }
+    // the handle is rebuilt on the new leader.
+    let ret = mgr.get_leader(&leader).open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to open on the new leader, err: {:?}", ret));
+    }
+    // the old leader is fenced off by the lease epoch.
+    let ret = meta_a.update_file_segments(1, 1, lease_a.epoch, &Vec::new());
+    if !ret.is_estale() {
+        return Err(format!("the old leader updated segments with ret: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_unreachable_with_lease() -> Result<(), String> {
+    // nothing listens on the leader rpc port, but the lease of "127.0.0.1" is still valid.
+    let port = TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?
+    .local_addr().map_err(|err| format!("failed to get addr, err: {}", err))?.port();
+    let cfg = new_config("unreachable", port)?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta_a = MockMetaServiceMgr::new("127.0.0.1", server.clone());
+    let lease_a = meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let file_leader = meta_a.get_file_leader(1).map_err(|err| format!("failed to get leader, err: {:?}", err))?;
+    let leader = mgr.resolve_leader(1, 1, &file_leader);
+    if leader != "127.0.0.1" {
+        return Err(format!("the leader with valid lease is taken over by: {}", leader));
+    }
+    // the meta server refuses to transfer the file until the lease expires.
+    let leader = mgr.takeover(1, 1, &file_leader).map_err(|err| format!("failed to takeover, err: {:?}", err))?;
+    if leader != "127.0.0.1" {
+        return Err(format!("the leader with valid lease is transferred to: {}", leader));
+    }
+    let ret = meta_a.update_file_segments(1, 1, lease_a.epoch, &Vec::new());
+    if !ret.is_success() {
+        return Err(format!("the leader with valid lease failed to update segments, ret: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+// serve the leader rpc of one file in memory, the first connection is dropped
+// without response if drop_first is true.
+fn start_fake_peer(listener: TcpListener, drop_first: bool) {
+    thread::spawn(move || {
+        let data = Arc::new(Mutex::new(Vec::<u8>::new()));
+        let mut dropped = !drop_first;
+        for conn in listener.incoming() {
+            let mut conn = match conn {
+                Ok(c) => c,
+                Err(_) => break,
+            };
+            if !dropped {
+                dropped = true;
+                let _ = read_frame::<TcpStream, RpcRequest, _>(&mut conn, max_request_data);
+                continue;
+            }
+            let data = data.clone();
+            thread::spawn(move || {
+                loop {
+                    let (req, body) = match read_frame::<TcpStream, RpcRequest, _>(&mut conn, max_request_data) {
+                        Ok(r) => r,
+                        Err(_) => break,
+                    };
+                    let mut resp = RpcResponse::default();
+                    let mut out = Vec::new();
+                    match RpcOp::from_u8(req.op) {
+                        Some(RpcOp::Write) => {
+                            let mut d = data.lock().unwrap();
+                            resp.offset = d.len() as u64;
+                            resp.size = body.len() as u32;
+                            d.extend_from_slice(&body);
+                        }
+                        Some(RpcOp::Read) => {
+                            let d = data.lock().unwrap();
+                            let start = std::cmp::min(req.offset as usize, d.len());
+                            let end = std::cmp::min(start + req.size as usize, d.len());
+                            out.extend_from_slice(&d[start..end]);
+                        }
+                        Some(_) => {}
+                        None => {
+                            resp.errno = Errno::Enotsupp as i32;
+                        }
+                    }
+                    if write_frame(&mut conn, &resp, &out).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn test_leader_peer_forward() -> Result<(), String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?;
+    let peer = listener.local_addr().map_err(|err| format!("failed to get addr, err: {}", err))?.to_string();
+    // the first read is retried on a new connection.
+    start_fake_peer(listener, true);
+    let cfg = new_config("peer", 1)?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let leader = mgr.get_leader(&peer);
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to open on peer, err: {:?}", ret));
+    }
+    let b = leader.write(1, 1, 0, b"hello").map_err(|err| format!("failed to write to peer, err: {:?}", err))?;
+    if b.size != 5 {
+        return Err(format!("got invalid block: {:?} from peer", b));
+    }
+    let data = leader.read(1, 1, 0, 5).map_err(|err| format!("failed to read from peer, err: {:?}", err))?;
+    if data != b"hello".to_vec() {
+        return Err(format!("got data: {:?} from peer", data));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close on peer, err: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_peer_unreachable() -> Result<(), String> {
+    let peer = TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?
+    .local_addr().map_err(|err| format!("failed to get addr, err: {}", err))?.to_string();
+    let cfg = new_config("unreach", 1)?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let ret = mgr.get_leader(&peer).read(1, 1, 0, 5);
+    match ret {
+        Ok(_) => {
+            return Err(format!("read from the unreachable peer"));
+        }
+        Err(err) => {
+            if !err.is_eunreach() {
+                return Err(format!("got err: {:?} from the unreachable peer", err));
+            }
+        }
+    }
+    mgr.stop();
+    Ok(())
+}
+
+// MemLeader keeps the data of one file in memory.
+#[derive(Default)]
+struct MemLeader {
+    data: Mutex<Vec<u8>>,
+}
+
+impl Leader for MemLeader {
+    fn open(&self, _ino: u64, _generation: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn write(&self, _ino: u64, _generation: u64, _offset: u64, data: &[u8]) -> Result<BlockIo, Errno> {
+        let mut d = self.data.lock().unwrap();
+        let b = BlockIo{
+            id0: 1,
+            id1: 1,
+            offset: d.len() as u64,
+            size: data.len() as u32,
+        };
+        d.extend_from_slice(data);
+        Ok(b)
+    }
+    fn read(&self, _ino: u64, _generation: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let d = self.data.lock().unwrap();
+        let start = std::cmp::min(offset as usize, d.len());
+        let end = std::cmp::min(start + size as usize, d.len());
+        Ok(d[start..end].to_vec())
+    }
+    fn flush(&self, _ino: u64, _generation: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn close(&self, _ino: u64, _generation: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn release(&self) {}
+}
+
+// start the leader server on a free port, which is also the machine name of the leader.
+fn start_leader_server(token: &str, max_conns: usize, leader: Arc<dyn Leader>,
+    server: &Arc<Mutex<MockMetaServer>>) -> Result<(LeaderServer, String), String> {
+    let peer = TcpListener::bind("127.0.0.1:0").map_err(|err| format!("failed to bind, err: {}", err))?
+    .local_addr().map_err(|err| format!("failed to get addr, err: {}", err))?.to_string();
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new(&peer, server.clone()));
+    let leader_server = LeaderServer::start(&peer, &peer, &String::from(token), max_conns, 2, leader, meta)?;
+    Ok((leader_server, peer))
+}
+
+#[test]
+fn test_leader_server() -> Result<(), String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("hahahaha", 4, Arc::new(MemLeader::default()), &server)?;
+    let cfg = new_config("server", 1)?;
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let leader = mgr.get_leader(&peer);
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to open on leader server, err: {:?}", ret));
+    }
+    for i in 0..3 {
+        let b = leader.write(1, 1, i * 5, b"hello").map_err(|err| format!("failed to write, err: {:?}", err))?;
+        if b.offset != i * 5 || b.size != 5 {
+            return Err(format!("got invalid block: {:?} from leader server", b));
+        }
+    }
+    let data = leader.read(1, 1, 5, 10).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"hellohello".to_vec() {
+        return Err(format!("got data: {:?} from leader server", data));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close on leader server, err: {:?}", ret));
+    }
+    mgr.stop();
+    leader_server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_server_auth() -> Result<(), String> {
+    // the peer signs the requests with another token.
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("another", 4, Arc::new(MemLeader::default()), &server)?;
+    let cfg = new_config("auth", 1)?;
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let ret = mgr.get_leader(&peer).write(1, 1, 0, b"hello");
+    match ret {
+        Ok(b) => {
+            return Err(format!("the unauthenticated write is served with block: {:?}", b));
+        }
+        Err(err) => {
+            if !matches!(err, Errno::Eaccess) {
+                return Err(format!("got err: {:?} for the unauthenticated write", err));
+            }
+        }
+    }
+    mgr.stop();
+    leader_server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_server_not_leader() -> Result<(), String> {
+    // the peer still sends the requests to the leader server after the file is taken over by "c".
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("hahahaha", 4, Arc::new(MemLeader::default()), &server)?;
+    let meta_c = MockMetaServiceMgr::new("c", server.clone());
+    meta_c.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let mut conn = TcpStream::connect(&peer).map_err(|err| format!("failed to connect, err: {}", err))?;
+    for op in vec![RpcOp::Open, RpcOp::Write, RpcOp::Read] {
+        let mut req = RpcRequest{
+            op: op as u8,
+            ino: 1,
+            generation: 1,
+            size: 5,
+            machine: String::from("b"),
+            ..Default::default()
+        };
+        let data: &[u8] = if op == RpcOp::Write { b"hello" } else { b"" };
+        sign_request(&String::from("hahahaha"), &mut req, data)?;
+        write_frame(&mut conn, &req, data).map_err(|err| format!("failed to send, err: {}", err))?;
+        let (resp, _) = read_frame::<TcpStream, RpcResponse, _>(&mut conn, |_| 5).map_err(|err| format!("failed to recv, err: {}", err))?;
+        if resp.errno != Errno::Eexists as i32 {
+            return Err(format!("got errno: {} for op: {:?} of the file led by another machine", resp.errno, op));
+        }
+    }
+    leader_server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_server_data_len() -> Result<(), String> {
+    // the frames claiming more data than the op carries are rejected before the data is read.
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("hahahaha", 4, Arc::new(MemLeader::default()), &server)?;
+    for (op, data_len) in vec![(RpcOp::Read, 1), (RpcOp::Write, MAX_WRITE_LEN + 1)] {
+        let mut conn = TcpStream::connect(&peer).map_err(|err| format!("failed to connect, err: {}", err))?;
+        let req = RpcRequest{
+            op: op as u8,
+            ino: 1,
+            generation: 1,
+            machine: String::from("b"),
+            ..Default::default()
+        };
+        let mut frame = Vec::<u8>::new();
+        write_frame(&mut frame, &req, b"").map_err(|err| format!("failed to encode, err: {}", err))?;
+        frame[8..12].copy_from_slice(&data_len.to_be_bytes());
+        conn.write_all(&frame).map_err(|err| format!("failed to send, err: {}", err))?;
+        let mut buf = [0u8; 1];
+        let n = conn.read(&mut buf).map_err(|err| format!("failed to recv, err: {}", err))?;
+        if n != 0 {
+            return Err(format!("got response for op: {:?} with data_len: {}", op, data_len));
+        }
+    }
+    leader_server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_server_max_conns() -> Result<(), String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("hahahaha", 1, Arc::new(MemLeader::default()), &server)?;
+    let cfg = new_config("max_conns", 1)?;
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    // the only worker is busy with this idle connection.
+    let _busy = TcpStream::connect(&peer).map_err(|err| format!("failed to connect, err: {}", err))?;
+    thread::sleep(Duration::from_millis(200));
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr(&cfg, &server, &exec);
+    let ret = mgr.get_leader(&peer).open(1, 1);
+    if ret.is_success() {
+        return Err(format!("the connection over the limit is served"));
+    }
+    mgr.stop();
+    leader_server.stop();
+    Ok(())
+}
+
+// SealedBackend holds the first sealed bytes of segment (1, 1).
+struct SealedBackend {
+    data: Vec<u8>,
+}
+
+impl BackendStore for SealedBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn write(&self, _id0: u64, _id1: u64, _offset: u64, _data: &[u8]) -> Errno {
+        Errno::Enotsupp
+    }
+    fn read(&self, _id0: u64, _id1: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        let start = std::cmp::min(offset as usize, self.data.len());
+        let end = std::cmp::min(start + size as usize, self.data.len());
+        Ok(self.data[start..end].to_vec())
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn delete(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+}
+
+#[test]
+fn test_leader_read_unsealed_tail() -> Result<(), String> {
+    // the leader has "hello world", and only "hello" is sealed.
+    let leader = Arc::new(MemLeader::default());
+    leader.write(1, 1, 0, b"hello world").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let (mut leader_server, peer) = start_leader_server("hahahaha", 4, leader, &server)?;
+    let cfg = new_config("tail", 1)?;
+    let meta_a = MockMetaServiceMgr::new(&peer, server.clone());
+    let lease = meta_a.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let seg = Segment{
+        seg_id0: 1,
+        seg_id1: 1,
+        max_size: 1024,
+        leader: peer.clone(),
+        blocks: vec![Block{
+            offset: 0,
+            seg_start_addr: 0,
+            seg_end_addr: 11,
+            size: 11,
+        }],
+        backend_size: 5,
+    };
+    let ret = meta_a.update_file_segments(1, 1, lease.epoch, &vec![seg]);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    let exec = Executor::create();
+    let mut mgr = new_leader_mgr_with_backend(&cfg, &server, &exec,
+    Box::new(SealedBackend{data: b"HELLO".to_vec()}));
+    // the sealed prefix comes from the backend, and the tail from the leader.
+    let data = mgr.read(&peer, 1, 1, 2, 8).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"LLO worl".to_vec() {
+        return Err(format!("got data: {:?}", String::from_utf8_lossy(&data)));
+    }
+    mgr.stop();
+    leader_server.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_overwrite() -> Result<(), String> {
+    let LocalLeader{mut mgr, leader, _exec, ..} = open_local_leader("overwrite", |_| {}, Box::new(NoBackend{}))?;
+    let writes: Vec<(u64, &[u8])> = vec![(0, b"hello world"), (6, b"WORLD"), (20, b"x"), (2, b"LL")];
+    for (offset, data) in &writes {
+        leader.write(1, 1, *offset, data).map_err(|err| format!("failed to write at {}, err: {:?}", offset, err))?;
+    }
+    let mut want = b"heLLo WORLD".to_vec();
+    want.extend_from_slice(&[0; 9]);
+    want.extend_from_slice(b"x");
+    let cases: Vec<(u64, u32)> = vec![(0, 100), (3, 5), (8, 14), (12, 4), (21, 10)];
+    for (offset, size) in cases {
+        let data = leader.read(1, 1, offset, size).map_err(|err| format!("failed to read at {}, err: {:?}", offset, err))?;
+        let start = std::cmp::min(offset as usize, want.len());
+        let end = std::cmp::min(offset as usize + size as usize, want.len());
+        if data != want[start..end].to_vec() {
+            return Err(format!("read [{}, {}) got: {:?}, want: {:?}", offset, offset + size as u64, data, &want[start..end]));
+        }
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_split_write() -> Result<(), String> {
+    let LocalLeader{server, mut mgr, leader, _exec, ..} = open_local_leader("split_write", |cfg| cfg.segment_configs[0].size = 16,
+    Box::new(NoBackend{}))?;
+    // the first write leaves 6 bytes in the first segment, the second one spans 4 segments.
+    let mut want: Vec<u8> = (0..60).map(|i| i as u8).collect();
+    let b = leader.write(1, 1, 0, &want[..10]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    if b.size != 10 {
+        return Err(format!("wrote {} bytes, want 10", b.size));
+    }
+    let b = leader.write(1, 1, 10, &want[10..]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    if b.size != 50 || b.offset != 10 {
+        return Err(format!("got block {:?} for the split write", b));
+    }
+    want[3] = 0xff;
+    leader.write(1, 1, 3, &want[3..4]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    // every segment is full except the last one, and no block is larger than the segment.
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    if segs.len() != 4 {
+        return Err(format!("got {} segments, want 4", segs.len()));
+    }
+    let mut total: i64 = 0;
+    for s in &segs {
+        for b in &s.blocks {
+            if b.seg_end_addr > 16 {
+                return Err(format!("block {:?} is out of the segment", b));
+            }
+            total += b.size;
+        }
+    }
+    if total != 61 {
+        return Err(format!("got {} bytes in blocks, want 61", total));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+// the size of the data in the segment files.
+fn segment_files_size(cfg: &Config) -> Result<u64, String> {
+    let dir = &cfg.segment_configs[0].dir;
+    let mut size: u64 = 0;
+    for e in std::fs::read_dir(dir).map_err(|err| format!("failed to read dir {}, err: {}", dir, err))? {
+        let e = e.map_err(|err| format!("failed to read dir {}, err: {}", dir, err))?;
+        if e.file_name().to_string_lossy().ends_with(".seg") {
+            size += SegmentFile::scan(&e.path().display().to_string(), false)?.data_size;
+        }
+    }
+    Ok(size)
+}
+
+#[test]
+fn test_leader_local_write_buffer() -> Result<(), String> {
+    let LocalLeader{cfg, server, mut mgr, leader, _exec} = open_local_leader("write_buffer", |cfg| {
+        cfg.leader_config.write_buffer_size = 16;
+        cfg.leader_config.write_flush_ms = 60000;
+    }, Box::new(NoBackend{}))?;
+    let mut want = Vec::<u8>::new();
+    for i in 0..5 {
+        let d = [b'a' + i; 3];
+        leader.write(1, 1, i as u64 * 3, &d).map_err(|err| format!("failed to write, err: {:?}", err))?;
+        want.extend_from_slice(&d);
+    }
+    // the writes are only in the buffer, and the reads still see them.
+    if segment_files_size(&cfg)? != 0 {
+        return Err(format!("the buffered writes are written into segment"));
+    }
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    // the write which doesn't follow the buffer flushes it.
+    leader.write(1, 1, 20, b"xy").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    if segment_files_size(&cfg)? != 15 {
+        return Err(format!("the buffer is not flushed by the random write"));
+    }
+    want.extend_from_slice(&[0; 5]);
+    want.extend_from_slice(b"xy");
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    let ret = leader.flush(1, 1);
+    if !ret.is_success() || segment_files_size(&cfg)? != 17 {
+        return Err(format!("failed to flush, err: {:?}", ret));
+    }
+    // the buffer is flushed once it is full.
+    leader.write(1, 1, 22, &[b'z'; 16]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 38, b"0123456789").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 48, b"abcdef").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    if segment_files_size(&cfg)? != 49 {
+        return Err(format!("got {} bytes in segment after the buffer is full", segment_files_size(&cfg)?));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    let sizes: Vec<i64> = segs.iter().flat_map(|s| s.blocks.iter().map(|b| b.size)).collect();
+    // the blocks following each other both in the file and in the segment are merged.
+    if sizes != vec![15, 34] {
+        return Err(format!("got blocks with sizes: {:?}", sizes));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_write_buffer_timer() -> Result<(), String> {
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("write_buffer_timer", |cfg| {
+        cfg.leader_config.write_buffer_size = 1024;
+        cfg.leader_config.write_flush_ms = 50;
+    }, Box::new(NoBackend{}))?;
+    leader.write(1, 1, 0, b"hello").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    for _ in 0..40 {
+        if segment_files_size(&cfg)? == 5 {
+            mgr.stop();
+            return Ok(());
+        }
+        thread::sleep(std::time::Duration::from_millis(50));
+    }
+    mgr.stop();
+    Err(format!("the buffer is not flushed by the timer"))
+}
+
+#[test]
+fn test_leader_local_write_buffer_lost() -> Result<(), String> {
+    // the lease expires before the timer flushes the buffer, and it is never renewed.
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("write_buffer_lost", |cfg| {
+        cfg.leader_config.write_buffer_size = 1024;
+        cfg.leader_config.write_flush_ms = 1500;
+        cfg.leader_config.lease_secs = 1;
+        cfg.leader_config.renew_interval_secs = 3600;
+    }, Box::new(NoBackend{}))?;
+    leader.write(1, 1, 0, b"hello").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    thread::sleep(Duration::from_millis(3000));
+    // the failure of the background flush is returned by the next flush.
+    let ret = leader.flush(1, 1);
+    if !ret.is_estale() {
+        return Err(format!("got err: {:?} for the flush after the buffered writes are lost", ret));
+    }
+    if segment_files_size(&cfg)? != 0 {
+        return Err(format!("the buffered writes are written without lease"));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_readahead() -> Result<(), String> {
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("readahead", |cfg| cfg.leader_config.readahead_window = 1 << 20,
+    Box::new(NoBackend{}))?;
+    let chunk: u32 = 64 << 10;
+    let want: Vec<u8> = (0..4 * chunk).map(|i| (i % 251) as u8).collect();
+    leader.write(1, 1, 0, &want).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    for i in 0..2 {
+        let offset = (i * chunk) as u64;
+        let data = leader.read(1, 1, offset, chunk).map_err(|err| format!("failed to read, err: {:?}", err))?;
+        if data != want[offset as usize..(offset + chunk as u64) as usize].to_vec() {
+            return Err(format!("read wrong data at {}", offset));
+        }
+    }
+    thread::sleep(std::time::Duration::from_millis(500));
+    // the data after the sequential reads is served from the cache, even if it is lost in the segment.
+    let dir = cfg.segment_configs[0].dir.clone();
+    for e in std::fs::read_dir(&dir).map_err(|err| format!("failed to read dir {}, err: {}", dir, err))? {
+        let path = e.map_err(|err| format!("failed to read dir {}, err: {}", dir, err))?.path();
+        if path.to_string_lossy().ends_with(".seg") {
+            let f = std::fs::OpenOptions::new().write(true).open(&path).map_err(|err| format!("failed to open {:?}, err: {}", path, err))?;
+            f.set_len(2 * chunk as u64).map_err(|err| format!("failed to truncate {:?}, err: {}", path, err))?;
+        }
+    }
+    let data = leader.read(1, 1, 2 * chunk as u64, 2 * chunk).map_err(|err| format!("failed to read ahead data, err: {:?}", err))?;
+    if data != want[2 * chunk as usize..].to_vec() {
+        return Err(format!("read wrong data ahead"));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_compact() -> Result<(), String> {
+    let LocalLeader{cfg, server, mut mgr, leader, _exec} = open_local_leader("compact", |cfg| cfg.segment_configs[0].size = 32,
+    Box::new(NoBackend{}))?;
+    // the first segment is full, and only 8 bytes of it are live after the overwrite.
+    let mut want = vec![b'a'; 32];
+    leader.write(1, 1, 0, &want).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 0, &[b'b'; 24]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 40, b"cccc").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    want[..24].copy_from_slice(&[b'b'; 24]);
+    want.extend_from_slice(&[0; 8]);
+    want.extend_from_slice(b"cccc");
+    if segment_files_size(&cfg)? != 60 {
+        return Err(format!("got {} bytes in segments before compaction", segment_files_size(&cfg)?));
+    }
+    let ret = mgr.compact(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to compact, err: {:?}", ret));
+    }
+    // the first segment is removed, and its live data is moved into a new one.
+    if segment_files_size(&cfg)? != 36 {
+        return Err(format!("got {} bytes in segments after compaction", segment_files_size(&cfg)?));
+    }
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    let sizes: Vec<i64> = segs.iter().map(|s| s.blocks.iter().map(|b| b.size).sum()).collect();
+    if sizes != vec![28, 8] {
+        return Err(format!("got segments with sizes: {:?} after compaction", sizes));
+    }
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    // nothing to compact any more.
+    let ret = mgr.compact(1, 1);
+    if !ret.is_success() || segment_files_size(&cfg)? != 36 {
+        return Err(format!("compact again with ret: {:?}", ret));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_gc_closed() -> Result<(), String> {
+    let LocalLeader{cfg, server, mut mgr, leader, _exec} = open_local_leader("gc_closed", |cfg| cfg.segment_configs[0].size = 32,
+    Box::new(NoBackend{}))?;
+    let mut want = vec![b'a'; 32];
+    leader.write(1, 1, 0, &want).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 0, &[b'b'; 24]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 40, b"cccc").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    want[..24].copy_from_slice(&[b'b'; 24]);
+    want.extend_from_slice(&[0; 8]);
+    want.extend_from_slice(b"cccc");
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    // the closed file is compacted as well.
+    mgr.gc();
+    if segment_files_size(&cfg)? != 36 {
+        return Err(format!("got {} bytes in segments after gc", segment_files_size(&cfg)?));
+    }
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    let sizes: Vec<i64> = segs.iter().map(|s| s.blocks.iter().map(|b| b.size).sum()).collect();
+    if sizes != vec![28, 8] {
+        return Err(format!("got segments with sizes: {:?} after gc", sizes));
+    }
+    // the gc neither leaves a handle of the file nor keeps its lease.
+    if leader.read(1, 1, 0, 100).is_ok() {
+        return Err(format!("read the closed file without open after gc"));
+    }
+    let other = MockMetaServiceMgr::new("c", server.clone());
+    let lease = other.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    if lease.leader != "c" {
+        return Err(format!("the lease is still held after gc: {:?}", lease));
+    }
+    let ret = other.release_file_lease(1, 1, lease.epoch);
+    if !ret.is_success() {
+        return Err(format!("failed to release lease, err: {:?}", ret));
+    }
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to reopen, err: {:?}", ret));
+    }
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_read_sealed_fallback() -> Result<(), String> {
+    let LocalLeader{cfg, server, mut mgr, leader, _exec} = open_local_leader("sealed_fallback", |_| {},
+    Box::new(SealedBackend{data: b"HELLO WORLD".to_vec()}))?;
+    leader.write(1, 1, 0, b"hello world").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    // the segment is sealed, and then lost locally.
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let lease = meta.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let mut segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    for s in segs.iter_mut() {
+        s.backend_size = s.blocks.iter().map(|b| b.seg_end_addr).max().unwrap_or(0);
+    }
+    let ret = meta.update_file_segments(1, 1, lease.epoch, &segs);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to reopen, err: {:?}", ret));
+    }
+    let dir = cfg.segment_configs[0].dir.clone();
+    for e in std::fs::read_dir(&dir).map_err(|err| format!("failed to read dir {}, err: {}", dir, err))? {
+        let path = e.map_err(|err| format!("failed to read dir {}, err: {}", dir, err))?.path();
+        if path.to_string_lossy().ends_with(".seg") {
+            let f = std::fs::OpenOptions::new().write(true).open(&path).map_err(|err| format!("failed to open {:?}, err: {}", path, err))?;
+            f.set_len(0).map_err(|err| format!("failed to truncate {:?}, err: {}", path, err))?;
+        }
+    }
+    let data = leader.read(1, 1, 0, 11).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"HELLO WORLD".to_vec() {
+        return Err(format!("got data: {:?}", String::from_utf8_lossy(&data)));
+    }
+    let status = mgr.dir_status();
+    if status.len() != 1 || status[0].dir != dir {
+        return Err(format!("got dir status: {:?}", status));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_read_corrupt() -> Result<(), String> {
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("read_corrupt", |_| {}, Box::new(NoBackend{}))?;
+    leader.write(1, 1, 0, b"hello world").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    // the records of the segment rebuild the block of the file.
+    let dir = cfg.segment_configs[0].dir.clone();
+    let mut path = String::new();
+    for e in std::fs::read_dir(&dir).map_err(|err| format!("failed to read dir {}, err: {}", dir, err))? {
+        let p = e.map_err(|err| format!("failed to read dir {}, err: {}", dir, err))?.path();
+        if p.to_string_lossy().ends_with(".seg") {
+            path = p.display().to_string();
+        }
+    }
+    let scan = SegmentFile::scan(&path, true)?;
+    let blocks = scan.blocks();
+    if blocks.len() != 1 || blocks[0].ino != 1 || blocks[0].offset != 0 || blocks[0].seg_end_addr - blocks[0].seg_start_addr != 11 {
+        return Err(format!("got blocks {:?} from {}", blocks, path));
+    }
+    // flip a byte of the data on disk.
+    let mut content = std::fs::read(&path).map_err(|err| format!("failed to read {}, err: {}", path, err))?;
+    let pos = (scan.records[0].pos + RECORD_HEADER_SIZE) as usize;
+    content[pos] ^= 0xff;
+    std::fs::write(&path, content).map_err(|err| format!("failed to write {}, err: {}", path, err))?;
+    if SegmentFile::scan(&path, true)?.corrupt.len() != 1 {
+        return Err(format!("the corrupted record is not found in {}", path));
+    }
+    match leader.read(1, 1, 0, 11) {
+        Ok(data) => {
+            return Err(format!("read the corrupted data: {:?}", String::from_utf8_lossy(&data)));
+        }
+        Err(err) => {
+            if !err.is_ecorrupt() {
+                return Err(format!("got err {:?} for the corrupted data", err));
+            }
+        }
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_read_lost() -> Result<(), String> {
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("read_lost", |_| {}, Box::new(NoBackend{}))?;
+    leader.write(1, 1, 0, b"hello world").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    // the tail is cut by crash, and the segment is extended to its blocks by the recovery.
+    let dir = cfg.segment_configs[0].dir.clone();
+    let mut path = String::new();
+    for e in std::fs::read_dir(&dir).map_err(|err| format!("failed to read dir {}, err: {}", dir, err))? {
+        let p = e.map_err(|err| format!("failed to read dir {}, err: {}", dir, err))?.path();
+        if p.to_string_lossy().ends_with(".seg") {
+            path = p.display().to_string();
+        }
+    }
+    let header = SegmentFile::scan(&path, false)?.header.ok_or(format!("no header in {}", path))?;
+    SegmentFile::resize(&path, header.id0, header.id1, header.max_size, 5)?;
+    SegmentFile::resize(&path, header.id0, header.id1, header.max_size, 11)?;
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to reopen, err: {:?}", ret));
+    }
+    let data = leader.read(1, 1, 0, 5).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"hello".to_vec() {
+        return Err(format!("got data: {:?}", String::from_utf8_lossy(&data)));
+    }
+    // the lost data is never read as zero.
+    if let Ok(data) = leader.read(1, 1, 0, 11) {
+        return Err(format!("read the lost data: {:?}", data));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+// AppendBackend keeps the uploaded segments in memory, the writes fail until fails drops to 0.
+struct AppendBackend {
+    segs: Arc<Mutex<HashMap<(u64, u64), Vec<u8>>>>,
+    fails: Arc<Mutex<u32>>,
+}
+
+impl BackendStore for AppendBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn write(&self, id0: u64, id1: u64, offset: u64, data: &[u8]) -> Errno {
+        let mut fails = self.fails.lock().unwrap();
+        if *fails > 0 {
+            *fails -= 1;
+            return Errno::Eintr;
+        }
+        let mut segs = self.segs.lock().unwrap();
+        let seg = segs.entry((id0, id1)).or_insert_with(Vec::new);
+        if seg.len() as u64 != offset {
+            return Errno::Eintr;
+        }
+        seg.extend_from_slice(data);
+        Errno::Esucc
+    }
+    fn read(&self, id0: u64, id1: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        match self.segs.lock().unwrap().get(&(id0, id1)) {
+            Some(d) => {
+                let start = std::cmp::min(offset as usize, d.len());
+                let end = std::cmp::min(start + size as usize, d.len());
+                Ok(d[start..end].to_vec())
+            }
+            None => Err(Errno::Enoent),
+        }
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn delete(&self, id0: u64, id1: u64) -> Errno {
+        self.segs.lock().unwrap().remove(&(id0, id1));
+        Errno::Esucc
+    }
+}
+
+#[test]
+fn test_leader_local_upload() -> Result<(), String> {
+    let segs = Arc::new(Mutex::new(HashMap::<(u64, u64), Vec<u8>>::new()));
+    let LocalLeader{server, mut mgr, leader, _exec, ..} = open_local_leader("upload", |cfg| {
+        cfg.segment_configs[0].size = 16;
+        cfg.leader_config.seal_idle_secs = 1;
+        cfg.leader_config.upload_chunk_size = 8;
+        cfg.leader_config.upload_retries = 3;
+        cfg.leader_config.upload_retry_ms = 10;
+    }, Box::new(AppendBackend{segs: segs.clone(), fails: Arc::new(Mutex::new(2))}))?;
+    // the 2 full segments are sealed at once, and the last one after it is idle.
+    let data: Vec<u8> = (0..40).map(|i| i as u8).collect();
+    leader.write(1, 1, 0, &data).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let start = Instant::now();
+    while mgr.upload_status().iter().any(|u| u.state != UploadState::Uploaded) || mgr.upload_status().len() != 3 {
+        if start.elapsed() > Duration::from_secs(10) {
+            return Err(format!("got upload status: {:?}", mgr.upload_status()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let status = mgr.upload_status();
+    let mut uploaded = Vec::<u8>::new();
+    let mut ids = Vec::<(u64, u64)>::new();
+    for u in &status {
+        ids.push((u.id0, u.id1));
+        if u.uploaded != u.size {
+            return Err(format!("got upload {:?}", u));
+        }
+    }
+    // the segments are appended in the order of the file data.
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let meta_segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    for s in &meta_segs {
+        uploaded.extend_from_slice(segs.lock().unwrap().get(&(s.seg_id0, s.seg_id1)).map_or(&[][..], |d| &d[..]));
+    }
+    if uploaded != data {
+        return Err(format!("got uploaded data: {:?}", uploaded));
+    }
+    // the backend size is recorded in meta server while the file is open.
+    let sizes: Vec<u64> = meta_segs.iter().map(|s| s.backend_size).collect();
+    if sizes != vec![16, 16, 8] {
+        return Err(format!("got backend sizes: {:?}", sizes));
+    }
+    // the sealed segment takes no more data.
+    leader.write(1, 1, 40, b"tail").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    let meta_segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    if meta_segs.len() != 4 || ids.contains(&(meta_segs[3].seg_id0, meta_segs[3].seg_id1)) || meta_segs[3].backend_size != 0 {
+        return Err(format!("got segments: {:?} after the write to the sealed segment", meta_segs));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_compact_uploaded() -> Result<(), String> {
+    let segs = Arc::new(Mutex::new(HashMap::<(u64, u64), Vec<u8>>::new()));
+    let LocalLeader{server, mut mgr, leader, _exec, ..} = open_local_leader("compact_uploaded", |cfg| {
+        cfg.segment_configs[0].size = 16;
+        cfg.leader_config.seal_idle_secs = 1;
+    }, Box::new(AppendBackend{segs: segs.clone(), fails: Arc::new(Mutex::new(0))}))?;
+    let mut want: Vec<u8> = (0..40).map(|i| i as u8).collect();
+    leader.write(1, 1, 0, &want).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let start = Instant::now();
+    while mgr.upload_status().iter().any(|u| u.state != UploadState::Uploaded) || mgr.upload_status().len() != 3 {
+        if start.elapsed() > Duration::from_secs(10) {
+            return Err(format!("got upload status: {:?}", mgr.upload_status()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let meta_segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    let mut ids: Vec<(u64, u64)> = meta_segs.iter().map(|s| (s.seg_id0, s.seg_id1)).collect();
+    let victim = ids.remove(0);
+    ids.sort();
+    // only 4 bytes of the first segment are live after the overwrite.
+    leader.write(1, 1, 0, &[b'a'; 12]).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    want[..12].copy_from_slice(&[b'a'; 12]);
+    let ret = mgr.compact(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to compact, err: {:?}", ret));
+    }
+    // the object of the compacted segment is deleted from the backend store with its file.
+    let mut left: Vec<(u64, u64)> = segs.lock().unwrap().keys().cloned().collect();
+    left.sort();
+    if left != ids {
+        return Err(format!("got objects: {:?} in backend store after compacting {:?}", left, victim));
+    }
+    let data = leader.read(1, 1, 0, 100).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != want {
+        return Err(format!("read got: {:?}, want: {:?}", data, want));
+    }
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+// SlowBackend serves the sealed data slowly, and counts the reads.
+struct SlowBackend {
+    data: Vec<u8>,
+    reads: Arc<Mutex<u32>>,
+}
+
+impl BackendStore for SlowBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn write(&self, _id0: u64, _id1: u64, _offset: u64, _data: &[u8]) -> Errno {
+        Errno::Enotsupp
+    }
+    fn read(&self, _id0: u64, _id1: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        *self.reads.lock().unwrap() += 1;
+        thread::sleep(Duration::from_millis(200));
+        let start = std::cmp::min(offset as usize, self.data.len());
+        let end = std::cmp::min(start + size as usize, self.data.len());
+        Ok(self.data[start..end].to_vec())
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Esucc
+    }
+    fn delete(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+}
+
+#[test]
+fn test_leader_local_fetch() -> Result<(), String> {
+    let reads = Arc::new(Mutex::new(0));
+    let LocalLeader{cfg, server, mut mgr, leader, _exec} = open_local_leader("fetch", |_| {},
+    Box::new(SlowBackend{data: b"hello worldagain".to_vec(), reads: reads.clone()}))?;
+    leader.write(1, 1, 0, b"hello world").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    leader.write(1, 1, 100, b"again").map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    // the segment is sealed, and then its local file is evicted.
+    let meta = MockMetaServiceMgr::new("b", server.clone());
+    let lease = meta.acquire_file_lease(1, 1, 30).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    let mut segs = meta.get_file_segments(1, 1, None, None).map_err(|err| format!("failed to get segments, err: {:?}", err))?;
+    for s in segs.iter_mut() {
+        s.backend_size = 16;
+    }
+    let ret = meta.update_file_segments(1, 1, lease.epoch, &segs);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments, err: {:?}", ret));
+    }
+    let path = format!("{}/{}.{}.seg", cfg.segment_configs[0].dir, segs[0].seg_id0, segs[0].seg_id1);
+    std::fs::remove_file(&path).map_err(|err| format!("failed to remove {}, err: {}", path, err))?;
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to reopen, err: {:?}", ret));
+    }
+    // the concurrent reads fetch the segment once.
+    let mut readers = Vec::new();
+    for _ in 0..4 {
+        let l = leader.clone();
+        readers.push(thread::spawn(move || l.read(1, 1, 0, 11)));
+    }
+    for r in readers {
+        let data = r.join().map_err(|_| String::from("failed to join the reader"))?
+        .map_err(|err| format!("failed to read, err: {:?}", err))?;
+        if data != b"hello world".to_vec() {
+            return Err(format!("got data: {:?}", String::from_utf8_lossy(&data)));
+        }
+    }
+    let stats = mgr.fetch_stats();
+    if *reads.lock().unwrap() != 1 || stats.fetched != 1 || stats.bytes != 16 {
+        return Err(format!("got {} backend reads with stats: {:?}", reads.lock().unwrap(), stats));
+    }
+    // the fetched file is in the segment format, and serves the reads locally.
+    let data = leader.read(1, 1, 100, 5).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if data != b"again".to_vec() || *reads.lock().unwrap() != 1 {
+        return Err(format!("got data: {:?}", String::from_utf8_lossy(&data)));
+    }
+    let want = vec![
+        ScannedBlock{ino: 1, offset: 0, seg_start_addr: 0, seg_end_addr: 11},
+        ScannedBlock{ino: 1, offset: 100, seg_start_addr: 11, seg_end_addr: 16},
+    ];
+    let scan = SegmentFile::scan(&path, true)?;
+    if scan.blocks() != want || !scan.corrupt.is_empty() {
+        return Err(format!("got scan {:?} of the fetched file", scan));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_leader_local_evict() -> Result<(), String> {
+    let segs = Arc::new(Mutex::new(HashMap::<(u64, u64), Vec<u8>>::new()));
+    let LocalLeader{cfg, mut mgr, leader, _exec, ..} = open_local_leader("evict", |cfg| {
+        cfg.segment_configs[0].size = 16;
+        cfg.leader_config.seal_idle_secs = 0;
+        cfg.leader_config.evict_interval_secs = 0;
+        cfg.leader_config.evict_capacity = 200;
+        cfg.leader_config.evict_high_watermark = 0.5;
+        cfg.leader_config.evict_low_watermark = 0.1;
+    }, Box::new(AppendBackend{segs: segs.clone(), fails: Arc::new(Mutex::new(0))}))?;
+    // the 2 full segments are uploaded, and the last one is still written.
+    let data: Vec<u8> = (0..40).map(|i| i as u8).collect();
+    leader.write(1, 1, 0, &data).map_err(|err| format!("failed to write, err: {:?}", err))?;
+    let start = Instant::now();
+    while mgr.upload_status().iter().filter(|u| u.state == UploadState::Uploaded).count() != 2 {
+        if start.elapsed() > Duration::from_secs(10) {
+            return Err(format!("got upload status: {:?}", mgr.upload_status()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let open: Vec<(u64, u64)> = mgr.upload_status().iter().filter(|u| u.state == UploadState::Open).map(|u| (u.id0, u.id1)).collect();
+    if open.len() != 1 {
+        return Err(format!("got upload status: {:?}", mgr.upload_status()));
+    }
+    let report = mgr.evict();
+    if report.evicted.len() != 2 || report.evicted.contains(&open[0]) {
+        return Err(format!("got evict report: {:?}", report));
+    }
+    let dir = &cfg.segment_configs[0].dir;
+    for (id0, id1) in &report.evicted {
+        let path = format!("{}/{}.{}.seg", dir, id0, id1);
+        if std::path::Path::new(&path).exists() {
+            return Err(format!("{} is not evicted", path));
+        }
+    }
+    let path = format!("{}/{}.{}.seg", dir, open[0].0, open[0].1);
+    if !std::path::Path::new(&path).exists() {
+        return Err(format!("the unsealed segment {} is evicted", path));
+    }
+    // the evicted segments are fetched back by the reads after the file is reopened.
+    let ret = leader.close(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to close, err: {:?}", ret));
+    }
+    let ret = leader.open(1, 1);
+    if !ret.is_success() {
+        return Err(format!("failed to reopen, err: {:?}", ret));
+    }
+    let got = leader.read(1, 1, 0, 40).map_err(|err| format!("failed to read, err: {:?}", err))?;
+    if got != data || mgr.fetch_stats().fetched != 2 {
+        return Err(format!("read got: {:?} with fetch stats: {:?}", got, mgr.fetch_stats()));
+    }
+    mgr.stop();
+    Ok(())
+}
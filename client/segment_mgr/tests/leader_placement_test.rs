@@ -0,0 +1,136 @@
+mod mock_meta;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use common::config::LeaderConfig;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{MachineInfo, MACHINE_UP};
+use segment_mgr::leader_placement::{LeaderPlacement, PlacementPolicy};
+use mock_meta::{MockMetaServer, MockMetaServiceMgr};
+
+fn new_machine(zone: &str, machine: &str, status: i32, weight: u32, load: u64) -> MachineInfo {
+    MachineInfo{
+        zone: String::from(zone),
+        machine: String::from(machine),
+        status: status,
+        weight: weight,
+        load: load,
+    }
+}
+
+fn new_placement(policy: &str, machines: Vec<MachineInfo>) -> LeaderPlacement {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    server.lock().unwrap().machines = machines;
+    let cfg = LeaderConfig{
+        placement_policy: String::from(policy),
+        ..Default::default()
+    };
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("a", server));
+    LeaderPlacement::new(&String::from("a"), &cfg, meta)
+}
+
+fn count_leaders(p: &LeaderPlacement, n: u64) -> HashMap<String, u64> {
+    let mut counts = HashMap::<String, u64>::new();
+    for i in 0..n {
+        let leader = p.choose(1, &format!("file_{}", i));
+        *counts.entry(leader).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn machines() -> Vec<MachineInfo> {
+    vec![
+        new_machine("zone1", "a", MACHINE_UP, 1, 0),
+        new_machine("zone1", "b", MACHINE_UP, 3, 0),
+        new_machine("zone1", "c", 0, 10, 0),
+        new_machine("zone2", "d", MACHINE_UP, 10, 0),
+    ]
+}
+
+#[test]
+fn test_placement_local() -> Result<(), String> {
+    let p = new_placement("local", machines());
+    let counts = count_leaders(&p, 10);
+    if counts.len() != 1 || counts.get("a") != Some(&10) {
+        return Err(format!("got leaders: {:?} by local policy", counts));
+    }
+    let p = new_placement("unknown", machines());
+    if p.policy() != PlacementPolicy::Local {
+        return Err(format!("got policy: {:?} for unknown policy", p.policy()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_zone() -> Result<(), String> {
+    let p = new_placement("zone", machines());
+    let counts = count_leaders(&p, 1000);
+    // the down machine and the machines in other zones are never chosen.
+    if counts.contains_key("c") || counts.contains_key("d") {
+        return Err(format!("got leaders: {:?} out of zone1", counts));
+    }
+    let (a, b) = (*counts.get("a").unwrap_or(&0), *counts.get("b").unwrap_or(&0));
+    if a < 150 || b < 600 {
+        return Err(format!("got leaders: {:?} not by weight 1:3", counts));
+    }
+    // the same file is placed on the same machine.
+    if p.choose(1, &String::from("f")) != p.choose(1, &String::from("f")) {
+        return Err(format!("the same file is placed on different machines"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_weight() -> Result<(), String> {
+    let p = new_placement("weight", machines());
+    let counts = count_leaders(&p, 1400);
+    if counts.contains_key("c") {
+        return Err(format!("got leaders: {:?} on the down machine", counts));
+    }
+    let d = *counts.get("d").unwrap_or(&0);
+    if d < 800 {
+        return Err(format!("got leaders: {:?} not by weight 1:3:10", counts));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_load() -> Result<(), String> {
+    let p = new_placement("load", vec![
+        new_machine("zone1", "a", MACHINE_UP, 1, 10),
+        new_machine("zone1", "b", MACHINE_UP, 1, 4),
+        new_machine("zone2", "d", MACHINE_UP, 1, 0),
+    ]);
+    // b is chosen until it is as loaded as a, then they take turns.
+    let counts = count_leaders(&p, 10);
+    if counts.get("a") != Some(&2) || counts.get("b") != Some(&8) {
+        return Err(format!("got leaders: {:?} by load", counts));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_no_machines() -> Result<(), String> {
+    let p = new_placement("zone", Vec::new());
+    let leader = p.choose(1, &String::from("f"));
+    if leader != "a" {
+        return Err(format!("got leader: {} without machines", leader));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_not_in_zone() -> Result<(), String> {
+    // a isn't registered, the files are never placed in the zones of others.
+    for policy in vec!["zone", "load"] {
+        let p = new_placement(policy, vec![
+            new_machine("zone1", "b", MACHINE_UP, 1, 0),
+            new_machine("zone2", "d", MACHINE_UP, 1, 0),
+        ]);
+        let counts = count_leaders(&p, 10);
+        if counts.len() != 1 || counts.get("a") != Some(&10) {
+            return Err(format!("got leaders: {:?} by {} policy out of zones", counts, policy));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+mod mock_meta;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use common::config::LeaderConfig;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use segment_mgr::lease_mgr::LeaseMgr;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr};
+
+fn new_lease_mgr(machine: &str, server: &Arc<Mutex<MockMetaServer>>, lease_secs: u64, renew_interval_secs: u64) -> LeaseMgr {
+    let cfg = LeaderConfig{
+        lease_secs: lease_secs,
+        renew_interval_secs: renew_interval_secs,
+        ..Default::default()
+    };
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new(machine, server.clone()));
+    LeaseMgr::new(&String::from(machine), &cfg, meta)
+}
+
+#[test]
+fn test_lease_acquire_release() -> Result<(), String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let mgr_a = new_lease_mgr("a", &server, 30, 10);
+    let mgr_b = new_lease_mgr("b", &server, 30, 10);
+    let (lease_a, _) = mgr_a.acquire(1, 1).map_err(|err| format!("a failed to acquire, err: {:?}", err))?;
+    if lease_a.leader != "a" || !mgr_a.is_valid(1, lease_a.epoch) {
+        return Err(format!("a got invalid lease: {:?}", lease_a));
+    }
+    // a holds the lease already, it is not granted again.
+    let (lease, granted) = mgr_a.acquire(1, 1).map_err(|err| format!("a failed to acquire again, err: {:?}", err))?;
+    if granted || lease.epoch != lease_a.epoch {
+        return Err(format!("a got lease: {:?} granted: {} again", lease, granted));
+    }
+    // b can't take over the file before the lease of a expires.
+    let (lease_b, _) = mgr_b.acquire(1, 1).map_err(|err| format!("b failed to acquire, err: {:?}", err))?;
+    if lease_b.leader != "a" || mgr_b.is_valid(1, lease_b.epoch) {
+        return Err(format!("b got lease: {:?} held by a", lease_b));
+    }
+    let ret = mgr_a.release(1);
+    if !ret.is_success() {
+        return Err(format!("a failed to release lease, err: {:?}", ret));
+    }
+    let (lease_b, _) = mgr_b.acquire(1, 1).map_err(|err| format!("b failed to acquire, err: {:?}", err))?;
+    if lease_b.leader != "b" || lease_b.epoch <= lease_a.epoch {
+        return Err(format!("b got lease: {:?} after a released lease: {:?}", lease_b, lease_a));
+    }
+    mgr_a.stop();
+    mgr_b.stop();
+    Ok(())
+}
+
+#[test]
+fn test_lease_renew() -> Result<(), String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let mgr = new_lease_mgr("a", &server, 2, 1);
+    let (lease, _) = mgr.acquire(1, 1).map_err(|err| format!("failed to acquire, err: {:?}", err))?;
+    thread::sleep(Duration::from_millis(3500));
+    if !mgr.is_valid(1, lease.epoch) {
+        return Err(format!("lease: {:?} is not renewed", lease));
+    }
+    mgr.stop();
+    Ok(())
+}
+
+#[test]
+fn test_lease_expire_takeover() -> Result<(), String> {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let mgr_a = new_lease_mgr("a", &server, 1, 1);
+    let mgr_b = new_lease_mgr("b", &server, 30, 10);
+    let meta_a = MockMetaServiceMgr::new("a", server.clone());
+    let (lease_a, _) = mgr_a.acquire(1, 1).map_err(|err| format!("a failed to acquire, err: {:?}", err))?;
+    // a is partitioned from the meta server and can't renew its lease.
+    server.lock().unwrap().down = true;
+    thread::sleep(Duration::from_millis(1500));
+    if mgr_a.is_valid(1, lease_a.epoch) {
+        return Err(format!("lease: {:?} is still valid after expired", lease_a));
+    }
+    server.lock().unwrap().down = false;
+    let (lease_b, _) = mgr_b.acquire(1, 1).map_err(|err| format!("b failed to acquire, err: {:?}", err))?;
+    if lease_b.leader != "b" || lease_b.epoch <= lease_a.epoch {
+        return Err(format!("b failed to take over the expired lease: {:?}, got: {:?}", lease_a, lease_b));
+    }
+    // the late writes of the stale leader are rejected.
+    let ret = meta_a.update_file_segments(1, 1, lease_a.epoch, &Vec::new());
+    if !ret.is_estale() {
+        return Err(format!("the stale leader updated segments with ret: {:?}", ret));
+    }
+    mgr_a.stop();
+    mgr_b.stop();
+    Ok(())
+}
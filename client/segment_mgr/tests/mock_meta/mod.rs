@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use common::config::Config;
+use common::error::Errno;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{DirEntry, DirEntryPlus, FileAttr, FileLeader, FileLease, MachineInfo, NewFileInfo, Segment, SegmentBlock, SegmentInfo, SetFileAttr};
+
+struct LeaseEntry {
+    leader: String,
+    generation: u64,
+    epoch: u64,
+    expire: Instant,
+}
+
+// MockMetaServer keeps the leases and segments in memory, it can be shared by the
+// MockMetaServiceMgr of several machines.
+#[derive(Default)]
+pub struct MockMetaServer {
+    leases: HashMap<u64, LeaseEntry>,
+    segments: HashMap<u64, Vec<Segment>>,
+    epoch: u64,
+    // the meta server is unreachable if true.
+    pub down: bool,
+    pub machines: Vec<MachineInfo>,
+}
+
+pub struct MockMetaServiceMgr {
+    machine: String,
+    pub server: Arc<Mutex<MockMetaServer>>,
+}
+
+impl MockMetaServiceMgr {
+    pub fn new(machine: &str, server: Arc<Mutex<MockMetaServer>>) -> Self {
+        MockMetaServiceMgr{
+            machine: String::from(machine),
+            server: server,
+        }
+    }
+
+    fn check_epoch(&self, server: &MockMetaServer, ino: u64, lease_epoch: u64) -> Errno {
+        if server.down {
+            return Errno::Eintr;
+        }
+        if let Some(l) = server.leases.get(&ino) {
+            if l.epoch != lease_epoch || l.leader != self.machine || l.expire <= Instant::now() {
+                return Errno::Estale;
+            }
+        }
+        Errno::Esucc
+    }
+
+    fn to_lease(ino: u64, l: &LeaseEntry) -> FileLease {
+        let now = Instant::now();
+        let mut ttl: i64 = 0;
+        if l.expire > now {
+            ttl = (l.expire - now).as_secs() as i64 + 1;
+        }
+        FileLease{
+            ino: ino,
+            generation: l.generation,
+            leader: l.leader.clone(),
+            epoch: l.epoch,
+            ttl: ttl,
+        }
+    }
+}
+
+impl MetaServiceMgr for MockMetaServiceMgr {
+    fn mount(&self, _uid: u32, _gid: u32) -> Result<(), Errno> {
+        Ok(())
+    }
+    fn read_dir(&self, _ino: u64, _offset: i64) -> Result<(Vec<DirEntry>, i64), Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn read_dir_plus(&self, _ino: u64, _offset: i64) -> Result<(Vec<DirEntryPlus>, i64), Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn read_dir_file_attr(&self, _ino: u64, _name: &String) -> Result<FileAttr, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn read_file_attr(&self, _ino: u64) -> Result<FileAttr, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn read_file_attrs(&self, _inos: &Vec<u64>) -> Result<Vec<FileAttr>, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn set_file_attr(&self, _attr: &SetFileAttr) -> Result<FileAttr, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn new_ino_leader(&self, _parent: u64, _name: &String, _uid: u32, _gid: u32, _perm: u32, _leader: &String) -> Result<NewFileInfo, Errno> {
+        Err(Errno::Enotsupp)
+    }
+    fn get_file_leader(&self, ino: u64) -> Result<FileLeader, Errno> {
+        let server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        let mut leader = FileLeader{
+            zone: String::new(),
+            leader: self.machine.clone(),
+            ino: ino,
+            lease_epoch: 0,
+            lease_ttl: 0,
+        };
+        if let Some(l) = server.leases.get(&ino) {
+            let lease = MockMetaServiceMgr::to_lease(ino, l);
+            leader.leader = lease.leader;
+            leader.lease_epoch = lease.epoch;
+            leader.lease_ttl = lease.ttl;
+        }
+        Ok(leader)
+    }
+    fn get_file_segments(&self, ino: u64, _generation: u64, _offset: Option<u64>, _size: Option<i64>) -> Result<Vec<Segment>, Errno> {
+        let server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        if let Some(s) = server.segments.get(&ino) {
+            return Ok(s.clone());
+        }
+        Ok(Vec::new())
+    }
+    fn get_segment_info(&self, seg_id0: u64, seg_id1: u64) -> Result<SegmentInfo, Errno> {
+        let server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        let mut info: Option<SegmentInfo> = None;
+        for (ino, segs) in &server.segments {
+            for s in segs.iter().filter(|s| s.seg_id0 == seg_id0 && s.seg_id1 == seg_id1) {
+                let i = info.get_or_insert(SegmentInfo{
+                    seg_id0: s.seg_id0,
+                    seg_id1: s.seg_id1,
+                    max_size: s.max_size,
+                    leader: s.leader.clone(),
+                    backend_size: s.backend_size,
+                    blocks: Vec::new(),
+                });
+                for b in &s.blocks {
+                    i.blocks.push(SegmentBlock{
+                        ino: *ino,
+                        generation: 0,
+                        offset: b.offset,
+                        seg_start_addr: b.seg_start_addr,
+                        seg_end_addr: b.seg_end_addr,
+                        size: b.size,
+                    });
+                }
+            }
+        }
+        info.ok_or(Errno::Enoent)
+    }
+    fn get_machine_id(&self) -> String {
+        self.machine.clone()
+    }
+    fn get_zone_machines(&self) -> Result<Vec<MachineInfo>, Errno> {
+        let server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        Ok(server.machines.clone())
+    }
+    fn add_file_block(&self, ino: u64, _generation: u64, lease_epoch: u64, _seg: &Segment) -> Errno {
+        let server = self.server.lock().unwrap();
+        self.check_epoch(&server, ino, lease_epoch)
+    }
+    fn update_file_segments(&self, ino: u64, _generation: u64, lease_epoch: u64, segs: &Vec<Segment>) -> Errno {
+        let mut server = self.server.lock().unwrap();
+        let ret = self.check_epoch(&server, ino, lease_epoch);
+        if !ret.is_success() {
+            return ret;
+        }
+        server.segments.insert(ino, segs.clone());
+        Errno::Esucc
+    }
+    fn acquire_file_lease(&self, ino: u64, generation: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        let mut server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        if let Some(l) = server.leases.get(&ino) {
+            if l.expire > Instant::now() && (l.leader != self.machine || l.generation == generation) {
+                return Ok(MockMetaServiceMgr::to_lease(ino, l));
+            }
+        }
+        server.epoch += 1;
+        let l = LeaseEntry{
+            leader: self.machine.clone(),
+            generation: generation,
+            epoch: server.epoch,
+            expire: Instant::now() + Duration::from_secs(lease_secs),
+        };
+        let lease = MockMetaServiceMgr::to_lease(ino, &l);
+        server.leases.insert(ino, l);
+        Ok(lease)
+    }
+    fn renew_file_lease(&self, ino: u64, _generation: u64, lease_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        let mut server = self.server.lock().unwrap();
+        let ret = self.check_epoch(&server, ino, lease_epoch);
+        if !ret.is_success() {
+            return Err(ret);
+        }
+        if let Some(l) = server.leases.get_mut(&ino) {
+            l.expire = Instant::now() + Duration::from_secs(lease_secs);
+            return Ok(MockMetaServiceMgr::to_lease(ino, l));
+        }
+        Err(Errno::Estale)
+    }
+    fn transfer_file_leader(&self, ino: u64, generation: u64, from_leader: &String, from_epoch: u64, lease_secs: u64) -> Result<FileLease, Errno> {
+        let mut server = self.server.lock().unwrap();
+        if server.down {
+            return Err(Errno::Eintr);
+        }
+        if let Some(l) = server.leases.get(&ino) {
+            if l.leader != *from_leader || l.epoch != from_epoch {
+                // the leadership has been changed by others.
+                return Err(Errno::Estale);
+            }
+            // the leader owns the file until its lease expires, the holder is returned.
+            if l.expire > Instant::now() {
+                return Ok(MockMetaServiceMgr::to_lease(ino, l));
+            }
+        }
+        server.epoch += 1;
+        let l = LeaseEntry{
+            leader: self.machine.clone(),
+            generation: generation,
+            epoch: server.epoch,
+            expire: Instant::now() + Duration::from_secs(lease_secs),
+        };
+        let lease = MockMetaServiceMgr::to_lease(ino, &l);
+        server.leases.insert(ino, l);
+        Ok(lease)
+    }
+    fn release_file_lease(&self, ino: u64, _generation: u64, lease_epoch: u64) -> Errno {
+        let mut server = self.server.lock().unwrap();
+        let ret = self.check_epoch(&server, ino, lease_epoch);
+        if !ret.is_success() {
+            return ret;
+        }
+        server.leases.remove(&ino);
+        Errno::Esucc
+    }
+}
+
+// the dir of the test name under the temp dir, the files left by the last run are removed.
+#[allow(dead_code)]
+pub fn new_test_dir(name: &str) -> Result<String, String> {
+    let dir = std::env::temp_dir().join(format!("yigfs_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).map_err(|err| format!("failed to create {:?}, err: {}", dir, err))?;
+    Ok(dir.display().to_string())
+}
+
+// write the config of machine "b" into base/yigfs.toml and parse it. segments are the (dir, size, num)
+// of the segment dirs, extra is appended as the other sections, such as [leader_config].
+#[allow(dead_code)]
+pub fn new_test_config(base: &String, segments: &[(String, u64, u32)], extra: &str) -> Result<Config, String> {
+    let mut segs = String::new();
+    for (dir, size, num) in segments {
+        segs.push_str(&format!("[[segment_configs]]\ndir = \"{}\"\nsize = {}\nnum = {}\n", dir, size, num));
+    }
+    let content = format!(r#"
+[mount_config]
+mnt = "/mnt/yigfs"
+[s3_config]
+region = "cn-bj-1"
+server = "s3.test.com"
+bucket = "mybucket"
+ak = "hahahaha"
+sk = "hahahaha"
+[metaserver_config]
+meta_server = "http://127.0.0.1:9088"
+[zone_config]
+zone = "zone1"
+machine = "b"
+{}
+[disk_cache_config]
+thread_num = 1
+[backend_store_config]
+backend_type = 1
+[backend_store_config.settings]
+{}"#, segs, extra);
+    let file = format!("{}/yigfs.toml", base);
+    std::fs::write(&file, content).map_err(|err| format!("failed to write {}, err: {}", file, err))?;
+    common::parse_config(file).map_err(|err| format!("failed to parse config, err: {}", err))
+}
@@ -0,0 +1,76 @@
+use segment_mgr::readahead::{ReadPattern, ReadCache};
+
+#[test]
+fn test_read_pattern() -> Result<(), String> {
+    let mut p = ReadPattern::new();
+    // (offset, size, range to read ahead)
+    let cases: Vec<(u64, u32, Option<(u64, u64)>)> = vec![
+        (0, 10, Some((10, 110))),
+        (10, 10, None),
+        // 50 bytes are read ahead of it, which is not less than half of the window.
+        (20, 40, None),
+        (60, 10, Some((110, 170))),
+        (70, 10, None),
+        // the random read resets the pattern.
+        (200, 10, None),
+        (210, 10, Some((220, 320))),
+        (500, 10, None),
+    ];
+    for (offset, size, want) in cases {
+        let got = p.on_read(offset, size, 100);
+        if got != want {
+            return Err(format!("read [{}, {}) got readahead: {:?}, want: {:?}", offset, offset + size as u64, got, want));
+        }
+    }
+    if ReadPattern::new().on_read(0, 10, 0).is_some() {
+        return Err(format!("read ahead with window 0"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_read_cache_get() -> Result<(), String> {
+    let mut c = ReadCache::new(100);
+    c.insert(1, 1, 0, b"0123456789".to_vec());
+    c.insert(1, 1, 10, b"abcdefghij".to_vec());
+    c.insert(1, 2, 20, b"ABCDEFGHIJ".to_vec());
+    // (id1, seg_addr, size, want)
+    let cases: Vec<(u64, u64, u32, Option<Vec<u8>>)> = vec![
+        (1, 0, 10, Some(b"0123456789".to_vec())),
+        (1, 5, 10, Some(b"56789abcde".to_vec())),
+        (1, 15, 10, None),
+        (1, 20, 1, None),
+        (2, 18, 4, None),
+        (2, 25, 5, Some(b"FGHIJ".to_vec())),
+    ];
+    for (id1, addr, size, want) in cases {
+        let got = c.get(1, id1, addr, size);
+        if got != want {
+            return Err(format!("get (1, {}) [{}, {}) got: {:?}, want: {:?}", id1, addr, addr + size as u64, got, want));
+        }
+    }
+    if !c.contains(1, 1, 12, 8) || c.contains(1, 1, 5, 10) {
+        return Err(format!("contains is wrong"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_read_cache_evict() -> Result<(), String> {
+    let mut c = ReadCache::new(30);
+    c.insert(1, 1, 0, vec![0; 10]);
+    c.insert(1, 1, 10, vec![1; 10]);
+    c.insert(1, 1, 20, vec![2; 10]);
+    // the first one is used recently, so the second one is evicted.
+    c.get(1, 1, 0, 10).ok_or(format!("the first data is not cached"))?;
+    c.insert(1, 1, 30, vec![3; 10]);
+    if c.size() != 30 || c.contains(1, 1, 10, 10) || !c.contains(1, 1, 0, 10) || !c.contains(1, 1, 30, 10) {
+        return Err(format!("evicted the wrong data, size: {}", c.size()));
+    }
+    // the data larger than the cache is not cached.
+    c.insert(1, 1, 40, vec![4; 31]);
+    if c.contains(1, 1, 40, 1) || c.size() != 30 {
+        return Err(format!("cached the data larger than the cache"));
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+mod mock_meta;
+
+use std::sync::{Arc, Mutex};
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{Block, Segment};
+use segment_mgr::recovery::{LostBlock, Recovery, SegmentTail, QUARANTINE_DIR};
+use segment_mgr::segment_mgr::SegmentMgr;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr, new_test_config, new_test_dir};
+
+// blocks are (file offset, segment addr, size).
+fn new_segment(id: u64, backend_size: u64, blocks: &[(u64, u64, i64)]) -> Segment {
+    let mut s = Segment{
+        seg_id0: id,
+        seg_id1: id,
+        max_size: 1024,
+        leader: String::from("b"),
+        blocks: Vec::new(),
+        backend_size: backend_size,
+    };
+    for (offset, seg_addr, size) in blocks {
+        s.blocks.push(Block{
+            offset: *offset,
+            seg_start_addr: *seg_addr,
+            seg_end_addr: *seg_addr + *size as u64,
+            size: *size,
+        });
+    }
+    s
+}
+
+fn file_size(path: &String) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+#[test]
+fn test_recovery_parse_file_name() -> Result<(), String> {
+    let cases = vec![
+        ("1.2.seg", Some((1, 2))),
+        ("18446744073709551615.0.seg", Some((u64::MAX, 0))),
+        ("1.2.3.seg", None),
+        ("1.seg", None),
+        ("a.2.seg", None),
+        ("1.2.seg.tmp", None),
+        ("yigfs.toml", None),
+    ];
+    for (name, want) in cases {
+        let got = Recovery::parse_file_name(name);
+        if got != want {
+            return Err(format!("parse {}: got {:?}, want {:?}", name, got, want));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_recovery_reconcile() -> Result<(), String> {
+    let dir = new_test_dir("recovery")?;
+    let cfg = new_test_config(&dir, &[(dir.clone(), 1024, 0)], "")?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta = Arc::new(MockMetaServiceMgr::new("b", server.clone()));
+    // segment 1 has data after its last block, segment 2 lost its tail, segment 3 is an orphan.
+    let ret = meta.update_file_segments(1, 1, 0, &vec![new_segment(1, 0, &[(0, 0, 20)]), new_segment(4, 0, &[(20, 0, 10)])]);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments of ino 1, err: {:?}", ret));
+    }
+    let ret = meta.update_file_segments(2, 1, 0, &vec![new_segment(2, 0, &[(0, 0, 10), (100, 10, 20)])]);
+    if !ret.is_success() {
+        return Err(format!("failed to update segments of ino 2, err: {:?}", ret));
+    }
+    let files = vec![(1, 30), (2, 15), (3, 8), (4, 10)];
+    for (id, size) in &files {
+        let path = format!("{}/{}.{}.seg", dir, id, id);
+        std::fs::write(&path, vec![1u8; *size]).map_err(|err| format!("failed to write {}, err: {}", path, err))?;
+    }
+    let seg_mgr = SegmentMgr::create(&cfg, meta.clone());
+
+    // only report the orphans first.
+    let report = seg_mgr.recover(false).map_err(|err| format!("failed to recover, err: {:?}", err))?;
+    let orphan = format!("{}/3.3.seg", dir);
+    if report.scanned != 4 || report.orphans != vec![orphan.clone()] || !report.quarantined.is_empty() {
+        return Err(format!("got report: {:?}", report));
+    }
+    if report.trimmed != vec![SegmentTail{id0: 1, id1: 1, file_size: 30, end: 20}] {
+        return Err(format!("got trimmed: {:?}", report.trimmed));
+    }
+    if report.truncated != vec![SegmentTail{id0: 2, id1: 2, file_size: 15, end: 30}] {
+        return Err(format!("got truncated: {:?}", report.truncated));
+    }
+    let want_lost = vec![LostBlock{id0: 2, id1: 2, ino: 2, generation: 0, offset: 105, size: 15, in_backend: false}];
+    if report.lost != want_lost {
+        return Err(format!("got lost: {:?}, want: {:?}", report.lost, want_lost));
+    }
+    for (id, want) in &[(1, 20), (2, 30), (3, 8), (4, 10)] {
+        let size = file_size(&format!("{}/{}.{}.seg", dir, id, id));
+        if size != *want {
+            return Err(format!("segment {} has size {} after recovery, want {}", id, size, want));
+        }
+    }
+
+    // the repaired segments are clean now, and the orphan is quarantined.
+    let report = seg_mgr.recover(true).map_err(|err| format!("failed to recover, err: {:?}", err))?;
+    let quarantined = format!("{}/{}/3.3.seg", dir, QUARANTINE_DIR);
+    if !report.trimmed.is_empty() || !report.truncated.is_empty() || report.quarantined != vec![quarantined.clone()] {
+        return Err(format!("got report: {:?}", report));
+    }
+    if std::path::Path::new(&orphan).exists() || file_size(&quarantined) != 8 {
+        return Err(format!("orphan {} is not moved to {}", orphan, quarantined));
+    }
+    let report = seg_mgr.recover(true).map_err(|err| format!("failed to recover, err: {:?}", err))?;
+    if report.scanned != 3 || !report.is_clean() {
+        return Err(format!("got report: {:?}", report));
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
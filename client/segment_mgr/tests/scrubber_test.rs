@@ -0,0 +1,141 @@
+mod mock_meta;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use common::config::ScrubConfig;
+use common::error::Errno;
+use io_engine::backend_storage::BackendStore;
+use io_engine::segment_format::{HEADER_SIZE, RECORD_HEADER_SIZE, SegmentFile, SegmentHeader};
+use metaservice_mgr::mgr::MetaServiceMgr;
+use metaservice_mgr::types::{Block, Segment};
+use segment_mgr::scrubber::{ScrubBlock, Scrubber};
+use segment_mgr::segment_mgr::SegmentMgr;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr, new_test_config, new_test_dir};
+
+// MapBackend keeps the sealed segments in memory.
+struct MapBackend {
+    segs: HashMap<(u64, u64), Vec<u8>>,
+}
+
+impl BackendStore for MapBackend {
+    fn open(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+    fn write(&self, _id0: u64, _id1: u64, _offset: u64, _data: &[u8]) -> Errno {
+        Errno::Enotsupp
+    }
+    fn read(&self, id0: u64, id1: u64, offset: u64, size: u32) -> Result<Vec<u8>, Errno> {
+        match self.segs.get(&(id0, id1)) {
+            Some(d) => {
+                let start = std::cmp::min(offset as usize, d.len());
+                let end = std::cmp::min(start + size as usize, d.len());
+                Ok(d[start..end].to_vec())
+            }
+            None => Err(Errno::Enoent),
+        }
+    }
+    fn close(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+    fn delete(&self, _id0: u64, _id1: u64) -> Errno {
+        Errno::Enotsupp
+    }
+}
+
+fn new_segment(id: u64, size: u64, backend_size: u64) -> Segment {
+    Segment{
+        seg_id0: id,
+        seg_id1: id,
+        max_size: 1024,
+        leader: String::from("b"),
+        blocks: vec![Block{
+            offset: 0,
+            seg_start_addr: 0,
+            seg_end_addr: size,
+            size: size as i64,
+        }],
+        backend_size: backend_size,
+    }
+}
+
+// write the data of ino at file offset 0 into the segment file, a byte of it is flipped if corrupt.
+fn write_segment(dir: &String, id: u64, ino: u64, data: &[u8], corrupt: bool) -> Result<(), String> {
+    let mut content = SegmentHeader::new(id, id, 1024).encode();
+    let (buf, _) = SegmentFile::encode_records(ino, 0, 0, data, HEADER_SIZE);
+    content.extend_from_slice(&buf);
+    if corrupt {
+        content[(HEADER_SIZE + RECORD_HEADER_SIZE) as usize] ^= 0xff;
+    }
+    let path = format!("{}/{}.{}.seg", dir, id, id);
+    std::fs::write(&path, content).map_err(|err| format!("failed to write {}, err: {}", path, err))
+}
+
+fn new_block(id: u64, size: u64) -> ScrubBlock {
+    ScrubBlock{
+        id0: id,
+        id1: id,
+        ino: id,
+        offset: 0,
+        size: size,
+    }
+}
+
+#[test]
+fn test_scrubber_repair() -> Result<(), String> {
+    let dir = new_test_dir("scrubber")?;
+    let cfg = new_test_config(&dir, &[(dir.clone(), 1024, 0)], "")?;
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta = Arc::new(MockMetaServiceMgr::new("b", server.clone()));
+    // segment 1 is sealed and corrupted, segment 2 is corrupted before it is sealed, the backend
+    // copy of segment 3 is bad, and segment 4 is in the legacy format.
+    for (id, backend_size) in &[(1, 11), (2, 0), (3, 11), (4, 11)] {
+        let ret = meta.update_file_segments(*id, 1, 0, &vec![new_segment(*id, 11, *backend_size)]);
+        if !ret.is_success() {
+            return Err(format!("failed to update segments of ino {}, err: {:?}", id, ret));
+        }
+    }
+    write_segment(&dir, 1, 1, b"hello world", true)?;
+    write_segment(&dir, 2, 2, b"hello world", true)?;
+    write_segment(&dir, 3, 3, b"hello world", false)?;
+    let legacy = format!("{}/4.4.seg", dir);
+    std::fs::write(&legacy, b"hello world").map_err(|err| format!("failed to write {}, err: {}", legacy, err))?;
+    let mut segs = HashMap::<(u64, u64), Vec<u8>>::new();
+    segs.insert((1, 1), b"hello world".to_vec());
+    segs.insert((3, 3), b"HELLO WORLD".to_vec());
+    segs.insert((4, 4), b"HELLO WORLD".to_vec());
+    let seg_mgr = Arc::new(SegmentMgr::create(&cfg, meta.clone()));
+    let scrub_cfg = ScrubConfig{
+        interval_secs: 0,
+        bytes_per_sec: 0,
+    };
+    let scrubber = Scrubber::new(&scrub_cfg, seg_mgr.clone(), meta.clone(), Arc::new(MapBackend{segs: segs.clone()}));
+    let report = scrubber.scrub();
+    if report.scanned != 4 || report.interrupted {
+        return Err(format!("got report: {:?}", report));
+    }
+    if report.repaired != vec![new_block(1, 11)] || report.unrecoverable != vec![new_block(2, 11)] {
+        return Err(format!("got repaired: {:?}, unrecoverable: {:?}", report.repaired, report.unrecoverable));
+    }
+    if report.mismatched != vec![new_block(3, 11), new_block(4, 11)] {
+        return Err(format!("got mismatched: {:?}", report.mismatched));
+    }
+    let path = format!("{}/1.1.seg", dir);
+    if !SegmentFile::scan(&path, true)?.corrupt.is_empty() {
+        return Err(format!("{} is not repaired", path));
+    }
+
+    // the scrub is throttled by the rate.
+    let scrub_cfg = ScrubConfig{
+        interval_secs: 0,
+        bytes_per_sec: report.bytes * 2,
+    };
+    let scrubber = Scrubber::new(&scrub_cfg, seg_mgr, meta, Arc::new(MapBackend{segs: segs}));
+    let start = Instant::now();
+    let report = scrubber.scrub();
+    if start.elapsed() < Duration::from_millis(400) || report.repaired.len() != 0 {
+        return Err(format!("got report: {:?} in {:?}", report, start.elapsed()));
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
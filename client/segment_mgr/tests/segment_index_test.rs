@@ -0,0 +1,97 @@
+mod mock_meta;
+
+use std::sync::{Arc, Mutex};
+use common::config::Config;
+use metaservice_mgr::mgr::MetaServiceMgr;
+use segment_mgr::segment_index::INDEX_FILE;
+use segment_mgr::segment_mgr::SegmentMgr;
+use mock_meta::{MockMetaServer, MockMetaServiceMgr, new_test_config, new_test_dir};
+
+// the segment dirs of size 1024 under base.
+fn new_config(base: &String, dirs: &[&str]) -> Result<Config, String> {
+    let segs: Vec<(String, u64, u32)> = dirs.iter().map(|d| (format!("{}/{}", base, d), 1024, 0)).collect();
+    new_test_config(base, &segs, "")
+}
+
+fn new_segment_mgr(cfg: &Config) -> SegmentMgr {
+    let server = Arc::new(Mutex::new(MockMetaServer::default()));
+    let meta: Arc<dyn MetaServiceMgr> = Arc::new(MockMetaServiceMgr::new("b", server));
+    SegmentMgr::create(cfg, meta)
+}
+
+#[test]
+fn test_segment_index_keeps_dirs() -> Result<(), String> {
+    let base = new_test_dir("segment_index")?;
+    let cfg = new_config(&base, &["d0"])?;
+    let mgr = new_segment_mgr(&cfg);
+    let d0 = format!("{}/d0", base);
+    let d1 = format!("{}/d1", base);
+    let mut ids = Vec::<(u64, u64)>::new();
+    for i in 0..20 {
+        let seg = mgr.new_segment(&String::from("b"));
+        let dir = mgr.get_segment_dir(seg.seg_id0, seg.seg_id1);
+        if dir != d0 {
+            return Err(format!("segment {} is placed in {}, want {}", i, dir, d0));
+        }
+        let path = format!("{}/{}.{}.seg", dir, seg.seg_id0, seg.seg_id1);
+        std::fs::write(&path, vec![i as u8; 10]).map_err(|err| format!("failed to write {}, err: {}", path, err))?;
+        ids.push((seg.seg_id0, seg.seg_id1));
+    }
+    // a segment file created before the index.
+    let legacy = (u64::MAX, 7);
+    std::fs::write(format!("{}/{}.{}.seg", d0, legacy.0, legacy.1), vec![0u8; 10]).map_err(|err| format!("failed to write, err: {}", err))?;
+    ids.push(legacy);
+    drop(mgr);
+
+    // the segments stay in d0 after d1 is added.
+    let cfg = new_config(&base, &["d0", "d1"])?;
+    let mgr = new_segment_mgr(&cfg);
+    for (id0, id1) in &ids {
+        let dir = mgr.get_segment_dir(*id0, *id1);
+        if dir != d0 {
+            return Err(format!("seg(id0: {}, id1: {}) is in {} after d1 is added, want {}", id0, id1, dir, d0));
+        }
+    }
+
+    // the rebalance moves some of them into d1.
+    let report = mgr.rebalance().map_err(|err| format!("failed to rebalance, err: {:?}", err))?;
+    if report.moved.is_empty() || report.moved.len() == ids.len() || report.missing != 0 {
+        return Err(format!("got rebalance report: {:?}", report));
+    }
+    for m in &report.moved {
+        if m.from != format!("{}/{}.{}.seg", d0, m.id0, m.id1) || m.to != format!("{}/{}.{}.seg", d1, m.id0, m.id1) {
+            return Err(format!("got move: {:?}", m));
+        }
+        if std::path::Path::new(&m.from).exists() || std::fs::metadata(&m.to).map(|m| m.len()).unwrap_or(0) != 10 {
+            return Err(format!("segment is not moved from {} to {}", m.from, m.to));
+        }
+    }
+    drop(mgr);
+
+    // the new dirs are kept after reload, and the removed segment is forgotten.
+    let mgr = new_segment_mgr(&cfg);
+    for (id0, id1) in &ids {
+        let dir = mgr.get_segment_dir(*id0, *id1);
+        let want = match report.moved.iter().any(|m| m.id0 == *id0 && m.id1 == *id1) {
+            true => &d1,
+            false => &d0,
+        };
+        if dir != *want {
+            return Err(format!("seg(id0: {}, id1: {}) is in {} after reload, want {}", id0, id1, dir, want));
+        }
+    }
+    let report = mgr.rebalance().map_err(|err| format!("failed to rebalance, err: {:?}", err))?;
+    if !report.moved.is_empty() {
+        return Err(format!("got moves after rebalance: {:?}", report.moved));
+    }
+    let (id0, id1) = ids[0];
+    let dir = mgr.get_segment_dir(id0, id1);
+    std::fs::remove_file(format!("{}/{}.{}.seg", dir, id0, id1)).map_err(|err| format!("failed to remove, err: {}", err))?;
+    mgr.remove_segment(id0, id1);
+    let content = std::fs::read_to_string(format!("{}/{}", dir, INDEX_FILE)).map_err(|err| format!("failed to read index, err: {}", err))?;
+    if !content.ends_with(&format!("- {} {}\n", id0, id1)) {
+        return Err(format!("got index {:?} after the removal of seg(id0: {}, id1: {})", content, id0, id1));
+    }
+    let _ = std::fs::remove_dir_all(&base);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use segment_mgr::write_buffer::WriteBuffer;
+
+#[test]
+fn test_write_buffer_next() -> Result<(), String> {
+    let mut b = WriteBuffer::new(1, 10, b"hello");
+    if !b.is_next(1, 15) || b.is_next(1, 14) || b.is_next(2, 15) {
+        return Err(format!("is_next is wrong for buffer [{}, {})", b.offset, b.end()));
+    }
+    b.data.extend_from_slice(b" world");
+    if b.end() != 21 {
+        return Err(format!("got end: {}, want 21", b.end()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_write_buffer_overlay() -> Result<(), String> {
+    let b = WriteBuffer::new(1, 4, b"abcd");
+    // (read offset, read size, data read from segments, want)
+    let cases: Vec<(u64, u32, Vec<u8>, Vec<u8>)> = vec![
+        (0, 10, b"0123456789".to_vec(), b"0123abcd89".to_vec()),
+        (0, 10, b"01".to_vec(), b"01\0\0abcd".to_vec()),
+        (5, 2, b"56".to_vec(), b"bc".to_vec()),
+        (6, 10, Vec::new(), b"cd".to_vec()),
+        (0, 4, b"0123".to_vec(), b"0123".to_vec()),
+        (8, 4, b"89".to_vec(), b"89".to_vec()),
+    ];
+    for (offset, size, data, want) in cases {
+        let mut got = data.clone();
+        b.overlay(offset, size, &mut got);
+        if got != want {
+            return Err(format!("overlay [{}, {}) on {:?} got: {:?}, want: {:?}",
+            offset, offset + size as u64, data, got, want));
+        }
+    }
+    Ok(())
+}
@@ -3,12 +3,17 @@
 use crate::yig_io_worker::YigIoWorkerFactory;
 use common::runtime::Executor;
 use common::error::Errno;
+use common::config::TlsConfig;
 use io_engine::backend_storage::{BackendStore, BackendStoreFactory};
-use io_engine::types::{MsgFileOp, MsgFileOpenOp};
+use io_engine::types::{MsgFileOp, MsgFileOpenOp, MsgFileReadOp, MsgFileReadData, MsgFileWriteOp, MsgFileWriteResp, MsgFileCloseOp,
+    MsgFileDelOp};
 use io_engine::io_thread_pool::IoThreadPool;
 use std::collections::HashMap;
 use crossbeam_channel::bounded;
 
+// the backend_type of yig in the backend_store_config.
+pub const BACKEND_TYPE_YIG: u32 = 1;
+
 pub struct YigBackend{
     bucket: String,
     yig_pool: IoThreadPool,
@@ -42,25 +47,125 @@ impl BackendStore for YigBackend{
             }
         }
     }
+    // append data to the object of the segment, offset must be the current size of the object.
     fn write(&self, id0: u64, id1: u64, offset: u64, data: &[u8])->Errno{
-        Errno::Enotsupp
+        let thr = self.yig_pool.get_thread(id0, id1);
+        let (tx, rx) = bounded::<MsgFileWriteResp>(1);
+        let msg = MsgFileWriteOp{
+            id0: id0,
+            id1: id1,
+            dir: self.bucket.clone(),
+            max_size: 0,
+            ino: 0,
+            offset: offset,
+            data: data.to_vec(),
+            resp_sender: tx,
+        };
+        let ret = thr.do_io(MsgFileOp::OpWrite(msg));
+        if !ret.is_success() {
+            println!("YigBackend::write: failed to send io write req for id0: {}, id1: {}, err: {:?}",
+            id0, id1, ret);
+            return ret;
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                if ret.err.is_success() && ret.offset != offset {
+                    println!("YigBackend::write: id0: {}, id1: {} is appended at {}, want {}",
+                    id0, id1, ret.offset, offset);
+                    return Errno::Eintr;
+                }
+                return ret.err;
+            }
+            Err(err) => {
+                println!("YigBackend::write: failed to got result for write id0: {}, id1: {}, err: {}",
+                id0, id1, err);
+                return Errno::Eintr;
+            }
+        }
     }
     fn read(&self, id0: u64, id1: u64, offset: u64, size: u32)->Result<Vec<u8>, Errno>{
-        Err(Errno::Enotsupp)
+        let thr = self.yig_pool.get_thread(id0, id1);
+        let (tx, rx) = bounded::<MsgFileReadData>(1);
+        let msg = MsgFileReadOp{
+            id0: id0,
+            id1: id1,
+            dir: self.bucket.clone(),
+            offset: offset,
+            size: size,
+            data_sender: tx,
+        };
+        let ret = thr.do_io(MsgFileOp::OpRead(msg));
+        if !ret.is_success() {
+            println!("YigBackend::read: failed to send io read req for id0: {}, id1: {}, err: {:?}",
+            id0, id1, ret);
+            return Err(ret);
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                if !ret.err.is_success() {
+                    return Err(ret.err);
+                }
+                return Ok(ret.data.unwrap_or_default());
+            }
+            Err(err) => {
+                println!("YigBackend::read: failed to got result for read id0: {}, id1: {}, err: {}",
+                id0, id1, err);
+                return Err(Errno::Eintr);
+            }
+        }
     }
     fn close(&self, id0: u64, id1: u64) -> Errno{
-        Errno::Enotsupp
+        let thr = self.yig_pool.get_thread(id0, id1);
+        let msg = MsgFileCloseOp{
+            id0: id0,
+            id1: id1,
+        };
+        let ret = thr.do_io(MsgFileOp::OpClose(msg));
+        if !ret.is_success() {
+            println!("YigBackend::close: failed to send io close req for id0: {}, id1: {}, err: {:?}",
+            id0, id1, ret);
+        }
+        return ret;
+    }
+    fn delete(&self, id0: u64, id1: u64) -> Errno {
+        let thr = self.yig_pool.get_thread(id0, id1);
+        let (tx, rx) = bounded::<Errno>(1);
+        let msg = MsgFileDelOp{
+            id0: id0,
+            id1: id1,
+            dir: self.bucket.clone(),
+            resp_sender: tx,
+        };
+        let ret = thr.do_io(MsgFileOp::OpDel(msg));
+        if !ret.is_success() {
+            println!("YigBackend::delete: failed to send io del req for id0: {}, id1: {}, err: {:?}",
+            id0, id1, ret);
+            return ret;
+        }
+        let ret = rx.recv();
+        match ret {
+            Ok(ret) => {
+                return ret;
+            }
+            Err(err) => {
+                println!("YigBackend::delete: failed to got result for del id0: {}, id1: {}, err: {}",
+                id0, id1, err);
+                return Errno::Eintr;
+            }
+        }
     }
 }
 
 impl YigBackend {
-    pub fn new(region: &String, endpoint: &String, ak: &String, sk: &String, bucket: &String, num: u32, exec: &Executor) -> Self {
+    pub fn new(region: &String, endpoint: &String, ak: &String, sk: &String, tls: &Option<TlsConfig>, bucket: &String, num: u32, exec: &Executor) -> Self {
         YigBackend{
             bucket: bucket.clone(),
             yig_pool: IoThreadPool::new(num, 
                 &format!("yig_io_thread_"), 
                 exec, 
-  &YigIoWorkerFactory::new(region, endpoint, ak, sk)),
+  &YigIoWorkerFactory::new(region, endpoint, ak, sk, tls)),
         }
     }
 }
@@ -69,6 +174,14 @@ pub struct YigBackendFactory{
     exec: Executor,
 }
 
+impl YigBackendFactory{
+    pub fn new(exec: &Executor) -> Box<dyn BackendStoreFactory> {
+        Box::new(YigBackendFactory{
+            exec: exec.clone(),
+        })
+    }
+}
+
 impl BackendStoreFactory for YigBackendFactory{
     fn new_backend_store(&self, cfg: &HashMap<String, String>) -> Result<Box<dyn BackendStore>, Errno>{
         let region: String;
@@ -77,6 +190,7 @@ impl BackendStoreFactory for YigBackendFactory{
         let sk: String;
         let bucket: String;
         let thread_num: u32;
+        let mut tls: Option<TlsConfig> = None;
 
         if let Some(r) = cfg.get("region"){
             region = r.clone();
@@ -120,6 +234,24 @@ impl BackendStoreFactory for YigBackendFactory{
             return Err(Errno::Eintr);
         }
 
-        Ok(Box::new(YigBackend::new(&region, &endpoint, &ak, &sk, &bucket, thread_num, &self.exec)))
+        // scheme is http by default, the tls_* settings are used for https.
+        if let Some(s) = cfg.get("scheme") {
+            if s == "https" {
+                match TlsConfig::from_settings(cfg) {
+                    Ok(ret) => {
+                        tls = Some(ret);
+                    }
+                    Err(err) => {
+                        println!("new_backend_store: {}", err);
+                        return Err(Errno::Eintr);
+                    }
+                }
+            } else if s != "http" {
+                println!("new_backend_store: invalid scheme: {}", s);
+                return Err(Errno::Eintr);
+            }
+        }
+
+        Ok(Box::new(YigBackend::new(&region, &endpoint, &ak, &sk, &tls, &bucket, thread_num, &self.exec)))
     }
 }
\ No newline at end of file
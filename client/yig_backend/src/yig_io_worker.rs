@@ -1,6 +1,7 @@
 use common::runtime::Executor;
 use common::error::Errno;
-use io_engine::types::{MsgFileOp, MsgFileOpenOp, MsgFileWriteResp};
+use common::config::TlsConfig;
+use io_engine::types::{MsgFileOp, MsgFileOpenOp, MsgFileReadData, MsgFileWriteResp};
 use io_engine::io_worker::{IoWorker, IoWorkerFactory};
 use s3::s3_client::S3Client;
 use crossbeam_channel::{Receiver, select};
@@ -12,6 +13,8 @@ pub struct S3Config{
     pub endpoint: String,
     pub ak: String,
     pub sk: String,
+    // talk to yig by https if tls is set.
+    pub tls: Option<TlsConfig>,
 }
 
 //initial proactor, trigger the async io to yig.
@@ -60,8 +63,20 @@ impl YigIoWorker{
             op_rx: op_rx,
             stop_rx: stop_rx,
             exec: Executor::create_single_threaded(),
-            s3_client: S3Client::new(s3_config.region.as_str(),
-            s3_config.endpoint.as_str(), s3_config.ak.as_str(), s3_config.sk.as_str()),
+            s3_client: YigIoWorker::new_s3_client(s3_config),
+        }
+    }
+
+    fn new_s3_client(s3_config: &S3Config) -> S3Client {
+        match &s3_config.tls {
+            Some(tls) => {
+                S3Client::new_with_tls(s3_config.region.as_str(), s3_config.endpoint.as_str(),
+                s3_config.ak.as_str(), s3_config.sk.as_str(), tls)
+            }
+            None => {
+                S3Client::new(s3_config.region.as_str(), s3_config.endpoint.as_str(),
+                s3_config.ak.as_str(), s3_config.sk.as_str())
+            }
         }
     }
 
@@ -93,7 +108,26 @@ impl YigIoWorker{
                     }
                 }
             }
-            MsgFileOp::OpRead(msg_read) => {}
+            MsgFileOp::OpRead(msg_read) => {
+                let obj = self.id_to_object_name(msg_read.id0, msg_read.id1);
+                let ret = self.read(&msg_read.dir, &obj, msg_read.offset, msg_read.size);
+                match ret {
+                    Ok(ret) => {
+                        msg_read.response(MsgFileReadData{
+                            data: Some(ret),
+                            err: Errno::Esucc,
+                        });
+                    }
+                    Err(err) => {
+                        println!("failed to read from yig for {}/{}, offset: {}, size: {}, err: {:?}",
+                        msg_read.dir, obj, msg_read.offset, msg_read.size, err);
+                        msg_read.response(MsgFileReadData{
+                            data: None,
+                            err: err,
+                        });
+                    }
+                }
+            }
             MsgFileOp::OpWrite(msg_write) => {
                 let obj = self.id_to_object_name(msg_write.id0, msg_write.id1);
                 let mut resp = MsgFileWriteResp{
@@ -126,6 +160,21 @@ impl YigIoWorker{
             MsgFileOp::OpClose(msg_close) => {
                 println!("close: id0: {}, id1: {}", msg_close.id0, msg_close.id1);
             }
+            MsgFileOp::OpDel(msg_del) => {
+                let obj = self.id_to_object_name(msg_del.id0, msg_del.id1);
+                let ret = self.delete(&msg_del.dir, &obj);
+                match ret {
+                    Ok(_) => {
+                        msg_del.response(Errno::Esucc);
+                    }
+                    Err(err) => {
+                        msg_del.response(err);
+                    }
+                }
+            }
+            MsgFileOp::OpReplace(msg_replace) => {
+                msg_replace.response(Errno::Enotsupp);
+            }
         }
     }
 
@@ -147,9 +196,38 @@ impl YigIoWorker{
         }
     }
 
+    fn read(&self, bucket: &String, object: &String, offset: u64, size: u32) -> Result<Vec<u8>, Errno>{
+        let ret = self.exec.get_runtime().
+        block_on(self.s3_client.get_object(bucket, object, &offset, &size));
+        match ret {
+            Ok(ret) => {
+                return Ok(ret);
+            }
+            Err(err) => {
+                println!("failed to get({}/{}, offset: {}, size: {}), err: {:?}",
+                bucket, object, offset, size, err);
+                return Err(err);
+            }
+        }
+    }
+
+    fn delete(&self, bucket: &String, object: &String) -> Result<(), Errno>{
+        let ret = self.exec.get_runtime().
+        block_on(self.s3_client.delete_object(bucket, object));
+        match ret {
+            Ok(_) => {
+                return Ok(());
+            }
+            Err(err) => {
+                println!("failed to delete {}/{}, err: {:?}", bucket, object, err);
+                return Err(err);
+            }
+        }
+    }
+
     fn write(&self, bucket: &String, object: &String, offset: u64, data: &[u8]) -> Result<u64, Errno>{
         let ret = self.exec.get_runtime().
-        block_on(self.s3_client.append_object(data, bucket, object, &offset));
+        block_on(self.s3_client.append_object(bucket, object, &offset, data));
         match ret {
             Ok(ret) => {
                 return Ok(ret.next_append_position);
@@ -174,13 +252,14 @@ impl IoWorkerFactory for YigIoWorkerFactory {
 }
 
 impl YigIoWorkerFactory{
-    pub fn new(region: &String, endpoint: &String, ak: &String, sk: &String)->Box<dyn IoWorkerFactory>{
+    pub fn new(region: &String, endpoint: &String, ak: &String, sk: &String, tls: &Option<TlsConfig>)->Box<dyn IoWorkerFactory>{
         Box::new(YigIoWorkerFactory{
             s3_cfg: S3Config{
                 region: region.clone(),
                 endpoint: endpoint.clone(),
                 ak: ak.clone(),
                 sk: sk.clone(),
+                tls: tls.clone(),
            },
         })
     }
@@ -1,6 +1,6 @@
 mod options;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use filesystem_mgr::{FilesystemMgr, MountOptions};
 use common::parse_config;
 use common::runtime::Executor;
@@ -10,6 +10,7 @@ use segment_mgr::leader_mgr::LeaderMgr;
 use metaservice_mgr::new_metaserver_mgr;
 use io_engine::backend_store_mgr::BackendStoreMgr;
 use io_engine::backend_storage::BackendStore;
+use yig_backend::backend::{BACKEND_TYPE_YIG, YigBackendFactory};
 
 fn main() {
     let opts = options::parse();
@@ -29,10 +30,37 @@ fn main() {
 
     let exec = Executor::create();
     let meta_service = new_metaserver_mgr(&cfg, &exec).unwrap();
-    let segment_mgr = Rc::new(SegmentMgr::create(&cfg, meta_service.clone()));
+    let segment_mgr = Arc::new(SegmentMgr::create(&cfg, meta_service.clone()));
+    // the segment files left by the crash must be reconciled before any file is opened.
+    let ret = segment_mgr.recover(cfg.recovery_config.quarantine);
+    match ret {
+        Ok(ret) => {
+            println!("recovered segments, scanned: {}, orphans: {:?}, quarantined: {:?}, trimmed: {:?}, truncated: {:?}, lost: {:?}",
+            ret.scanned, ret.orphans, ret.quarantined, ret.trimmed, ret.truncated, ret.lost);
+        }
+        Err(err) => {
+            if !err.is_enotsupp() {
+                println!("failed to recover segments, err: {:?}", err);
+                return;
+            }
+            println!("meta server doesn't support segment info, skip the recovery of segments.");
+        }
+    }
+    if opts.rebalance {
+        match segment_mgr.rebalance() {
+            Ok(ret) => {
+                println!("rebalanced segments, moved: {}, missing: {}", ret.moved.len(), ret.missing);
+            }
+            Err(err) => {
+                println!("failed to rebalance segments, err: {:?}", err);
+            }
+        }
+        return;
+    }
     // create backend store.
     // [TODO] we should create backend store as plugin. but currently, only hardcoded.
-    let backend_store_mgr = BackendStoreMgr::new();
+    let mut backend_store_mgr = BackendStoreMgr::new();
+    backend_store_mgr.register(BACKEND_TYPE_YIG, YigBackendFactory::new(&exec));
     let backend_store: Box<dyn BackendStore>;
     let ret = 
     backend_store_mgr.get_backend_store(cfg.backend_store_config.backend_type, &cfg.backend_store_config.settings);
@@ -45,8 +73,20 @@ fn main() {
             return;
         }
     }
-    let leader_mgr = LeaderMgr::new(&meta_service.get_machine_id(),
-    cfg.disk_cache_config.thread_num, &exec, segment_mgr.clone(), backend_store);
+    // the machines of one bucket share the sk, which authenticates the leader rpc by default.
+    let mut leader_cfg = cfg.leader_config.clone();
+    if leader_cfg.rpc_token.is_empty() {
+        leader_cfg.rpc_token = cfg.s3_config.sk.clone();
+    }
+    let mut leader_mgr = LeaderMgr::new(&meta_service.get_machine_id(), &leader_cfg,
+    cfg.disk_cache_config.thread_num, &exec, segment_mgr.clone(), meta_service.clone(), backend_store);
+    let ret = leader_mgr.start_server(&cfg.zone_config.bind_addr);
+    if !ret.is_success() {
+        println!("failed to start leader server on {}, err: {:?}", cfg.zone_config.bind_addr, ret);
+        leader_mgr.stop();
+        return;
+    }
+    leader_mgr.start_scrubber(&cfg.scrub_config);
     let mut filesystem = FilesystemMgr::create(meta_service.clone(), leader_mgr);
     let mount_options = MountOptions{
         mnt: cfg.mount_config.mnt.clone(),
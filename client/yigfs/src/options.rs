@@ -4,6 +4,8 @@ use clap::{App, Arg};
 pub struct Options{
     // region
     pub config_file_path: String,
+    // move the segments into the data dirs chosen for them and exit, without mounting.
+    pub rebalance: bool,
 }
 
 pub fn parse() -> Options {
@@ -14,12 +16,16 @@ pub fn parse() -> Options {
         .long("config")
         .required(true)
         .takes_value(true))
+    .arg(Arg::with_name("rebalance")
+        .help("move the segments into their data dirs after the data dirs are changed, and exit")
+        .long("rebalance"))
     .get_matches();
 
     let mut opts: Options = Default::default();
     if let Some(value) = matches.value_of("config"){
         opts.config_file_path = String::from(value);
     }
+    opts.rebalance = matches.is_present("rebalance");
     
     opts
 }
\ No newline at end of file
@@ -0,0 +1,286 @@
+package api
+
+import (
+	"bytes"
+	"crypto/hmac"
+	"crypto/sha256"
+	"encoding/hex"
+	"fmt"
+	"io/ioutil"
+	"net/http"
+	"net/url"
+	"sort"
+	"strings"
+	"sync"
+	"time"
+
+	"github.com/kataras/iris"
+	"github.com/hopkings2008/yigfs/server/types"
+	. "github.com/hopkings2008/yigfs/server/error"
+	"github.com/hopkings2008/yigfs/server/helper"
+)
+
+const (
+	AUTH_TYPE_NONE = ""
+	AUTH_TYPE_V4 = "v4"
+	AUTH_TYPE_TOKEN = "token"
+
+	AUTH_V4_ALGORITHM = "AWS4-HMAC-SHA256"
+	AUTH_V4_SERVICE = "yigfs"
+	AUTH_DATE_LAYOUT = "20060102T150405Z"
+	DEFAULT_AUTH_TIME_WINDOW = 300
+
+	HDR_AUTHORIZATION = "Authorization"
+	HDR_AMZ_DATE = "X-Amz-Date"
+	HDR_AMZ_CONTENT_SHA256 = "X-Amz-Content-Sha256"
+	HDR_YIGFS_DATE = "X-Yigfs-Date"
+	HDR_YIGFS_NONCE = "X-Yigfs-Nonce"
+	HDR_YIGFS_SIGNATURE = "X-Yigfs-Signature"
+)
+
+// nonces seen inside the time window, used to reject replayed requests.
+type nonceCache struct {
+	lock sync.Mutex
+	nonces map[string]time.Time
+	lastSweep time.Time
+}
+
+var authNonces = &nonceCache {
+	nonces: make(map[string]time.Time),
+}
+
+// the clock of the auth, replaced by the tests.
+var authNow = time.Now
+
+// remember the nonce until expire, return false if it is already used.
+func (c *nonceCache) checkAndAdd(nonce string, now time.Time, expire time.Time) bool {
+	c.lock.Lock()
+	defer c.lock.Unlock()
+
+	if now.Sub(c.lastSweep) > time.Minute {
+		for k, v := range c.nonces {
+			if v.Before(now) {
+				delete(c.nonces, k)
+			}
+		}
+		c.lastSweep = now
+	}
+
+	if v, ok := c.nonces[nonce]; ok && !v.Before(now) {
+		return false
+	}
+	c.nonces[nonce] = expire
+	return true
+}
+
+func authTimeWindow() time.Duration {
+	window := helper.CONFIG.MetaServiceConfig.AuthTimeWindow
+	if window <= 0 {
+		window = DEFAULT_AUTH_TIME_WINDOW
+	}
+	return time.Duration(window) * time.Second
+}
+
+func hmacSha256(key []byte, data string) []byte {
+	mac := hmac.New(sha256.New, key)
+	mac.Write([]byte(data))
+	return mac.Sum(nil)
+}
+
+func sha256Hex(data []byte) string {
+	sum := sha256.Sum256(data)
+	return hex.EncodeToString(sum[:])
+}
+
+// check the request date is inside the time window and the nonce is not replayed.
+func checkDateAndNonce(date string, nonce string) error {
+	if date == "" || nonce == "" {
+		return fmt.Errorf("missing date or nonce")
+	}
+	reqTime, err := time.Parse(AUTH_DATE_LAYOUT, date)
+	if err != nil {
+		return fmt.Errorf("invalid date %s, err: %v", date, err)
+	}
+	now := authNow().UTC()
+	window := authTimeWindow()
+	if reqTime.Before(now.Add(-window)) || reqTime.After(now.Add(window)) {
+		return fmt.Errorf("date %s is out of the time window", date)
+	}
+	if !authNonces.checkAndAdd(nonce, now, reqTime.Add(window)) {
+		return fmt.Errorf("nonce %s is replayed", nonce)
+	}
+	return nil
+}
+
+// token auth: hex(hmac_sha256(token, "METHOD\npath\ndate\nnonce\nhex(sha256(body))")).
+func verifyToken(r *http.Request, body []byte) error {
+	token := helper.CONFIG.MetaServiceConfig.AuthToken
+	signature := r.Header.Get(HDR_YIGFS_SIGNATURE)
+	date := r.Header.Get(HDR_YIGFS_DATE)
+	nonce := r.Header.Get(HDR_YIGFS_NONCE)
+	if signature == "" {
+		return fmt.Errorf("missing signature")
+	}
+
+	stringToSign := fmt.Sprintf("%s\n%s\n%s\n%s\n%s", r.Method, r.URL.EscapedPath(), date, nonce, sha256Hex(body))
+	expected := hex.EncodeToString(hmacSha256([]byte(token), stringToSign))
+	if !hmac.Equal([]byte(expected), []byte(signature)) {
+		return fmt.Errorf("signature does not match")
+	}
+
+	return checkDateAndNonce(date, nonce)
+}
+
+// parse "AWS4-HMAC-SHA256 Credential=ak/date/region/yigfs/aws4_request, SignedHeaders=a;b, Signature=xx".
+func parseV4Authorization(auth string) (credential string, signedHeaders string, signature string, err error) {
+	if !strings.HasPrefix(auth, AUTH_V4_ALGORITHM + " ") {
+		err = fmt.Errorf("unsupported authorization: %s", auth)
+		return
+	}
+	for _, field := range strings.Split(strings.TrimPrefix(auth, AUTH_V4_ALGORITHM + " "), ",") {
+		kv := strings.SplitN(strings.TrimSpace(field), "=", 2)
+		if len(kv) != 2 {
+			continue
+		}
+		switch kv[0] {
+		case "Credential":
+			credential = kv[1]
+		case "SignedHeaders":
+			signedHeaders = kv[1]
+		case "Signature":
+			signature = kv[1]
+		}
+	}
+	if credential == "" || signedHeaders == "" || signature == "" {
+		err = fmt.Errorf("invalid authorization: %s", auth)
+	}
+	return
+}
+
+func canonicalQuery(query url.Values) string {
+	keys := make([]string, 0, len(query))
+	for k := range query {
+		keys = append(keys, k)
+	}
+	sort.Strings(keys)
+	pairs := []string{}
+	for _, k := range keys {
+		values := query[k]
+		sort.Strings(values)
+		for _, v := range values {
+			pairs = append(pairs, url.QueryEscape(k) + "=" + url.QueryEscape(v))
+		}
+	}
+	return strings.Join(pairs, "&")
+}
+
+// v4 auth: the same signature the s3 client computes, with the service "yigfs". the clients sign
+// with the ak/sk of their bucket in s3_config, so auth_access_key and auth_secret_key must be
+// set to them.
+func verifyV4(r *http.Request, body []byte) error {
+	credential, signedHeaders, signature, err := parseV4Authorization(r.Header.Get(HDR_AUTHORIZATION))
+	if err != nil {
+		return err
+	}
+	// ak/date/region/service/aws4_request
+	scope := strings.Split(credential, "/")
+	if len(scope) != 5 || scope[3] != AUTH_V4_SERVICE || scope[4] != "aws4_request" {
+		return fmt.Errorf("invalid credential: %s", credential)
+	}
+	if scope[0] != helper.CONFIG.MetaServiceConfig.AuthAccessKey {
+		return fmt.Errorf("unknown access key: %s", scope[0])
+	}
+
+	payloadHash := sha256Hex(body)
+	if r.Header.Get(HDR_AMZ_CONTENT_SHA256) != payloadHash {
+		return fmt.Errorf("content sha256 does not match the body")
+	}
+
+	hasNonce := false
+	var canonicalHeaders strings.Builder
+	for _, name := range strings.Split(signedHeaders, ";") {
+		var value string
+		if name == "host" {
+			value = r.Host
+		} else {
+			values := r.Header[http.CanonicalHeaderKey(name)]
+			for i, v := range values {
+				values[i] = strings.Join(strings.Fields(v), " ")
+			}
+			value = strings.Join(values, ",")
+		}
+		if name == strings.ToLower(HDR_YIGFS_NONCE) {
+			hasNonce = true
+		}
+		canonicalHeaders.WriteString(name + ":" + value + "\n")
+	}
+	if !hasNonce {
+		return fmt.Errorf("nonce is not signed")
+	}
+
+	amzDate := r.Header.Get(HDR_AMZ_DATE)
+	canonicalRequest := strings.Join([]string{
+		r.Method,
+		r.URL.EscapedPath(),
+		canonicalQuery(r.URL.Query()),
+		canonicalHeaders.String(),
+		signedHeaders,
+		payloadHash,
+	}, "\n")
+	stringToSign := strings.Join([]string{
+		AUTH_V4_ALGORITHM,
+		amzDate,
+		strings.Join(scope[1:], "/"),
+		sha256Hex([]byte(canonicalRequest)),
+	}, "\n")
+
+	key := hmacSha256([]byte("AWS4" + helper.CONFIG.MetaServiceConfig.AuthSecretKey), scope[1])
+	key = hmacSha256(key, scope[2])
+	key = hmacSha256(key, scope[3])
+	key = hmacSha256(key, scope[4])
+	expected := hex.EncodeToString(hmacSha256(key, stringToSign))
+	if !hmac.Equal([]byte(expected), []byte(signature)) {
+		return fmt.Errorf("signature does not match")
+	}
+
+	return checkDateAndNonce(amzDate, r.Header.Get(HDR_YIGFS_NONCE))
+}
+
+// AuthHandler verifies the request signature before the api handlers run.
+func AuthHandler(ctx iris.Context) {
+	authType := helper.CONFIG.MetaServiceConfig.AuthType
+	if authType == AUTH_TYPE_NONE {
+		ctx.Next()
+		return
+	}
+
+	r := ctx.Request()
+	reqContext := r.Context()
+
+	body, err := ioutil.ReadAll(r.Body)
+	if err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read request body for auth, err: %v", err))
+		ctx.StatusCode(iris.StatusBadRequest)
+		ctx.JSON(&types.AuthResp{Result: GetErrInfo(ErrYigFsInvaildParams)})
+		return
+	}
+	// the handlers read the body again.
+	r.Body = ioutil.NopCloser(bytes.NewReader(body))
+
+	switch authType {
+	case AUTH_TYPE_TOKEN:
+		err = verifyToken(r, body)
+	case AUTH_TYPE_V4:
+		err = verifyV4(r, body)
+	default:
+		err = fmt.Errorf("unsupported auth_type: %s", authType)
+	}
+	if err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to auth %s %s, err: %v", r.Method, r.URL.Path, err))
+		ctx.StatusCode(iris.StatusForbidden)
+		ctx.JSON(&types.AuthResp{Result: GetErrInfo(ErrYigFsAccessDenied)})
+		return
+	}
+
+	ctx.Next()
+}
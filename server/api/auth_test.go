@@ -0,0 +1,133 @@
+package api
+
+import (
+	"bytes"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+
+	"github.com/hopkings2008/yigfs/server/helper"
+	"github.com/stretchr/testify/require"
+)
+
+// the requests signed by the client auth of metaservice_mgr for PUT /v1/file/attr with the body
+// below, with the bucket ak "hahahaha", sk "hehehehe" and the token "hahahaha".
+const (
+	testAuthUrl = "http://127.0.0.1:9088/v1/file/attr"
+	testAuthBody = `{"ino":1}`
+	testAuthDate = "20261019T043247Z"
+	testAuthV4 = "AWS4-HMAC-SHA256 Credential=hahahaha/20261019/cn-bj-1/yigfs/aws4_request, " +
+		"SignedHeaders=content-type;host;x-amz-content-sha256;x-amz-date;x-amz-decoded-content-length;x-yigfs-nonce, " +
+		"Signature=7dfa9aab2f26432b7a5e867b5a73f9295b8d8edec6970cbf4432277fa249b508"
+	testAuthV4Nonce = "c22b969552c24bb98d8ce09ba1846e85"
+	testAuthTokenNonce = "768cbdbf964842c099b2b78b05df5508"
+	testAuthTokenSignature = "e83131a0297662cfba6ecae82344504bb16a51093815bc0f711242b338ebbfd5"
+)
+
+// set the auth config and the clock at the signing date with skew, and forget the nonces.
+func setupAuth(t *testing.T, skew time.Duration) {
+	helper.CONFIG.MetaServiceConfig.AuthToken = "hahahaha"
+	helper.CONFIG.MetaServiceConfig.AuthAccessKey = "hahahaha"
+	helper.CONFIG.MetaServiceConfig.AuthSecretKey = "hehehehe"
+	helper.CONFIG.MetaServiceConfig.AuthTimeWindow = 300
+	date, err := time.Parse(AUTH_DATE_LAYOUT, testAuthDate)
+	require.Nil(t, err)
+	authNow = func() time.Time { return date.Add(skew) }
+	authNonces = &nonceCache {
+		nonces: make(map[string]time.Time),
+	}
+}
+
+func newV4Request() (*http.Request, []byte) {
+	body := []byte(testAuthBody)
+	r := httptest.NewRequest("PUT", testAuthUrl, bytes.NewReader(body))
+	r.Header.Set("Content-Type", "application/json")
+	r.Header.Set(HDR_AUTHORIZATION, testAuthV4)
+	r.Header.Set(HDR_AMZ_DATE, testAuthDate)
+	r.Header.Set(HDR_AMZ_CONTENT_SHA256, sha256Hex(body))
+	r.Header.Set("X-Amz-Decoded-Content-Length", "9")
+	r.Header.Set(HDR_YIGFS_NONCE, testAuthV4Nonce)
+	return r, body
+}
+
+func newTokenRequest() (*http.Request, []byte) {
+	body := []byte(testAuthBody)
+	r := httptest.NewRequest("PUT", testAuthUrl, bytes.NewReader(body))
+	r.Header.Set("Content-Type", "application/json")
+	r.Header.Set(HDR_YIGFS_DATE, testAuthDate)
+	r.Header.Set(HDR_YIGFS_NONCE, testAuthTokenNonce)
+	r.Header.Set(HDR_YIGFS_SIGNATURE, testAuthTokenSignature)
+	return r, body
+}
+
+func Test_AuthV4(t *testing.T) {
+	r := require.New(t)
+	setupAuth(t, time.Minute)
+
+	req, body := newV4Request()
+	r.Nil(verifyV4(req, body))
+	// the same request is a replay.
+	req, body = newV4Request()
+	r.NotNil(verifyV4(req, body))
+
+	// the body is changed.
+	setupAuth(t, time.Minute)
+	req, _ = newV4Request()
+	r.NotNil(verifyV4(req, []byte(`{"ino":2}`)))
+
+	// the server has other keys than the bucket of the client.
+	setupAuth(t, time.Minute)
+	helper.CONFIG.MetaServiceConfig.AuthSecretKey = "hohohoho"
+	req, body = newV4Request()
+	r.NotNil(verifyV4(req, body))
+}
+
+func Test_AuthToken(t *testing.T) {
+	r := require.New(t)
+	setupAuth(t, time.Minute)
+
+	req, body := newTokenRequest()
+	r.Nil(verifyToken(req, body))
+	// the same request is a replay.
+	req, body = newTokenRequest()
+	r.NotNil(verifyToken(req, body))
+
+	// the token is different.
+	setupAuth(t, time.Minute)
+	helper.CONFIG.MetaServiceConfig.AuthToken = "hohohoho"
+	req, body = newTokenRequest()
+	r.NotNil(verifyToken(req, body))
+}
+
+func Test_AuthClockSkew(t *testing.T) {
+	r := require.New(t)
+	for _, skew := range []time.Duration{-10 * time.Minute, 10 * time.Minute} {
+		setupAuth(t, skew)
+		req, body := newV4Request()
+		r.NotNil(verifyV4(req, body))
+		req, body = newTokenRequest()
+		r.NotNil(verifyToken(req, body))
+	}
+
+	// inside the time window on both sides.
+	for _, skew := range []time.Duration{-4 * time.Minute, 4 * time.Minute} {
+		setupAuth(t, skew)
+		req, body := newV4Request()
+		r.Nil(verifyV4(req, body))
+		req, body = newTokenRequest()
+		r.Nil(verifyToken(req, body))
+	}
+}
+
+func Test_AuthNonceReplay(t *testing.T) {
+	r := require.New(t)
+	setupAuth(t, 0)
+	now := authNow()
+	r.True(authNonces.checkAndAdd("a", now, now.Add(time.Minute)))
+	r.False(authNonces.checkAndAdd("a", now, now.Add(time.Minute)))
+	r.True(authNonces.checkAndAdd("b", now, now.Add(time.Minute)))
+	// the nonce is usable again after it expires.
+	later := now.Add(2 * time.Minute)
+	r.True(authNonces.checkAndAdd("a", later, later.Add(time.Minute)))
+}
@@ -43,6 +43,21 @@ func(yigFs MetaAPIHandlers) CreateSegmentHandler(ctx iris.Context) {
 	uuidStr := uuid.New()
 	segReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
 
+	// reject the write from the leader whose lease is taken over.
+	lease := &types.FileLeaseReq {
+		ZoneId: segReq.ZoneId,
+		Region: segReq.Region,
+		BucketName: segReq.BucketName,
+		Ino: segReq.Ino,
+		LeaseEpoch: segReq.LeaseEpoch,
+	}
+	err := yigFs.YigFsAPI.CheckFileLeaseEpoch(reqContext, lease)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
 	// check segment leader
 	isExisted, err := yigFs.YigFsAPI.CheckSegmentLeader(reqContext, segReq)
 	if err != nil {
@@ -108,12 +123,28 @@ func(yigFs MetaAPIHandlers) UpdateSegmentsHandler(ctx iris.Context) {
 	uuidStr := uuid.New()
 	segsReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
 
+	// reject the update from the leader whose lease is taken over.
+	lease := &types.FileLeaseReq {
+		ZoneId: segsReq.ZoneId,
+		Region: segsReq.Region,
+		BucketName: segsReq.BucketName,
+		Ino: segsReq.Ino,
+		LeaseEpoch: segsReq.LeaseEpoch,
+	}
+	err = yigFs.YigFsAPI.CheckFileLeaseEpoch(reqContext, lease)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
 	// update segments
 	for _, segment := range segsReq.Segments {
 		segmentInfo := types.CreateBlocksInfo {
 			SegmentId0: segment.SegmentId0,
 			SegmentId1: segment.SegmentId1,
 			MaxSize: segment.MaxSize,
+			BackendSize: segment.BackendSize,
 			Blocks: segment.Blocks,
 		}
 
@@ -140,6 +171,16 @@ func(yigFs MetaAPIHandlers) UpdateSegmentsHandler(ctx iris.Context) {
 			ctx.JSON(resp)
 			return
 		}
+
+		// the uploaded size is updated even if no blocks are changed.
+		if segment.BackendSize > 0 {
+			err = yigFs.YigFsAPI.UpdateSegmentBackendSize(reqContext, segReq)
+			if err != nil {
+				resp.Result = GetErrInfo(err)
+				ctx.JSON(resp)
+				return
+			}
+		}
 	}
 
 	//  update file size and blocks number.
@@ -255,3 +296,51 @@ func(yigFs MetaAPIHandlers) UpdateSegBlockInfoHandler(ctx iris.Context) {
 	ctx.JSON(resp)
 	return
 }
+
+func(yigFs MetaAPIHandlers) GetSegmentInfoHandler(ctx iris.Context) {
+	resp := &types.GetSegmentInfoResp {
+		Result: types.YigFsMetaError{},
+	}
+	defer GetSpendTime("GetSegmentInfoHandler")()
+
+	r := ctx.Request()
+	reqContext := r.Context()
+
+	// get req
+	segReq := &types.GetSegLeaderReq{}
+	if err := ctx.ReadJSON(&segReq); err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read GetSegmentInfoReq from body, err: %v", err))
+		resp.Result = GetErrInfo(ErrYigFsInvaildParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	// check request params
+	if segReq.BucketName == "" || segReq.ZoneId == "" {
+		helper.Logger.Error(reqContext, "Some GetSegmentInfo required parameters are missing.")
+		resp.Result = GetErrInfo(ErrYigFsMissingRequiredParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	if segReq.Region == "" {
+		segReq.Region = "cn-bj-1"
+	}
+
+	uuidStr := uuid.New()
+	segReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
+
+	// get the segment and its blocks from tidb
+	info, err := yigFs.YigFsAPI.GetSegmentInfo(reqContext, segReq)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
+	resp = info
+	resp.Result = GetErrInfo(NoYigFsErr)
+
+	ctx.JSON(resp)
+	return
+}
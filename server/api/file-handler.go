@@ -292,3 +292,110 @@ func(yigFs MetaAPIHandlers) SetFileAttrHandler(ctx iris.Context) {
 	ctx.JSON(resp)
 	return
 }
+
+func(yigFs MetaAPIHandlers) GetFileAttrsHandler(ctx iris.Context) {
+	resp := &types.GetFileAttrsResp{
+		Files: []*types.FileInfo{},
+		Result: types.YigFsMetaError{},
+	}
+	defer GetSpendTime("GetFileAttrsHandler")()
+
+	r := ctx.Request()
+	reqContext := r.Context()
+
+	// get req
+	fileReq := &types.GetFileAttrsReq{}
+	if err := ctx.ReadJSON(&fileReq); err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read GetFileAttrsReq from body, err: %v", err))
+		resp.Result = GetErrInfo(ErrYigFsInvaildParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	// check request params
+	if fileReq.BucketName == "" || len(fileReq.Inos) == 0 {
+		helper.Logger.Error(reqContext, "Some GetFileAttrs required parameters are missing.")
+		resp.Result = GetErrInfo(ErrYigFsMissingRequiredParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	if len(fileReq.Inos) > types.MAX_GET_FILE_ATTRS {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Too many inos %d in GetFileAttrs, max: %d", len(fileReq.Inos), types.MAX_GET_FILE_ATTRS))
+		resp.Result = GetErrInfo(ErrYigFsInvaildParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	if fileReq.Region == "" {
+		fileReq.Region = "cn-bj-1"
+	}
+
+	uuidStr := uuid.New()
+	fileReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
+
+	// the missing inos are not returned.
+	getFileAttrsResp, err := yigFs.YigFsAPI.GetFileAttrs(reqContext, fileReq)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
+	if getFileAttrsResp != nil {
+		resp.Files = getFileAttrsResp
+	}
+	resp.Result = GetErrInfo(NoYigFsErr)
+
+	ctx.JSON(resp)
+	return
+}
+
+func(yigFs MetaAPIHandlers) GetDirFilesAttrsHandler(ctx iris.Context) {
+	resp := &types.GetDirFilesAttrsResp {
+		Files: []*types.GetDirFileAttrsInfo{},
+		Result: types.YigFsMetaError {},
+	}
+	defer GetSpendTime("GetDirFilesAttrs")()
+
+	r := ctx.Request()
+	reqContext := r.Context()
+
+	// get req
+	dirReq := &types.GetDirFilesReq{}
+	if err := ctx.ReadJSON(&dirReq); err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read GetDirFilesReq from body, err: %v", err))
+		resp.Result = GetErrInfo(ErrYigFsInvaildParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	// check request params
+	if dirReq.BucketName == "" || dirReq.ParentIno == 0 {
+		helper.Logger.Error(reqContext, "Some GetDirFilesAttrs required parameters are missing.")
+		resp.Result = GetErrInfo(ErrYigFsMissingRequiredParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	if dirReq.Region == "" {
+		dirReq.Region = "cn-bj-1"
+	}
+
+	uuidStr := uuid.New()
+	dirReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
+
+	getDirFilesResp, offset, err := yigFs.YigFsAPI.ListDirFilesAttrs(reqContext, dirReq)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
+	resp.Files = getDirFilesResp
+	resp.Result = GetErrInfo(NoYigFsErr)
+	resp.Offset = offset
+
+	ctx.JSON(resp)
+	return
+}
@@ -57,3 +57,80 @@ func(yigFs MetaAPIHandlers) GetFileLeaderHandler(ctx iris.Context) {
 	ctx.JSON(resp)
 	return
 }
+
+// read and check the FileLeaseReq of the lease handlers.
+func ReadFileLeaseReq(ctx iris.Context, action string) (leaseReq *types.FileLeaseReq, err error) {
+	reqContext := ctx.Request().Context()
+
+	leaseReq = &types.FileLeaseReq{}
+	if err = ctx.ReadJSON(&leaseReq); err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read FileLeaseReq of %s from body, err: %v", action, err))
+		err = ErrYigFsInvaildParams
+		return
+	}
+
+	// check request params
+	if leaseReq.BucketName == "" || leaseReq.ZoneId == "" || leaseReq.Machine == "" || leaseReq.Ino == 0 {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Some %s required parameters are missing.", action))
+		err = ErrYigFsMissingRequiredParams
+		return
+	}
+
+	if leaseReq.Region == "" {
+		leaseReq.Region = "cn-bj-1"
+	}
+
+	uuidStr := uuid.New()
+	leaseReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
+	return
+}
+
+func(yigFs MetaAPIHandlers) fileLeaseHandler(ctx iris.Context, action string,
+	leaseFunc func(ctx context.Context, lease *types.FileLeaseReq) (*types.LeaderInfo, error)) {
+	resp := &types.FileLeaseResp {
+		Result: types.YigFsMetaError{},
+	}
+	defer GetSpendTime(action)()
+
+	leaseReq, err := ReadFileLeaseReq(ctx, action)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
+	if action == "TransferFileLeaderHandler" && leaseReq.FromLeader == "" {
+		helper.Logger.Error(leaseReq.Ctx, "TransferFileLeader required parameter from_leader is missing.")
+		resp.Result = GetErrInfo(ErrYigFsMissingRequiredParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	// the current holder is returned with ErrYigFsLeaseHeld and ErrYigFsLeaseStale.
+	leaderInfo, err := leaseFunc(ctx.Request().Context(), leaseReq)
+	resp.LeaderInfo = leaderInfo
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+	} else {
+		resp.Result = GetErrInfo(NoYigFsErr)
+	}
+
+	ctx.JSON(resp)
+	return
+}
+
+func(yigFs MetaAPIHandlers) AcquireFileLeaseHandler(ctx iris.Context) {
+	yigFs.fileLeaseHandler(ctx, "AcquireFileLeaseHandler", yigFs.YigFsAPI.AcquireFileLease)
+}
+
+func(yigFs MetaAPIHandlers) RenewFileLeaseHandler(ctx iris.Context) {
+	yigFs.fileLeaseHandler(ctx, "RenewFileLeaseHandler", yigFs.YigFsAPI.RenewFileLease)
+}
+
+func(yigFs MetaAPIHandlers) ReleaseFileLeaseHandler(ctx iris.Context) {
+	yigFs.fileLeaseHandler(ctx, "ReleaseFileLeaseHandler", yigFs.YigFsAPI.ReleaseFileLease)
+}
+
+func(yigFs MetaAPIHandlers) TransferFileLeaderHandler(ctx iris.Context) {
+	yigFs.fileLeaseHandler(ctx, "TransferFileLeaderHandler", yigFs.YigFsAPI.TransferFileLeader)
+}
@@ -11,13 +11,23 @@ type YigFsLayer interface {
 	ListDirFiles(ctx context.Context, dir *types.GetDirFilesReq) (listDirFilesResp []*types.GetDirFileInfo, offset uint64, err error)
 	GetDirFileAttr(ctx context.Context, file *types.GetDirFileInfoReq) (resp *types.FileInfo, err error)
 	GetFileAttr(ctx context.Context, file *types.GetFileInfoReq) (resp *types.FileInfo, err error)
+	GetFileAttrs(ctx context.Context, file *types.GetFileAttrsReq) (resp []*types.FileInfo, err error)
+	ListDirFilesAttrs(ctx context.Context, dir *types.GetDirFilesReq) (resp []*types.GetDirFileAttrsInfo, offset uint64, err error)
 	InitDirAndZone(ctx context.Context, rootDir *types.InitDirReq) (err error)
 	GetFileLeader(ctx context.Context, leader *types.GetLeaderReq) (resp *types.GetLeaderResp, err error)
+	ListZoneMachines(ctx context.Context, zone *types.GetZoneMachinesReq) (resp []*types.MachineInfo, err error)
 	CreateFile(ctx context.Context, file *types.CreateFileReq) (resp *types.CreateFileResp, err error)
 	SetFileAttr(ctx context.Context, file *types.SetFileAttrReq) (resp *types.SetFileAttrResp, err error)
+	AcquireFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error)
+	RenewFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error)
+	ReleaseFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error)
+	TransferFileLeader(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error)
+	CheckFileLeaseEpoch(ctx context.Context, lease *types.FileLeaseReq) (err error)
 	CheckSegmentLeader(ctx context.Context, segment *types.CreateSegmentReq) (isExisted int, err error)
 	CreateFileSegment(ctx context.Context, seg *types.CreateSegmentReq, isLeaderExisted int) (err error)
 	UpdateFileSizeAndBlock(ctx context.Context, file *types.GetFileInfoReq) (err error)
 	GetFileSegmentsInfo(ctx context.Context, seg *types.GetSegmentReq) (resp *types.GetSegmentResp, err error)
 	UpdateSegBlockInfo(ctx context.Context, seg *types.UpdateSegBlockInfoReq) (err error)
+	GetSegmentInfo(ctx context.Context, segment *types.GetSegLeaderReq) (resp *types.GetSegmentInfoResp, err error)
+	UpdateSegmentBackendSize(ctx context.Context, segment *types.CreateSegmentReq) (err error)
 }
@@ -0,0 +1,60 @@
+package api
+
+import (
+	"context"
+	"fmt"
+
+	"github.com/kataras/iris"
+	"github.com/google/uuid"
+	"github.com/hopkings2008/yigfs/server/types"
+	. "github.com/hopkings2008/yigfs/server/error"
+	"github.com/hopkings2008/yigfs/server/helper"
+)
+
+func(yigFs MetaAPIHandlers) GetZoneMachinesHandler(ctx iris.Context) {
+	r := ctx.Request()
+	reqContext := r.Context()
+
+	resp := &types.GetZoneMachinesResp {
+		Result: types.YigFsMetaError{},
+		Machines: []*types.MachineInfo{},
+	}
+	defer GetSpendTime("GetZoneMachinesHandler")()
+
+	// get req
+	zoneReq := &types.GetZoneMachinesReq{}
+	if err := ctx.ReadJSON(&zoneReq); err != nil {
+		helper.Logger.Error(reqContext, fmt.Sprintf("Failed to read GetZoneMachinesReq from body, err: %v", err))
+		resp.Result = GetErrInfo(ErrYigFsInvaildParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	// check request params
+	if zoneReq.BucketName == "" {
+		helper.Logger.Error(reqContext, "Some getZoneMachines required parameters are missing.")
+		resp.Result = GetErrInfo(ErrYigFsMissingRequiredParams)
+		ctx.JSON(resp)
+		return
+	}
+
+	if zoneReq.Region == "" {
+		zoneReq.Region = "cn-bj-1"
+	}
+
+	uuidStr := uuid.New()
+	zoneReq.Ctx = context.WithValue(reqContext, types.CTX_REQ_ID, uuidStr)
+
+	// list the machines of all the zones, the client filters them by its policy.
+	machines, err := yigFs.YigFsAPI.ListZoneMachines(reqContext, zoneReq)
+	if err != nil {
+		resp.Result = GetErrInfo(err)
+		ctx.JSON(resp)
+		return
+	}
+
+	resp.Result = GetErrInfo(NoYigFsErr)
+	resp.Machines = machines
+	ctx.JSON(resp)
+	return
+}
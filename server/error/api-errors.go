@@ -41,6 +41,9 @@ const (
 	ErrYigFsMachineNotMatchLeader
 	ErrYigFsMissingSegmentLeader
 	ErrYigFsNoVaildSegments
+	ErrYigFsLeaseHeld
+	ErrYigFsLeaseStale
+	ErrYigFsAccessDenied
 )
 
 var ErrorCodeResponse = map[ApiErrorCode]ApiErrorStruct{
@@ -124,6 +127,21 @@ var ErrorCodeResponse = map[ApiErrorCode]ApiErrorStruct{
 		Description:    "No vaild segments to upload.",
 		HttpStatusCode: 40014,
 	},
+	ErrYigFsLeaseHeld: {
+		AwsErrorCode:   "ErrYigFsLeaseHeld",
+		Description:    "The lease of file leader is held by another machine.",
+		HttpStatusCode: 40015,
+	},
+	ErrYigFsLeaseStale: {
+		AwsErrorCode:   "ErrYigFsLeaseStale",
+		Description:    "The lease epoch is stale, the lease is expired or taken over by another machine.",
+		HttpStatusCode: 40016,
+	},
+	ErrYigFsAccessDenied: {
+		AwsErrorCode:   "ErrYigFsAccessDenied",
+		Description:    "Access denied, the request signature is invalid or expired.",
+		HttpStatusCode: 40017,
+	},
 }
 
 func (e ApiErrorCode) AwsErrorCode() string {
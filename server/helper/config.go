@@ -26,6 +26,12 @@ type MetaServiceConfig struct {
 	LogLevel          string        `toml:"log_level"`
 	TlsKeyFile        string        `toml:"tls_key_file"`
 	TlsCertFile       string        `toml:"tls_cert_file"`
+	AuthType          string        `toml:"auth_type"`
+	AuthToken         string        `toml:"auth_token"`
+	// the ak/sk of the bucket which the clients sign the v4 requests with.
+	AuthAccessKey     string        `toml:"auth_access_key"`
+	AuthSecretKey     string        `toml:"auth_secret_key"`
+	AuthTimeWindow    int           `toml:"auth_time_window"`
 }
 
 var CONFIG Config
@@ -48,6 +48,8 @@ CREATE TABLE `file_leader` (
   `ino` bigint(20) UNSIGNED DEFAULT 0,
   `generation` bigint(20) UNSIGNED DEFAULT 0,
   `leader` varchar(255) DEFAULT NULL,
+  `lease_epoch` bigint(20) UNSIGNED DEFAULT 0,
+  `lease_expire` bigint(20) DEFAULT 0,
   `ctime` datetime DEFAULT CURRENT_TIMESTAMP,
   `mtime` datetime DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
   `is_deleted` tinyint(1) DEFAULT 0,
@@ -67,6 +69,7 @@ CREATE TABLE `segment_info` (
   `leader` varchar(255) DEFAULT NULL,
   `max_size` int(11) DEFAULT 0,
   `latest_offset` int(11) DEFAULT 0,
+  `backend_size` bigint(20) UNSIGNED DEFAULT 0,
   `ctime` datetime DEFAULT CURRENT_TIMESTAMP,
   `mtime` datetime DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
   `is_deleted` tinyint(1) DEFAULT 0,
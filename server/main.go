@@ -34,16 +34,33 @@ func main() {
 		YigFsAPI: apiServerConfig.YigFsLayer,
 	}
 
+	// verify the request signature before any handler
+	app.Use(api.AuthHandler)
+
 	// ListDirFiles
 	app.Get("/v1/dir/files", apiHandlers.GetDirFilesHandler)
+	// ListDirFilesAttrs
+	app.Get("/v1/dir/files/attrs", apiHandlers.GetDirFilesAttrsHandler)
 	// GetDirFileAttr
 	app.Get("/v1/dir/file/attr", apiHandlers.GetDirFileAttrHandler)
 	// GetFileAttr
 	app.Get("/v1/file/attr", apiHandlers.GetFileAttrHandler)
+	// GetFileAttrs
+	app.Get("/v1/file/attrs", apiHandlers.GetFileAttrsHandler)
 	// InitDir
 	app.Put("/v1/dir", apiHandlers.InitDirHandler)
 	// GetFileLeader
 	app.Get("/v1/file/leader", apiHandlers.GetFileLeaderHandler)
+	// GetZoneMachines
+	app.Get("/v1/zone/machines", apiHandlers.GetZoneMachinesHandler)
+	// AcquireFileLease
+	app.Put("/v1/file/leader/lease", apiHandlers.AcquireFileLeaseHandler)
+	// RenewFileLease
+	app.Post("/v1/file/leader/lease", apiHandlers.RenewFileLeaseHandler)
+	// ReleaseFileLease
+	app.Delete("/v1/file/leader/lease", apiHandlers.ReleaseFileLeaseHandler)
+	// TransferFileLeader
+	app.Post("/v1/file/leader/transfer", apiHandlers.TransferFileLeaderHandler)
 	// CreateFile
 	app.Put("/v1/dir/file", apiHandlers.CreateFileHandler)
 	// SetFileAttr
@@ -56,6 +73,8 @@ func main() {
 	app.Get("/v1/file/segments", apiHandlers.GetSegmentsHandler)
 	// UpdateSegBlockInfo
 	app.Put("/v1/segment/block", apiHandlers.UpdateSegBlockInfoHandler)
+	// GetSegmentInfo
+	app.Get("/v1/segment/info", apiHandlers.GetSegmentInfoHandler)
 
 	port := ":" + helper.CONFIG.MetaServiceConfig.Port
     	err := app.Run(iris.TLS(port, helper.CONFIG.MetaServiceConfig.TlsCertFile, helper.CONFIG.MetaServiceConfig.TlsKeyFile))
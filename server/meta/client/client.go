@@ -18,22 +18,36 @@ type Client interface {
 	GetDirFileInfo(ctx context.Context, file *types.GetDirFileInfoReq) (resp *types.FileInfo, err error)
 	// Get file info from ino
 	GetFileInfo(ctx context.Context, file *types.GetFileInfoReq) (resp *types.FileInfo, err error)
+	// Get files info of inos
+	GetFilesInfo(ctx context.Context, file *types.GetFileAttrsReq) (resp []*types.FileInfo, err error)
+	// List dir files with their attrs
+	ListDirFilesInfo(ctx context.Context, dir *types.GetDirFilesReq) (resp []*types.FileInfo, offset uint64, err error)
 	// Create or update zone
 	CreateOrUpdateZone(ctx context.Context, zone *types.InitDirReq) (err error)
 	// Get file leader
 	GetFileLeaderInfo(ctx context.Context, leader *types.GetLeaderReq) (resp *types.GetLeaderResp, err error)
 	// Create or update file leader
 	CreateOrUpdateFileLeader(ctx context.Context, leader *types.GetLeaderReq) (err error)
+	// Get file lease
+	GetFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.FileLeaseInfo, err error)
+	// Create file leader with lease
+	CreateFileLease(ctx context.Context, lease *types.FileLeaseReq, epoch uint64, expire int64) (isCreated bool, err error)
+	// Update file lease if its epoch is not changed
+	UpdateFileLease(ctx context.Context, lease *types.FileLeaseReq, oldEpoch uint64, leader string, epoch uint64, expire int64) (isUpdated bool, err error)
 	// Get one update machine
 	GetOneUpMachine(ctx context.Context, zone *types.GetLeaderReq) (leader string, err error)
 	// Get machine indo
 	GetMachineInfo(ctx context.Context, zone *types.GetLeaderReq) (resp *types.GetMachineInfoResp, err error)
+	// List the machines of all the zones with their load
+	ListZoneMachines(ctx context.Context, zone *types.GetZoneMachinesReq) (resp []*types.MachineInfo, err error)
 	// Set file attr
 	SetFileAttr(ctx context.Context, file *types.SetFileAttrReq) (err error)
 	// get segment info
 	GetSegmentInfo(ctx context.Context, segment *types.GetSegLeaderReq) (resp *types.LeaderInfo, err error)
 	// create segment info
 	CreateSegmentInfo(ctx context.Context, segment *types.CreateSegmentReq) (err error)
+	// update the size of the segment uploaded to the backend store
+	UpdateSegmentBackendSize(ctx context.Context, segment *types.CreateSegmentReq) (err error)
 	// get covered blocks
 	GetCoveredExistedBlocks(ctx context.Context, blockInfo *types.DescriptBlockInfo, block *types.BlockInfo) (blocks []*types.BlockInfo, err error)
 	// deleted blocks
@@ -66,5 +80,7 @@ type Client interface {
 	GetSegsBlockInfo(ctx context.Context, seg *types.GetSegmentReq, segmentMap map[interface{}][]int64, offsetMap map[int64]int64) (resp *types.GetSegmentResp, err error)
 	// update segment block info
 	UpdateSegBlockInfo(ctx context.Context, seg *types.UpdateSegBlockInfoReq) (err error)
+	// get the blocks of all the files in the segment
+	GetSegmentBlocks(ctx context.Context, segment *types.GetSegLeaderReq) (blocks []*types.SegmentBlockInfo, err error)
 }
 
@@ -30,6 +30,12 @@ func GetFileInfoSql() (sqltext string) {
 	return sqltext
 }
 
+func ListFilesInfoSql() (sqltext string) {
+	sqltext = "select ino, generation, parent_ino, file_name, size, type, ctime, mtime, atime, perm, nlink," +
+		" uid, gid, blocks from file where region=? and bucket_name=?"
+	return sqltext
+}
+
 func GetFileExistedSql() (sqltext string) {
 	sqltext = "select 1 from file where region=? and bucket_name=? and ino=? limit 1;"
 	return sqltext
@@ -46,7 +52,7 @@ func UpdateFileSizeAndBlocksSql() (sqltext string) {
 }
 
 func (t *TidbClient) ListDirFiles(ctx context.Context, dir *types.GetDirFilesReq) (dirFilesResp []*types.GetDirFileInfo, offset uint64, err error) {
-	var maxNum = 1000
+	var maxNum = types.MAX_LIST_DIR_FILES
 	args := make([]interface{}, 0)
 	sqltext := "select ino, file_name, type from file where region=? and bucket_name=? and parent_ino=? and ino > ? order by ino limit ?;"
 	args = append(args, dir.Region, dir.BucketName, dir.ParentIno, dir.Offset, maxNum)
@@ -84,7 +90,11 @@ func (t *TidbClient) ListDirFiles(ctx context.Context, dir *types.GetDirFilesReq
 
 	dirLength := len(dirFilesResp)
 	if dirLength > 0 {
-		offset = dirFilesResp[dirLength - 1].Ino + 1   //nextStartIno
+		// the next page starts after the last listed ino, since the query is "ino > offset".
+		offset = dirFilesResp[dirLength - 1].Ino
+	} else {
+		// end of dir, keep the cursor.
+		offset = dir.Offset
 	}
 
 	helper.Logger.Info(ctx, fmt.Sprintf("succeed to list dir files, sqltext: %v, req offset: %v, resp offset: %v", sqltext, dir.Offset, offset))
@@ -198,6 +208,114 @@ func (t *TidbClient) GetFileInfo(ctx context.Context, file *types.GetFileInfoReq
 	return
 }
 
+// scan the rows of ListFilesInfoSql.
+func scanFilesInfo(ctx context.Context, rows *sql.Rows, region string, bucket string) (files []*types.FileInfo, err error) {
+	for rows.Next() {
+		var tmp = &types.FileInfo{}
+		var ctime, mtime, atime string
+		err = rows.Scan(
+			&tmp.Ino,
+			&tmp.Generation,
+			&tmp.ParentIno,
+			&tmp.FileName,
+			&tmp.Size,
+			&tmp.Type,
+			&ctime,
+			&mtime,
+			&atime,
+			&tmp.Perm,
+			&tmp.Nlink,
+			&tmp.Uid,
+			&tmp.Gid,
+			&tmp.Blocks)
+		if err != nil {
+			helper.Logger.Error(ctx, fmt.Sprintf("Failed to scan files info in row, err: %v", err))
+			err = ErrYIgFsInternalErr
+			return
+		}
+
+		cTime, err := time.Parse(types.TIME_LAYOUT_TIDB, ctime)
+		if err != nil {
+			return files, err
+		}
+		mTime, err := time.Parse(types.TIME_LAYOUT_TIDB, mtime)
+		if err != nil {
+			return files, err
+		}
+		aTime, err := time.Parse(types.TIME_LAYOUT_TIDB, atime)
+		if err != nil {
+			return files, err
+		}
+
+		tmp.Ctime = cTime.UnixNano()
+		tmp.Mtime = mTime.UnixNano()
+		tmp.Atime = aTime.UnixNano()
+		tmp.Region = region
+		tmp.BucketName = bucket
+		files = append(files, tmp)
+	}
+	err = rows.Err()
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to scan files info in rows, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+	return
+}
+
+func (t *TidbClient) GetFilesInfo(ctx context.Context, file *types.GetFileAttrsReq) (resp []*types.FileInfo, err error) {
+	args := make([]interface{}, 0)
+	args = append(args, file.Region, file.BucketName)
+	marks := make([]string, 0)
+	for _, ino := range file.Inos {
+		marks = append(marks, "?")
+		args = append(args, ino)
+	}
+	sqltext := ListFilesInfoSql() + " and ino in (" + strings.Join(marks, ",") + ")"
+
+	rows, err := t.Client.Query(sqltext, args...)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to query files info, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+	defer rows.Close()
+
+	resp, err = scanFilesInfo(ctx, rows, file.Region, file.BucketName)
+	if err != nil {
+		return
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("succeed to get files info, inos: %d, found: %d", len(file.Inos), len(resp)))
+	return
+}
+
+func (t *TidbClient) ListDirFilesInfo(ctx context.Context, dir *types.GetDirFilesReq) (resp []*types.FileInfo, offset uint64, err error) {
+	sqltext := ListFilesInfoSql() + " and parent_ino=? and ino > ? order by ino limit ?;"
+	rows, err := t.Client.Query(sqltext, dir.Region, dir.BucketName, dir.ParentIno, dir.Offset, types.MAX_LIST_DIR_FILES)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to query dir files info, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+	defer rows.Close()
+
+	resp, err = scanFilesInfo(ctx, rows, dir.Region, dir.BucketName)
+	if err != nil {
+		return
+	}
+
+	if len(resp) > 0 {
+		// the same cursor as ListDirFiles.
+		offset = resp[len(resp) - 1].Ino
+	} else {
+		offset = dir.Offset
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("succeed to list dir files info, req offset: %v, resp offset: %v", dir.Offset, offset))
+	return
+}
+
 func (t *TidbClient) InitRootDirs(ctx context.Context, rootDir *types.InitDirReq) (err error) {
 	var tx interface{}
 	var sqlTx *sql.Tx
@@ -304,7 +422,7 @@ func (t *TidbClient) CreateFile(ctx context.Context, file *types.CreateFileReq)
 
 	// create file leader
 	sqltext = CreateOrUpdateFileLeaderSql()
-	_, err = sqlTx.Exec(sqltext, file.ZoneId, file.Region, file.BucketName, ino, file.Generation, file.Machine, types.NotDeleted)
+	_, err = sqlTx.Exec(sqltext, file.ZoneId, file.Region, file.BucketName, ino, file.Generation, file.Leader, types.NotDeleted)
 	if err != nil {
 		helper.Logger.Error(ctx, fmt.Sprintf("CreateFile: Failed to create file leader to tidb, err: %v", err))
 		err = ErrYIgFsInternalErr
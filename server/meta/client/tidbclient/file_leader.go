@@ -4,6 +4,7 @@ import (
 	"context"
 	"database/sql"
 	"fmt"
+	"time"
 
 	. "github.com/hopkings2008/yigfs/server/error"
 	"github.com/hopkings2008/yigfs/server/types"
@@ -12,8 +13,16 @@ import (
 
 
 func CreateOrUpdateFileLeaderSql() (sqltext string) {
+	// the lease of the old leader is revoked if the leader is changed,
+	// the lease columns must be updated before the leader.
 	sqltext = "insert into file_leader(zone_id, region, bucket_name, ino, generation, leader, is_deleted) values(?,?,?,?,?,?,?)" +
-		" on duplicate key update leader=values(leader), is_deleted=values(is_deleted)"
+		" on duplicate key update lease_epoch=if(leader=values(leader), lease_epoch, lease_epoch+1)," +
+		" lease_expire=if(leader=values(leader), lease_expire, 0), leader=values(leader), is_deleted=values(is_deleted)"
+	return sqltext
+}
+
+func GetFileLeaseSql() (sqltext string) {
+	sqltext = "select leader, lease_epoch, lease_expire from file_leader where zone_id=? and region=? and bucket_name=? and ino=?"
 	return sqltext
 }
 
@@ -22,10 +31,13 @@ func (t *TidbClient) GetFileLeaderInfo(ctx context.Context, leader *types.GetLea
 		LeaderInfo: &types.LeaderInfo{},
 	}
 
-	sqltext := "select leader from file_leader where zone_id=? and region=? and bucket_name=? and ino=?"
+	var leaseExpire int64
+	sqltext := GetFileLeaseSql()
 	row := t.Client.QueryRow(sqltext, leader.ZoneId, leader.Region, leader.BucketName, leader.Ino)
 	err = row.Scan (
 		&resp.LeaderInfo.Leader,
+		&resp.LeaderInfo.LeaseEpoch,
+		&leaseExpire,
 	)
 
 	if err == sql.ErrNoRows {
@@ -38,6 +50,9 @@ func (t *TidbClient) GetFileLeaderInfo(ctx context.Context, leader *types.GetLea
 	}
 
 	resp.LeaderInfo.ZoneId = leader.ZoneId
+	if resp.LeaderInfo.LeaseEpoch > 0 {
+		resp.LeaderInfo.LeaseTtl = leaseExpire - time.Now().Unix()
+	}
 	helper.Logger.Info(ctx, fmt.Sprintf("succeed to get the file leader from tidb, sqltext: %v", sqltext))
 	return
 }
@@ -54,3 +69,73 @@ func (t *TidbClient) CreateOrUpdateFileLeader(ctx context.Context, leader *types
 	helper.Logger.Info(ctx, fmt.Sprintf("Succeed to create file leader to tidb, sqltext: %v", sqltext))
 	return
 }
+
+func (t *TidbClient) GetFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.FileLeaseInfo, err error) {
+	resp = &types.FileLeaseInfo{}
+	sqltext := GetFileLeaseSql()
+	row := t.Client.QueryRow(sqltext, lease.ZoneId, lease.Region, lease.BucketName, lease.Ino)
+	err = row.Scan(
+		&resp.Leader,
+		&resp.LeaseEpoch,
+		&resp.LeaseExpire,
+	)
+
+	if err == sql.ErrNoRows {
+		err = ErrYigFsNoSuchLeader
+		return
+	} else if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get the file lease, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("succeed to get the file lease from tidb, sqltext: %v", sqltext))
+	return
+}
+
+// create the leader with lease, isCreated is false if the leader is created by others.
+func (t *TidbClient) CreateFileLease(ctx context.Context, lease *types.FileLeaseReq, epoch uint64, expire int64) (isCreated bool, err error) {
+	sqltext := "insert ignore into file_leader(zone_id, region, bucket_name, ino, generation, leader, lease_epoch, lease_expire, is_deleted)" +
+		" values(?,?,?,?,?,?,?,?,?)"
+	result, err := t.Client.Exec(sqltext, lease.ZoneId, lease.Region, lease.BucketName, lease.Ino, lease.Generation,
+		lease.Machine, epoch, expire, types.NotDeleted)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to create file lease to tidb, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	rows, err := result.RowsAffected()
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get the created rows of file lease, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	isCreated = rows > 0
+	helper.Logger.Info(ctx, fmt.Sprintf("Succeed to create file lease to tidb, sqltext: %v, created: %v", sqltext, isCreated))
+	return
+}
+
+// update the lease only if its epoch is still oldEpoch. isUpdated is false if the epoch is changed
+// by others, or nothing is changed.
+func (t *TidbClient) UpdateFileLease(ctx context.Context, lease *types.FileLeaseReq, oldEpoch uint64, leader string, epoch uint64, expire int64) (isUpdated bool, err error) {
+	sqltext := "update file_leader set leader=?, lease_epoch=?, lease_expire=? where zone_id=? and region=? and bucket_name=? and ino=? and lease_epoch=?"
+	result, err := t.Client.Exec(sqltext, leader, epoch, expire, lease.ZoneId, lease.Region, lease.BucketName, lease.Ino, oldEpoch)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to update file lease to tidb, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	rows, err := result.RowsAffected()
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get the updated rows of file lease, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	isUpdated = rows > 0
+	helper.Logger.Info(ctx, fmt.Sprintf("Succeed to update file lease to tidb, sqltext: %v, updated: %v", sqltext, isUpdated))
+	return
+}
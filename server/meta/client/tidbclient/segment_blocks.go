@@ -118,6 +118,7 @@ func (t *TidbClient) GetSegsBlockInfo(ctx context.Context, seg *types.GetSegment
 		err = row.Scan (
 			&segment.Leader,
 			&segment.MaxSize,
+			&segment.BackendSize,
 		)
 
 		if err != nil {
@@ -130,4 +131,46 @@ func (t *TidbClient) GetSegsBlockInfo(ctx context.Context, seg *types.GetSegment
 	}
 	
 	return
-}
\ No newline at end of file
+}
+
+func (t *TidbClient) GetSegmentBlocks(ctx context.Context, segment *types.GetSegLeaderReq) (blocks []*types.SegmentBlockInfo, err error) {
+	sqltext := "select f.ino, f.generation, f.offset, s.seg_start_addr, s.seg_end_addr, s.size from file_blocks f join segment_blocks s" +
+		" on s.seg_id0=f.seg_id0 and s.seg_id1=f.seg_id1 and s.block_id=f.block_id and s.is_deleted=?" +
+		" where f.region=? and f.bucket_name=? and f.seg_id0=? and f.seg_id1=? and f.is_deleted=? order by s.seg_start_addr"
+	rows, err := t.Client.Query(sqltext, types.NotDeleted, segment.Region, segment.BucketName, segment.SegmentId0, segment.SegmentId1, types.NotDeleted)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get the segment blocks, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+	defer rows.Close()
+
+	blocks = []*types.SegmentBlockInfo{}
+	for rows.Next() {
+		block := &types.SegmentBlockInfo{}
+		err = rows.Scan(
+			&block.Ino,
+			&block.Generation,
+			&block.Offset,
+			&block.SegStartAddr,
+			&block.SegEndAddr,
+			&block.Size)
+		if err != nil {
+			helper.Logger.Error(ctx, fmt.Sprintf("Failed to scan the segment block, err: %v", err))
+			err = ErrYIgFsInternalErr
+			return
+		}
+		blocks = append(blocks, block)
+	}
+
+	err = rows.Err()
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to iterate the segment blocks, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("Succeed to get the segment blocks, seg_id0: %v, seg_id1: %v, blocks: %d",
+		segment.SegmentId0, segment.SegmentId1, len(blocks)))
+	return
+}
@@ -11,12 +11,12 @@ import (
 )
 
 func GetSegmentLeaderSql() (sqltext string) {
-	sqltext = "select leader, max_size from segment_info where zone_id=? and region=? and bucket_name=? and seg_id0=? and seg_id1=?"
+	sqltext = "select leader, max_size, backend_size from segment_info where zone_id=? and region=? and bucket_name=? and seg_id0=? and seg_id1=?"
 	return sqltext
 }
 
 func CreateSegmentInfoSql() (sqltext string) {
-	sqltext = "insert into segment_info(zone_id, region, bucket_name, seg_id0, seg_id1, leader, max_size) values(?,?,?,?,?,?,?)"
+	sqltext = "insert into segment_info(zone_id, region, bucket_name, seg_id0, seg_id1, leader, max_size, backend_size) values(?,?,?,?,?,?,?,?)"
 	return sqltext
 }
 
@@ -28,6 +28,7 @@ func (t *TidbClient) GetSegmentInfo(ctx context.Context, segment *types.GetSegLe
 	err = row.Scan (
 		&resp.Leader,
 		&resp.MaxSize,
+		&resp.BackendSize,
 	)
 
 	if err == sql.ErrNoRows {
@@ -47,7 +48,7 @@ func (t *TidbClient) GetSegmentInfo(ctx context.Context, segment *types.GetSegLe
 func (t *TidbClient) CreateSegmentInfo(ctx context.Context, segment *types.CreateSegmentReq) (err error) {
 	sqltext := CreateSegmentInfoSql()
 	args := []interface{}{segment.ZoneId, segment.Region, segment.BucketName, segment.Segment.SegmentId0,
-		segment.Segment.SegmentId1, segment.Machine, segment.Segment.MaxSize}
+		segment.Segment.SegmentId1, segment.Machine, segment.Segment.MaxSize, segment.Segment.BackendSize}
 		
 	_, err = t.Client.Exec(sqltext, args...)
 	if err != nil {
@@ -74,4 +75,18 @@ func (t *TidbClient) UpdateSegBlockInfo(ctx context.Context, seg *types.UpdateSe
 	return
 }
 
+// the backend size only grows, the stale updates are ignored.
+func (t *TidbClient) UpdateSegmentBackendSize(ctx context.Context, segment *types.CreateSegmentReq) (err error) {
+	sqltext := "update segment_info set backend_size=greatest(backend_size, ?) where zone_id=? and region=? and bucket_name=? and seg_id0=? and seg_id1=? and is_deleted=?"
+	_, err = t.Client.Exec(sqltext, segment.Segment.BackendSize, segment.ZoneId, segment.Region, segment.BucketName,
+		segment.Segment.SegmentId0, segment.Segment.SegmentId1, types.NotDeleted)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to update segment backend size to tidb, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
 
+	helper.Logger.Info(ctx, fmt.Sprintf("Succeed to update segment backend size to tidb, seg_id0: %v, seg_id1: %v, backend_size: %v",
+		segment.Segment.SegmentId0, segment.Segment.SegmentId1, segment.Segment.BackendSize))
+	return
+}
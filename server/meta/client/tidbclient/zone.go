@@ -71,3 +71,43 @@ func (t *TidbClient) GetMachineInfo(ctx context.Context, zone *types.GetLeaderRe
 	return
 }
 
+// list the machines of all the zones in the bucket, with the number of files they lead.
+func (t *TidbClient) ListZoneMachines(ctx context.Context, zone *types.GetZoneMachinesReq) (resp []*types.MachineInfo, err error) {
+	sqltext := "select z.id, z.machine, z.status, z.weight, count(l.ino) from zone z left join file_leader l" +
+		" on l.region=z.region and l.bucket_name=z.bucket_name and l.leader=z.machine and l.is_deleted=?" +
+		" where z.region=? and z.bucket_name=? group by z.id, z.machine, z.status, z.weight"
+	rows, err := t.Client.Query(sqltext, types.NotDeleted, zone.Region, zone.BucketName)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to list zone machines, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+	defer rows.Close()
+
+	resp = []*types.MachineInfo{}
+	for rows.Next() {
+		machine := &types.MachineInfo{}
+		err = rows.Scan(
+			&machine.ZoneId,
+			&machine.Machine,
+			&machine.Status,
+			&machine.Weight,
+			&machine.Load)
+		if err != nil {
+			helper.Logger.Error(ctx, fmt.Sprintf("Failed to scan zone machine, err: %v", err))
+			err = ErrYIgFsInternalErr
+			return
+		}
+		resp = append(resp, machine)
+	}
+
+	err = rows.Err()
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to iterate zone machines, err: %v", err))
+		err = ErrYIgFsInternalErr
+		return
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("succeed to list zone machines, sqltext: %v", sqltext))
+	return
+}
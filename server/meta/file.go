@@ -23,6 +23,14 @@ func(m *Meta) GetFileInfo(ctx context.Context, file *types.GetFileInfoReq) (resp
 	return m.Client.GetFileInfo(ctx, file)
 }
 
+func(m *Meta) GetFilesInfo(ctx context.Context, file *types.GetFileAttrsReq) (resp []*types.FileInfo, err error) {
+	return m.Client.GetFilesInfo(ctx, file)
+}
+
+func(m *Meta) ListDirFilesInfo(ctx context.Context, dir *types.GetDirFilesReq) (resp []*types.FileInfo, offset uint64, err error) {
+	return m.Client.ListDirFilesInfo(ctx, dir)
+}
+
 func(m *Meta) InitRootDirs(ctx context.Context, rootDir *types.InitDirReq) (err error) {
 	return m.Client.InitRootDirs(ctx, rootDir) 
 }
@@ -14,3 +14,15 @@ func(m *Meta) GetFileLeaderInfo(ctx context.Context, leader *types.GetLeaderReq)
 func(m *Meta) CreateOrUpdateFileLeader(ctx context.Context, leader *types.GetLeaderReq) (err error) {
 	return m.Client.CreateOrUpdateFileLeader(ctx, leader)
 }
+
+func(m *Meta) GetFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.FileLeaseInfo, err error) {
+	return m.Client.GetFileLease(ctx, lease)
+}
+
+func(m *Meta) CreateFileLease(ctx context.Context, lease *types.FileLeaseReq, epoch uint64, expire int64) (isCreated bool, err error) {
+	return m.Client.CreateFileLease(ctx, lease, epoch, expire)
+}
+
+func(m *Meta) UpdateFileLease(ctx context.Context, lease *types.FileLeaseReq, oldEpoch uint64, leader string, epoch uint64, expire int64) (isUpdated bool, err error) {
+	return m.Client.UpdateFileLease(ctx, lease, oldEpoch, leader, epoch, expire)
+}
@@ -13,4 +13,8 @@ func (m *Meta) InsertSegmentBlock(ctx context.Context, blockInfo *types.Descript
 
 func(m *Meta) GetSegsBlockInfo(ctx context.Context, seg *types.GetSegmentReq, segmentMap map[interface{}][]int64, offsetMap map[int64]int64) (resp *types.GetSegmentResp, err error) {
 	return m.Client.GetSegsBlockInfo(ctx, seg, segmentMap, offsetMap)
+}
+
+func(m *Meta) GetSegmentBlocks(ctx context.Context, segment *types.GetSegLeaderReq) (blocks []*types.SegmentBlockInfo, err error) {
+	return m.Client.GetSegmentBlocks(ctx, segment)
 }
\ No newline at end of file
@@ -17,4 +17,8 @@ func(m *Meta) CreateSegmentInfo(ctx context.Context, segment *types.CreateSegmen
 
 func(m *Meta) UpdateSegBlockInfo(ctx context.Context, seg *types.UpdateSegBlockInfoReq) (err error) {
 	return m.Client.UpdateSegBlockInfo(ctx, seg)
+}
+
+func(m *Meta) UpdateSegmentBackendSize(ctx context.Context, segment *types.CreateSegmentReq) (err error) {
+	return m.Client.UpdateSegmentBackendSize(ctx, segment)
 }
\ No newline at end of file
@@ -18,3 +18,7 @@ func(m *Meta) GetOneUpMachine(ctx context.Context, zone *types.GetLeaderReq) (le
 func(m *Meta) GetMachineInfo(ctx context.Context, zone *types.GetLeaderReq) (resp *types.GetMachineInfoResp, err error) {
 	return m.Client.GetMachineInfo(ctx, zone)
 }
+
+func(m *Meta) ListZoneMachines(ctx context.Context, zone *types.GetZoneMachinesReq) (resp []*types.MachineInfo, err error) {
+	return m.Client.ListZoneMachines(ctx, zone)
+}
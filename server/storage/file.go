@@ -43,6 +43,19 @@ func(yigFs *YigFsStorage) CreateFile(ctx context.Context, file *types.CreateFile
 
 	switch err {
 	case ErrYigFsNoSuchFile:
+		// the proposed leader must be up, otherwise the request machine leads the file.
+		if file.Leader != "" && file.Leader != file.Machine {
+			err = CheckUpMachine(ctx, file.Region, file.BucketName, file.Leader, yigFs)
+			if err != nil {
+				helper.Logger.Warn(ctx, fmt.Sprintf("CreateFile: the proposed leader %s is not up, lead by %s, region: %s, bucket: %s, filename: %s, err: %v",
+					file.Leader, file.Machine, file.Region, file.BucketName, file.FileName, err))
+				file.Leader = ""
+			}
+		}
+		if file.Leader == "" {
+			file.Leader = file.Machine
+		}
+
 		// if file does not exist, create it.
 		err = yigFs.MetaStorage.Client.CreateFile(ctx, file)
 		if err != nil {
@@ -62,7 +75,7 @@ func(yigFs *YigFsStorage) CreateFile(ctx context.Context, file *types.CreateFile
 		// leader info
 		resp.LeaderInfo = &types.LeaderInfo {
 			ZoneId: file.ZoneId,
-			Leader: file.Machine,
+			Leader: file.Leader,
 		}
 		return
 	case nil:
@@ -112,6 +125,39 @@ func(yigFs *YigFsStorage) GetFileAttr(ctx context.Context, file *types.GetFileIn
 	return
 }
 
+func(yigFs *YigFsStorage) GetFileAttrs(ctx context.Context, file *types.GetFileAttrsReq) (resp []*types.FileInfo, err error) {
+	resp, err = yigFs.MetaStorage.Client.GetFilesInfo(ctx, file)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get files attr, region: %s, bucket: %s, inos: %d, err: %v", file.Region, file.BucketName, len(file.Inos), err))
+		return
+	}
+	return
+}
+
+func(yigFs *YigFsStorage) ListDirFilesAttrs(ctx context.Context, dir *types.GetDirFilesReq) (resp []*types.GetDirFileAttrsInfo, offset uint64, err error) {
+	files, offset, err := yigFs.MetaStorage.Client.ListDirFilesInfo(ctx, dir)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to list dir files attr, err: %v", err))
+		return
+	}
+
+	if len(files) == 0 {
+		helper.Logger.Error(ctx, fmt.Sprintf("Not found files in target dir, region: %s, bucket: %s, offset: %d", dir.Region, dir.BucketName, dir.Offset))
+		err = ErrYigFsNotFindTargetDirFiles
+		return
+	}
+
+	for _, file := range files {
+		resp = append(resp, &types.GetDirFileAttrsInfo {
+			Ino: file.Ino,
+			FileName: file.FileName,
+			Type: file.Type,
+			Attr: file,
+		})
+	}
+	return
+}
+
 func(yigFs *YigFsStorage) InitDirAndZone(ctx context.Context, rootDir *types.InitDirReq) (err error) {
 	// init dir
 	err = yigFs.MetaStorage.Client.InitRootDirs(ctx, rootDir)
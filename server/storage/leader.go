@@ -79,6 +79,11 @@ func GetUpFileLeader(ctx context.Context, leader *types.GetLeaderReq, yigFs *Yig
 		}
 		return getMachineResp, nil
 	case nil:
+		// the leader holding a valid lease is kept, others can take over only after the lease expires.
+		if resp.LeaderInfo.LeaseEpoch > 0 && resp.LeaderInfo.LeaseTtl > 0 {
+			return resp, nil
+		}
+
 		// if leader exist, determine where leader status is up
 		leader.Machine = resp.LeaderInfo.Leader
 		var getMachineInfoResp = &types.GetMachineInfoResp{}
@@ -0,0 +1,233 @@
+package storage
+
+import (
+	"context"
+	"fmt"
+	"time"
+
+	"github.com/hopkings2008/yigfs/server/types"
+	. "github.com/hopkings2008/yigfs/server/error"
+	"github.com/hopkings2008/yigfs/server/helper"
+)
+
+
+func GetLeaseExpire(leaseSecs uint64, now int64) int64 {
+	if leaseSecs == 0 {
+		leaseSecs = types.DEFAULT_LEASE_SECS
+	}
+	if leaseSecs > types.MAX_LEASE_SECS {
+		leaseSecs = types.MAX_LEASE_SECS
+	}
+	return now + int64(leaseSecs)
+}
+
+func GetLeaseLeaderInfo(zoneId string, lease *types.FileLeaseInfo, now int64) *types.LeaderInfo {
+	return &types.LeaderInfo {
+		ZoneId: zoneId,
+		Leader: lease.Leader,
+		LeaseEpoch: lease.LeaseEpoch,
+		LeaseTtl: lease.LeaseExpire - now,
+	}
+}
+
+// set the lease to leader with epoch if the lease is not changed since cur.
+// returns ErrYigFsLeaseHeld with the current lease if it is changed by others.
+func SwapFileLease(ctx context.Context, lease *types.FileLeaseReq, cur *types.FileLeaseInfo, leader string, epoch uint64, expire int64, yigFs *YigFsStorage) (resp *types.LeaderInfo, err error) {
+	now := time.Now().Unix()
+	isUpdated, err := yigFs.MetaStorage.Client.UpdateFileLease(ctx, lease, cur.LeaseEpoch, leader, epoch, expire)
+	if err != nil {
+		return
+	}
+
+	if isUpdated {
+		resp = &types.LeaderInfo {
+			ZoneId: lease.ZoneId,
+			Leader: leader,
+			LeaseEpoch: epoch,
+			LeaseTtl: expire - now,
+		}
+		return
+	}
+
+	// nothing is changed by the update, or the lease is changed by others.
+	latest, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	if err != nil {
+		return
+	}
+
+	resp = GetLeaseLeaderInfo(lease.ZoneId, latest, now)
+	if latest.Leader != leader || latest.LeaseEpoch != epoch {
+		helper.Logger.Warn(ctx, fmt.Sprintf("The file lease is changed, ino: %d, leader: %s, epoch: %d, want leader: %s, epoch: %d",
+			lease.Ino, latest.Leader, latest.LeaseEpoch, leader, epoch))
+		err = ErrYigFsLeaseHeld
+	}
+	return
+}
+
+func(yigFs *YigFsStorage) AcquireFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error) {
+	now := time.Now().Unix()
+	expire := GetLeaseExpire(lease.LeaseSecs, now)
+
+	cur, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	switch err {
+	case ErrYigFsNoSuchLeader:
+		var isCreated bool
+		isCreated, err = yigFs.MetaStorage.Client.CreateFileLease(ctx, lease, 1, expire)
+		if err != nil {
+			return
+		}
+		if isCreated {
+			resp = &types.LeaderInfo {
+				ZoneId: lease.ZoneId,
+				Leader: lease.Machine,
+				LeaseEpoch: 1,
+				LeaseTtl: expire - now,
+			}
+			return
+		}
+		// the leader is created by others at the same time.
+		cur, err = yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+		if err != nil {
+			return
+		}
+	case nil:
+	default:
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get file lease, zone_id: %s, region: %s, bucket: %s, ino: %d, err: %v",
+			lease.ZoneId, lease.Region, lease.BucketName, lease.Ino, err))
+		return
+	}
+
+	if cur.Leader == lease.Machine {
+		// extend the lease of this machine, a leader without lease gets the first epoch.
+		epoch := cur.LeaseEpoch
+		if epoch == 0 {
+			epoch = 1
+		}
+		return SwapFileLease(ctx, lease, cur, lease.Machine, epoch, expire, yigFs)
+	}
+
+	if cur.LeaseEpoch > 0 && cur.LeaseExpire > now {
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		err = ErrYigFsLeaseHeld
+		return
+	}
+
+	// the lease of the other leader is expired, take it over with a new epoch.
+	helper.Logger.Info(ctx, fmt.Sprintf("Take over the expired lease of ino: %d from leader: %s, epoch: %d, new leader: %s",
+		lease.Ino, cur.Leader, cur.LeaseEpoch, lease.Machine))
+	return SwapFileLease(ctx, lease, cur, lease.Machine, cur.LeaseEpoch + 1, expire, yigFs)
+}
+
+func(yigFs *YigFsStorage) RenewFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error) {
+	now := time.Now().Unix()
+	cur, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	if err == ErrYigFsNoSuchLeader {
+		err = ErrYigFsLeaseStale
+		return
+	} else if err != nil {
+		return
+	}
+
+	// the lease is renewable even if it is expired, as long as no one else takes it over.
+	if cur.Leader != lease.Machine || cur.LeaseEpoch != lease.LeaseEpoch {
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		err = ErrYigFsLeaseStale
+		return
+	}
+
+	resp, err = SwapFileLease(ctx, lease, cur, lease.Machine, cur.LeaseEpoch, GetLeaseExpire(lease.LeaseSecs, now), yigFs)
+	if err == ErrYigFsLeaseHeld {
+		err = ErrYigFsLeaseStale
+	}
+	return
+}
+
+func(yigFs *YigFsStorage) ReleaseFileLease(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error) {
+	now := time.Now().Unix()
+	cur, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	if err == ErrYigFsNoSuchLeader {
+		err = ErrYigFsLeaseStale
+		return
+	} else if err != nil {
+		return
+	}
+
+	if cur.Leader != lease.Machine || cur.LeaseEpoch != lease.LeaseEpoch {
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		err = ErrYigFsLeaseStale
+		return
+	}
+
+	// keep the leader and epoch, so that the writes of this epoch are still accepted until others take over.
+	resp, err = SwapFileLease(ctx, lease, cur, lease.Machine, cur.LeaseEpoch, now, yigFs)
+	if err == ErrYigFsLeaseHeld {
+		err = ErrYigFsLeaseStale
+	}
+	return
+}
+
+func(yigFs *YigFsStorage) TransferFileLeader(ctx context.Context, lease *types.FileLeaseReq) (resp *types.LeaderInfo, err error) {
+	now := time.Now().Unix()
+	cur, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	if err == ErrYigFsNoSuchLeader {
+		err = ErrYigFsLeaseStale
+		return
+	} else if err != nil {
+		return
+	}
+
+	if cur.Leader == lease.Machine && cur.LeaseEpoch > 0 && cur.LeaseExpire > now {
+		// transferred already.
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		return
+	}
+
+	// the leadership is changed since the request machine saw it.
+	if cur.Leader != lease.FromLeader || cur.LeaseEpoch != lease.LeaseEpoch {
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		err = ErrYigFsLeaseStale
+		return
+	}
+
+	// an unreachable leader still owns the file until its lease expires.
+	if cur.LeaseEpoch > 0 && cur.LeaseExpire > now {
+		resp = GetLeaseLeaderInfo(lease.ZoneId, cur, now)
+		err = ErrYigFsLeaseHeld
+		return
+	}
+
+	helper.Logger.Info(ctx, fmt.Sprintf("Transfer the leader of ino: %d from: %s, epoch: %d to: %s",
+		lease.Ino, cur.Leader, cur.LeaseEpoch, lease.Machine))
+	return SwapFileLease(ctx, lease, cur, lease.Machine, cur.LeaseEpoch + 1, GetLeaseExpire(lease.LeaseSecs, now), yigFs)
+}
+
+// the writes of segments must carry the current lease epoch of the file.
+func(yigFs *YigFsStorage) CheckFileLeaseEpoch(ctx context.Context, lease *types.FileLeaseReq) (err error) {
+	cur, err := yigFs.MetaStorage.Client.GetFileLease(ctx, lease)
+	if err == ErrYigFsNoSuchLeader {
+		// the file without leader has no lease to check.
+		if lease.LeaseEpoch == 0 {
+			return nil
+		}
+		return ErrYigFsLeaseStale
+	} else if err != nil {
+		return
+	}
+
+	if lease.LeaseEpoch == 0 {
+		// the clients without lease can't write while the lease is held.
+		if cur.LeaseEpoch > 0 && cur.LeaseExpire > time.Now().Unix() {
+			helper.Logger.Error(ctx, fmt.Sprintf("Reject the write without lease, ino: %d, leader: %s, epoch: %d",
+				lease.Ino, cur.Leader, cur.LeaseEpoch))
+			return ErrYigFsLeaseStale
+		}
+		return nil
+	}
+
+	if cur.LeaseEpoch != lease.LeaseEpoch {
+		helper.Logger.Error(ctx, fmt.Sprintf("Reject the write with stale lease epoch: %d, ino: %d, leader: %s, epoch: %d",
+			lease.LeaseEpoch, lease.Ino, cur.Leader, cur.LeaseEpoch))
+		return ErrYigFsLeaseStale
+	}
+	return nil
+}
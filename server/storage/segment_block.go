@@ -5,6 +5,7 @@ import (
 	"fmt"
 
 	"github.com/hopkings2008/yigfs/server/types"
+	. "github.com/hopkings2008/yigfs/server/error"
 	"github.com/hopkings2008/yigfs/server/helper"
 )
 
@@ -20,3 +21,41 @@ func(yigFs *YigFsStorage) UpdateSegBlockInfo(ctx context.Context, seg *types.Upd
 	return
 }
 
+// the leader, max size and the blocks of all the files in the segment.
+func(yigFs *YigFsStorage) GetSegmentInfo(ctx context.Context, segment *types.GetSegLeaderReq) (resp *types.GetSegmentInfoResp, err error) {
+	leader, err := yigFs.MetaStorage.Client.GetSegmentInfo(ctx, segment)
+	if err == ErrYigFsNoSuchLeader {
+		err = ErrYigFsNoTargetSegment
+		return
+	} else if err != nil {
+		return
+	}
+
+	blocks, err := yigFs.MetaStorage.Client.GetSegmentBlocks(ctx, segment)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to get segment blocks, zone: %s, region: %s, bucket: %s, seg_id0: %v, seg_id1: %v, err: %v",
+			segment.ZoneId, segment.Region, segment.BucketName, segment.SegmentId0, segment.SegmentId1, err))
+		return
+	}
+
+	resp = &types.GetSegmentInfoResp {
+		SegmentId0: segment.SegmentId0,
+		SegmentId1: segment.SegmentId1,
+		MaxSize: leader.MaxSize,
+		Leader: leader.Leader,
+		BackendSize: leader.BackendSize,
+		Blocks: blocks,
+	}
+	return
+}
+
+func(yigFs *YigFsStorage) UpdateSegmentBackendSize(ctx context.Context, segment *types.CreateSegmentReq) (err error) {
+	err = yigFs.MetaStorage.Client.UpdateSegmentBackendSize(ctx, segment)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to update segment backend size, zone: %s, region: %s, bucket: %s, seg_id0: %v, seg_id1: %v, err: %v",
+			segment.ZoneId, segment.Region, segment.BucketName, segment.Segment.SegmentId0, segment.Segment.SegmentId1, err))
+		return
+	}
+
+	return
+}
@@ -0,0 +1,40 @@
+package storage
+
+import (
+	"context"
+	"fmt"
+
+	"github.com/hopkings2008/yigfs/server/types"
+	. "github.com/hopkings2008/yigfs/server/error"
+	"github.com/hopkings2008/yigfs/server/helper"
+)
+
+
+func(yigFs *YigFsStorage) ListZoneMachines(ctx context.Context, zone *types.GetZoneMachinesReq) (resp []*types.MachineInfo, err error) {
+	resp, err = yigFs.MetaStorage.Client.ListZoneMachines(ctx, zone)
+	if err != nil {
+		helper.Logger.Error(ctx, fmt.Sprintf("Failed to list zone machines, region: %s, bucket: %s, err: %v", zone.Region, zone.BucketName, err))
+		return
+	}
+	return
+}
+
+// check machine is up in any zone of the bucket, returns ErrYigFsNoSuchMachine if not.
+func CheckUpMachine(ctx context.Context, region string, bucketName string, machine string, yigFs *YigFsStorage) (err error) {
+	zone := &types.GetZoneMachinesReq {
+		Region: region,
+		BucketName: bucketName,
+	}
+
+	machines, err := yigFs.MetaStorage.Client.ListZoneMachines(ctx, zone)
+	if err != nil {
+		return
+	}
+
+	for _, m := range machines {
+		if m.Machine == machine && m.Status == types.MachineUp {
+			return nil
+		}
+	}
+	return ErrYigFsNoSuchMachine
+}
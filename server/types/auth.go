@@ -0,0 +1,6 @@
+package types
+
+
+type AuthResp struct {
+	Result YigFsMetaError `json:"result"`
+}
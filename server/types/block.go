@@ -27,6 +27,8 @@ type SegmentInfo struct {
 	SegmentId1 uint64 `json:"seg_id1"`
 	Leader string `json:"leader"`
 	MaxSize int `json:"max_size,omitempty"`
+	// the data in [0, backend_size) of the segment is uploaded to the backend store.
+	BackendSize uint64 `json:"backend_size"`
 	Blocks []*BlockInfo `json:"blocks"`
 }
 
@@ -47,6 +49,7 @@ type CreateSegmentReq struct {
 	Machine string `json:"machine"`
 	Ino uint64 `json:"ino"`
 	Generation uint64 `json:"generation"`
+	LeaseEpoch uint64 `json:"lease_epoch"`
 	Segment CreateBlocksInfo `json:"segment"`
 }
 
@@ -55,6 +58,7 @@ type CreateBlocksInfo struct {
 	SegmentId1 uint64 `json:"seg_id1"`
 	Leader string `json:"leader,omitempty"`
 	MaxSize int `json:"max_size"`
+	BackendSize uint64 `json:"backend_size"`
 	Blocks []*BlockInfo `json:"blocks"`
 }
 
@@ -72,6 +76,7 @@ type UpdateSegmentsReq struct {
 	ZoneId string `json:"zone"`
 	Ino uint64 `json:"ino"`
 	Generation uint64 `json:"generation"`
+	LeaseEpoch uint64 `json:"lease_epoch"`
 	Segments []*CreateBlocksInfo `json:"segments"`
 }
 
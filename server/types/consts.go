@@ -14,6 +14,11 @@ const (
 	MAXMUM_INO_VALUE = uint64(1 << 64 -1)
 )
 
+const (
+	MAX_LIST_DIR_FILES = 1000
+	MAX_GET_FILE_ATTRS = 1000
+)
+
 const (
 	COMMON_FILE = iota + 1
 	DIR_FILE
@@ -29,6 +34,11 @@ const (
 	RootParentDirIno
 )
 
+const (
+	DEFAULT_LEASE_SECS = 30
+	MAX_LEASE_SECS = 300
+)
+
 const (
 	MachineDown = iota
 	MachineUp
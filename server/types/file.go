@@ -73,6 +73,8 @@ type CreateFileReq struct {
 	Uid uint32 `json:"uid"`
 	Gid uint32 `json:"gid"`
 	Blocks uint32 `json:"blocks"`
+	// the machine proposed to lead the new file, the request machine leads it if empty.
+	Leader string `json:"leader"`
 }
 
 type CreateFileResp struct {
@@ -139,3 +141,28 @@ type FileInfo struct {
 	Gid uint32 `json:"gid"`
 	Blocks uint32 `json:"blocks"`
 }
+
+type GetFileAttrsReq struct {
+	Ctx context.Context `json:"-"`
+	Region string `json:"region"`
+	BucketName string `json:"bucket"`
+	Inos []uint64 `json:"inos"`
+}
+
+type GetFileAttrsResp struct {
+	Result YigFsMetaError `json:"result"`
+	Files []*FileInfo `json:"files"`
+}
+
+type GetDirFileAttrsInfo struct {
+	Ino uint64 `json:"ino"`
+	FileName string `json:"file_name"`
+	Type uint32 `json:"type"`
+	Attr *FileInfo `json:"attr"`
+}
+
+type GetDirFilesAttrsResp struct {
+	Files []*GetDirFileAttrsInfo `json:"files"`
+	Result YigFsMetaError `json:"result"`
+	Offset uint64 `json:"offset"`
+}
@@ -23,6 +23,9 @@ type LeaderInfo struct {
 	ZoneId string `json:"zone"`
 	Leader string `json:"leader"`
 	MaxSize int `json:"max_size,omitempty"`
+	BackendSize uint64 `json:"backend_size,omitempty"`
+	LeaseEpoch uint64 `json:"lease_epoch,omitempty"`
+	LeaseTtl int64 `json:"lease_ttl,omitempty"`
 }
 
 type GetSegLeaderReq struct {
@@ -33,3 +36,28 @@ type GetSegLeaderReq struct {
 	SegmentId0 uint64 `json:"seg_id0"`
 	SegmentId1 uint64 `json:"seg_id1"`
 }
+
+type FileLeaseReq struct {
+	Ctx context.Context `json:"-"`
+	ZoneId string `json:"zone"`
+	Region string `json:"region"`
+	BucketName string `json:"bucket"`
+	Machine string `json:"machine"`
+	Ino uint64 `json:"ino"`
+	Generation uint64 `json:"generation"`
+	LeaseEpoch uint64 `json:"lease_epoch"`
+	LeaseSecs uint64 `json:"lease_secs"`
+	FromLeader string `json:"from_leader"`
+}
+
+type FileLeaseResp struct {
+	Result YigFsMetaError `json:"result"`
+	LeaderInfo *LeaderInfo `json:"leader_info"`
+}
+
+type FileLeaseInfo struct {
+	Leader string
+	LeaseEpoch uint64
+	// unix seconds
+	LeaseExpire int64
+}
@@ -16,4 +16,24 @@ type UpdateSegBlockInfo struct {
 	SegmentId0 uint64 `json:"seg_id0"`
 	SegmentId1 uint64 `json:"seg_id1"`
 	LatestOffset int `json:"latest_offset"`
-}
\ No newline at end of file
+}
+
+type GetSegmentInfoResp struct {
+	Result YigFsMetaError `json:"result"`
+	SegmentId0 uint64 `json:"seg_id0"`
+	SegmentId1 uint64 `json:"seg_id1"`
+	MaxSize int `json:"max_size"`
+	Leader string `json:"leader"`
+	BackendSize uint64 `json:"backend_size"`
+	Blocks []*SegmentBlockInfo `json:"blocks"`
+}
+
+// the block of one file stored in the segment.
+type SegmentBlockInfo struct {
+	Ino uint64 `json:"ino"`
+	Generation uint64 `json:"generation"`
+	Offset int64 `json:"offset"`
+	SegStartAddr int64 `json:"seg_start_addr"`
+	SegEndAddr int64 `json:"seg_end_addr"`
+	Size int `json:"size"`
+}
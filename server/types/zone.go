@@ -1,8 +1,31 @@
 package types
 
+import (
+	"context"
+)
 
 type GetMachineInfoResp struct {
 	Status int `json:"status"`
 	Weight uint32 `json:"weight"`
 }
 
+type GetZoneMachinesReq struct {
+	Ctx context.Context `json:"-"`
+	ZoneId string `json:"zone"`
+	Region string `json:"region"`
+	BucketName string `json:"bucket"`
+}
+
+type MachineInfo struct {
+	ZoneId string `json:"zone"`
+	Machine string `json:"machine"`
+	Status int `json:"status"`
+	Weight uint32 `json:"weight"`
+	// the number of files led by the machine.
+	Load uint64 `json:"load"`
+}
+
+type GetZoneMachinesResp struct {
+	Result YigFsMetaError `json:"result"`
+	Machines []*MachineInfo `json:"machines"`
+}